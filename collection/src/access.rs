@@ -0,0 +1,267 @@
+use metashrew_support::index_pointer::KeyValuePointer;
+use alkanes_runtime::runtime::AlkaneResponder;
+use alkanes_support::response::CallResponse;
+use serde::{Serialize, Deserialize};
+use anyhow::Result;
+use orbital_support::{revert, errors::codes::*};
+use std::sync::Arc;
+
+use crate::Collection;
+use crate::opcodes::StoragePointer;
+use crate::receipts::{Receipt, RECEIPT_KIND_RENTAL_CLAIM, RECEIPT_KIND_RENTER, RECEIPT_KIND_STAKER};
+use crate::staking::Stake;
+
+/// Built-in policy: the orbital is held, rented or staked by the caller
+const DEFAULT_POLICY_ID: u128 = 0;
+
+/// Upper bound on owner-defined policies, keeps the registry a single storage read
+const MAX_ACCESS_POLICIES: usize = 64;
+
+/// `SetAccessPolicy` flag bits
+const POLICY_ALLOW_HOLDER: u128 = 1;
+const POLICY_ALLOW_RENTER: u128 = 2;
+const POLICY_ALLOW_STAKER: u128 = 4;
+const POLICY_ANY_INDEX: u128 = 8;
+
+/// Reasons reported alongside an access answer
+const ACCESS_HOLDER: u128 = 1;
+const ACCESS_RENTER: u128 = 2;
+const ACCESS_STAKER: u128 = 3;
+pub(crate) const DENIED_NOT_PRESENTED: u128 = 100;
+const DENIED_STAKE_TOO_RECENT: u128 = 101;
+const DENIED_TRAIT_MISMATCH: u128 = 102;
+
+/// Rules an orbital and the presented tokens must satisfy to be granted access
+#[derive(Clone, Default, Debug, Serialize, Deserialize)]
+pub(crate) struct AccessPolicy {
+    pub id: u128,
+    /// Policy name packed like names and symbols
+    pub name: u128,
+    pub allow_holder: bool,
+    pub allow_renter: bool,
+    pub allow_staker: bool,
+    /// Any orbital of the collection qualifies, the checked index is ignored
+    pub any_index: bool,
+    pub min_staked_blocks: u64,
+    pub trait_type: u128,
+    /// Required trait value packed like names and symbols, zero for no requirement
+    pub trait_value: u128,
+}
+
+impl Collection {
+    /// Define or replace an owner policy, the built-in policy 0 is fixed
+    pub(crate) fn set_access_policy(
+        &self,
+        policy_id: u128,
+        name: u128,
+        flags: u128,
+        min_staked_blocks: u128,
+        trait_type: u128,
+        trait_value: u128,
+    ) -> Result<CallResponse> {
+        self.only_owner()?;
+        let context: alkanes_support::context::Context = self.context()?;
+        let response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        if policy_id == DEFAULT_POLICY_ID {
+            return Err(revert!(ERR_INVALID_INPUT, "Policy {} is built in", DEFAULT_POLICY_ID));
+        }
+
+        if flags & (POLICY_ALLOW_HOLDER | POLICY_ALLOW_RENTER | POLICY_ALLOW_STAKER) == 0 {
+            return Err(revert!(ERR_INVALID_INPUT, "Policy must allow holders, renters or stakers"));
+        }
+
+        if trait_value != 0 && trait_type >= self.render_engine()?.trait_types().len() as u128 {
+            return Err(revert!(ERR_NOT_FOUND, "Unknown trait type {}", trait_type));
+        }
+
+        let policy: AccessPolicy = AccessPolicy {
+            id: policy_id,
+            name,
+            allow_holder: flags & POLICY_ALLOW_HOLDER != 0,
+            allow_renter: flags & POLICY_ALLOW_RENTER != 0,
+            allow_staker: flags & POLICY_ALLOW_STAKER != 0,
+            any_index: flags & POLICY_ANY_INDEX != 0,
+            min_staked_blocks: u64::try_from(min_staked_blocks).map_err(|_| revert!(ERR_INVALID_INPUT, "Minimum stake out of range"))?,
+            trait_type,
+            trait_value,
+        };
+
+        let mut policies: Vec<AccessPolicy> = self.get_access_policy_list()?;
+        match policies.iter_mut().find(|existing| existing.id == policy_id) {
+            Some(existing) => *existing = policy,
+            None => {
+                if policies.len() >= MAX_ACCESS_POLICIES {
+                    return Err(revert!(ERR_LIMIT_EXCEEDED, "At most {} access policies can be defined", MAX_ACCESS_POLICIES));
+                }
+                policies.push(policy);
+            }
+        }
+
+        self.set_access_policy_list(&policies)?;
+        Ok(response)
+    }
+
+    pub(crate) fn remove_access_policy(&self, policy_id: u128) -> Result<CallResponse> {
+        self.only_owner()?;
+        let context: alkanes_support::context::Context = self.context()?;
+        let response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let mut policies: Vec<AccessPolicy> = self.get_access_policy_list()?;
+        let before: usize = policies.len();
+        policies.retain(|policy| policy.id != policy_id);
+
+        if policies.len() == before {
+            return Err(revert!(ERR_NOT_FOUND, "Unknown access policy {}", policy_id));
+        }
+
+        self.set_access_policy_list(&policies)?;
+        Ok(response)
+    }
+
+    /// Answers whether the presented tokens grant access to an index under a policy.
+    /// Returns granted (u128, 0 or 1) followed by the reason code (u128).
+    pub(crate) fn check_access(&self, index: u128, policy_id: u128) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let policy: AccessPolicy = self.access_policy(policy_id)?;
+        let reason: u128 = self.evaluate_access(index, &policy)?;
+        let granted: u128 = if reason < DENIED_NOT_PRESENTED { 1 } else { 0 };
+
+        let mut data: Vec<u8> = Vec::with_capacity(32);
+        data.extend_from_slice(&granted.to_le_bytes());
+        data.extend_from_slice(&reason.to_le_bytes());
+        response.data = data;
+        Ok(response)
+    }
+
+    /// Returns a policy as JSON
+    pub(crate) fn get_access_policy(&self, policy_id: u128) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let policy: AccessPolicy = self.access_policy(policy_id)?;
+        response.data = serde_json::to_vec(&policy)
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to serialize access policy"))?;
+        Ok(response)
+    }
+
+    /// Returns every policy, the built-in one first, as a JSON array
+    pub(crate) fn get_access_policies(&self) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let mut policies: Vec<AccessPolicy> = vec![self.default_access_policy()];
+        policies.extend(self.get_access_policy_list()?);
+
+        response.data = serde_json::to_vec(&policies)
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to serialize access policies"))?;
+        Ok(response)
+    }
+
+    pub(crate) fn default_access_policy(&self) -> AccessPolicy {
+        AccessPolicy {
+            id: DEFAULT_POLICY_ID,
+            allow_holder: true,
+            allow_renter: true,
+            allow_staker: true,
+            ..Default::default()
+        }
+    }
+
+    fn access_policy(&self, policy_id: u128) -> Result<AccessPolicy> {
+        if policy_id == DEFAULT_POLICY_ID {
+            return Ok(self.default_access_policy());
+        }
+
+        self.get_access_policy_list()?
+            .into_iter()
+            .find(|policy| policy.id == policy_id)
+            .ok_or_else(|| revert!(ERR_NOT_FOUND, "Unknown access policy {}", policy_id))
+    }
+
+    /// Returns the reason code of the access decision, grants are below `DENIED_NOT_PRESENTED`
+    pub(crate) fn evaluate_access(&self, index: u128, policy: &AccessPolicy) -> Result<u128> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let height: u64 = self.height();
+
+        let mut denial: u128 = DENIED_NOT_PRESENTED;
+        for transfer in context.incoming_alkanes.0.iter() {
+            if transfer.value == 0 {
+                continue;
+            }
+
+            // Which orbital the presented token stands for, and in what capacity
+            let (held_index, reason): (u128, u128) = if let Some(held_index) = self.index_of_instance(&transfer.id) {
+                if !policy.allow_holder {
+                    continue;
+                }
+                (held_index, ACCESS_HOLDER)
+            } else {
+                let receipt: Receipt = match self.get_receipt(&transfer.id)? {
+                    Some(receipt) => receipt,
+                    None => continue,
+                };
+
+                // Rental receipts count while their side of the rental holds the use of the orbital
+                let rental_user: bool = self.rental_user_of(&transfer.id, height)?.is_some();
+                match receipt.kind {
+                    RECEIPT_KIND_RENTER if policy.allow_renter && rental_user => (receipt.index, ACCESS_RENTER),
+                    RECEIPT_KIND_RENTAL_CLAIM if policy.allow_holder && rental_user => (receipt.index, ACCESS_HOLDER),
+                    RECEIPT_KIND_STAKER if policy.allow_staker => (receipt.index, ACCESS_STAKER),
+                    _ => continue,
+                }
+            };
+
+            if !policy.any_index && held_index != index {
+                continue;
+            }
+
+            if reason == ACCESS_STAKER {
+                // Receipts of earlier, ended stakes no longer count
+                let stake: Stake = match self.get_stake_of(held_index)? {
+                    Some(stake) if stake.receipt() == transfer.id => stake,
+                    _ => continue,
+                };
+
+                if stake.staked_blocks(height) < policy.min_staked_blocks {
+                    denial = DENIED_STAKE_TOO_RECENT;
+                    continue;
+                }
+            }
+
+            if policy.trait_value != 0 {
+                let value: String = self.render_engine()?.trait_value(self.art_of(held_index), policy.trait_type)?;
+                if self.encode_string_to_u128(&value) != policy.trait_value {
+                    denial = DENIED_TRAIT_MISMATCH;
+                    continue;
+                }
+            }
+
+            return Ok(reason);
+        }
+
+        Ok(denial)
+    }
+
+    fn access_policies_pointer(&self) -> StoragePointer {
+        StoragePointer::from_keyword("/access/policies")
+    }
+
+    fn set_access_policy_list(&self, policies: &[AccessPolicy]) -> Result<()> {
+        let serialized: Vec<u8> = bincode::serialize(policies)
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to serialize access policies"))?;
+        self.access_policies_pointer().set(Arc::new(serialized));
+        Ok(())
+    }
+
+    fn get_access_policy_list(&self) -> Result<Vec<AccessPolicy>> {
+        let stored: Arc<Vec<u8>> = self.access_policies_pointer().get();
+        if stored.is_empty() {
+            return Ok(vec![]);
+        }
+
+        bincode::deserialize(&stored).map_err(|_| revert!(ERR_STORAGE, "Failed to deserialize access policies"))
+    }
+}
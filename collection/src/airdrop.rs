@@ -0,0 +1,116 @@
+//! Owner mints out of the premine allowance straight to their recipients. An orbital for an
+//! alkane is handed over by calling the recipient with it; alkanes cannot address outputs,
+//! so orbitals for outputs are returned with the response, attributed to their output, for
+//! the edicts of the transaction to deliver.
+
+use metashrew_support::index_pointer::KeyValuePointer;
+use alkanes_runtime::runtime::AlkaneResponder;
+use alkanes_support::{cellpack::Cellpack, id::AlkaneId, parcel::AlkaneTransferParcel, response::CallResponse};
+use anyhow::Result;
+use orbital_support::{revert, errors::{codes::*, Revert}};
+
+use crate::{Collection, MAX_PREMINE_MINTS, counters};
+use crate::opcodes::StoragePointer;
+use crate::public_mint::PUBLIC_MINT_STAGE;
+
+/// Inputs ahead of the recipient descriptors of `AirdropMint`: opcode, count
+const AIRDROP_MINT_HEADER: usize = 2;
+
+/// Words per recipient descriptor: kind followed by two kind specific values
+const DESCRIPTOR_WORDS: usize = 3;
+
+/// Recipient is an output of the transaction, the first value is its index
+const RECIPIENT_OUTPUT: u128 = 0;
+
+/// Recipient is an alkane, the values are its block and tx
+const RECIPIENT_ALKANE: u128 = 1;
+
+/// Opcode alkane recipients implement to take an airdropped orbital, taking (index).
+/// Whatever the recipient returns is passed on with the response.
+const RECIPIENT_ON_AIRDROP: u128 = 63;
+
+impl Collection {
+    /// Mint one orbital to each of `count` recipients described in the inputs
+    pub(crate) fn airdrop_mint(&self, count: u128) -> Result<CallResponse> {
+        self.only_owner()?;
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let words: &[u128] = context.inputs.get(AIRDROP_MINT_HEADER..).unwrap_or(&[]);
+        let expected: u128 = count.saturating_mul(DESCRIPTOR_WORDS as u128);
+        if count == 0 || words.len() as u128 != expected {
+            return Err(Revert::new(ERR_INVALID_INPUT, format!("Airdrops list one {} word descriptor per recipient", DESCRIPTOR_WORDS))
+                .field("inputs").expected(expected).actual(words.len() as u128).into());
+        }
+
+        self.reserve_premine(count)?;
+
+        for descriptor in words.chunks_exact(DESCRIPTOR_WORDS) {
+            self.observe_mint_per_block(PUBLIC_MINT_STAGE)?;
+            let (index, transfer) = self.create_mint_transfer()?;
+
+            match descriptor[0] {
+                RECIPIENT_OUTPUT => {
+                    self.set_minted_by(index, &self.output_identity(descriptor[1])?);
+                    response.alkanes.0.push(transfer);
+                }
+                RECIPIENT_ALKANE => {
+                    let cellpack: Cellpack = Cellpack {
+                        target: AlkaneId { block: descriptor[1], tx: descriptor[2] },
+                        inputs: vec![RECIPIENT_ON_AIRDROP, index],
+                    };
+                    let returned: CallResponse = self.call(&cellpack, &AlkaneTransferParcel(vec![transfer]), self.fuel())?;
+                    response.alkanes.0.extend(returned.alkanes.0);
+                }
+                kind => return Err(revert!(ERR_INVALID_INPUT, "Unknown recipient kind {}", kind)),
+            }
+        }
+
+        Ok(response)
+    }
+
+    /// Returns the orbitals still to be airdropped out of the premine as u128
+    pub(crate) fn get_premine_remaining(&self) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let remaining: u128 = self.premine().saturating_sub(self.premine_minted());
+        response.data = remaining.to_le_bytes().to_vec();
+        Ok(response)
+    }
+
+    /// Record the premine given to `Initialize`
+    pub(crate) fn initialize_premine(&self, premine: u128) -> Result<()> {
+        if premine > MAX_PREMINE_MINTS {
+            return Err(Revert::new(ERR_INVALID_INPUT, format!("Premine must be at most {}", MAX_PREMINE_MINTS))
+                .field("premine").expected(MAX_PREMINE_MINTS).actual(premine).into());
+        }
+
+        self.premine_pointer().set_value::<u128>(premine);
+        Ok(())
+    }
+
+    /// Orbitals set aside at initialization for the owner to airdrop
+    pub(crate) fn premine(&self) -> u128 {
+        self.premine_pointer().get_value::<u128>()
+    }
+
+    /// Orbitals airdropped or vaulted so far out of the premine allowance
+    pub(crate) fn premine_minted(&self) -> u128 {
+        self.airdrop_minted_pointer().get_value::<u128>()
+    }
+
+    /// Count `count` more orbitals against the premine allowance
+    pub(crate) fn reserve_premine(&self, count: u128) -> Result<()> {
+        counters::increment(&mut self.airdrop_minted_pointer(), count, self.premine(), "premine")?;
+        Ok(())
+    }
+
+    fn premine_pointer(&self) -> StoragePointer {
+        StoragePointer::from_keyword("/airdrop/premine")
+    }
+
+    fn airdrop_minted_pointer(&self) -> StoragePointer {
+        StoragePointer::from_keyword("/airdrop/minted")
+    }
+}
@@ -0,0 +1,116 @@
+//! Batch queries return items in a fixed order: orbitals by index, receipts by the order
+//! they were issued in. Each page carries the cursor for the page after it, a position in
+//! that order rather than in storage, so a crawl resumed from a cursor neither skips nor
+//! repeats items however the underlying storage is laid out. Cursors follow
+//! `orbital_support::pagination`.
+
+use alkanes_runtime::runtime::AlkaneResponder;
+use alkanes_support::{id::AlkaneId, response::CallResponse};
+use serde::Serialize;
+use anyhow::Result;
+use orbital_support::{revert, errors::codes::*, pagination::{self, PageBounds}};
+
+use crate::Collection;
+use crate::receipts::Receipt;
+use crate::svg_generator::RenderConfig;
+
+/// One page of a batch query
+#[derive(Serialize)]
+pub(crate) struct Page<T> {
+    items: Vec<T>,
+    /// Cursor to request the following page with, None once the last item was returned
+    next: Option<u128>,
+    /// Positions in the whole listing, a hint for progress since sparse listings skip some
+    total: u128,
+}
+
+#[derive(Serialize)]
+struct InstanceItem {
+    index: u128,
+    block: u128,
+    tx: u128,
+}
+
+#[derive(Serialize)]
+struct AttributesItem {
+    index: u128,
+    attributes: serde_json::Value,
+}
+
+#[derive(Serialize)]
+struct ReceiptItem {
+    block: u128,
+    tx: u128,
+    receipt: Receipt,
+}
+
+impl Collection {
+    /// Returns a page of orbital alkane IDs in index order as JSON
+    pub(crate) fn get_instances(&self, cursor: u128, limit: u128) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let page: Page<InstanceItem> = self.page(cursor, limit, self.instances_count(), |index| {
+            let id: AlkaneId = self.lookup_instance(index)?;
+            Ok(Some(InstanceItem { index, block: id.block, tx: id.tx }))
+        })?;
+
+        response.data = serde_json::to_vec(&page)
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to serialize instances"))?;
+        Ok(response)
+    }
+
+    /// Returns a page of orbital attributes in index order as JSON. Orbitals rendered as a
+    /// preview have their attributes withheld and are listed with null.
+    pub(crate) fn get_attributes_batch(&self, cursor: u128, limit: u128) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let page: Page<AttributesItem> = self.page(cursor, limit, self.instances_count(), |index| {
+            let config: RenderConfig = self.render_config_for(index)?;
+            let attributes: serde_json::Value = if config.preview {
+                serde_json::Value::Null
+            } else {
+                serde_json::from_str(&self.render_engine_at(config.traits_version)?.attributes(self.art_of(index))?)
+                    .map_err(|_| revert!(ERR_STORAGE, "Failed to parse attributes of {}", index))?
+            };
+            Ok(Some(AttributesItem { index, attributes }))
+        })?;
+
+        response.data = serde_json::to_vec(&page)
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to serialize attributes"))?;
+        Ok(response)
+    }
+
+    /// Returns a page of receipts in issue order as JSON
+    pub(crate) fn get_receipts(&self, cursor: u128, limit: u128) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let page: Page<ReceiptItem> = self.page(cursor, limit, self.receipts_issued(), |position| {
+            let id: AlkaneId = self.receipt_at(position)?;
+            let receipt: Receipt = self.get_receipt(&id)?
+                .ok_or_else(|| revert!(ERR_STORAGE, "Receipt {}:{} is not registered", id.block, id.tx))?;
+            Ok(Some(ReceiptItem { block: id.block, tx: id.tx, receipt }))
+        })?;
+
+        response.data = serde_json::to_vec(&page)
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to serialize receipts"))?;
+        Ok(response)
+    }
+
+    /// Collect the items of up to `limit` positions out of `total`, resuming at `cursor`.
+    /// Positions `item` returns None for are left out of the page.
+    pub(crate) fn page<T, F: Fn(u128) -> Result<Option<T>>>(&self, cursor: u128, limit: u128, total: u128, item: F) -> Result<Page<T>> {
+        let bounds: PageBounds = pagination::page_bounds(cursor, limit, total)?;
+
+        let mut items: Vec<T> = Vec::new();
+        for position in bounds.positions() {
+            if let Some(found) = item(position)? {
+                items.push(found);
+            }
+        }
+
+        Ok(Page { items, next: bounds.next, total: bounds.total })
+    }
+}
@@ -0,0 +1,218 @@
+//! Renderings are pure functions of an orbital's art and render configuration, so storing
+//! them is only ever a shortcut. Cached entries carry the inputs they were rendered from and
+//! are ignored once those change; pruning an entry falls back to rendering it again, which
+//! yields the same bytes. Honorary art is stored already and never cached.
+
+use metashrew_support::index_pointer::KeyValuePointer;
+use alkanes_runtime::runtime::AlkaneResponder;
+use alkanes_support::response::CallResponse;
+use anyhow::Result;
+use orbital_support::{revert, errors::{codes::*, Revert}};
+use std::sync::Arc;
+
+use crate::{Collection, counters};
+use crate::opcodes::StoragePointer;
+use crate::svg_generator::RenderConfig;
+
+/// Full renderings served by `GetData`
+pub(crate) const CACHE_RENDERS: u128 = 1;
+
+/// Thumbnails served by `GetThumbnail`
+pub(crate) const CACHE_THUMBNAILS: u128 = 2;
+
+/// Orbitals one `WarmCache` renders, rendering is the expensive part
+const MAX_WARM: u128 = 16;
+
+/// Orbitals one `PruneCache` clears
+const MAX_PRUNE: u128 = 256;
+
+/// Cached entries start with the art (u128), preview flag (u8) and trait table version
+/// (u128) they were rendered from
+const ENTRY_HEADER: usize = 33;
+
+impl Collection {
+    /// Render `count` minted orbitals from `start` into the cache of a subsystem
+    pub(crate) fn warm_cache(&self, subsystem: u128, start: u128, count: u128) -> Result<CallResponse> {
+        self.only_owner()?;
+        let context: alkanes_support::context::Context = self.context()?;
+        let response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        for index in self.cache_range(subsystem, start, count, MAX_WARM)? {
+            self.fill_cache_entry(subsystem, index)?;
+        }
+
+        Ok(response)
+    }
+
+    /// Drop the cached entries of `count` orbitals from `start`, they render on demand again
+    pub(crate) fn prune_cache(&self, subsystem: u128, start: u128, count: u128) -> Result<CallResponse> {
+        self.only_owner()?;
+        let context: alkanes_support::context::Context = self.context()?;
+        let response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        for index in self.cache_range(subsystem, start, count, MAX_PRUNE)? {
+            self.clear_cache_entry(subsystem, index);
+        }
+
+        Ok(response)
+    }
+
+    /// Rendering of an orbital for a subsystem, from the cache while it still matches
+    pub(crate) fn render_cached(&self, subsystem: u128, index: u128) -> Result<String> {
+        let config: RenderConfig = self.render_config_for(index)?;
+        let art: u128 = self.art_of(index);
+
+        let entry: Arc<Vec<u8>> = self.cache_pointer(subsystem, index).get();
+        if !self.has_honorary_art(index)
+            && entry.len() >= ENTRY_HEADER
+            && entry[..16] == art.to_le_bytes()
+            && entry[16] == config.preview as u8
+            && entry[17..33] == config.traits_version.to_le_bytes()
+        {
+            if let Ok(svg) = String::from_utf8(entry[ENTRY_HEADER..].to_vec()) {
+                return Ok(svg);
+            }
+        }
+
+        self.render_uncached(subsystem, index, art, &config)
+    }
+
+    /// Cached entries of a subsystem and the bytes they hold
+    pub(crate) fn cache_usage(&self, subsystem: u128) -> (u128, u128) {
+        (
+            self.cache_entries_pointer(subsystem).get_value::<u128>(),
+            self.cache_bytes_pointer(subsystem).get_value::<u128>(),
+        )
+    }
+
+    /// Render an orbital into the cache of a subsystem, replacing what it held
+    fn fill_cache_entry(&self, subsystem: u128, index: u128) -> Result<()> {
+        if self.has_honorary_art(index) {
+            return Ok(());
+        }
+
+        let config: RenderConfig = self.render_config_for(index)?;
+        let art: u128 = self.art_of(index);
+
+        let mut entry: Vec<u8> = Vec::with_capacity(ENTRY_HEADER);
+        entry.extend_from_slice(&art.to_le_bytes());
+        entry.push(config.preview as u8);
+        entry.extend_from_slice(&config.traits_version.to_le_bytes());
+        entry.extend_from_slice(self.render_uncached(subsystem, index, art, &config)?.as_bytes());
+
+        self.clear_cache_entry(subsystem, index);
+        counters::increment(&mut self.cache_entries_pointer(subsystem), 1, u128::MAX, "cache_entries")?;
+        counters::increment(&mut self.cache_bytes_pointer(subsystem), entry.len() as u128, u128::MAX, "cache_bytes")?;
+        self.cache_pointer(subsystem, index).set(Arc::new(entry));
+        Ok(())
+    }
+
+    fn render_uncached(&self, subsystem: u128, index: u128, art: u128, config: &RenderConfig) -> Result<String> {
+        match subsystem {
+            CACHE_RENDERS => self.orbital_engine(index, config.traits_version)?.render(art, config),
+            CACHE_THUMBNAILS => self.orbital_engine(index, config.traits_version)?.thumbnail(art, config),
+            _ => Err(revert!(ERR_NOT_FOUND, "Unknown cache {}", subsystem)),
+        }
+    }
+
+    /// Indices `start..start + count` within the minted orbitals, for a known subsystem
+    fn cache_range(&self, subsystem: u128, start: u128, count: u128, max: u128) -> Result<std::ops::Range<u128>> {
+        if subsystem != CACHE_RENDERS && subsystem != CACHE_THUMBNAILS {
+            return Err(revert!(ERR_NOT_FOUND, "Unknown cache {}", subsystem));
+        }
+
+        if count == 0 || count > max {
+            return Err(Revert::new(ERR_LIMIT_EXCEEDED, format!("Cache batches cover 1 to {} orbitals", max))
+                .field("count").expected(max).actual(count).into());
+        }
+
+        let end: u128 = start.saturating_add(count).min(self.instances_count());
+        Ok(start.min(end)..end)
+    }
+
+    fn clear_cache_entry(&self, subsystem: u128, index: u128) {
+        let mut pointer: StoragePointer = self.cache_pointer(subsystem, index);
+        let stored: usize = pointer.get().len();
+        if stored == 0 {
+            return;
+        }
+
+        counters::decrement(&mut self.cache_entries_pointer(subsystem), 1);
+        counters::decrement(&mut self.cache_bytes_pointer(subsystem), stored as u128);
+        pointer.set(Arc::new(vec![]));
+    }
+
+    fn cache_pointer(&self, subsystem: u128, index: u128) -> StoragePointer {
+        StoragePointer::from_keyword("/cache/")
+            .select(&subsystem.to_le_bytes().to_vec())
+            .keyword("/entries/")
+            .select(&index.to_le_bytes().to_vec())
+    }
+
+    fn cache_entries_pointer(&self, subsystem: u128) -> StoragePointer {
+        StoragePointer::from_keyword("/cache/").select(&subsystem.to_le_bytes().to_vec()).keyword("/count")
+    }
+
+    fn cache_bytes_pointer(&self, subsystem: u128) -> StoragePointer {
+        StoragePointer::from_keyword("/cache/").select(&subsystem.to_le_bytes().to_vec()).keyword("/bytes")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Fill, serve and prune the cache of a subsystem for `indices`, checking every
+    /// rendering against the one drawn without the cache
+    fn assert_regenerates(subsystem: u128, indices: std::ops::Range<u128>) {
+        let collection: Collection = Collection::default();
+
+        for index in indices {
+            let config: RenderConfig = collection.render_config_for(index).unwrap();
+            let uncached: String = collection.render_uncached(subsystem, index, collection.art_of(index), &config).unwrap();
+            assert_eq!(collection.render_cached(subsystem, index).unwrap(), uncached);
+
+            collection.fill_cache_entry(subsystem, index).unwrap();
+            let entry: Arc<Vec<u8>> = collection.cache_pointer(subsystem, index).get();
+            assert_eq!(&entry[ENTRY_HEADER..], uncached.as_bytes());
+            assert_eq!(collection.render_cached(subsystem, index).unwrap(), uncached);
+
+            collection.clear_cache_entry(subsystem, index);
+            assert!(collection.cache_pointer(subsystem, index).get().is_empty());
+            assert_eq!(collection.render_cached(subsystem, index).unwrap(), uncached);
+
+            collection.fill_cache_entry(subsystem, index).unwrap();
+            assert_eq!(collection.cache_pointer(subsystem, index).get(), entry);
+            collection.clear_cache_entry(subsystem, index);
+        }
+
+        assert_eq!(collection.cache_usage(subsystem), (0, 0));
+    }
+
+    #[test]
+    fn pruned_renders_regenerate_identically() {
+        assert_regenerates(CACHE_RENDERS, 0..4);
+    }
+
+    #[test]
+    fn pruned_thumbnails_regenerate_identically() {
+        assert_regenerates(CACHE_THUMBNAILS, 4..8);
+    }
+
+    #[test]
+    fn entries_from_other_inputs_are_not_served() {
+        let collection: Collection = Collection::default();
+
+        let config: RenderConfig = collection.render_config_for(9).unwrap();
+        let mut stale: Vec<u8> = Vec::with_capacity(ENTRY_HEADER);
+        stale.extend_from_slice(&collection.art_of(9).wrapping_add(1).to_le_bytes());
+        stale.push(config.preview as u8);
+        stale.extend_from_slice(&config.traits_version.to_le_bytes());
+        stale.extend_from_slice(b"<svg/>");
+        collection.cache_pointer(CACHE_THUMBNAILS, 9).set(Arc::new(stale));
+
+        let uncached: String = collection.render_uncached(CACHE_THUMBNAILS, 9, collection.art_of(9), &config).unwrap();
+        assert_eq!(collection.render_cached(CACHE_THUMBNAILS, 9).unwrap(), uncached);
+        collection.cache_pointer(CACHE_THUMBNAILS, 9).set(Arc::new(vec![]));
+    }
+}
@@ -0,0 +1,145 @@
+use metashrew_support::index_pointer::KeyValuePointer;
+use alkanes_runtime::runtime::AlkaneResponder;
+use alkanes_support::response::CallResponse;
+use serde::{Serialize, Deserialize};
+use anyhow::Result;
+use orbital_support::{revert, errors::codes::*};
+
+use crate::Collection;
+use crate::opcodes::StoragePointer;
+use crate::events::{EventLog, LOG_METADATA};
+
+/// Kinds of metadata changes, stable across releases since indexers key on them
+pub(crate) const CHANGE_FREEZE: u8 = 1;
+pub(crate) const CHANGE_REVEAL_BLOCK: u8 = 2;
+pub(crate) const CHANGE_PREVIEW_GATE: u8 = 3;
+pub(crate) const CHANGE_RENDER_CANARY: u8 = 4;
+pub(crate) const CHANGE_TRAIT_TABLE: u8 = 5;
+pub(crate) const CHANGE_HONORARY_ART: u8 = 6;
+
+fn change_name(kind: u8) -> &'static str {
+    match kind {
+        CHANGE_FREEZE => "freeze",
+        CHANGE_REVEAL_BLOCK => "reveal-block",
+        CHANGE_PREVIEW_GATE => "preview-gate",
+        CHANGE_RENDER_CANARY => "render-canary",
+        CHANGE_TRAIT_TABLE => "trait-table",
+        CHANGE_HONORARY_ART => "honorary-art",
+        _ => "unknown",
+    }
+}
+
+/// One operation that changed, or can change, rendered output
+#[derive(Clone, Default, Debug, Serialize, Deserialize)]
+struct ChangeEntry {
+    /// Metadata version the change produced
+    version: u128,
+    kind: u8,
+    /// Orbital the change applies to, None for collection wide changes
+    index: Option<u128>,
+    /// Kind specific value, such as the new reveal block, gate state or end of a canary
+    /// probation (0 when the trial was cancelled)
+    detail: u128,
+    height: u64,
+}
+
+/// Changelog entry as returned by `GetMetadataChangelog`
+#[derive(Serialize)]
+struct ChangeView {
+    version: u128,
+    kind: u8,
+    name: &'static str,
+    index: Option<u128>,
+    detail: u128,
+    height: u64,
+}
+
+impl Collection {
+    /// Bump the metadata version and append the change to the collection log, or to the
+    /// log of a single orbital when `index` is given
+    pub(crate) fn record_metadata_change(&self, kind: u8, index: Option<u128>, detail: u128) -> Result<()> {
+        let version: u128 = self.recorded_metadata_version().checked_add(1)
+            .ok_or_else(|| revert!(ERR_OVERFLOW, "metadata version overflow"))?;
+        self.metadata_version_pointer().set_value::<u128>(version);
+
+        let entry: ChangeEntry = ChangeEntry { version, kind, index, detail, height: self.height() };
+        let serialized: Vec<u8> = bincode::serialize(&entry)
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to serialize metadata change"))?;
+
+        let sequence: u128 = self.changelog(index).append(&serialized)?;
+        match index {
+            // Listeners follow the collection log, the one `GetEvents` serves
+            None => self.notify_listeners(LOG_METADATA, sequence),
+            Some(_) => Ok(()),
+        }
+    }
+
+    /// Returns every retained change affecting an orbital, collection wide ones included,
+    /// as a JSON array in version order
+    pub(crate) fn get_metadata_changelog(&self, index: u128) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let mut entries: Vec<ChangeEntry> = self.changelog_entries(None)?;
+        entries.extend(self.changelog_entries(Some(index))?);
+        entries.sort_by_key(|entry| entry.version);
+
+        let views: Vec<ChangeView> = entries.into_iter().map(Self::change_view).collect();
+
+        response.data = serde_json::to_vec(&views)
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to serialize metadata changelog"))?;
+        Ok(response)
+    }
+
+    /// Entries across every changelog and their approximate unpacked size in bytes. Each change adds
+    /// one entry and one metadata version, so the version bounds the entry count; entries
+    /// the ring buffers overwrote are included.
+    pub(crate) fn changelog_usage(&self) -> (u128, u128) {
+        let entry: ChangeEntry = ChangeEntry { index: Some(0), ..Default::default() };
+        let entry_bytes: u64 = bincode::serialized_size(&entry).unwrap_or(0);
+        let entries: u128 = self.recorded_metadata_version();
+        (entries, entries.saturating_mul(entry_bytes as u128))
+    }
+
+    /// Collection wide changelog
+    pub(crate) fn metadata_log(&self) -> EventLog {
+        self.changelog(None)
+    }
+
+    /// One logged change as the JSON `GetMetadataChangelog` returns for it
+    pub(crate) fn metadata_change_json(&self, payload: &[u8]) -> Result<serde_json::Value> {
+        let entry: ChangeEntry = bincode::deserialize(payload)
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to deserialize metadata change"))?;
+        serde_json::to_value(Self::change_view(entry))
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to serialize metadata change"))
+    }
+
+    fn change_view(entry: ChangeEntry) -> ChangeView {
+        ChangeView {
+            version: entry.version,
+            kind: entry.kind,
+            name: change_name(entry.kind),
+            index: entry.index,
+            detail: entry.detail,
+            height: entry.height,
+        }
+    }
+
+    fn changelog_entries(&self, index: Option<u128>) -> Result<Vec<ChangeEntry>> {
+        self.changelog(index).retained().iter()
+            .map(|payload| {
+                bincode::deserialize(payload)
+                    .map_err(|_| revert!(ERR_STORAGE, "Failed to deserialize metadata change"))
+            })
+            .collect()
+    }
+
+    /// Log of the collection, or of a single orbital when `index` is given
+    fn changelog(&self, index: Option<u128>) -> EventLog {
+        let base: StoragePointer = match index {
+            Some(index) => StoragePointer::from_keyword("/changelog/tokens/").select(&index.to_le_bytes().to_vec()),
+            None => StoragePointer::from_keyword("/changelog/collection"),
+        };
+        self.event_log(LOG_METADATA, base)
+    }
+}
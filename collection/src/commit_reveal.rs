@@ -0,0 +1,260 @@
+//! Trait entries are a fixed list and an orbital normally draws the entry at its own index,
+//! so the art of the next mint is known in advance. A revealed mint instead swaps its entry
+//! with one of the entries still ahead of it, picked by the header of the reveal block. The
+//! swaps keep the assignment a permutation, so unrevealed mints still draw unique art.
+//! Every reveal shuffles however late it lands, so holding a commit back cannot steer it
+//! onto a known entry. Only a transaction spending the ticket outpoint of the commit can
+//! reveal it.
+
+use metashrew_support::index_pointer::KeyValuePointer;
+use alkanes_runtime::runtime::AlkaneResponder;
+use alkanes_support::{id::AlkaneId, parcel::AlkaneTransfer, response::CallResponse};
+use serde::{Serialize, Deserialize};
+use anyhow::Result;
+use orbital_support::{revert, errors::{codes::*, Revert}, rng::Rng};
+use std::sync::Arc;
+
+use crate::{Collection, MINT_IN_STAGE_HEADER, counters};
+use crate::opcodes::StoragePointer;
+use crate::tickets::Ticket;
+
+/// Salt of the reveal stream, followed by the commitment ID
+pub(crate) const REVEAL_SALT: &[u8] = b"orbital/reveal/";
+
+/// Paid stage mint waiting for its reveal
+#[derive(Clone, Default, Debug, Serialize, Deserialize)]
+struct Commitment {
+    id: u128,
+    stage_id: u128,
+    /// Script hash identity of the committing minter, whose address allowance it holds
+    identity: [u8; 32],
+    /// Attributed outpoint of the commit, spent to reveal
+    ticket: Ticket,
+    committed_at: u64,
+    /// Escrow holding the payment until the reveal, 0 for free stages
+    escrow_id: u128,
+    /// Orbital minted by the reveal
+    index: Option<u128>,
+    /// Refunded instead of revealed
+    cancelled: bool,
+}
+
+/// Commitment as returned by `GetCommitment`
+#[derive(Serialize)]
+struct CommitmentView {
+    id: u128,
+    stage_id: u128,
+    identity: String,
+    ticket_txid: String,
+    ticket_vout: u32,
+    committed_at: u64,
+    reveal_from: u64,
+    escrow_id: u128,
+    index: Option<u128>,
+    cancelled: bool,
+}
+
+impl Collection {
+    /// First phase of a stage mint: every check of `MintInStage` runs and the allowance is
+    /// taken, the payment goes into escrow until the reveal. Returns the commitment ID.
+    pub(crate) fn commit_mint_in_stage(&self, stage_id: u128) -> Result<CallResponse> {
+        let (change, payment): (Vec<AlkaneTransfer>, Option<(AlkaneId, u128)>) = match self.reserve_stage_mint(stage_id, MINT_IN_STAGE_HEADER)? {
+            Some(reserved) => reserved,
+            None => {
+                // Declined without reverting so the hook failure stays recorded, the payment is returned
+                let context: alkanes_support::context::Context = self.context()?;
+                return Ok(CallResponse::forward(&context.incoming_alkanes));
+            }
+        };
+
+        let mut count_pointer: StoragePointer = self.commitment_count_pointer();
+        let id: u128 = count_pointer.get_value::<u128>().checked_add(1)
+            .ok_or_else(|| revert!(ERR_OVERFLOW, "commitment id overflow"))?;
+        count_pointer.set_value::<u128>(id);
+
+        let identity: [u8; 32] = self.minter_identity()?;
+        let ticket: Ticket = self.attributed_ticket()?;
+        let escrow_id: u128 = match payment {
+            Some((token, price)) => self.escrow_payment(ticket, &token, price, id)?,
+            None => 0,
+        };
+
+        let commitment: Commitment = Commitment {
+            id,
+            stage_id,
+            identity,
+            ticket,
+            committed_at: self.height(),
+            escrow_id,
+            index: None,
+            cancelled: false,
+        };
+        self.save_commitment(&commitment)?;
+
+        let mut response: CallResponse = CallResponse::default();
+        response.alkanes.0 = change;
+        response.data = id.to_le_bytes().to_vec();
+        Ok(response)
+    }
+
+    /// Second phase, in a later block: mint the committed orbital with its art drawn from
+    /// the entries not minted yet
+    pub(crate) fn reveal_mint(&self, commit_id: u128) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut commitment: Commitment = self.commitment(commit_id)?;
+
+        if commitment.index.is_some() || commitment.cancelled {
+            return Err(revert!(ERR_INVALID_STATE, "Commitment {} was already revealed or refunded", commit_id));
+        }
+
+        self.observe_ticket_spent(&commitment.ticket)?;
+
+        let height: u64 = self.height();
+        if height <= commitment.committed_at {
+            return Err(Revert::new(ERR_INVALID_STATE, "Reveal in a later block than the commit")
+                .field("height").expected(commitment.committed_at as u128 + 1).actual(height as u128).into());
+        }
+
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+        self.observe_mint_per_block(commitment.stage_id)?;
+        let (index, transfer) = self.create_mint_transfer()?;
+        response.alkanes.0.push(transfer);
+        self.shuffle_art(index, &mut self.reveal_rng(commit_id))?;
+
+        if commitment.escrow_id != 0 {
+            self.settle_escrow(commitment.escrow_id)?;
+        }
+
+        commitment.index = Some(index);
+        self.save_commitment(&commitment)?;
+
+        self.record_minter(index)?;
+        self.run_post_mint_hook(commitment.stage_id, index)?;
+        Ok(response)
+    }
+
+    /// Returns a commitment and the block it can be revealed from as JSON
+    pub(crate) fn get_commitment(&self, commit_id: u128) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let commitment: Commitment = self.commitment(commit_id)?;
+        let view: CommitmentView = CommitmentView {
+            id: commitment.id,
+            stage_id: commitment.stage_id,
+            identity: orbital_support::hex::encode(&commitment.identity),
+            ticket_txid: orbital_support::hex::encode(&commitment.ticket.txid),
+            ticket_vout: commitment.ticket.vout,
+            committed_at: commitment.committed_at,
+            reveal_from: commitment.committed_at.saturating_add(1),
+            escrow_id: commitment.escrow_id,
+            index: commitment.index,
+            cancelled: commitment.cancelled,
+        };
+
+        response.data = serde_json::to_vec(&view)
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to serialize commitment"))?;
+        Ok(response)
+    }
+
+    /// Give up an unrevealed commitment whose payment is refunded, handing its stage supply
+    /// and address allowance back. Returns the stage the commitment was made in.
+    pub(crate) fn cancel_commitment(&self, commit_id: u128) -> Result<u128> {
+        let mut commitment: Commitment = self.commitment(commit_id)?;
+        if commitment.index.is_some() || commitment.cancelled {
+            return Err(revert!(ERR_INVALID_STATE, "Commitment {} was already revealed or refunded", commit_id));
+        }
+
+        self.update_mint_stage(commitment.stage_id, |stage| {
+            stage.total_minted = stage.total_minted.saturating_sub(1);
+            Ok(())
+        })?;
+        self.release_address_mints(commitment.stage_id, &commitment.identity, 1);
+
+        commitment.cancelled = true;
+        self.save_commitment(&commitment)?;
+        Ok(commitment.stage_id)
+    }
+
+    /// Commitments made and the approximate bytes they occupy
+    pub(crate) fn commitments_usage(&self) -> (u128, u128) {
+        let commitment: Commitment = Commitment { index: Some(0), ..Default::default() };
+        let record_bytes: u64 = bincode::serialized_size(&commitment).unwrap_or(0);
+        let count: u128 = self.commitment_count_pointer().get_value::<u128>();
+        (count, count.saturating_mul(record_bytes as u128))
+    }
+
+    /// Trait entry an orbital renders, its own index unless a reveal swapped it
+    pub(crate) fn art_of(&self, index: u128) -> u128 {
+        let pointer: StoragePointer = self.art_pointer(index);
+        if pointer.get().is_empty() {
+            index
+        } else {
+            pointer.get_value::<u128>()
+        }
+    }
+
+    /// Swap the entry of a freshly minted index with one at or past it, so entries of
+    /// minted orbitals never move again
+    fn shuffle_art(&self, index: u128, rng: &mut Rng) -> Result<()> {
+        let remaining: u128 = self.svg_generator()?.trait_count().saturating_sub(index);
+        if remaining == 0 {
+            return Err(revert!(ERR_LIMIT_EXCEEDED, "No trait entries left to draw from"));
+        }
+
+        let target: u128 = index + rng.below(remaining);
+        let drawn: u128 = self.art_of(target);
+        self.art_pointer(target).set_value::<u128>(self.art_of(index));
+        self.art_pointer(index).set_value::<u128>(drawn);
+        counters::increment(&mut self.shuffled_pointer(), 1, u128::MAX, "shuffled")?;
+        Ok(())
+    }
+
+    /// Reveals so far, each drawing its entry from the shuffle seeded by the header of the
+    /// reveal block and salted with its commitment ID
+    pub(crate) fn shuffled_reveals(&self) -> u128 {
+        self.shuffled_pointer().get_value::<u128>()
+    }
+
+    /// Randomness of a reveal, seeded by the current block header and salted with the
+    /// commitment ID
+    fn reveal_rng(&self, commit_id: u128) -> Rng {
+        let block: Vec<u8> = self.block();
+        let mut salt: Vec<u8> = REVEAL_SALT.to_vec();
+        salt.extend_from_slice(&commit_id.to_le_bytes());
+        Rng::new(&block[..block.len().min(80)], &salt)
+    }
+
+    fn commitment(&self, commit_id: u128) -> Result<Commitment> {
+        let stored: Arc<Vec<u8>> = self.commitment_pointer(commit_id).get();
+        if stored.is_empty() {
+            return Err(revert!(ERR_NOT_FOUND, "Commitment {} not found", commit_id));
+        }
+
+        bincode::deserialize(&stored)
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to deserialize commitment"))
+    }
+
+    fn save_commitment(&self, commitment: &Commitment) -> Result<()> {
+        let serialized: Vec<u8> = bincode::serialize(commitment)
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to serialize commitment"))?;
+        self.commitment_pointer(commitment.id).set(Arc::new(serialized));
+        Ok(())
+    }
+
+    fn commitment_count_pointer(&self) -> StoragePointer {
+        StoragePointer::from_keyword("/commitments/count")
+    }
+
+    fn commitment_pointer(&self, commit_id: u128) -> StoragePointer {
+        StoragePointer::from_keyword("/commitments/").select(&commit_id.to_le_bytes().to_vec())
+    }
+
+    fn shuffled_pointer(&self) -> StoragePointer {
+        StoragePointer::from_keyword("/art/shuffled")
+    }
+
+    fn art_pointer(&self, index: u128) -> StoragePointer {
+        StoragePointer::from_keyword("/art/").select(&index.to_le_bytes().to_vec())
+    }
+}
@@ -0,0 +1,132 @@
+use metashrew_support::index_pointer::KeyValuePointer;
+use alkanes_runtime::runtime::AlkaneResponder;
+use alkanes_support::response::CallResponse;
+use serde::{Serialize, Deserialize};
+use anyhow::Result;
+use orbital_support::{revert, errors::{codes::*, Revert}};
+use std::sync::Arc;
+
+use crate::{Collection, decode_u128_to_string};
+use crate::opcodes::StoragePointer;
+
+/// Upper bound on jurisdiction tags per record
+const MAX_COMPLIANCE_TAGS: usize = 16;
+
+/// Inputs ahead of the tag list of `SetCollectionCompliance`
+const COLLECTION_COMPLIANCE_HEADER: usize = 4;
+
+/// Inputs ahead of the tag list of `SetTokenCompliance`
+const TOKEN_COMPLIANCE_HEADER: usize = 5;
+
+/// Licensing and jurisdiction references of the underlying art
+#[derive(Clone, Default, Debug, Serialize, Deserialize)]
+struct ComplianceRecord {
+    /// Jurisdiction tags such as ISO 3166 country codes
+    jurisdictions: Vec<String>,
+    /// Hex hash of the license document
+    license_hash: String,
+    set_at: u64,
+}
+
+/// Records applying to an orbital, returned by `GetCompliance`
+#[derive(Serialize)]
+struct ComplianceView {
+    collection: Option<ComplianceRecord>,
+    token: Option<ComplianceRecord>,
+}
+
+impl Collection {
+    /// Attach the collection wide record. The license hash is given as two little endian
+    /// u128 halves and `tag_count` packed string tags follow the fixed inputs.
+    /// A zero hash without tags removes the record.
+    pub(crate) fn set_collection_compliance(&self, license_low: u128, license_high: u128, tag_count: u128) -> Result<CallResponse> {
+        self.only_owner()?;
+        let context: alkanes_support::context::Context = self.context()?;
+        let response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let record: Option<ComplianceRecord> = self.read_compliance_record(&context.inputs, COLLECTION_COMPLIANCE_HEADER, license_low, license_high, tag_count)?;
+        self.set_compliance_record(self.collection_compliance_pointer(), record)?;
+        Ok(response)
+    }
+
+    /// Attach a record to a single orbital, inputs laid out as for the collection record
+    pub(crate) fn set_token_compliance(&self, index: u128, license_low: u128, license_high: u128, tag_count: u128) -> Result<CallResponse> {
+        self.only_owner()?;
+        let context: alkanes_support::context::Context = self.context()?;
+        let response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        self.lookup_instance(index)?;
+        let record: Option<ComplianceRecord> = self.read_compliance_record(&context.inputs, TOKEN_COMPLIANCE_HEADER, license_low, license_high, tag_count)?;
+        self.set_compliance_record(self.token_compliance_pointer(index), record)?;
+        Ok(response)
+    }
+
+    /// Returns the collection and orbital compliance records as JSON, null where unset
+    pub(crate) fn get_compliance(&self, index: u128) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let view: ComplianceView = ComplianceView {
+            collection: self.get_compliance_record(self.collection_compliance_pointer())?,
+            token: self.get_compliance_record(self.token_compliance_pointer(index))?,
+        };
+
+        response.data = serde_json::to_vec(&view)
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to serialize compliance records"))?;
+        Ok(response)
+    }
+
+    fn read_compliance_record(&self, inputs: &[u128], header: usize, license_low: u128, license_high: u128, tag_count: u128) -> Result<Option<ComplianceRecord>> {
+        let tag_count: usize = usize::try_from(tag_count).unwrap_or(usize::MAX);
+        if tag_count > MAX_COMPLIANCE_TAGS {
+            return Err(Revert::new(ERR_LIMIT_EXCEEDED, format!("At most {} jurisdiction tags", MAX_COMPLIANCE_TAGS))
+                .field("tag_count").expected(MAX_COMPLIANCE_TAGS as u128).actual(tag_count as u128).into());
+        }
+
+        if inputs.len() < header + tag_count {
+            return Err(revert!(ERR_INVALID_INPUT, "Jurisdiction tag list is truncated"));
+        }
+
+        if license_low == 0 && license_high == 0 && tag_count == 0 {
+            return Ok(None);
+        }
+
+        let mut license_hash: Vec<u8> = license_low.to_le_bytes().to_vec();
+        license_hash.extend_from_slice(&license_high.to_le_bytes());
+
+        Ok(Some(ComplianceRecord {
+            jurisdictions: inputs[header..header + tag_count].iter().map(|tag| decode_u128_to_string(*tag)).collect(),
+            license_hash: orbital_support::hex::encode(&license_hash),
+            set_at: self.height(),
+        }))
+    }
+
+    fn collection_compliance_pointer(&self) -> StoragePointer {
+        StoragePointer::from_keyword("/compliance/collection")
+    }
+
+    fn token_compliance_pointer(&self, index: u128) -> StoragePointer {
+        StoragePointer::from_keyword("/compliance/tokens/").select(&index.to_le_bytes().to_vec())
+    }
+
+    fn set_compliance_record(&self, mut pointer: StoragePointer, record: Option<ComplianceRecord>) -> Result<()> {
+        let serialized: Vec<u8> = match record {
+            Some(record) => bincode::serialize(&record)
+                .map_err(|_| revert!(ERR_STORAGE, "Failed to serialize compliance record"))?,
+            None => vec![],
+        };
+        pointer.set(Arc::new(serialized));
+        Ok(())
+    }
+
+    fn get_compliance_record(&self, pointer: StoragePointer) -> Result<Option<ComplianceRecord>> {
+        let stored: Arc<Vec<u8>> = pointer.get();
+        if stored.is_empty() {
+            return Ok(None);
+        }
+
+        let record: ComplianceRecord = bincode::deserialize(&stored)
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to deserialize compliance record"))?;
+        Ok(Some(record))
+    }
+}
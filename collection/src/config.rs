@@ -0,0 +1,192 @@
+use metashrew_support::index_pointer::KeyValuePointer;
+use alkanes_runtime::{runtime::AlkaneResponder, token::Token};
+use alkanes_support::response::CallResponse;
+use bitcoin::hashes::{sha256, Hash};
+use anyhow::Result;
+use orbital_support::{revert, errors::{codes::*, Revert}};
+use std::sync::Arc;
+
+use crate::{Collection, Stage, ORBITAL_INSTANCE_ID, SCHEDULE_BY_HEIGHT};
+use crate::opcodes::StoragePointer;
+
+/// Production deployment, every safety rail at its strict value
+pub(crate) const MODE_MAINNET: u8 = 0;
+
+/// Testnet or canary deployment with relaxed rails for quick iteration
+pub(crate) const MODE_CANARY: u8 = 1;
+
+/// Mode baked in by the `canary` feature, recorded at initialization
+const BUILD_MODE: u8 = if cfg!(feature = "canary") { MODE_CANARY } else { MODE_MAINNET };
+
+/// Furthest a stage may open ahead of initialization, about ten years of blocks
+const MAX_START_DELAY_BLOCKS: u64 = 10 * 52_560;
+
+/// Layout of the configuration preimage, first in it so clients recomputing the hash can
+/// tell which fields it covers. Version 2 added the pricing curve, rollover, token gate,
+/// accepted payments and sats pricing of every stage.
+const CONFIG_PREIMAGE_VERSION: u32 = 2;
+
+/// Append a length prefixed (u32 little endian) byte string
+fn push_bytes(preimage: &mut Vec<u8>, bytes: &[u8]) {
+    preimage.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+    preimage.extend_from_slice(bytes);
+}
+
+impl Collection {
+    /// Canonical encoding of the effective configuration, all integers little endian:
+    /// preimage version (u32), name, symbol (length prefixed), max supply (u128), premine
+    /// (u128), template (u128), render engine (u128), stage count (u32), then per stage id
+    /// (u128), price_per_item (u64), max_mints_per_address (u32), max_supply (u128),
+    /// schedule_mode (u8), start_block, end_block, start_time, end_time, soft_close_window,
+    /// soft_close_extension, max_extension (u64 each), free (u8), whitelist root (32 bytes),
+    /// start_price, end_price, decay_interval_blocks (u64 each), rollover (u8), gate_block,
+    /// gate_tx, gate_min_balance (u128 each), accepted payment count (u32) followed by block,
+    /// tx and price_per_item (u128 each) of every accepted payment, and priced_in_sats (u8).
+    fn config_preimage(&self, stages: &[Stage]) -> Vec<u8> {
+        let mut preimage: Vec<u8> = Vec::new();
+        preimage.extend_from_slice(&CONFIG_PREIMAGE_VERSION.to_le_bytes());
+        push_bytes(&mut preimage, self.name().as_bytes());
+        push_bytes(&mut preimage, self.symbol().as_bytes());
+        preimage.extend_from_slice(&self.max_supply().to_le_bytes());
+        preimage.extend_from_slice(&self.premine().to_le_bytes());
+        preimage.extend_from_slice(&ORBITAL_INSTANCE_ID.to_le_bytes());
+        preimage.extend_from_slice(&self.render_engine_id().to_le_bytes());
+
+        preimage.extend_from_slice(&(stages.len() as u32).to_le_bytes());
+        for stage in stages {
+            preimage.extend_from_slice(&stage.id.to_le_bytes());
+            preimage.extend_from_slice(&stage.price_per_item.to_le_bytes());
+            preimage.extend_from_slice(&stage.max_mints_per_address.to_le_bytes());
+            preimage.extend_from_slice(&stage.max_supply.to_le_bytes());
+            preimage.push(stage.schedule_mode);
+            for value in [
+                stage.start_block, stage.end_block, stage.start_time, stage.end_time,
+                stage.soft_close_window, stage.soft_close_extension, stage.max_extension,
+            ] {
+                preimage.extend_from_slice(&value.to_le_bytes());
+            }
+            preimage.push(stage.free as u8);
+            preimage.extend_from_slice(&stage.whitelist_root);
+
+            for value in [stage.start_price, stage.end_price, stage.decay_interval_blocks] {
+                preimage.extend_from_slice(&value.to_le_bytes());
+            }
+            preimage.push(stage.rollover as u8);
+            for value in [stage.gate_block, stage.gate_tx, stage.gate_min_balance] {
+                preimage.extend_from_slice(&value.to_le_bytes());
+            }
+            preimage.extend_from_slice(&(stage.accepted_payments.len() as u32).to_le_bytes());
+            for payment in &stage.accepted_payments {
+                for value in [payment.block, payment.tx, payment.price_per_item] {
+                    preimage.extend_from_slice(&value.to_le_bytes());
+                }
+            }
+            preimage.push(stage.priced_in_sats as u8);
+        }
+
+        preimage
+    }
+
+    /// Reject test parameters among the stages in effect at initialization, see
+    /// `observe_deployable_stage`
+    pub(crate) fn observe_deployable_config(&self) -> Result<()> {
+        for stage in self.get_mint_stages()? {
+            self.observe_deployable_stage(&stage, true)?;
+        }
+
+        Ok(())
+    }
+
+    /// Reject test parameters for a stage in mainnet builds: a window opening more than ten
+    /// years out or closing before it opens, a zero price not flagged free, and, when the
+    /// stage is new or `rescheduled`, a window opening in the past. Canary builds skip the
+    /// check.
+    pub(crate) fn observe_deployable_stage(&self, stage: &Stage, rescheduled: bool) -> Result<()> {
+        if BUILD_MODE != MODE_MAINNET {
+            return Ok(());
+        }
+
+        let height: u64 = self.height();
+        if stage.schedule_mode == SCHEDULE_BY_HEIGHT {
+            if rescheduled && stage.start_block < height {
+                return Err(Revert::new(ERR_INVALID_INPUT, format!("Stage {} starts in the past", stage.id))
+                    .field("start_block").expected(height as u128).actual(stage.start_block as u128).into());
+            }
+
+            if stage.start_block > height.saturating_add(MAX_START_DELAY_BLOCKS) {
+                return Err(Revert::new(ERR_INVALID_INPUT, format!("Stage {} starts more than ten years out", stage.id))
+                    .field("start_block").expected(height.saturating_add(MAX_START_DELAY_BLOCKS) as u128).actual(stage.start_block as u128).into());
+            }
+
+            if stage.end_block < stage.start_block {
+                return Err(revert!(ERR_INVALID_INPUT, "Stage {} ends before it starts", stage.id));
+            }
+        }
+
+        if stage.price_per_item == 0 && !stage.free {
+            return Err(revert!(ERR_INVALID_INPUT, "Stage {} has a zero price without being flagged free", stage.id));
+        }
+
+        Ok(())
+    }
+
+    /// Whether `Initialize` seeds the placeholder stages. Only canary builds do; mainnet
+    /// collections open with no stage until the owner sets real windows.
+    pub(crate) fn seeds_placeholder_stages(&self) -> bool {
+        BUILD_MODE == MODE_CANARY
+    }
+
+    /// Hash the configuration in effect and commit to it, called once at initialization
+    pub(crate) fn commit_config(&self) -> Result<[u8; 32]> {
+        let stages: Vec<Stage> = self.get_mint_stages()?;
+        let hash: [u8; 32] = sha256::Hash::hash(&self.config_preimage(&stages)).to_byte_array();
+        self.config_hash_pointer().set(Arc::new(hash.to_vec()));
+        Ok(hash)
+    }
+
+    /// Returns the 32 byte sha256 of the configuration committed at initialization
+    pub(crate) fn get_config_hash(&self) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let stored: Arc<Vec<u8>> = self.config_hash_pointer().get();
+        if stored.is_empty() {
+            return Err(revert!(ERR_INVALID_STATE, "Collection is not initialized"));
+        }
+
+        response.data = stored.as_ref().clone();
+        Ok(response)
+    }
+
+    /// Record the build mode, called once at initialization
+    pub(crate) fn commit_mode(&self) -> u8 {
+        self.mode_pointer().set(Arc::new(vec![BUILD_MODE]));
+        BUILD_MODE
+    }
+
+    /// Mode recorded at initialization, mainnet unless a canary build initialized it
+    pub(crate) fn deployment_mode(&self) -> u8 {
+        self.mode_pointer().get().first().copied().unwrap_or(MODE_MAINNET)
+    }
+
+    pub(crate) fn is_canary(&self) -> bool {
+        self.deployment_mode() == MODE_CANARY
+    }
+
+    /// Returns the deployment mode as u128, 0 mainnet and 1 canary
+    pub(crate) fn get_deployment_mode(&self) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        response.data = (self.deployment_mode() as u128).to_le_bytes().to_vec();
+        Ok(response)
+    }
+
+    fn mode_pointer(&self) -> StoragePointer {
+        StoragePointer::from_keyword("/mode")
+    }
+
+    fn config_hash_pointer(&self) -> StoragePointer {
+        StoragePointer::from_keyword("/config-hash")
+    }
+}
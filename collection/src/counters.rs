@@ -0,0 +1,104 @@
+//! Counters are updated with one read and one write, both at the moment of the update.
+//! Messages run one after another, but a message calls out to hooks, scorers and freshly
+//! deployed instances, and any of those can dispatch into the collection again. A count
+//! read before such a call and written back after it would drop the nested update, so
+//! callers never carry a counter value across a call.
+
+use metashrew_support::index_pointer::KeyValuePointer;
+use anyhow::Result;
+use orbital_support::{revert, errors::{codes::*, Revert}};
+
+use crate::opcodes::StoragePointer;
+
+/// Add `by` to the u128 counter under `pointer` and return the new value. Fails without
+/// writing when the result passes `limit`, reporting `field` as the offending input.
+pub(crate) fn increment(pointer: &mut StoragePointer, by: u128, limit: u128, field: &str) -> Result<u128> {
+    let current: u128 = pointer.get_value::<u128>();
+    let next: u128 = current.checked_add(by)
+        .ok_or_else(|| revert!(ERR_OVERFLOW, "{} overflow", field))?;
+
+    if next > limit {
+        return Err(Revert::new(ERR_LIMIT_EXCEEDED, format!("{} limit reached", field))
+            .field(field).expected(limit).actual(next).into());
+    }
+
+    pointer.set_value::<u128>(next);
+    Ok(next)
+}
+
+/// Take `by` off the u128 counter under `pointer`, stopping at zero, and return the new value
+pub(crate) fn decrement(pointer: &mut StoragePointer, by: u128) -> u128 {
+    let next: u128 = pointer.get_value::<u128>().saturating_sub(by);
+    pointer.set_value::<u128>(next);
+    next
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pointer(name: &str) -> StoragePointer {
+        StoragePointer::from_keyword("/test/counters/").select(&name.as_bytes().to_vec())
+    }
+
+    fn code(error: &anyhow::Error) -> u16 {
+        error.downcast_ref::<Revert>().map(|revert| revert.code).unwrap_or_default()
+    }
+
+    #[test]
+    fn increments_accumulate_on_the_same_pointer() {
+        let mut counter: StoragePointer = pointer("accumulate");
+        for expected in 1..=5 {
+            assert_eq!(increment(&mut counter, 1, u128::MAX, "count").unwrap(), expected);
+        }
+        assert_eq!(increment(&mut counter, 10, u128::MAX, "count").unwrap(), 15);
+        assert_eq!(counter.get_value::<u128>(), 15);
+    }
+
+    #[test]
+    fn nested_dispatches_keep_each_others_updates() {
+        // The outer message and a dispatch nested inside it each hold their own pointer
+        let mut outer: StoragePointer = pointer("nested");
+        increment(&mut outer, 1, 10, "count").unwrap();
+
+        let mut nested: StoragePointer = pointer("nested");
+        increment(&mut nested, 2, 10, "count").unwrap();
+        decrement(&mut nested, 1);
+
+        assert_eq!(increment(&mut outer, 1, 10, "count").unwrap(), 3);
+        assert_eq!(pointer("nested").get_value::<u128>(), 3);
+    }
+
+    #[test]
+    fn increments_stop_at_the_cap_without_writing() {
+        let mut counter: StoragePointer = pointer("cap");
+        assert_eq!(increment(&mut counter, 3, 3, "count").unwrap(), 3);
+
+        let error: anyhow::Error = increment(&mut counter, 1, 3, "count").unwrap_err();
+        assert_eq!(code(&error), ERR_LIMIT_EXCEEDED);
+        let revert: &Revert = error.downcast_ref::<Revert>().unwrap();
+        assert_eq!((revert.field.as_deref(), revert.expected, revert.actual), (Some("count"), Some(3), Some(4)));
+        assert_eq!(counter.get_value::<u128>(), 3);
+    }
+
+    #[test]
+    fn increments_report_overflow_without_writing() {
+        let mut counter: StoragePointer = pointer("overflow");
+        increment(&mut counter, u128::MAX, u128::MAX, "count").unwrap();
+
+        let error: anyhow::Error = increment(&mut counter, 1, u128::MAX, "count").unwrap_err();
+        assert_eq!(code(&error), ERR_OVERFLOW);
+        assert_eq!(counter.get_value::<u128>(), u128::MAX);
+    }
+
+    #[test]
+    fn decrements_saturate_at_zero() {
+        let mut counter: StoragePointer = pointer("saturate");
+        assert_eq!(decrement(&mut counter, 1), 0);
+
+        increment(&mut counter, 2, u128::MAX, "count").unwrap();
+        assert_eq!(decrement(&mut counter, 1), 1);
+        assert_eq!(decrement(&mut counter, 5), 0);
+        assert_eq!(counter.get_value::<u128>(), 0);
+    }
+}
@@ -0,0 +1,153 @@
+//! A stage can accept several alkanes on top of the payment token, each at its own fixed
+//! price per item. A mint pays in the first accepted alkane it brings enough of, in list
+//! order, and in the payment token at the stage price otherwise. Every currency is kept
+//! apart in the treasury, and the mints paid in each are counted per stage.
+
+use metashrew_support::index_pointer::KeyValuePointer;
+use alkanes_runtime::runtime::AlkaneResponder;
+use alkanes_support::{id::AlkaneId, parcel::AlkaneTransferParcel, response::CallResponse};
+use serde::{Serialize, Deserialize};
+use anyhow::Result;
+use orbital_support::{revert, errors::{codes::*, Revert}};
+
+use crate::{Collection, Stage, counters, encode_alkane_id};
+use crate::opcodes::StoragePointer;
+
+/// Alternative currencies a stage accepts at most
+const MAX_ACCEPTED_PAYMENTS: usize = 8;
+
+/// An alkane a stage accepts in place of the payment token
+#[derive(Clone, Default, Debug, Serialize, Deserialize)]
+pub(crate) struct AcceptedPayment {
+    pub block: u128,
+    pub tx: u128,
+    pub price_per_item: u128,
+}
+
+impl AcceptedPayment {
+    pub(crate) fn token(&self) -> AlkaneId {
+        AlkaneId { block: self.block, tx: self.tx }
+    }
+}
+
+/// Accepted currency of a stage as returned by `GetAcceptedPayments`
+#[derive(Serialize)]
+struct AcceptedPaymentView {
+    block: u128,
+    tx: u128,
+    price_per_item: u128,
+    /// Stage mints paid in the currency
+    mints: u128,
+    default: bool,
+}
+
+impl Collection {
+    /// Accept an alkane in a stage at `price_per_item`, replacing its price when already
+    /// accepted; a zero price stops accepting it
+    pub(crate) fn set_stage_accepted_payment(&self, stage_id: u128, block: u128, tx: u128, price_per_item: u128) -> Result<CallResponse> {
+        self.only_owner()?;
+        let context: alkanes_support::context::Context = self.context()?;
+        let response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let token: AlkaneId = AlkaneId { block, tx };
+        if block == 0 && tx == 0 {
+            return Err(revert!(ERR_INVALID_INPUT, "Accepted payments need an alkane ID"));
+        }
+
+        if token == context.myself {
+            return Err(revert!(ERR_INVALID_INPUT, "The collection auth token cannot pay for a stage"));
+        }
+
+        if self.payment_token()? == Some(token) {
+            return Err(revert!(ERR_INVALID_INPUT, "The payment token is priced by the stage itself"));
+        }
+
+        self.update_mint_stage(stage_id, |stage| {
+            let existing: Option<usize> = stage.accepted_payments.iter().position(|payment| payment.token() == token);
+            match (existing, price_per_item) {
+                (Some(position), 0) => {
+                    stage.accepted_payments.remove(position);
+                }
+                (Some(position), price) => stage.accepted_payments[position].price_per_item = price,
+                (None, 0) => return Err(revert!(ERR_NOT_FOUND, "Stage {} does not accept {}:{}", stage_id, block, tx)),
+                (None, price) => {
+                    if stage.accepted_payments.len() >= MAX_ACCEPTED_PAYMENTS {
+                        return Err(Revert::new(ERR_LIMIT_EXCEEDED, format!("Stages accept at most {} alternative currencies", MAX_ACCEPTED_PAYMENTS))
+                            .field("accepted_payments").expected(MAX_ACCEPTED_PAYMENTS as u128).actual(MAX_ACCEPTED_PAYMENTS as u128 + 1).into());
+                    }
+                    stage.accepted_payments.push(AcceptedPayment { block, tx, price_per_item: price });
+                }
+            }
+            Ok(())
+        })?;
+        Ok(response)
+    }
+
+    /// Returns the currencies a stage accepts, the payment token first, with their price
+    /// and the mints paid in each as JSON
+    pub(crate) fn get_accepted_payments(&self, stage_id: u128) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let stage: Stage = self.get_mint_stage(stage_id)?;
+        let mut views: Vec<AcceptedPaymentView> = Vec::new();
+        if let Some(token) = self.payment_token()? {
+            views.push(AcceptedPaymentView {
+                block: token.block,
+                tx: token.tx,
+                price_per_item: self.stage_quote(&stage, 1, self.height())?.0,
+                mints: self.currency_mints_pointer(stage_id, &token).get_value::<u128>(),
+                default: true,
+            });
+        }
+
+        for payment in &stage.accepted_payments {
+            views.push(AcceptedPaymentView {
+                block: payment.block,
+                tx: payment.tx,
+                price_per_item: payment.price_per_item,
+                mints: self.currency_mints_pointer(stage_id, &payment.token()).get_value::<u128>(),
+                default: false,
+            });
+        }
+
+        response.data = serde_json::to_vec(&views)
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to serialize accepted payments"))?;
+        Ok(response)
+    }
+
+    /// Currency and price one mint of a stage is paid with: the first accepted alkane the
+    /// minter sent enough of, else the payment token at `price`. None for a free mint.
+    pub(crate) fn stage_payment(&self, stage: &Stage, incoming: &AlkaneTransferParcel, price: u128) -> Result<Option<(AlkaneId, u128)>> {
+        for payment in &stage.accepted_payments {
+            let token: AlkaneId = payment.token();
+            let sent: u128 = incoming.0.iter()
+                .filter(|transfer| transfer.id == token)
+                .fold(0u128, |sum, transfer| sum.saturating_add(transfer.value));
+            if sent >= payment.price_per_item {
+                return Ok(Some((token, payment.price_per_item)));
+            }
+        }
+
+        if price == 0 {
+            return Ok(None);
+        }
+
+        let token: AlkaneId = self.payment_token()?
+            .ok_or_else(|| revert!(ERR_INVALID_STATE, "No payment token configured"))?;
+        Ok(Some((token, price)))
+    }
+
+    /// Count a stage mint against the currency it was paid in
+    pub(crate) fn record_currency_mint(&self, stage_id: u128, token: &AlkaneId) -> Result<()> {
+        counters::increment(&mut self.currency_mints_pointer(stage_id, token), 1, u128::MAX, "currency_mints")?;
+        Ok(())
+    }
+
+    fn currency_mints_pointer(&self, stage_id: u128, token: &AlkaneId) -> StoragePointer {
+        StoragePointer::from_keyword("/currencies/mints/")
+            .select(&stage_id.to_le_bytes().to_vec())
+            .keyword("/")
+            .select(&encode_alkane_id(token))
+    }
+}
@@ -0,0 +1,120 @@
+use metashrew_support::index_pointer::KeyValuePointer;
+use alkanes_runtime::runtime::AlkaneResponder;
+use alkanes_support::response::CallResponse;
+use serde::{Serialize, Deserialize};
+use anyhow::Result;
+use orbital_support::{revert, errors::{codes::*, Revert}};
+use std::sync::Arc;
+
+use crate::Collection;
+use crate::opcodes::StoragePointer;
+
+/// Inputs ahead of the description words: opcode, index, hash_low, hash_high, description_words
+const DERIVATIVE_HEADER: usize = 5;
+
+/// Upper bound on description words, 16 bytes each
+const MAX_DESCRIPTION_WORDS: usize = 8;
+
+/// Derivative work acknowledged by the holder of the orbital it derives from
+#[derive(Clone, Default, Debug, Serialize, Deserialize)]
+struct Derivative {
+    index: u128,
+    position: u128,
+    /// Hex hash of the derivative content
+    content_hash: String,
+    description: String,
+    /// Hex script hash identity of the registering holder
+    registered_by: String,
+    registered_at: u64,
+}
+
+impl Collection {
+    /// Holder registers a derivative of its orbital. The content hash is given as two
+    /// little endian u128 halves; `description_words` u128 words of packed UTF-8 follow
+    /// the fixed inputs. Returns the position of the derivative in the orbital's list.
+    pub(crate) fn register_derivative(&self, index: u128, hash_low: u128, hash_high: u128, description_words: u128) -> Result<CallResponse> {
+        self.only_holder(index)?;
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let words: usize = usize::try_from(description_words).unwrap_or(usize::MAX);
+        if words > MAX_DESCRIPTION_WORDS {
+            return Err(Revert::new(ERR_LIMIT_EXCEEDED, format!("Descriptions hold at most {} words", MAX_DESCRIPTION_WORDS))
+                .field("description_words").expected(MAX_DESCRIPTION_WORDS as u128).actual(description_words).into());
+        }
+
+        if context.inputs.len() < DERIVATIVE_HEADER + words {
+            return Err(revert!(ERR_INVALID_INPUT, "Derivative description is truncated"));
+        }
+
+        if hash_low == 0 && hash_high == 0 {
+            return Err(revert!(ERR_INVALID_INPUT, "Derivative content hash must be set"));
+        }
+
+        let bytes: Vec<u8> = context.inputs[DERIVATIVE_HEADER..DERIVATIVE_HEADER + words].iter()
+            .flat_map(|word| word.to_le_bytes())
+            .take_while(|byte| *byte != 0)
+            .collect();
+        let description: String = String::from_utf8(bytes)
+            .map_err(|_| revert!(ERR_INVALID_INPUT, "Derivative description is not UTF-8"))?;
+
+        let mut content_hash: Vec<u8> = hash_low.to_le_bytes().to_vec();
+        content_hash.extend_from_slice(&hash_high.to_le_bytes());
+
+        let mut count_pointer: StoragePointer = self.derivative_count_pointer(index);
+        let position: u128 = count_pointer.get_value::<u128>();
+        let derivative: Derivative = Derivative {
+            index,
+            position,
+            content_hash: orbital_support::hex::encode(&content_hash),
+            description,
+            registered_by: orbital_support::hex::encode(&self.minter_identity()?),
+            registered_at: self.height(),
+        };
+
+        let serialized: Vec<u8> = bincode::serialize(&derivative)
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to serialize derivative"))?;
+        self.derivative_pointer(index, position).set(Arc::new(serialized));
+        count_pointer.set_value::<u128>(position + 1);
+
+        response.data = position.to_le_bytes().to_vec();
+        Ok(response)
+    }
+
+    /// Returns the number of derivatives registered against an orbital as u128
+    pub(crate) fn get_derivative_count(&self, index: u128) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        response.data = self.derivative_count_pointer(index).get_value::<u128>().to_le_bytes().to_vec();
+        Ok(response)
+    }
+
+    /// Returns a registered derivative as JSON
+    pub(crate) fn get_derivative(&self, index: u128, position: u128) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let stored: Arc<Vec<u8>> = self.derivative_pointer(index, position).get();
+        if stored.is_empty() {
+            return Err(revert!(ERR_NOT_FOUND, "Orbital {} has no derivative {}", index, position));
+        }
+
+        let derivative: Derivative = bincode::deserialize(&stored)
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to deserialize derivative"))?;
+        response.data = serde_json::to_vec(&derivative)
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to serialize derivative"))?;
+        Ok(response)
+    }
+
+    fn derivative_count_pointer(&self, index: u128) -> StoragePointer {
+        StoragePointer::from_keyword("/derivatives/count/").select(&index.to_le_bytes().to_vec())
+    }
+
+    fn derivative_pointer(&self, index: u128, position: u128) -> StoragePointer {
+        StoragePointer::from_keyword("/derivatives/")
+            .select(&index.to_le_bytes().to_vec())
+            .keyword("/")
+            .select(&position.to_le_bytes().to_vec())
+    }
+}
@@ -0,0 +1,83 @@
+use alkanes_runtime::runtime::AlkaneResponder;
+use alkanes_support::response::CallResponse;
+use serde::Serialize;
+use anyhow::Result;
+use orbital_support::{revert, errors::codes::*};
+
+use crate::{Collection, cache};
+
+/// Footprint of one subsystem. Bytes count stored values only, keys and the
+/// per-entry overhead of the index are left out.
+#[derive(Serialize)]
+struct Usage {
+    entries: u128,
+    bytes: u128,
+}
+
+impl Usage {
+    fn new((entries, bytes): (u128, u128)) -> Self {
+        Usage { entries, bytes }
+    }
+}
+
+/// Storage footprint by subsystem as returned by `GetStorageUsage`
+#[derive(Serialize)]
+struct StorageUsage {
+    instances: Usage,
+    stages: Usage,
+    whitelists: Usage,
+    logs: Usage,
+    receipts: Usage,
+    commitments: Usage,
+    render_cache: Usage,
+    thumbnail_cache: Usage,
+    total_bytes: u128,
+}
+
+impl Collection {
+    /// Returns the approximate storage footprint of the larger subsystems as JSON, to judge
+    /// when pruning or a lighter configuration pays off
+    pub(crate) fn get_storage_usage(&self) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let instance_count: u128 = self.instances_count();
+
+        let mut stage_bytes: u128 = 0;
+        let mut whitelisted: u128 = 0;
+        let stage_count: u128 = self.stage_count_pointer().get_value::<u128>();
+        for slot in 0..stage_count {
+            stage_bytes += self.stage_slot_pointer(slot).get().len() as u128;
+            if self.read_stage_slot(slot)?.whitelist_root != [0; 32] {
+                whitelisted += 1;
+            }
+        }
+
+        let instances: Usage = Usage::new((instance_count, instance_count.saturating_mul(32)));
+        let stages: Usage = Usage::new((stage_count, stage_bytes));
+        let logs: Usage = Usage::new(self.changelog_usage());
+        let receipts: Usage = Usage::new(self.receipts_usage());
+        let commitments: Usage = Usage::new(self.commitments_usage());
+        let render_cache: Usage = Usage::new(self.cache_usage(cache::CACHE_RENDERS));
+        let thumbnail_cache: Usage = Usage::new(self.cache_usage(cache::CACHE_THUMBNAILS));
+        // Whitelist roots live inside the stage records and are already counted there
+        let total_bytes: u128 = [&instances, &stages, &logs, &receipts, &commitments, &render_cache, &thumbnail_cache].iter()
+            .fold(0u128, |total, usage| total.saturating_add(usage.bytes));
+
+        let usage: StorageUsage = StorageUsage {
+            instances,
+            stages,
+            whitelists: Usage::new((whitelisted, whitelisted * 32)),
+            logs,
+            receipts,
+            commitments,
+            render_cache,
+            thumbnail_cache,
+            total_bytes,
+        };
+
+        response.data = serde_json::to_vec(&usage)
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to serialize storage usage"))?;
+        Ok(response)
+    }
+}
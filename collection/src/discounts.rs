@@ -0,0 +1,158 @@
+//! Quantity discounts on batches: the owner keeps a table of tiers, each taking a share off
+//! the price of batches of at least its size. `MintBatch` and stage quotes for several
+//! orbitals get the discount of the largest tier they reach, single mints pay the full price.
+
+use metashrew_support::index_pointer::KeyValuePointer;
+use alkanes_runtime::runtime::AlkaneResponder;
+use alkanes_support::response::CallResponse;
+use serde::{Serialize, Deserialize};
+use anyhow::Result;
+use orbital_support::{revert, errors::{codes::*, Revert}};
+use std::sync::Arc;
+
+use crate::Collection;
+use crate::opcodes::StoragePointer;
+
+/// Tiers the table holds at most
+const MAX_DISCOUNT_TIERS: usize = 8;
+
+/// Basis points of the full price
+const BPS_DENOMINATOR: u128 = 10_000;
+
+/// Discount of batches holding at least `min_count` orbitals
+#[derive(Clone, Default, Debug, Serialize, Deserialize)]
+struct DiscountTier {
+    min_count: u128,
+    bps: u128,
+}
+
+/// Take the discount of the largest tier `count` reaches off `price`, rounding the discount down
+fn apply_discount(tiers: &[DiscountTier], count: u128, price: u128) -> Result<u128> {
+    let bps: u128 = tiers.iter()
+        .rev()
+        .find(|tier| tier.min_count <= count)
+        .map_or(0, |tier| tier.bps);
+
+    let discount: u128 = price.checked_mul(bps)
+        .ok_or_else(|| revert!(ERR_OVERFLOW, "price overflow"))? / BPS_DENOMINATOR;
+    Ok(price - discount)
+}
+
+impl Collection {
+    /// Take `bps` basis points off batches of at least `min_count` orbitals, replacing the
+    /// discount of an existing tier of that size; zero basis points removes the tier
+    pub(crate) fn set_discount_tier(&self, min_count: u128, bps: u128) -> Result<CallResponse> {
+        self.only_owner()?;
+        let context: alkanes_support::context::Context = self.context()?;
+        let response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        if min_count < 2 {
+            return Err(Revert::new(ERR_INVALID_INPUT, "Discount tiers start at batches of 2")
+                .field("min_count").expected(2).actual(min_count).into());
+        }
+
+        if bps >= BPS_DENOMINATOR {
+            return Err(Revert::new(ERR_INVALID_INPUT, "Discounts stay below the full price")
+                .field("bps").expected(BPS_DENOMINATOR - 1).actual(bps).into());
+        }
+
+        let mut tiers: Vec<DiscountTier> = self.discount_tiers()?;
+        let existing: Option<usize> = tiers.iter().position(|tier| tier.min_count == min_count);
+        match (existing, bps) {
+            (Some(position), 0) => {
+                tiers.remove(position);
+            }
+            (Some(position), bps) => tiers[position].bps = bps,
+            (None, 0) => return Err(revert!(ERR_NOT_FOUND, "No discount tier for batches of {}", min_count)),
+            (None, bps) => {
+                if tiers.len() >= MAX_DISCOUNT_TIERS {
+                    return Err(Revert::new(ERR_LIMIT_EXCEEDED, format!("At most {} discount tiers", MAX_DISCOUNT_TIERS))
+                        .field("tiers").expected(MAX_DISCOUNT_TIERS as u128).actual(MAX_DISCOUNT_TIERS as u128 + 1).into());
+                }
+                tiers.push(DiscountTier { min_count, bps });
+                tiers.sort_by_key(|tier| tier.min_count);
+            }
+        }
+
+        self.save_discount_tiers(&tiers)?;
+        Ok(response)
+    }
+
+    /// Returns the discount tiers by batch size as JSON
+    pub(crate) fn get_discount_tiers(&self) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        response.data = serde_json::to_vec(&self.discount_tiers()?)
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to serialize discount tiers"))?;
+        Ok(response)
+    }
+
+    /// Price of a batch of `count` orbitals costing `price` in full, less the discount of
+    /// the largest tier it reaches
+    pub(crate) fn discounted_batch_price(&self, count: u128, price: u128) -> Result<u128> {
+        apply_discount(&self.discount_tiers()?, count, price)
+    }
+
+    fn discount_tiers(&self) -> Result<Vec<DiscountTier>> {
+        let stored: Arc<Vec<u8>> = self.discount_tiers_pointer().get();
+        if stored.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        bincode::deserialize(&stored)
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to deserialize discount tiers"))
+    }
+
+    fn save_discount_tiers(&self, tiers: &[DiscountTier]) -> Result<()> {
+        let serialized: Vec<u8> = bincode::serialize(tiers)
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to serialize discount tiers"))?;
+        self.discount_tiers_pointer().set(Arc::new(serialized));
+        Ok(())
+    }
+
+    fn discount_tiers_pointer(&self) -> StoragePointer {
+        StoragePointer::from_keyword("/discounts/tiers")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tiers() -> Vec<DiscountTier> {
+        vec![
+            DiscountTier { min_count: 5, bps: 1_000 },
+            DiscountTier { min_count: 10, bps: 2_500 },
+        ]
+    }
+
+    #[test]
+    fn batches_below_the_first_tier_pay_in_full() {
+        assert_eq!(apply_discount(&tiers(), 1, 100).unwrap(), 100);
+        assert_eq!(apply_discount(&tiers(), 4, 400).unwrap(), 400);
+    }
+
+    #[test]
+    fn batches_get_the_largest_tier_they_reach() {
+        assert_eq!(apply_discount(&tiers(), 5, 500).unwrap(), 450);
+        assert_eq!(apply_discount(&tiers(), 9, 900).unwrap(), 810);
+        assert_eq!(apply_discount(&tiers(), 10, 1_000).unwrap(), 750);
+    }
+
+    #[test]
+    fn discounts_round_down() {
+        assert_eq!(apply_discount(&tiers(), 5, 9).unwrap(), 9);
+        assert_eq!(apply_discount(&tiers(), 5, 19).unwrap(), 18);
+    }
+
+    #[test]
+    fn empty_table_pays_in_full() {
+        assert_eq!(apply_discount(&[], 50, 5_000).unwrap(), 5_000);
+    }
+
+    #[test]
+    fn discount_overflow_reverts() {
+        assert!(apply_discount(&tiers(), 5, u128::MAX).is_err());
+    }
+}
@@ -0,0 +1,180 @@
+//! Payments taken ahead of a mint are held here instead of the treasury until the mint
+//! happens. Should the mint become impossible first, the payer claims the payment back by
+//! spending the ticket outpoint of the transaction that paid.
+
+use metashrew_support::index_pointer::KeyValuePointer;
+use alkanes_runtime::runtime::AlkaneResponder;
+use alkanes_support::{id::AlkaneId, parcel::AlkaneTransfer, response::CallResponse};
+use serde::{Serialize, Deserialize};
+use anyhow::Result;
+use orbital_support::{revert, errors::codes::*};
+use std::sync::Arc;
+
+use crate::Collection;
+use crate::opcodes::StoragePointer;
+use crate::tickets::Ticket;
+
+/// Held for a mint that has not happened yet
+const ESCROW_HELD: u8 = 0;
+
+/// Released to the treasury by the mint it paid for
+const ESCROW_SETTLED: u8 = 1;
+
+/// Returned to the payer
+const ESCROW_REFUNDED: u8 = 2;
+
+fn escrow_status_name(status: u8) -> &'static str {
+    match status {
+        ESCROW_HELD => "held",
+        ESCROW_SETTLED => "settled",
+        ESCROW_REFUNDED => "refunded",
+        _ => "unknown",
+    }
+}
+
+#[derive(Clone, Default, Debug, Serialize, Deserialize)]
+struct Escrow {
+    id: u128,
+    /// Attributed outpoint of the paying transaction, spent to claim the refund
+    ticket: Ticket,
+    token_block: u128,
+    token_tx: u128,
+    amount: u128,
+    /// Commitment the payment was taken for
+    commit_id: u128,
+    status: u8,
+    opened_at: u64,
+}
+
+/// Escrow as returned by `GetEscrow`
+#[derive(Serialize)]
+struct EscrowView {
+    id: u128,
+    ticket_txid: String,
+    ticket_vout: u32,
+    token_block: u128,
+    token_tx: u128,
+    amount: u128,
+    commit_id: u128,
+    status: &'static str,
+    refundable: bool,
+    opened_at: u64,
+}
+
+impl Escrow {
+    fn token(&self) -> AlkaneId {
+        AlkaneId { block: self.token_block, tx: self.token_tx }
+    }
+}
+
+impl Collection {
+    /// Move a payment in `token` the treasury just took for a commitment into escrow,
+    /// returning the escrow ID
+    pub(crate) fn escrow_payment(&self, ticket: Ticket, token: &AlkaneId, amount: u128, commit_id: u128) -> Result<u128> {
+        self.debit_treasury(token, amount)?;
+
+        let mut count_pointer: StoragePointer = self.escrow_count_pointer();
+        let id: u128 = count_pointer.get_value::<u128>().checked_add(1)
+            .ok_or_else(|| revert!(ERR_OVERFLOW, "escrow id overflow"))?;
+        count_pointer.set_value::<u128>(id);
+
+        self.save_escrow(&Escrow {
+            id,
+            ticket,
+            token_block: token.block,
+            token_tx: token.tx,
+            amount,
+            commit_id,
+            status: ESCROW_HELD,
+            opened_at: self.height(),
+        })?;
+        Ok(id)
+    }
+
+    /// Release a held payment to the treasury once its mint happened
+    pub(crate) fn settle_escrow(&self, escrow_id: u128) -> Result<()> {
+        let mut escrow: Escrow = self.escrow(escrow_id)?;
+        if escrow.status != ESCROW_HELD {
+            return Err(revert!(ERR_INVALID_STATE, "Escrow {} is {}", escrow_id, escrow_status_name(escrow.status)));
+        }
+
+        self.credit_treasury(&escrow.token(), escrow.amount)?;
+        escrow.status = ESCROW_SETTLED;
+        self.save_escrow(&escrow)
+    }
+
+    /// Payer recovers a held payment once no further orbital can be minted. The commitment it
+    /// paid for is cancelled and its mint allowance handed back.
+    pub(crate) fn claim_refund(&self, escrow_id: u128) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let mut escrow: Escrow = self.escrow(escrow_id)?;
+        if escrow.status != ESCROW_HELD {
+            return Err(revert!(ERR_INVALID_STATE, "Escrow {} is {}", escrow_id, escrow_status_name(escrow.status)));
+        }
+
+        self.observe_ticket_spent(&escrow.ticket)?;
+
+        if self.can_mint_more() {
+            return Err(revert!(ERR_INVALID_STATE, "Escrow {} can still be settled by its mint", escrow_id));
+        }
+
+        let stage_id: u128 = self.cancel_commitment(escrow.commit_id)?;
+        self.refund_stage_revenue(stage_id, &escrow.token(), escrow.amount)?;
+        escrow.status = ESCROW_REFUNDED;
+        self.save_escrow(&escrow)?;
+
+        response.alkanes.0.push(AlkaneTransfer { id: escrow.token(), value: escrow.amount });
+        Ok(response)
+    }
+
+    /// Returns an escrow and whether it can be refunded now as JSON
+    pub(crate) fn get_escrow(&self, escrow_id: u128) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let escrow: Escrow = self.escrow(escrow_id)?;
+        let view: EscrowView = EscrowView {
+            id: escrow.id,
+            ticket_txid: orbital_support::hex::encode(&escrow.ticket.txid),
+            ticket_vout: escrow.ticket.vout,
+            token_block: escrow.token_block,
+            token_tx: escrow.token_tx,
+            amount: escrow.amount,
+            commit_id: escrow.commit_id,
+            status: escrow_status_name(escrow.status),
+            refundable: escrow.status == ESCROW_HELD && !self.can_mint_more(),
+            opened_at: escrow.opened_at,
+        };
+
+        response.data = serde_json::to_vec(&view)
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to serialize escrow"))?;
+        Ok(response)
+    }
+
+    fn escrow(&self, escrow_id: u128) -> Result<Escrow> {
+        let stored: Arc<Vec<u8>> = self.escrow_pointer(escrow_id).get();
+        if stored.is_empty() {
+            return Err(revert!(ERR_NOT_FOUND, "Escrow {} not found", escrow_id));
+        }
+
+        bincode::deserialize(&stored)
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to deserialize escrow"))
+    }
+
+    fn save_escrow(&self, escrow: &Escrow) -> Result<()> {
+        let serialized: Vec<u8> = bincode::serialize(escrow)
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to serialize escrow"))?;
+        self.escrow_pointer(escrow.id).set(Arc::new(serialized));
+        Ok(())
+    }
+
+    fn escrow_count_pointer(&self) -> StoragePointer {
+        StoragePointer::from_keyword("/escrow/count")
+    }
+
+    fn escrow_pointer(&self, escrow_id: u128) -> StoragePointer {
+        StoragePointer::from_keyword("/escrow/").select(&escrow_id.to_le_bytes().to_vec())
+    }
+}
@@ -0,0 +1,377 @@
+//! Append-only logs kept as ring buffers. Every event gets the next sequence number of its
+//! log and lands in slot `sequence % retention`, overwriting the event `retention` places
+//! before it. Slots store the sequence next to the payload, so an overwritten or moved
+//! event reads as gone instead of as a different one. Changing the retention of a log
+//! moves its slots, older events may read as gone afterwards. Slots are written packed
+//! with `compress::pack`; slots written before packing are still read as they are.
+
+use metashrew_support::index_pointer::KeyValuePointer;
+use alkanes_runtime::runtime::AlkaneResponder;
+use alkanes_support::response::CallResponse;
+use serde::{Serialize, Deserialize};
+use anyhow::Result;
+use orbital_support::{revert, compress, errors::{codes::*, Revert}, pagination::MAX_PAGE_SIZE};
+use std::sync::Arc;
+
+use crate::{Collection, counters, opcodes};
+use crate::opcodes::StoragePointer;
+
+/// Orbitals minted
+pub(crate) const LOG_MINT: u128 = 1;
+
+/// Mutating owner calls
+pub(crate) const LOG_ADMIN: u128 = 2;
+
+/// Metadata changelogs, the collection log and the log of every orbital
+pub(crate) const LOG_METADATA: u128 = 3;
+
+/// Rentals and loans of orbitals escrowed in the collection
+pub(crate) const LOG_MARKET: u128 = 4;
+
+/// Orbitals staked and unstaked
+pub(crate) const LOG_STAKING: u128 = 5;
+
+pub(crate) fn log_name(log: u128) -> Option<&'static str> {
+    match log {
+        LOG_MINT => Some("mint"),
+        LOG_ADMIN => Some("admin"),
+        LOG_METADATA => Some("metadata"),
+        LOG_MARKET => Some("market"),
+        LOG_STAKING => Some("staking"),
+        _ => None,
+    }
+}
+
+/// Kinds of market events, stable across releases since indexers key on them
+pub(crate) const MARKET_RENTAL_OFFERED: u8 = 1;
+pub(crate) const MARKET_RENTED: u8 = 2;
+pub(crate) const MARKET_RENTAL_ENDED: u8 = 3;
+pub(crate) const MARKET_LOAN_FUNDED: u8 = 4;
+pub(crate) const MARKET_LOAN_REPAID: u8 = 5;
+pub(crate) const MARKET_LOAN_DEFAULTED: u8 = 6;
+
+fn market_action_name(action: u8) -> &'static str {
+    match action {
+        MARKET_RENTAL_OFFERED => "rental-offered",
+        MARKET_RENTED => "rented",
+        MARKET_RENTAL_ENDED => "rental-ended",
+        MARKET_LOAN_FUNDED => "loan-funded",
+        MARKET_LOAN_REPAID => "loan-repaid",
+        MARKET_LOAN_DEFAULTED => "loan-defaulted",
+        _ => "unknown",
+    }
+}
+
+pub(crate) const STAKING_STAKED: u8 = 1;
+pub(crate) const STAKING_UNSTAKED: u8 = 2;
+
+fn staking_action_name(action: u8) -> &'static str {
+    match action {
+        STAKING_STAKED => "staked",
+        STAKING_UNSTAKED => "unstaked",
+        _ => "unknown",
+    }
+}
+
+/// Events a log keeps until the owner sets a retention
+const DEFAULT_RETENTION: u128 = 1024;
+
+/// Upper bound on the retention of a log
+const MAX_RETENTION: u128 = 65536;
+
+/// One ring buffer of events
+pub(crate) struct EventLog {
+    base: StoragePointer,
+    retention: u128,
+}
+
+impl EventLog {
+    /// Sequence number of the next event, equal to the number of events ever appended
+    pub(crate) fn next_sequence(&self) -> u128 {
+        self.base.keyword("/next").get_value::<u128>()
+    }
+
+    /// Oldest sequence number still retained
+    pub(crate) fn first_retained(&self) -> u128 {
+        self.next_sequence().saturating_sub(self.retention)
+    }
+
+    pub(crate) fn append(&self, payload: &[u8]) -> Result<u128> {
+        let mut next_pointer: StoragePointer = self.base.keyword("/next");
+        let sequence: u128 = next_pointer.get_value::<u128>();
+
+        let mut entry: Vec<u8> = Vec::with_capacity(16 + payload.len());
+        entry.extend_from_slice(&sequence.to_le_bytes());
+        entry.extend_from_slice(payload);
+        self.slot_pointer(sequence).set(Arc::new(compress::pack(&entry)));
+
+        counters::increment(&mut next_pointer, 1, u128::MAX, "event_sequence")?;
+        Ok(sequence)
+    }
+
+    /// Payload of an event, None once it left the buffer
+    pub(crate) fn get(&self, sequence: u128) -> Option<Vec<u8>> {
+        if sequence < self.first_retained() || sequence >= self.next_sequence() {
+            return None;
+        }
+
+        let packed: Arc<Vec<u8>> = self.slot_pointer(sequence).get();
+        let entry: Vec<u8> = if packed.is_empty() {
+            self.legacy_slot_pointer(sequence).get().as_ref().clone()
+        } else {
+            compress::unpack(&packed).ok()?
+        };
+        if entry.len() < 16 || entry[..16] != sequence.to_le_bytes() {
+            return None;
+        }
+
+        Some(entry[16..].to_vec())
+    }
+
+    /// Payloads of every retained event, oldest first
+    pub(crate) fn retained(&self) -> Vec<Vec<u8>> {
+        (self.first_retained()..self.next_sequence())
+            .filter_map(|sequence| self.get(sequence))
+            .collect()
+    }
+
+    fn slot_pointer(&self, sequence: u128) -> StoragePointer {
+        self.base.keyword("/packed-slots/").select(&(sequence % self.retention).to_le_bytes().to_vec())
+    }
+
+    /// Slot as written before slots were packed
+    fn legacy_slot_pointer(&self, sequence: u128) -> StoragePointer {
+        self.base.keyword("/slots/").select(&(sequence % self.retention).to_le_bytes().to_vec())
+    }
+}
+
+#[derive(Clone, Default, Debug, Serialize, Deserialize)]
+pub(crate) struct MintEvent {
+    pub index: u128,
+    pub block: u128,
+    pub tx: u128,
+    pub height: u64,
+}
+
+#[derive(Clone, Default, Debug, Serialize, Deserialize)]
+pub(crate) struct AdminEvent {
+    pub opcode: u128,
+    pub height: u64,
+}
+
+#[derive(Clone, Default, Debug, Serialize, Deserialize)]
+struct MarketEvent {
+    action: u8,
+    index: u128,
+    /// Price, principal or repayment the action moved, 0 when none
+    amount: u128,
+    height: u64,
+}
+
+#[derive(Serialize)]
+struct MarketEventView {
+    action: u8,
+    name: &'static str,
+    index: u128,
+    amount: u128,
+    height: u64,
+}
+
+#[derive(Clone, Default, Debug, Serialize, Deserialize)]
+struct StakingEvent {
+    action: u8,
+    index: u128,
+    height: u64,
+}
+
+#[derive(Serialize)]
+struct StakingEventView {
+    action: u8,
+    name: &'static str,
+    index: u128,
+    height: u64,
+}
+
+/// Event as returned by `GetEvents`
+#[derive(Serialize)]
+struct EventItem {
+    sequence: u128,
+    event: serde_json::Value,
+}
+
+/// Slice of one log as returned by `GetEvents`
+#[derive(Serialize)]
+struct EventsPage {
+    topic: &'static str,
+    events: Vec<EventItem>,
+    /// Oldest sequence still retained, earlier ones were overwritten
+    first_retained: u128,
+    /// Sequence to resume from, None once the newest event was returned
+    next: Option<u128>,
+}
+
+impl Collection {
+    /// Set how many events a log keeps
+    pub(crate) fn set_event_retention(&self, log: u128, retention: u128) -> Result<CallResponse> {
+        self.only_owner()?;
+        let context: alkanes_support::context::Context = self.context()?;
+        let response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        if log_name(log).is_none() {
+            return Err(revert!(ERR_NOT_FOUND, "Unknown event log {}", log));
+        }
+
+        if retention == 0 || retention > MAX_RETENTION {
+            return Err(Revert::new(ERR_INVALID_INPUT, format!("Logs retain 1 to {} events", MAX_RETENTION))
+                .field("retention").expected(MAX_RETENTION).actual(retention).into());
+        }
+
+        self.retention_pointer(log).set_value::<u128>(retention);
+        Ok(response)
+    }
+
+    /// Returns up to `count` events of one topic from sequence `start` on as JSON. The
+    /// metadata topic is the collection wide changelog, changes to single orbitals are
+    /// served by `GetMetadataChangelog`.
+    pub(crate) fn get_events(&self, topic: u128, start: u128, count: u128) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let name: &'static str = log_name(topic)
+            .ok_or_else(|| revert!(ERR_NOT_FOUND, "Unknown event topic {}", topic))?;
+
+        if count == 0 || count > MAX_PAGE_SIZE {
+            return Err(Revert::new(ERR_LIMIT_EXCEEDED, format!("Event queries return 1 to {} events", MAX_PAGE_SIZE))
+                .field("count").expected(MAX_PAGE_SIZE).actual(count).into());
+        }
+
+        let log: EventLog = self.topic_log(topic);
+        let first_retained: u128 = log.first_retained();
+        let next_sequence: u128 = log.next_sequence();
+        let from: u128 = start.max(first_retained).min(next_sequence);
+        let to: u128 = from.saturating_add(count).min(next_sequence);
+
+        let mut events: Vec<EventItem> = Vec::new();
+        for sequence in from..to {
+            if let Some(payload) = log.get(sequence) {
+                events.push(EventItem { sequence, event: self.event_json(topic, &payload)? });
+            }
+        }
+
+        let page: EventsPage = EventsPage {
+            topic: name,
+            events,
+            first_retained,
+            next: if to < next_sequence { Some(to) } else { None },
+        };
+
+        response.data = serde_json::to_vec(&page)
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to serialize events"))?;
+        Ok(response)
+    }
+
+    /// Ring buffer under `base` with the retention configured for `log`
+    pub(crate) fn event_log(&self, log: u128, base: StoragePointer) -> EventLog {
+        let retention: u128 = match self.retention_pointer(log).get_value::<u128>() {
+            0 => DEFAULT_RETENTION,
+            retention => retention,
+        };
+        EventLog { base, retention }
+    }
+
+    pub(crate) fn mint_log(&self) -> EventLog {
+        self.event_log(LOG_MINT, StoragePointer::from_keyword("/events/mint"))
+    }
+
+    pub(crate) fn admin_log(&self) -> EventLog {
+        self.event_log(LOG_ADMIN, StoragePointer::from_keyword("/events/admin"))
+    }
+
+    pub(crate) fn record_mint_event(&self, event: &MintEvent) -> Result<()> {
+        let serialized: Vec<u8> = bincode::serialize(event)
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to serialize mint event"))?;
+        let sequence: u128 = self.mint_log().append(&serialized)?;
+        self.notify_listeners(LOG_MINT, sequence)
+    }
+
+    /// Log an authorized owner call, views are left out
+    pub(crate) fn record_admin_event(&self, opcode: u128) -> Result<()> {
+        if opcodes::is_read_only(opcode) {
+            return Ok(());
+        }
+
+        let serialized: Vec<u8> = bincode::serialize(&AdminEvent { opcode, height: self.height() })
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to serialize admin event"))?;
+        let sequence: u128 = self.admin_log().append(&serialized)?;
+        self.notify_listeners(LOG_ADMIN, sequence)
+    }
+
+    pub(crate) fn record_market_event(&self, action: u8, index: u128, amount: u128) -> Result<()> {
+        let serialized: Vec<u8> = bincode::serialize(&MarketEvent { action, index, amount, height: self.height() })
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to serialize market event"))?;
+        let sequence: u128 = self.market_log().append(&serialized)?;
+        self.notify_listeners(LOG_MARKET, sequence)
+    }
+
+    pub(crate) fn record_staking_event(&self, action: u8, index: u128) -> Result<()> {
+        let serialized: Vec<u8> = bincode::serialize(&StakingEvent { action, index, height: self.height() })
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to serialize staking event"))?;
+        let sequence: u128 = self.staking_log().append(&serialized)?;
+        self.notify_listeners(LOG_STAKING, sequence)
+    }
+
+    fn market_log(&self) -> EventLog {
+        self.event_log(LOG_MARKET, StoragePointer::from_keyword("/events/market"))
+    }
+
+    fn staking_log(&self) -> EventLog {
+        self.event_log(LOG_STAKING, StoragePointer::from_keyword("/events/staking"))
+    }
+
+    fn topic_log(&self, topic: u128) -> EventLog {
+        match topic {
+            LOG_MINT => self.mint_log(),
+            LOG_ADMIN => self.admin_log(),
+            LOG_METADATA => self.metadata_log(),
+            LOG_MARKET => self.market_log(),
+            _ => self.staking_log(),
+        }
+    }
+
+    fn event_json(&self, topic: u128, payload: &[u8]) -> Result<serde_json::Value> {
+        let value: Result<serde_json::Value, serde_json::Error> = match topic {
+            LOG_MINT => serde_json::to_value(Self::decode_event::<MintEvent>(payload)?),
+            LOG_ADMIN => serde_json::to_value(Self::decode_event::<AdminEvent>(payload)?),
+            LOG_METADATA => return self.metadata_change_json(payload),
+            LOG_MARKET => {
+                let event: MarketEvent = Self::decode_event(payload)?;
+                serde_json::to_value(MarketEventView {
+                    action: event.action,
+                    name: market_action_name(event.action),
+                    index: event.index,
+                    amount: event.amount,
+                    height: event.height,
+                })
+            }
+            _ => {
+                let event: StakingEvent = Self::decode_event(payload)?;
+                serde_json::to_value(StakingEventView {
+                    action: event.action,
+                    name: staking_action_name(event.action),
+                    index: event.index,
+                    height: event.height,
+                })
+            }
+        };
+        value.map_err(|_| revert!(ERR_STORAGE, "Failed to serialize event"))
+    }
+
+    fn decode_event<T: serde::de::DeserializeOwned>(payload: &[u8]) -> Result<T> {
+        bincode::deserialize(payload)
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to deserialize event"))
+    }
+
+    fn retention_pointer(&self, log: u128) -> StoragePointer {
+        StoragePointer::from_keyword("/events/retention/").select(&log.to_le_bytes().to_vec())
+    }
+}
@@ -0,0 +1,138 @@
+use metashrew_support::index_pointer::KeyValuePointer;
+use alkanes_runtime::runtime::AlkaneResponder;
+use alkanes_support::response::CallResponse;
+use serde::{Serialize, Deserialize};
+use anyhow::Result;
+use orbital_support::{revert, errors::codes::*};
+use std::sync::Arc;
+
+use crate::Collection;
+use crate::opcodes::StoragePointer;
+
+/// Upper bound on curated entries, keeps `GetFeatured` within fuel
+const MAX_FEATURED: usize = 100;
+
+/// Secondary listing curated by the collection owner
+#[derive(Clone, Copy, Default, Debug, PartialEq, Serialize, Deserialize)]
+struct FeaturedListing {
+    venue_block: u128,
+    venue_tx: u128,
+    listing_id: u128,
+    featured_at: u64,
+}
+
+/// Index curated by the collection owner
+#[derive(Clone, Copy, Default, Debug, PartialEq, Serialize, Deserialize)]
+struct FeaturedIndex {
+    index: u128,
+    featured_at: u64,
+}
+
+#[derive(Clone, Default, Debug, Serialize, Deserialize)]
+struct Featured {
+    listings: Vec<FeaturedListing>,
+    indices: Vec<FeaturedIndex>,
+}
+
+impl Collection {
+    /// Flag a listing on a marketplace contract as featured
+    pub(crate) fn feature_listing(&self, venue_block: u128, venue_tx: u128, listing_id: u128) -> Result<CallResponse> {
+        self.only_owner()?;
+        let context: alkanes_support::context::Context = self.context()?;
+        let response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let mut featured: Featured = self.get_featured_entries()?;
+        featured.listings.retain(|entry| {
+            !(entry.venue_block == venue_block && entry.venue_tx == venue_tx && entry.listing_id == listing_id)
+        });
+        featured.listings.push(FeaturedListing {
+            venue_block,
+            venue_tx,
+            listing_id,
+            featured_at: self.height(),
+        });
+
+        self.set_featured_entries(&featured)?;
+        Ok(response)
+    }
+
+    pub(crate) fn unfeature_listing(&self, venue_block: u128, venue_tx: u128, listing_id: u128) -> Result<CallResponse> {
+        self.only_owner()?;
+        let context: alkanes_support::context::Context = self.context()?;
+        let response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let mut featured: Featured = self.get_featured_entries()?;
+        featured.listings.retain(|entry| {
+            !(entry.venue_block == venue_block && entry.venue_tx == venue_tx && entry.listing_id == listing_id)
+        });
+
+        self.set_featured_entries(&featured)?;
+        Ok(response)
+    }
+
+    /// Flag an index as featured regardless of where it is listed
+    pub(crate) fn feature_index(&self, index: u128) -> Result<CallResponse> {
+        self.only_owner()?;
+        let context: alkanes_support::context::Context = self.context()?;
+        let response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        self.lookup_instance(index)?;
+
+        let mut featured: Featured = self.get_featured_entries()?;
+        featured.indices.retain(|entry| entry.index != index);
+        featured.indices.push(FeaturedIndex {
+            index,
+            featured_at: self.height(),
+        });
+
+        self.set_featured_entries(&featured)?;
+        Ok(response)
+    }
+
+    pub(crate) fn unfeature_index(&self, index: u128) -> Result<CallResponse> {
+        self.only_owner()?;
+        let context: alkanes_support::context::Context = self.context()?;
+        let response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let mut featured: Featured = self.get_featured_entries()?;
+        featured.indices.retain(|entry| entry.index != index);
+
+        self.set_featured_entries(&featured)?;
+        Ok(response)
+    }
+
+    /// Returns the featured listings and indices as JSON
+    pub(crate) fn get_featured(&self) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let featured: Featured = self.get_featured_entries()?;
+        response.data = serde_json::to_vec(&featured)
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to serialize featured entries"))?;
+        Ok(response)
+    }
+
+    fn featured_pointer(&self) -> StoragePointer {
+        StoragePointer::from_keyword("/featured")
+    }
+
+    fn get_featured_entries(&self) -> Result<Featured> {
+        let stored: Arc<Vec<u8>> = self.featured_pointer().get();
+        if stored.is_empty() {
+            return Ok(Featured::default());
+        }
+
+        bincode::deserialize(&stored).map_err(|_| revert!(ERR_STORAGE, "Failed to deserialize featured entries"))
+    }
+
+    fn set_featured_entries(&self, featured: &Featured) -> Result<()> {
+        if featured.listings.len() + featured.indices.len() > MAX_FEATURED {
+            return Err(revert!(ERR_LIMIT_EXCEEDED, "At most {} entries can be featured", MAX_FEATURED));
+        }
+
+        let serialized: Vec<u8> = bincode::serialize(featured)
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to serialize featured entries"))?;
+        self.featured_pointer().set(Arc::new(serialized));
+        Ok(())
+    }
+}
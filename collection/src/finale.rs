@@ -0,0 +1,247 @@
+//! The mint that sells out the supply is recorded with its minter and the ticket outpoint
+//! of the transaction it landed in. Spending that ticket claims the finale the owner
+//! configured: a bonus orbital minted past the supply, drawing the next
+//! trait entry, or a payout in the payment token from the treasury. The finale is fixed
+//! once the supply sells out.
+
+use metashrew_support::index_pointer::KeyValuePointer;
+use alkanes_runtime::{runtime::AlkaneResponder, token::Token};
+use alkanes_support::{id::AlkaneId, parcel::AlkaneTransfer, response::CallResponse};
+use serde::{Serialize, Deserialize};
+use anyhow::Result;
+use orbital_support::{revert, errors::{codes::*, Revert}};
+use std::sync::Arc;
+
+use crate::{Collection, events};
+use crate::opcodes::StoragePointer;
+use crate::tickets::Ticket;
+
+/// Selling out triggers nothing
+const FINALE_NONE: u8 = 0;
+
+/// The final minter claims one orbital past the supply
+const FINALE_BONUS_ORBITAL: u8 = 1;
+
+/// The final minter claims `amount` of the payment token from the treasury
+const FINALE_TREASURY_BONUS: u8 = 2;
+
+/// Finale configuration and, once the supply sold out, the mint that did
+#[derive(Clone, Default, Debug, Serialize, Deserialize)]
+struct Finale {
+    action: u8,
+    amount: u128,
+    sold_out: bool,
+    final_index: u128,
+    sold_out_block: u64,
+    winner: [u8; 32],
+    /// Attributed outpoint of the final mint, spent to claim the finale
+    winner_ticket: Ticket,
+    claimed: bool,
+}
+
+/// Finale state as returned by `GetFinale`
+#[derive(Serialize)]
+struct FinaleView {
+    action: u8,
+    amount: u128,
+    sold_out: bool,
+    final_index: Option<u128>,
+    sold_out_block: Option<u64>,
+    winner: Option<String>,
+    winner_ticket_txid: Option<String>,
+    winner_ticket_vout: Option<u32>,
+    claimed: bool,
+}
+
+impl Collection {
+    /// Configure what the final minter can claim, `amount` only counts for a treasury bonus
+    pub(crate) fn set_finale(&self, action: u128, amount: u128) -> Result<CallResponse> {
+        self.only_owner()?;
+        let context: alkanes_support::context::Context = self.context()?;
+        let response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let mut finale: Finale = self.finale()?;
+        if finale.sold_out {
+            return Err(revert!(ERR_INVALID_STATE, "The finale is fixed once the supply sold out"));
+        }
+
+        match u8::try_from(action).unwrap_or(u8::MAX) {
+            FINALE_NONE => finale.amount = 0,
+            FINALE_BONUS_ORBITAL => {
+                let entries: u128 = self.svg_generator()?.trait_count();
+                if entries <= self.supply_cap() {
+                    return Err(Revert::new(ERR_INVALID_INPUT, "A bonus orbital needs a trait entry past the supply")
+                        .field("traits").expected(self.supply_cap() + 1).actual(entries).into());
+                }
+                finale.amount = 0;
+            }
+            FINALE_TREASURY_BONUS => {
+                if amount == 0 {
+                    return Err(revert!(ERR_INVALID_INPUT, "A treasury bonus pays a positive amount"));
+                }
+                if self.payment_token()?.is_none() {
+                    return Err(revert!(ERR_INVALID_STATE, "No payment token configured"));
+                }
+                finale.amount = amount;
+            }
+            _ => return Err(Revert::new(ERR_INVALID_INPUT, "Unknown finale action")
+                .field("action").expected(FINALE_TREASURY_BONUS as u128).actual(action).into()),
+        }
+
+        finale.action = action as u8;
+        self.save_finale(&finale)?;
+        Ok(response)
+    }
+
+    /// Hand the final minter its finale, from a transaction spending the winner ticket
+    pub(crate) fn claim_finale(&self) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let mut finale: Finale = self.finale()?;
+        if !finale.sold_out {
+            return Err(revert!(ERR_INVALID_STATE, "The supply has not sold out"));
+        }
+
+        if finale.action == FINALE_NONE {
+            return Err(revert!(ERR_NOT_FOUND, "No finale configured"));
+        }
+
+        if finale.claimed {
+            return Err(revert!(ERR_INVALID_STATE, "The finale was already claimed"));
+        }
+
+        self.observe_ticket_spent(&finale.winner_ticket)?;
+
+        finale.claimed = true;
+        self.save_finale(&finale)?;
+
+        match finale.action {
+            FINALE_BONUS_ORBITAL => response.alkanes.0.push(self.mint_bonus_orbital()?),
+            _ => {
+                let token: AlkaneId = self.payment_token()?
+                    .ok_or_else(|| revert!(ERR_INVALID_STATE, "No payment token configured"))?;
+                self.debit_treasury(&token, finale.amount)?;
+                response.alkanes.0.push(AlkaneTransfer { id: token, value: finale.amount });
+            }
+        }
+
+        Ok(response)
+    }
+
+    /// Returns the orbitals left to mint as u128, 0 once minting is closed
+    pub(crate) fn get_remaining_supply(&self) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let remaining: u128 = if self.is_mint_closed() { 0 } else { self.remaining_supply() };
+        response.data = remaining.to_le_bytes().to_vec();
+        Ok(response)
+    }
+
+    /// Returns the finale configuration and the mint that sold out the supply as JSON
+    pub(crate) fn get_finale(&self) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let finale: Finale = self.finale()?;
+        let view: FinaleView = FinaleView {
+            action: finale.action,
+            amount: finale.amount,
+            sold_out: finale.sold_out,
+            final_index: finale.sold_out.then_some(finale.final_index),
+            sold_out_block: finale.sold_out.then_some(finale.sold_out_block),
+            winner: finale.sold_out.then(|| orbital_support::hex::encode(&finale.winner)),
+            winner_ticket_txid: finale.sold_out.then(|| orbital_support::hex::encode(&finale.winner_ticket.txid)),
+            winner_ticket_vout: finale.sold_out.then_some(finale.winner_ticket.vout),
+            claimed: finale.claimed,
+        };
+
+        response.data = serde_json::to_vec(&view)
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to serialize finale"))?;
+        Ok(response)
+    }
+
+    /// Record the mint of orbital `index` as the final one when it used up the supply
+    pub(crate) fn record_finale(&self, index: u128) -> Result<()> {
+        if self.remaining_supply() > 0 {
+            return Ok(());
+        }
+
+        let mut finale: Finale = self.finale()?;
+        if finale.sold_out {
+            return Ok(());
+        }
+
+        finale.sold_out = true;
+        finale.final_index = index;
+        finale.sold_out_block = self.height();
+        finale.winner = self.minter_identity()?;
+        finale.winner_ticket = self.attributed_ticket()?;
+        self.save_finale(&finale)
+    }
+
+    /// Attribute the recorded final mint to the identity and ticket it was minted for, when
+    /// the queue minted it on behalf of a waitlisted minter
+    pub(crate) fn assign_finale_winner(&self, index: u128, winner: &[u8; 32], ticket: &Ticket) -> Result<()> {
+        let mut finale: Finale = self.finale()?;
+        if !finale.sold_out || finale.final_index != index {
+            return Ok(());
+        }
+
+        finale.winner = *winner;
+        finale.winner_ticket = *ticket;
+        self.save_finale(&finale)
+    }
+
+    /// Orbitals the stages, public mint, vouchers and premine can still mint, less those
+    /// held by queued mints
+    fn remaining_supply(&self) -> u128 {
+        self.supply_cap()
+            .saturating_sub(self.instances_count())
+            .saturating_sub(self.waitlist_pending())
+    }
+
+    fn supply_cap(&self) -> u128 {
+        self.max_mints().min(self.max_supply())
+    }
+
+    fn mint_bonus_orbital(&self) -> Result<AlkaneTransfer> {
+        let index: u128 = self.instances_count();
+        if index >= self.svg_generator()?.trait_count() {
+            return Err(revert!(ERR_LIMIT_EXCEEDED, "No trait entry left for a bonus orbital"));
+        }
+
+        let (orbital_id, transfer) = self.deploy_instance(index, &self.name(), &self.symbol())?;
+        self.add_instance(&orbital_id)?;
+        self.record_mint_event(&events::MintEvent {
+            index,
+            block: orbital_id.block,
+            tx: orbital_id.tx,
+            height: self.height(),
+        })?;
+
+        Ok(transfer)
+    }
+
+    fn finale(&self) -> Result<Finale> {
+        let stored: Arc<Vec<u8>> = self.finale_pointer().get();
+        if stored.is_empty() {
+            return Ok(Finale::default());
+        }
+
+        bincode::deserialize(&stored)
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to deserialize finale"))
+    }
+
+    fn save_finale(&self, finale: &Finale) -> Result<()> {
+        let serialized: Vec<u8> = bincode::serialize(finale)
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to serialize finale"))?;
+        self.finale_pointer().set(Arc::new(serialized));
+        Ok(())
+    }
+
+    fn finale_pointer(&self) -> StoragePointer {
+        StoragePointer::from_keyword("/finale")
+    }
+}
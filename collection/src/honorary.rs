@@ -0,0 +1,191 @@
+//! Honorary orbitals show art uploaded for them instead of what the engine draws from their
+//! trait entry, while keeping the traits of the entry. The SVG is uploaded in chunks and
+//! only takes effect once sealed, which holds the whole document to the
+//! `orbital_support::svg` policy; uploads that violate it are rejected rather than stored
+//! where wallets would inline them.
+
+use metashrew_support::index_pointer::KeyValuePointer;
+use alkanes_runtime::runtime::AlkaneResponder;
+use alkanes_support::response::CallResponse;
+use anyhow::Result;
+use orbital_support::{revert, errors::{codes::*, Revert}, svg};
+use std::sync::Arc;
+
+use crate::{Collection, changelog};
+use crate::opcodes::StoragePointer;
+use crate::render_engine::RenderEngine;
+use crate::svg_generator::{RenderConfig, SvgGenerator};
+use crate::unlockables::read_words;
+
+/// Inputs ahead of the chunk bytes of `UploadHonoraryChunk`: opcode, index, chunk, length
+const HONORARY_CHUNK_HEADER: usize = 4;
+
+/// Largest chunk one upload carries, in bytes
+const MAX_HONORARY_CHUNK_BYTES: usize = 1024;
+
+/// Chunks an upload spans at most, enough for a document at the policy size cap
+const MAX_HONORARY_CHUNKS: u128 = (svg::MAX_SVG_BYTES / MAX_HONORARY_CHUNK_BYTES) as u128;
+
+/// Serves the sealed art of an honorary orbital, with the traits of its entry
+struct HonoraryEngine {
+    art: String,
+    engine: Box<dyn RenderEngine>,
+}
+
+impl RenderEngine for HonoraryEngine {
+    fn content_type(&self) -> &'static str {
+        "image/svg+xml"
+    }
+
+    fn trait_types(&self) -> &'static [&'static str] {
+        self.engine.trait_types()
+    }
+
+    fn traits(&self, art: u128) -> Result<Vec<String>> {
+        self.engine.traits(art)
+    }
+
+    fn render(&self, _art: u128, config: &RenderConfig) -> Result<String> {
+        Ok(if config.preview { SvgGenerator::watermark(&self.art) } else { self.art.clone() })
+    }
+
+    fn thumbnail(&self, art: u128, config: &RenderConfig) -> Result<String> {
+        self.render(art, config)
+    }
+}
+
+impl Collection {
+    /// Upload chunk `chunk` of the honorary art of orbital `index`, `length` bytes packed
+    /// into the words after the fixed inputs. Chunks are uploaded in order; uploading
+    /// chunk 0 again starts the document over.
+    pub(crate) fn upload_honorary_chunk(&self, index: u128, chunk: u128, length: u128) -> Result<CallResponse> {
+        self.only_owner()?;
+        let context: alkanes_support::context::Context = self.context()?;
+        let response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        self.observe_honorary_mutable(index)?;
+        if chunk >= MAX_HONORARY_CHUNKS {
+            return Err(Revert::new(ERR_LIMIT_EXCEEDED, format!("Honorary art holds at most {} chunks", MAX_HONORARY_CHUNKS))
+                .field("chunk").expected(MAX_HONORARY_CHUNKS - 1).actual(chunk).into());
+        }
+
+        let length: usize = usize::try_from(length).unwrap_or(usize::MAX);
+        if length == 0 || length > MAX_HONORARY_CHUNK_BYTES {
+            return Err(Revert::new(ERR_INVALID_INPUT, format!("Chunks hold 1 to {} bytes", MAX_HONORARY_CHUNK_BYTES))
+                .field("length").expected(MAX_HONORARY_CHUNK_BYTES as u128).actual(length as u128).into());
+        }
+
+        let words: usize = length.div_ceil(16);
+        if context.inputs.len() < HONORARY_CHUNK_HEADER + words {
+            return Err(revert!(ERR_INVALID_INPUT, "Honorary art chunk is truncated"));
+        }
+
+        let mut bytes: Vec<u8> = read_words(&context.inputs, HONORARY_CHUNK_HEADER, words);
+        bytes.truncate(length);
+
+        let mut count: StoragePointer = self.honorary_chunk_count_pointer(index);
+        if chunk == 0 {
+            count.set_value::<u128>(0);
+        }
+
+        if chunk > count.get_value::<u128>() {
+            return Err(Revert::new(ERR_INVALID_INPUT, "Chunks are uploaded in order")
+                .field("chunk").expected(count.get_value::<u128>()).actual(chunk).into());
+        }
+
+        self.honorary_chunk_pointer(index, chunk).set(Arc::new(bytes));
+        if chunk == count.get_value::<u128>() {
+            count.set_value::<u128>(chunk + 1);
+        }
+
+        Ok(response)
+    }
+
+    /// Serve the uploaded chunks as the art of orbital `index`, once they form a UTF-8
+    /// document passing the SVG policy
+    pub(crate) fn seal_honorary_art(&self, index: u128) -> Result<CallResponse> {
+        self.only_owner()?;
+        let context: alkanes_support::context::Context = self.context()?;
+        let response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        self.observe_honorary_mutable(index)?;
+        let chunks: u128 = self.honorary_chunk_count_pointer(index).get_value::<u128>();
+        if chunks == 0 {
+            return Err(revert!(ERR_NOT_FOUND, "No honorary art uploaded for orbital {}", index));
+        }
+
+        let bytes: Vec<u8> = (0..chunks)
+            .flat_map(|chunk| self.honorary_chunk_pointer(index, chunk).get().as_ref().clone())
+            .collect();
+        let art: String = String::from_utf8(bytes)
+            .map_err(|_| revert!(ERR_INVALID_INPUT, "Honorary art is not UTF-8"))?;
+        svg::validate(&art)?;
+
+        self.honorary_art_pointer(index).set(Arc::new(art.into_bytes()));
+        self.honorary_chunk_count_pointer(index).set_value::<u128>(0);
+        self.record_metadata_change(changelog::CHANGE_HONORARY_ART, Some(index), 1)?;
+        Ok(response)
+    }
+
+    /// Draw orbital `index` from its trait entry again
+    pub(crate) fn clear_honorary_art(&self, index: u128) -> Result<CallResponse> {
+        self.only_owner()?;
+        let context: alkanes_support::context::Context = self.context()?;
+        let response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        self.observe_honorary_mutable(index)?;
+        if !self.has_honorary_art(index) {
+            return Err(revert!(ERR_NOT_FOUND, "Orbital {} has no honorary art", index));
+        }
+
+        self.honorary_art_pointer(index).set(Arc::new(vec![]));
+        self.record_metadata_change(changelog::CHANGE_HONORARY_ART, Some(index), 0)?;
+        Ok(response)
+    }
+
+    /// Engine drawing orbital `index` from a trait table version, its honorary art if it
+    /// has some
+    pub(crate) fn orbital_engine(&self, index: u128, version: u128) -> Result<Box<dyn RenderEngine>> {
+        let engine: Box<dyn RenderEngine> = self.render_engine_at(version)?;
+        let stored: Arc<Vec<u8>> = self.honorary_art_pointer(index).get();
+        if stored.is_empty() {
+            return Ok(engine);
+        }
+
+        let art: String = String::from_utf8(stored.as_ref().clone())
+            .map_err(|_| revert!(ERR_STORAGE, "Honorary art of orbital {} is not UTF-8", index))?;
+        Ok(Box::new(HonoraryEngine { art, engine }))
+    }
+
+    pub(crate) fn has_honorary_art(&self, index: u128) -> bool {
+        !self.honorary_art_pointer(index).get().is_empty()
+    }
+
+    fn observe_honorary_mutable(&self, index: u128) -> Result<()> {
+        if self.is_metadata_frozen() {
+            return Err(revert!(ERR_INVALID_STATE, "Honorary art cannot change once the metadata is frozen"));
+        }
+
+        if index >= self.max_supply() {
+            return Err(Revert::new(ERR_NOT_FOUND, "No orbital under this index")
+                .field("index").expected(self.max_supply().saturating_sub(1)).actual(index).into());
+        }
+
+        Ok(())
+    }
+
+    fn honorary_art_pointer(&self, index: u128) -> StoragePointer {
+        StoragePointer::from_keyword("/honorary/art/").select(&index.to_le_bytes().to_vec())
+    }
+
+    fn honorary_chunk_count_pointer(&self, index: u128) -> StoragePointer {
+        StoragePointer::from_keyword("/honorary/chunk-count/").select(&index.to_le_bytes().to_vec())
+    }
+
+    fn honorary_chunk_pointer(&self, index: u128, chunk: u128) -> StoragePointer {
+        StoragePointer::from_keyword("/honorary/chunks/")
+            .select(&index.to_le_bytes().to_vec())
+            .keyword("/")
+            .select(&chunk.to_le_bytes().to_vec())
+    }
+}
@@ -0,0 +1,267 @@
+use metashrew_support::index_pointer::KeyValuePointer;
+use alkanes_runtime::runtime::AlkaneResponder;
+use alkanes_support::{cellpack::Cellpack, id::AlkaneId, parcel::AlkaneTransferParcel, response::CallResponse};
+use serde::{Serialize, Deserialize};
+use anyhow::Result;
+use orbital_support::{revert, errors::codes::*};
+use std::sync::Arc;
+
+use crate::Collection;
+use crate::opcodes::StoragePointer;
+
+/// Hook slots
+const HOOK_PRE_MINT: u128 = 0;
+const HOOK_POST_MINT: u128 = 1;
+
+/// Opcodes hook alkanes implement, both take (stage, index, payer low, payer high).
+/// The pre-mint hook is staticcalled and answers a u128, zero vetoes the mint.
+/// The post-mint hook is called once the orbital is minted.
+const HOOK_ON_PRE_MINT: u128 = 60;
+const HOOK_ON_POST_MINT: u128 = 61;
+
+/// A failing hook is skipped and the mint proceeds
+const HOOK_FAIL_OPEN: u8 = 0;
+
+/// A failing pre-mint hook declines the mint, a failing post-mint hook reverts it
+const HOOK_FAIL_CLOSED: u8 = 1;
+
+/// An installed hook with its failure policy and circuit breaker state
+#[derive(Clone, Default, Debug, Serialize, Deserialize)]
+struct HookConfig {
+    block: u128,
+    tx: u128,
+    policy: u8,
+    /// Consecutive failures that trip the breaker, zero never trips
+    breaker_threshold: u32,
+    consecutive_failures: u32,
+    total_failures: u128,
+    tripped: bool,
+    tripped_at: u64,
+    last_failure_block: u64,
+}
+
+/// Everything external that participates in the mint path
+#[derive(Serialize)]
+struct HooksReport {
+    pre_mint: HookConfig,
+    post_mint: HookConfig,
+    /// Zero when no scorer is configured
+    sybil_scorer_block: u128,
+    sybil_scorer_tx: u128,
+    sybil_threshold: u128,
+}
+
+impl HookConfig {
+    fn hook(&self) -> AlkaneId {
+        AlkaneId { block: self.block, tx: self.tx }
+    }
+
+    fn is_installed(&self) -> bool {
+        self.block != 0 || self.tx != 0
+    }
+
+    /// Record a failure, tripping the breaker once the threshold is reached
+    fn record_failure(&mut self, height: u64) {
+        self.consecutive_failures = self.consecutive_failures.saturating_add(1);
+        self.total_failures = self.total_failures.saturating_add(1);
+        self.last_failure_block = height;
+
+        if !self.tripped && self.breaker_threshold > 0 && self.consecutive_failures >= self.breaker_threshold {
+            self.tripped = true;
+            self.tripped_at = height;
+        }
+    }
+}
+
+impl Collection {
+    /// Install the alkane invoked in a hook slot, a zero ID clears the slot.
+    /// Installing resets the breaker, the failure policy is kept.
+    pub(crate) fn set_mint_hook(&self, slot: u128, block: u128, tx: u128) -> Result<CallResponse> {
+        self.only_owner()?;
+        let context: alkanes_support::context::Context = self.context()?;
+        let response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let existing: HookConfig = self.get_hook_config(slot)?;
+        let config: HookConfig = HookConfig {
+            block,
+            tx,
+            policy: existing.policy,
+            breaker_threshold: existing.breaker_threshold,
+            ..Default::default()
+        };
+
+        self.set_hook_config(slot, &config)?;
+        Ok(response)
+    }
+
+    /// Choose fail-open or fail-closed and how many consecutive failures trip the breaker
+    pub(crate) fn set_hook_policy(&self, slot: u128, fail_closed: u128, breaker_threshold: u128) -> Result<CallResponse> {
+        self.only_owner()?;
+        let context: alkanes_support::context::Context = self.context()?;
+        let response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let mut config: HookConfig = self.get_hook_config(slot)?;
+        config.policy = if fail_closed != 0 { HOOK_FAIL_CLOSED } else { HOOK_FAIL_OPEN };
+        config.breaker_threshold = u32::try_from(breaker_threshold).map_err(|_| revert!(ERR_INVALID_INPUT, "Breaker threshold out of range"))?;
+
+        self.set_hook_config(slot, &config)?;
+        Ok(response)
+    }
+
+    /// Re-enable a hook whose breaker tripped
+    pub(crate) fn reset_hook_breaker(&self, slot: u128) -> Result<CallResponse> {
+        self.only_owner()?;
+        let context: alkanes_support::context::Context = self.context()?;
+        let response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let mut config: HookConfig = self.get_hook_config(slot)?;
+        config.tripped = false;
+        config.consecutive_failures = 0;
+
+        self.set_hook_config(slot, &config)?;
+        Ok(response)
+    }
+
+    /// Give the pre-mint hook the chance to veto minting `index` in a stage.
+    /// Returns false when a failing fail-closed hook declines the mint; the caller
+    /// must then return without reverting so the failure count is kept.
+    pub(crate) fn run_pre_mint_hook(&self, stage_id: u128, index: u128) -> Result<bool> {
+        let mut config: HookConfig = self.get_hook_config(HOOK_PRE_MINT)?;
+        if !config.is_installed() || config.tripped {
+            return Ok(true);
+        }
+
+        match self.pre_mint_approval(&config, stage_id, index) {
+            Ok(0) => Err(revert!(ERR_UNAUTHORIZED, "Pre-mint hook vetoed the mint")),
+            Ok(_) => {
+                self.record_hook_success(HOOK_PRE_MINT, &mut config)?;
+                Ok(true)
+            }
+            Err(_) => {
+                config.record_failure(self.height());
+                self.set_hook_config(HOOK_PRE_MINT, &config)?;
+                Ok(config.policy == HOOK_FAIL_OPEN)
+            }
+        }
+    }
+
+    /// Read-only rehearsal of the pre-mint hook, failing where the mint would be vetoed or declined
+    pub(crate) fn check_pre_mint_hook(&self, stage_id: u128, index: u128) -> Result<()> {
+        let config: HookConfig = self.get_hook_config(HOOK_PRE_MINT)?;
+        if !config.is_installed() || config.tripped {
+            return Ok(());
+        }
+
+        match self.pre_mint_approval(&config, stage_id, index) {
+            Ok(0) => Err(revert!(ERR_UNAUTHORIZED, "Pre-mint hook would veto the mint")),
+            Ok(_) => Ok(()),
+            Err(_) if config.policy == HOOK_FAIL_OPEN => Ok(()),
+            Err(e) => Err(revert!(ERR_EXTERNAL_CALL, "Pre-mint hook fails, the mint would be declined: {}", e)),
+        }
+    }
+
+    /// Notify the post-mint hook that `index` was minted in a stage
+    pub(crate) fn run_post_mint_hook(&self, stage_id: u128, index: u128) -> Result<()> {
+        let mut config: HookConfig = self.get_hook_config(HOOK_POST_MINT)?;
+        if !config.is_installed() || config.tripped {
+            return Ok(());
+        }
+
+        let cellpack: Cellpack = self.hook_cellpack(config.hook(), HOOK_ON_POST_MINT, stage_id, index)?;
+        match self.call(&cellpack, &AlkaneTransferParcel::default(), self.fuel()) {
+            Ok(_) => self.record_hook_success(HOOK_POST_MINT, &mut config),
+            // The revert discards the failure count, only fail-open post-mint failures trip the breaker
+            Err(e) if config.policy == HOOK_FAIL_CLOSED => Err(revert!(ERR_EXTERNAL_CALL, "Post-mint hook failed: {}", e)),
+            Err(_) => {
+                config.record_failure(self.height());
+                self.set_hook_config(HOOK_POST_MINT, &config)
+            }
+        }
+    }
+
+    /// Returns both hook slots with policy, breaker state and failure counters,
+    /// together with the sybil scorer, as JSON
+    pub(crate) fn get_hooks_config(&self) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let (sybil_scorer, sybil_threshold) = self.sybil_settings()?;
+        let sybil_scorer: AlkaneId = sybil_scorer.unwrap_or_default();
+        let report: HooksReport = HooksReport {
+            pre_mint: self.get_hook_config(HOOK_PRE_MINT)?,
+            post_mint: self.get_hook_config(HOOK_POST_MINT)?,
+            sybil_scorer_block: sybil_scorer.block,
+            sybil_scorer_tx: sybil_scorer.tx,
+            sybil_threshold,
+        };
+
+        response.data = serde_json::to_vec(&report)
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to serialize hooks config"))?;
+        Ok(response)
+    }
+
+    fn pre_mint_approval(&self, config: &HookConfig, stage_id: u128, index: u128) -> Result<u128> {
+        let cellpack: Cellpack = self.hook_cellpack(config.hook(), HOOK_ON_PRE_MINT, stage_id, index)?;
+        let response: CallResponse = self.staticcall(&cellpack, &AlkaneTransferParcel::default(), self.fuel())?;
+
+        let bytes: [u8; 16] = response.data.get(..16)
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or_else(|| revert!(ERR_EXTERNAL_CALL, "Invalid pre-mint hook response"))?;
+        Ok(u128::from_le_bytes(bytes))
+    }
+
+    fn record_hook_success(&self, slot: u128, config: &mut HookConfig) -> Result<()> {
+        if config.consecutive_failures == 0 {
+            return Ok(());
+        }
+
+        config.consecutive_failures = 0;
+        self.set_hook_config(slot, config)
+    }
+
+    fn hook_cellpack(&self, hook: AlkaneId, opcode: u128, stage_id: u128, index: u128) -> Result<Cellpack> {
+        let payer: [u8; 32] = self.minter_identity()?;
+
+        Ok(Cellpack {
+            target: hook,
+            inputs: vec![
+                opcode,
+                stage_id,
+                index,
+                u128::from_le_bytes(payer[..16].try_into().unwrap()),
+                u128::from_le_bytes(payer[16..].try_into().unwrap()),
+            ],
+        })
+    }
+
+    fn observe_hook_slot(&self, slot: u128) -> Result<()> {
+        match slot {
+            HOOK_PRE_MINT | HOOK_POST_MINT => Ok(()),
+            _ => Err(revert!(ERR_NOT_FOUND, "Unknown hook slot {}", slot)),
+        }
+    }
+
+    fn hook_pointer(&self, slot: u128) -> StoragePointer {
+        StoragePointer::from_keyword("/hooks/").select(&slot.to_le_bytes().to_vec())
+    }
+
+    fn set_hook_config(&self, slot: u128, config: &HookConfig) -> Result<()> {
+        self.observe_hook_slot(slot)?;
+
+        let serialized: Vec<u8> = bincode::serialize(config)
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to serialize hook config"))?;
+        self.hook_pointer(slot).set(Arc::new(serialized));
+        Ok(())
+    }
+
+    fn get_hook_config(&self, slot: u128) -> Result<HookConfig> {
+        self.observe_hook_slot(slot)?;
+
+        let stored: Arc<Vec<u8>> = self.hook_pointer(slot).get();
+        if stored.is_empty() {
+            return Ok(HookConfig::default());
+        }
+
+        bincode::deserialize(&stored).map_err(|_| revert!(ERR_STORAGE, "Failed to deserialize hook config"))
+    }
+}
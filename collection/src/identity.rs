@@ -0,0 +1,194 @@
+use metashrew_support::index_pointer::KeyValuePointer;
+use alkanes_runtime::runtime::AlkaneResponder;
+use alkanes_support::{id::AlkaneId, response::CallResponse};
+use bitcoin::{consensus::deserialize, hashes::Hash, Transaction, TxOut};
+use ordinals::{Artifact, Runestone};
+use protorune_support::protostone::Protostone;
+use serde::Serialize;
+use anyhow::Result;
+use orbital_support::{revert, address, errors::{codes::*, Revert}};
+use std::sync::Arc;
+
+use crate::Collection;
+use crate::opcodes::StoragePointer;
+
+/// How the caller of the current transaction is attributed, returned by `GetIdentityOfCaller`
+#[derive(Serialize)]
+struct CallerIdentity {
+    /// Hex sha256 of the attributed output script
+    identity: String,
+    /// Output the identity was taken from
+    vout: u32,
+    /// Hex of the attributed output script
+    script_pubkey: String,
+    /// Rule that selected the output
+    method: &'static str,
+    caller_block: u128,
+    caller_tx: u128,
+}
+
+/// Identity from its two little endian u128 halves, the form opcodes take identities in
+pub(crate) fn identity_from_halves(low: u128, high: u128) -> [u8; 32] {
+    let mut identity: [u8; 32] = [0; 32];
+    identity[..16].copy_from_slice(&low.to_le_bytes());
+    identity[16..].copy_from_slice(&high.to_le_bytes());
+    identity
+}
+
+impl Collection {
+    /// Output the current transaction is attributed to: the output designated by
+    /// `MintInStageTo` for this transaction, otherwise the first spendable one,
+    /// which is where the minted orbital lands by default.
+    pub(crate) fn attributed_output(&self) -> Result<(u32, TxOut, &'static str)> {
+        let tx: Transaction = self.decode_transaction()?;
+
+        let designated: u32 = self.attribution_pointer(&tx).get_value::<u32>();
+        if designated != 0 {
+            let vout: u32 = designated - 1;
+            return Ok((vout, tx.output[vout as usize].clone(), "designated-output"));
+        }
+
+        tx.output.into_iter()
+            .enumerate()
+            .find(|(_, output)| !output.script_pubkey.is_op_return())
+            .map(|(vout, output)| (vout as u32, output, "first-spendable-output"))
+            .ok_or_else(|| revert!(ERR_INVALID_STATE, "Transaction has no spendable output"))
+    }
+
+    /// Attribute the rest of the current transaction to one of its outputs, for custodial
+    /// and exchange flows where the first spendable output is not the minter
+    pub(crate) fn designate_output(&self, vout: u128) -> Result<()> {
+        let tx: Transaction = self.decode_transaction()?;
+        let (vout, _) = Self::spendable_output(&tx, vout)?;
+
+        self.attribution_pointer(&tx).set_value::<u32>(vout + 1);
+        Ok(())
+    }
+
+    /// Require what this call returns to land on the attributed output: the call comes
+    /// straight from the transaction and its protostone points there. Any script can be put
+    /// on an output, so this is what ties a mint counted against an identity to that
+    /// script receiving it.
+    pub(crate) fn observe_delivered_to_minter(&self) -> Result<()> {
+        let context: alkanes_support::context::Context = self.context()?;
+        if context.caller != AlkaneId::default() {
+            return Err(revert!(ERR_UNAUTHORIZED, "Identity bound mints cannot go through another alkane"));
+        }
+
+        let tx: Transaction = self.decode_transaction()?;
+        let (vout, _, _) = self.attributed_output()?;
+        let pointer: u32 = Self::protostone_pointer(&tx, context.vout)?;
+        if pointer != vout {
+            return Err(Revert::new(ERR_UNAUTHORIZED, "Protostone does not point at the attributed output")
+                .field("pointer").expected(vout as u128).actual(pointer as u128).into());
+        }
+
+        Ok(())
+    }
+
+    /// Output the protostone at virtual output `shadow_vout` sends its alkanes to, the first
+    /// spendable output when it sets no pointer
+    fn protostone_pointer(tx: &Transaction, shadow_vout: u32) -> Result<u32> {
+        let runestone: Runestone = match Runestone::decipher(tx) {
+            Some(Artifact::Runestone(runestone)) => runestone,
+            _ => return Err(revert!(ERR_INVALID_STATE, "Transaction carries no runestone")),
+        };
+
+        let protostones: Vec<Protostone> = Protostone::from_runestone(&runestone)
+            .map_err(|_| revert!(ERR_FAILED, "Failed to decode the protostones"))?;
+        let protostone: &Protostone = shadow_vout.checked_sub(tx.output.len() as u32 + 1)
+            .and_then(|position| protostones.get(position as usize))
+            .ok_or_else(|| revert!(ERR_INVALID_STATE, "No protostone at virtual output {}", shadow_vout))?;
+
+        match protostone.pointer {
+            Some(pointer) => Ok(pointer),
+            None => tx.output.iter()
+                .position(|output| !output.script_pubkey.is_op_return())
+                .map(|vout| vout as u32)
+                .ok_or_else(|| revert!(ERR_INVALID_STATE, "Transaction has no spendable output")),
+        }
+    }
+
+    /// Identity of an output of the current transaction
+    pub(crate) fn output_identity(&self, vout: u128) -> Result<[u8; 32]> {
+        let tx: Transaction = self.decode_transaction()?;
+        let (_, output) = Self::spendable_output(&tx, vout)?;
+        Ok(address::script_identity(output.script_pubkey.as_bytes()))
+    }
+
+    fn spendable_output(tx: &Transaction, vout: u128) -> Result<(u32, &TxOut)> {
+        let vout: u32 = u32::try_from(vout).map_err(|_| revert!(ERR_INVALID_INPUT, "Output index out of range"))?;
+
+        let output: &TxOut = tx.output.get(vout as usize)
+            .ok_or_else(|| Revert::new(ERR_INVALID_INPUT, format!("Output {} does not exist", vout))
+                .field("vout").expected(tx.output.len() as u128).actual(vout as u128))?;
+
+        if output.script_pubkey.is_op_return() {
+            return Err(revert!(ERR_INVALID_INPUT, "Output {} is an OP_RETURN", vout));
+        }
+
+        Ok((vout, output))
+    }
+
+    pub(crate) fn decode_transaction(&self) -> Result<Transaction> {
+        deserialize(&self.transaction())
+            .map_err(|_| revert!(ERR_FAILED, "Failed to decode the current transaction"))
+    }
+
+    /// Attribution is scoped to the transaction by keying it on the txid
+    fn attribution_pointer(&self, tx: &Transaction) -> StoragePointer {
+        StoragePointer::from_keyword("/attribution/").select(&tx.compute_txid().to_byte_array().to_vec())
+    }
+
+    /// Record the identity an index was minted to
+    pub(crate) fn record_minter(&self, index: u128) -> Result<()> {
+        let identity: [u8; 32] = self.minter_identity()?;
+        self.set_minted_by(index, &identity);
+        Ok(())
+    }
+
+    pub(crate) fn set_minted_by(&self, index: u128, identity: &[u8; 32]) {
+        self.minter_pointer(index).set(Arc::new(identity.to_vec()));
+    }
+
+    /// Returns the 32 byte identity an index was minted to, empty for premined orbitals
+    pub(crate) fn get_minted_by(&self, index: u128) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        response.data = self.minter_pointer(index).get().as_ref().clone();
+        Ok(response)
+    }
+
+    fn minter_pointer(&self, index: u128) -> StoragePointer {
+        StoragePointer::from_keyword("/minted-by/").select(&index.to_le_bytes().to_vec())
+    }
+
+    /// Identity of the minter: the sha256 of the script of the attributed output
+    pub(crate) fn minter_identity(&self) -> Result<[u8; 32]> {
+        let (_, output, _) = self.attributed_output()?;
+        Ok(address::script_identity(output.script_pubkey.as_bytes()))
+    }
+
+    /// Returns the identity the current transaction would be attributed to as JSON,
+    /// so integrators can check attribution before paying for a mint
+    pub(crate) fn get_identity_of_caller(&self) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let (vout, output, method) = self.attributed_output()?;
+        let script: &[u8] = output.script_pubkey.as_bytes();
+        let identity: CallerIdentity = CallerIdentity {
+            identity: orbital_support::hex::encode(&address::script_identity(script)),
+            vout,
+            script_pubkey: orbital_support::hex::encode(script),
+            method,
+            caller_block: context.caller.block,
+            caller_tx: context.caller.tx,
+        };
+
+        response.data = serde_json::to_vec(&identity)
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to serialize caller identity"))?;
+        Ok(response)
+    }
+}
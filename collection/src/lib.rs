@@ -2,7 +2,7 @@ use metashrew_support::index_pointer::KeyValuePointer;
 use metashrew_support::compat::to_arraybuffer_layout;
 
 use alkanes_runtime::{
-  declare_alkane, message::MessageDispatch, storage::StoragePointer, token::Token,
+  declare_alkane, message::MessageDispatch, token::Token,
   runtime::AlkaneResponder
 };
 
@@ -11,24 +11,104 @@ use alkanes_support::{
   parcel::{AlkaneTransfer, AlkaneTransferParcel}, response::CallResponse
 };
 
+use bitcoin::hashes::{sha256, Hash};
 use serde::{Serialize, Deserialize};
-use anyhow::{anyhow, Result};
+use anyhow::Result;
+use orbital_support::{revert, errors::{codes::*, Revert}, pricing::DutchCurve};
 use std::sync::Arc;
 mod svg_generator;
-use svg_generator::SvgGenerator;
+use svg_generator::RenderConfig;
+mod render_engine;
+use render_engine::RenderEngine;
+mod access;
+mod airdrop;
+mod batch;
+mod cache;
+mod changelog;
+mod commit_reveal;
+mod compliance;
+mod config;
+mod counters;
+mod currencies;
+mod derivatives;
+mod diagnostics;
+mod discounts;
+mod escrow;
+mod events;
+mod featured;
+mod finale;
+mod honorary;
+mod hooks;
+mod identity;
+mod license;
+mod limits;
+mod listeners;
+mod loans;
+mod mint_status;
+mod multicall;
+mod opcodes;
+use opcodes::StoragePointer;
+use tickets::Ticket;
+mod oracle;
+mod parametric;
+mod partners;
+mod points;
+mod public_mint;
+mod quests;
+mod receipts;
+mod referrals;
+mod render_canary;
+mod render_info;
+mod rentals;
+mod revenue;
+mod roles;
+mod royalties;
+mod simulate;
+mod stages;
+mod staking;
+mod sybil;
+mod tickets;
+mod token_gates;
+mod trait_tables;
+mod treasury;
+mod unlockables;
+mod venues;
+mod vesting;
+mod vouchers;
+mod waitlist;
+mod whitelist;
 
 /// Template ID for orbital NFT
 const ORBITAL_INSTANCE_ID: u128 = 0x69f;
 
-/// Name of the NFT collection
+/// Version of the collection <-> instance protocol (opcode map and payload layouts)
+const PROTOCOL_VERSION: u128 = 2;
+
+/// Instance opcode reporting its protocol version
+const INSTANCE_GET_PROTOCOL_VERSION: u128 = 103;
+
+/// Name of the NFT collection when `Initialize` is given none
 const CONTRACT_NAME: &str = "Ador Alkane";
 
-/// Symbol of the NFT collection
+/// Symbol of the NFT collection when `Initialize` is given none
 const CONTRACT_SYMBOL: &str = "Adr";
 
-/// Number of NFTs to be premined during contract initialization
-/// This value can be set to 0 if no premine is needed
-const PREMINE_MINTS: u128 = 10;
+/// Upper bound on the premine `Initialize` accepts, orbitals the owner can airdrop on top
+/// of stage and public supply
+const MAX_PREMINE_MINTS: u128 = 1000;
+
+/// Inputs ahead of the whitelist proof of `MintInStage`: opcode, stage_id
+const MINT_IN_STAGE_HEADER: usize = 2;
+
+/// Inputs ahead of the whitelist proof of `MintInStageTo`: opcode, stage_id, vout
+const MINT_IN_STAGE_TO_HEADER: usize = 3;
+
+/// Stage window is bounded by block heights
+const SCHEDULE_BY_HEIGHT: u8 = 0;
+
+/// Stage window is bounded by block timestamps, falling back to heights
+/// when the block time is not available
+const SCHEDULE_BY_TIME: u8 = 1;
 
 /// Defines a single minting stage.
 #[derive(Clone, Default, Debug, Serialize, Deserialize)]
@@ -36,11 +116,196 @@ struct Stage {
     id: u128,
     price_per_item: u64,
     max_mints_per_address: u32,
-    whitelist: Vec<String>,
+    /// Merkle root of the whitelisted identities under `merkle::SCHEME`, all zeros for an open stage
+    whitelist_root: [u8; 32],
     max_supply: u128,
     start_block: u64,
     end_block: u64,
     total_minted: u128,
+    schedule_mode: u8,
+    start_time: u64,
+    end_time: u64,
+    /// Mints landing within this many blocks of end_block extend the stage (0 disables)
+    soft_close_window: u64,
+    /// Blocks added to end_block per soft-close extension
+    soft_close_extension: u64,
+    /// Upper bound on the total number of blocks the stage can be extended by
+    max_extension: u64,
+    /// Blocks the stage has been extended by so far
+    extended_by: u64,
+    /// Marks a zero price as intentional, mainnet initialization rejects it otherwise
+    free: bool,
+    /// Dutch auction curve: the price falls from start_price to end_price one step every
+    /// decay_interval_blocks over the stage window before any soft-close extension.
+    /// A decay interval of 0 keeps the fixed price_per_item.
+    start_price: u64,
+    end_price: u64,
+    decay_interval_blocks: u64,
+    /// Supply left unsold when the stage ends moves to the next stage once that one starts
+    rollover: bool,
+    /// Stage the unsold supply moved to, 0 until the rollover happened
+    rolled_into: u128,
+    /// Alkane callers present at least gate_min_balance of to mint, zero for no gate
+    gate_block: u128,
+    gate_tx: u128,
+    gate_min_balance: u128,
+    /// Alkanes accepted in place of the payment token, each at its own price per item
+    accepted_payments: Vec<currencies::AcceptedPayment>,
+    /// Prices are sats, paid in the payment token at the oracle rate
+    priced_in_sats: bool,
+}
+
+impl Stage {
+    fn token_gate(&self) -> Option<AlkaneId> {
+        if self.gate_block == 0 && self.gate_tx == 0 {
+            return None;
+        }
+
+        Some(AlkaneId { block: self.gate_block, tx: self.gate_tx })
+    }
+
+    /// Whether the stage window contains the given block height and time
+    fn is_active(&self, height: u64, time: Option<u64>) -> bool {
+        match (self.schedule_mode, time) {
+            (SCHEDULE_BY_TIME, Some(time)) => self.start_time <= time && time <= self.end_time,
+            _ => self.start_block <= height && height <= self.end_block,
+        }
+    }
+
+    /// Per item price at a height and the last block of the curve step it falls in
+    fn price_at(&self, height: u64) -> (u128, u64) {
+        if self.decay_interval_blocks == 0 {
+            return (self.price_per_item as u128, u64::MAX);
+        }
+
+        let interval: u64 = self.decay_interval_blocks;
+        let window_end: u64 = self.end_block.saturating_sub(self.extended_by);
+        let curve: DutchCurve = DutchCurve {
+            start_price: self.start_price as u128,
+            end_price: self.end_price as u128,
+            start_block: self.start_block,
+            interval,
+            steps: (window_end.saturating_sub(self.start_block) / interval).max(1),
+        };
+
+        curve.price_at(height)
+    }
+
+    /// Price of `count` items at a height and the last block that price holds for.
+    /// Fixed prices hold until the stage ends and curve prices until the next step;
+    /// time scheduled stages cannot map their end to a block and only guarantee the current one.
+    fn quote(&self, count: u128, height: u64) -> Result<(u128, u64)> {
+        let (unit_price, step_end) = self.price_at(height);
+        let price: u128 = unit_price.checked_mul(count)
+            .ok_or_else(|| revert!(ERR_OVERFLOW, "price overflow"))?;
+
+        let valid_until: u64 = match self.schedule_mode {
+            SCHEDULE_BY_TIME => height,
+            _ => self.end_block.min(step_end).max(height),
+        };
+
+        Ok((price, valid_until))
+    }
+
+    /// Count `count` mints at a height against the stage supply
+    fn record_mints(&mut self, count: u128, height: u64) -> Result<()> {
+        let minted: u128 = self.total_minted.saturating_add(count);
+        if minted > self.max_supply {
+            return Err(Revert::new(ERR_LIMIT_EXCEEDED, "Exceeds max supply for this stage")
+                .field("max_supply").expected(self.max_supply).actual(minted).into());
+        }
+
+        self.total_minted = minted;
+        self.apply_soft_close(height);
+        Ok(())
+    }
+
+    /// Push end_block out when a mint arrives in the closing window, within max_extension.
+    /// Only applies to height scheduled stages.
+    fn apply_soft_close(&mut self, height: u64) {
+        if self.schedule_mode != SCHEDULE_BY_HEIGHT || self.soft_close_window == 0 {
+            return;
+        }
+
+        if self.end_block.saturating_sub(height) >= self.soft_close_window {
+            return;
+        }
+
+        let remaining: u64 = self.max_extension.saturating_sub(self.extended_by);
+        let extension: u64 = self.soft_close_extension.min(remaining);
+        self.end_block = self.end_block.saturating_add(extension);
+        self.extended_by += extension;
+    }
+}
+
+/// Serialize an alkane ID as block followed by tx, 32 bytes little endian
+fn encode_alkane_id(id: &AlkaneId) -> Vec<u8> {
+    let mut bytes: Vec<u8> = Vec::with_capacity(32);
+    bytes.extend_from_slice(&id.block.to_le_bytes());
+    bytes.extend_from_slice(&id.tx.to_le_bytes());
+    bytes
+}
+
+fn decode_alkane_id(bytes: &[u8]) -> Result<AlkaneId> {
+    if bytes.len() != 32 {
+        return Err(revert!(ERR_INVALID_INPUT, "Invalid alkane ID length"));
+    }
+
+    Ok(AlkaneId {
+        block: u128::from_le_bytes(bytes[..16].try_into().unwrap()),
+        tx: u128::from_le_bytes(bytes[16..32].try_into().unwrap()),
+    })
+}
+
+/// Unpack a string packed into a little endian u128, stopping at the first zero byte
+fn decode_u128_to_string(value: u128) -> String {
+    value.to_le_bytes().iter()
+        .take_while(|byte| **byte != 0)
+        .map(|byte| *byte as char)
+        .collect()
+}
+
+/// Schedule of a single stage as reported in the launch timeline
+#[derive(Serialize)]
+struct StageWindow {
+    id: u128,
+    schedule_mode: u8,
+    start_block: u64,
+    end_block: u64,
+    start_time: u64,
+    end_time: u64,
+    soft_close_window: u64,
+    soft_close_extension: u64,
+    max_extension: u64,
+    extended_by: u64,
+    active: bool,
+}
+
+/// Full launch schedule returned by `GetTimeline`
+#[derive(Serialize)]
+struct Timeline {
+    current_block: u64,
+    current_time: Option<u64>,
+    stages: Vec<StageWindow>,
+    reveal_block: u64,
+    metadata_frozen: bool,
+    /// Deployment mode, 0 mainnet and 1 canary
+    mode: u8,
+}
+
+/// Deployment facts recorded by `Initialize`, returned by `GetInitializationState`
+#[derive(Default, Serialize, Deserialize)]
+struct InitializationState {
+    initialized: bool,
+    block: u64,
+    /// Script hash of the first spendable output of the initializing transaction
+    initializer: String,
+    caller_block: u128,
+    caller_tx: u128,
+    /// Hex sha256 of the configuration committed at initialization, see `GetConfigHash`
+    config_hash: String,
+    /// Deployment mode, 0 mainnet and 1 canary
+    mode: u8,
 }
 
 #[derive(Default)]
@@ -51,11 +316,378 @@ impl AlkaneResponder for Collection {}
 #[derive(MessageDispatch)]
 enum CollectionMessage {
   #[opcode(0)]
-  Initialize,
+  Initialize { name_part1: u128, name_part2: u128, symbol: u128, max_supply: u128, premine: u128, engine: u128 },
 
   #[opcode(77)]
   MintInStage { stage_id: u128 },
 
+  #[opcode(78)]
+  MintInStageTo { stage_id: u128, vout: u128 },
+
+  #[opcode(79)]
+  Mint,
+
+  #[opcode(80)]
+  MintBatch { count: u128 },
+
+  #[opcode(81)]
+  CommitMintInStage { stage_id: u128 },
+
+  #[opcode(82)]
+  RevealMint { commit_id: u128 },
+
+  #[opcode(83)]
+  ClaimFreeMint { count: u128, allocation: u128 },
+
+  #[opcode(84)]
+  AirdropMint { count: u128 },
+
+  #[opcode(85)]
+  MintInStageReferred { stage_id: u128, referrer_txid_low: u128, referrer_txid_high: u128, referrer_vout: u128 },
+
+  #[opcode(200)]
+  FreezeMetadata,
+
+  #[opcode(201)]
+  SetRevealBlock { block: u128 },
+
+  #[opcode(210)]
+  GrantRole { role: u128, block: u128, tx: u128 },
+
+  #[opcode(211)]
+  RevokeRole { role: u128 },
+
+  #[opcode(220)]
+  SetDefaultRoyalty { recipient_block: u128, recipient_tx: u128, bps: u128 },
+
+  #[opcode(221)]
+  SetRoyaltyOverride { index: u128, recipient_block: u128, recipient_tx: u128, bps: u128 },
+
+  #[opcode(222)]
+  ClearRoyaltyOverride { index: u128 },
+
+  #[opcode(230)]
+  SetVenueEnforcement { enabled: u128 },
+
+  #[opcode(231)]
+  ApproveVenue { block: u128, tx: u128 },
+
+  #[opcode(232)]
+  RemoveVenue { block: u128, tx: u128 },
+
+  #[opcode(234)]
+  AcknowledgeEscrowDeposit { index: u128 },
+
+  #[opcode(235)]
+  ReleaseEscrowDeposit { index: u128 },
+
+  #[opcode(240)]
+  FeatureListing { venue_block: u128, venue_tx: u128, listing_id: u128 },
+
+  #[opcode(241)]
+  UnfeatureListing { venue_block: u128, venue_tx: u128, listing_id: u128 },
+
+  #[opcode(242)]
+  FeatureIndex { index: u128 },
+
+  #[opcode(243)]
+  UnfeatureIndex { index: u128 },
+
+  #[opcode(250)]
+  OfferRental { index: u128, price: u128, duration_blocks: u128, payment_block: u128, payment_tx: u128 },
+
+  #[opcode(251)]
+  Rent { index: u128 },
+
+  #[opcode(252)]
+  WithdrawRentalProceeds { index: u128 },
+
+  #[opcode(253)]
+  EndRental { index: u128 },
+
+  #[opcode(260)]
+  RequestLoan { index: u128, principal: u128, interest_per_block: u128, duration_blocks: u128, payment_block: u128, payment_tx: u128 },
+
+  #[opcode(261)]
+  CancelLoanRequest { loan_id: u128 },
+
+  #[opcode(262)]
+  FundLoan { loan_id: u128 },
+
+  #[opcode(263)]
+  WithdrawLoanPrincipal { loan_id: u128 },
+
+  #[opcode(264)]
+  RepayLoan { loan_id: u128 },
+
+  #[opcode(265)]
+  ClaimLoan { loan_id: u128 },
+
+  #[opcode(270)]
+  Stake { index: u128 },
+
+  #[opcode(271)]
+  Unstake { index: u128 },
+
+  #[opcode(280)]
+  SetAccessPolicy { policy_id: u128, name: u128, flags: u128, min_staked_blocks: u128, trait_type: u128, trait_value: u128 },
+
+  #[opcode(281)]
+  RemoveAccessPolicy { policy_id: u128 },
+
+  #[opcode(290)]
+  SetPointsValue { action: u128, points: u128 },
+
+  #[opcode(291)]
+  StartSeason,
+
+  #[opcode(292)]
+  CheckIn { index: u128 },
+
+  #[opcode(293)]
+  ClaimStakingPoints { index: u128 },
+
+  #[opcode(294)]
+  RecordActivity { index: u128, action: u128 },
+
+  #[opcode(295)]
+  SetBadgeReward { badge_id: u128, cost: u128 },
+
+  #[opcode(296)]
+  RedeemBadge { index: u128, badge_id: u128 },
+
+  #[opcode(297)]
+  SetQuest { quest_id: u128, kind: u128, threshold: u128, reward_points: u128, reward_badge: u128 },
+
+  #[opcode(298)]
+  RemoveQuest { quest_id: u128 },
+
+  #[opcode(299)]
+  CompleteQuest { index: u128, quest_id: u128 },
+
+  #[opcode(300)]
+  SetSybilGate { block: u128, tx: u128, threshold: u128 },
+
+  #[opcode(301)]
+  SetStageSybilGate { stage_id: u128, enabled: u128 },
+
+  #[opcode(302)]
+  SetMintHook { slot: u128, block: u128, tx: u128 },
+
+  #[opcode(303)]
+  SetHookPolicy { slot: u128, fail_closed: u128, breaker_threshold: u128 },
+
+  #[opcode(304)]
+  ResetHookBreaker { slot: u128 },
+
+  #[opcode(310)]
+  SetPartner { partner_id: u128, credential_block: u128, credential_tx: u128 },
+
+  #[opcode(311)]
+  RemovePartner { partner_id: u128 },
+
+  #[opcode(312)]
+  PartnerMint { partner_id: u128, stage_id: u128, entries: u128 },
+
+  #[opcode(313)]
+  SetPartnerTerms { partner_id: u128, quota: u128, price_per_item: u128, payment_block: u128, payment_tx: u128 },
+
+  #[opcode(314)]
+  SetPartnerClawback { partner_id: u128, window_blocks: u128 },
+
+  #[opcode(315)]
+  Clawback { index: u128 },
+
+  #[opcode(320)]
+  SetCollectionCompliance { license_low: u128, license_high: u128, tag_count: u128 },
+
+  #[opcode(321)]
+  SetTokenCompliance { index: u128, license_low: u128, license_high: u128, tag_count: u128 },
+
+  #[opcode(322)]
+  SetCollectionLicense { license: u128 },
+
+  #[opcode(323)]
+  SetLicense { index: u128, license: u128 },
+
+  #[opcode(324)]
+  RegisterDerivative { index: u128, hash_low: u128, hash_high: u128, description_words: u128 },
+
+  #[opcode(325)]
+  SetPaymentToken { block: u128, tx: u128 },
+
+  #[opcode(326)]
+  WithdrawProceeds { amount: u128 },
+
+  #[opcode(327)]
+  SetPreviewGate { enabled: u128 },
+
+  #[opcode(328)]
+  SetUnlockable { index: u128, payload_words: u128, key_words: u128 },
+
+  #[opcode(329)]
+  RotateUnlockableKey { index: u128, key_words: u128 },
+
+  #[opcode(330)]
+  ReleaseUnlockableKey { index: u128 },
+
+  #[opcode(331)]
+  SetStage {
+    stage_id: u128,
+    price_per_item: u128,
+    max_mints_per_address: u128,
+    max_supply: u128,
+    schedule_mode: u128,
+    start_block: u128,
+    end_block: u128,
+    start_time: u128,
+    end_time: u128,
+    whitelist_low: u128,
+    whitelist_high: u128,
+    free: u128,
+  },
+
+  #[opcode(332)]
+  RemoveStage { stage_id: u128 },
+
+  #[opcode(333)]
+  RotateContentKey { key_words: u128 },
+
+  #[opcode(334)]
+  UploadUnlockableChunk { index: u128, epoch: u128, chunk: u128, length: u128 },
+
+  #[opcode(335)]
+  ReleaseContentKey { index: u128 },
+
+  #[opcode(336)]
+  SetPublicMint { price_per_item: u128, max_supply: u128 },
+
+  #[opcode(337)]
+  StartRenderCanary { preview_gate: u128, probation_blocks: u128, count: u128 },
+
+  #[opcode(338)]
+  ConfirmRenderCanary,
+
+  #[opcode(339)]
+  CancelRenderCanary,
+
+  #[opcode(340)]
+  SetMintBatchCap { cap: u128 },
+
+  #[opcode(341)]
+  SetStagePricingCurve { stage_id: u128, start_price: u128, end_price: u128, decay_interval_blocks: u128 },
+
+  #[opcode(342)]
+  SetMaxMintPerBlock { stage_id: u128, max: u128 },
+
+  #[opcode(343)]
+  WarmCache { subsystem: u128, start: u128, count: u128 },
+
+  #[opcode(344)]
+  PruneCache { subsystem: u128, start: u128, count: u128 },
+
+  #[opcode(345)]
+  ClaimRefund { escrow_id: u128 },
+
+  #[opcode(346)]
+  SetEventRetention { log: u128, retention: u128 },
+
+  #[opcode(347)]
+  PauseMint,
+
+  #[opcode(348)]
+  ResumeMint,
+
+  #[opcode(349)]
+  CloseMint,
+
+  #[opcode(350)]
+  RegisterListener { topic: u128, deposit: u128 },
+
+  #[opcode(351)]
+  UnregisterListener { topic: u128 },
+
+  #[opcode(352)]
+  RemoveListener { topic: u128, block: u128, tx: u128 },
+
+  #[opcode(353)]
+  SetStageRollover { stage_id: u128, enabled: u128 },
+
+  #[opcode(354)]
+  TopUpListener { topic: u128, block: u128, tx: u128, amount: u128 },
+
+  #[opcode(355)]
+  SetListenerFee { fee: u128 },
+
+  #[opcode(356)]
+  SetStageTokenGate { stage_id: u128, block: u128, tx: u128, min_balance: u128 },
+
+  #[opcode(357)]
+  SetVoucher { identity_low: u128, identity_high: u128, count: u128 },
+
+  #[opcode(358)]
+  SetVoucherRoot { root_low: u128, root_high: u128, total: u128 },
+
+  #[opcode(359)]
+  VestPremine { count: u128, start_block: u128, interval_blocks: u128, tranches: u128 },
+
+  #[opcode(360)]
+  ClaimVested { count: u128 },
+
+  #[opcode(361)]
+  UploadTraitTableChunk { version: u128, chunk: u128, length: u128 },
+
+  #[opcode(362)]
+  ActivateTraitTable { version: u128 },
+
+  #[opcode(363)]
+  SetReferralRate { bps: u128 },
+
+  #[opcode(364)]
+  ClaimReferralRewards { code_txid_low: u128, code_txid_high: u128, code_vout: u128, block: u128, tx: u128 },
+
+  #[opcode(365)]
+  SetFinale { action: u128, amount: u128 },
+
+  #[opcode(366)]
+  ClaimFinale,
+
+  #[opcode(367)]
+  SetWaitlist { enabled: u128 },
+
+  #[opcode(368)]
+  ProcessQueue { count: u128 },
+
+  #[opcode(369)]
+  ClaimQueuedMints { count: u128 },
+
+  #[opcode(370)]
+  SetStageAcceptedPayment { stage_id: u128, block: u128, tx: u128, price_per_item: u128 },
+
+  #[opcode(371)]
+  WithdrawTreasuryToken { block: u128, tx: u128, amount: u128 },
+
+  #[opcode(372)]
+  SetPriceOracle { block: u128, tx: u128, min_rate: u128, max_rate: u128, max_age: u128 },
+
+  #[opcode(373)]
+  SetStageSatsPricing { stage_id: u128, enabled: u128 },
+
+  #[opcode(374)]
+  UploadHonoraryChunk { index: u128, chunk: u128, length: u128 },
+
+  #[opcode(375)]
+  SealHonoraryArt { index: u128 },
+
+  #[opcode(376)]
+  ClearHonoraryArt { index: u128 },
+
+  #[opcode(377)]
+  SetDiscountTier { min_count: u128, bps: u128 },
+
+  #[opcode(378)]
+  SetMaxMintsPerAddress { max: u128 },
+
   #[opcode(99)]
   #[returns(String)]
   GetName,
@@ -72,6 +704,10 @@ enum CollectionMessage {
   #[returns(u128)]
   GetOrbitalCount,
 
+  #[opcode(103)]
+  #[returns(u128)]
+  GetProtocolVersion,
+
   #[opcode(999)]
   #[returns(String)]
   GetAttributes { index: u128 },
@@ -90,88 +726,738 @@ enum CollectionMessage {
 
   #[opcode(1003)]
   #[returns(u128)]
-  GetStageTotalMinted { stage_id: u128 }
+  GetStageTotalMinted { stage_id: u128 },
+
+  #[opcode(1004)]
+  #[returns(String)]
+  GetDataUri { index: u128 },
+
+  #[opcode(1005)]
+  #[returns(String)]
+  GetMetadata { index: u128 },
+
+  #[opcode(1006)]
+  #[returns(Vec<u8>)]
+  GetThumbnail { index: u128 },
+
+  #[opcode(1007)]
+  #[returns(String)]
+  GetContentType,
+
+  #[opcode(1008)]
+  #[returns(Vec<u8>)]
+  GetMetadataState,
+
+  #[opcode(1009)]
+  #[returns(Vec<u8>)]
+  GetActiveStages,
+
+  #[opcode(1010)]
+  #[returns(String)]
+  GetStageInfo { stage_id: u128 },
+
+  #[opcode(1011)]
+  #[returns(String)]
+  GetTimeline,
+
+  #[opcode(1012)]
+  #[returns(Vec<u8>)]
+  GetRoleCredential { role: u128 },
+
+  #[opcode(1013)]
+  #[returns(Vec<u8>)]
+  GetRoyaltyInfo { index: u128, sale_amount: u128 },
+
+  #[opcode(1014)]
+  #[returns(u128)]
+  IsApprovedVenue { block: u128, tx: u128 },
+
+  #[opcode(1015)]
+  #[returns(Vec<u8>)]
+  GetVenues,
+
+  #[opcode(1016)]
+  #[returns(Vec<u8>)]
+  GetVenueState { index: u128 },
+
+  #[opcode(1017)]
+  #[returns(String)]
+  GetFeatured,
+
+  #[opcode(1018)]
+  #[returns(String)]
+  GetRental { index: u128 },
+
+  #[opcode(1019)]
+  #[returns(String)]
+  GetReceiptInfo { block: u128, tx: u128 },
+
+  #[opcode(1020)]
+  #[returns(String)]
+  GetLoan { loan_id: u128 },
+
+  #[opcode(1021)]
+  #[returns(u128)]
+  GetLoanAmountDue { loan_id: u128 },
+
+  #[opcode(1022)]
+  #[returns(u128)]
+  GetLoanOf { index: u128 },
+
+  #[opcode(1023)]
+  #[returns(String)]
+  GetStake { index: u128 },
+
+  #[opcode(1024)]
+  #[returns(Vec<u8>)]
+  CheckAccess { index: u128, policy_id: u128 },
+
+  #[opcode(1025)]
+  #[returns(String)]
+  GetAccessPolicy { policy_id: u128 },
+
+  #[opcode(1026)]
+  #[returns(String)]
+  GetAccessPolicies,
+
+  #[opcode(1027)]
+  #[returns(String)]
+  GetPoints { index: u128 },
+
+  #[opcode(1028)]
+  #[returns(String)]
+  GetLeaderboard { season: u128 },
+
+  #[opcode(1029)]
+  #[returns(String)]
+  GetPointsTable,
+
+  #[opcode(1030)]
+  #[returns(String)]
+  GetQuests,
+
+  #[opcode(1031)]
+  #[returns(u128)]
+  IsQuestCompleted { index: u128, quest_id: u128 },
+
+  #[opcode(1032)]
+  #[returns(String)]
+  GetSybilGate,
+
+  #[opcode(1033)]
+  #[returns(String)]
+  GetHooksConfig,
+
+  #[opcode(1034)]
+  #[returns(Vec<u8>)]
+  SimulateMint { stage_id: u128, count: u128 },
+
+  #[opcode(1035)]
+  #[returns(Vec<u8>)]
+  GetQuote { stage_id: u128, count: u128 },
+
+  #[opcode(1036)]
+  #[returns(String)]
+  GetInitializationState,
+
+  #[opcode(1037)]
+  #[returns(Vec<u8>)]
+  GetConfigHash,
+
+  #[opcode(1038)]
+  #[returns(u128)]
+  GetDeploymentMode,
+
+  #[opcode(1039)]
+  #[returns(Vec<u8>)]
+  GetWhitelistRoot { stage_id: u128 },
+
+  #[opcode(1040)]
+  #[returns(String)]
+  GetWhitelistScheme { stage_id: u128 },
+
+  #[opcode(1041)]
+  #[returns(String)]
+  GetIdentityOfCaller,
+
+  #[opcode(1042)]
+  #[returns(Vec<u8>)]
+  GetMintedBy { index: u128 },
+
+  #[opcode(1043)]
+  #[returns(String)]
+  GetPartner { partner_id: u128 },
+
+  #[opcode(1044)]
+  #[returns(Vec<u8>)]
+  GetPartnerSettlement { partner_id: u128 },
+
+  #[opcode(1045)]
+  #[returns(String)]
+  GetClawbackStatus { index: u128 },
+
+  #[opcode(1046)]
+  #[returns(String)]
+  GetCompliance { index: u128 },
+
+  #[opcode(1047)]
+  #[returns(u128)]
+  GetRemainingMints { stage_id: u128, identity_low: u128, identity_high: u128 },
+
+  #[opcode(1048)]
+  #[returns(String)]
+  GetLicense { index: u128 },
+
+  #[opcode(1049)]
+  #[returns(u128)]
+  GetDerivativeCount { index: u128 },
+
+  #[opcode(1050)]
+  #[returns(String)]
+  GetDerivative { index: u128, position: u128 },
+
+  #[opcode(1051)]
+  #[returns(Vec<u8>)]
+  GetPaymentToken,
+
+  #[opcode(1052)]
+  #[returns(u128)]
+  GetTreasuryBalance,
+
+  #[opcode(1053)]
+  #[returns(Vec<u8>)]
+  GetFullData { index: u128 },
+
+  #[opcode(1054)]
+  #[returns(String)]
+  GetUnlockable { index: u128 },
+
+  #[opcode(1055)]
+  #[returns(String)]
+  GetStages,
+
+  #[opcode(1056)]
+  #[returns(Vec<u8>)]
+  GetUnlockableChunk { index: u128, epoch: u128, chunk: u128 },
+
+  #[opcode(1057)]
+  #[returns(String)]
+  GetContentKeyState { index: u128 },
+
+  #[opcode(1058)]
+  #[returns(String)]
+  GetMetadataChangelog { index: u128 },
+
+  #[opcode(1059)]
+  #[returns(Vec<u8>)]
+  DryRunRender { index: u128, preview_gate: u128 },
+
+  #[opcode(1060)]
+  #[returns(String)]
+  GetPublicMint,
+
+  #[opcode(1061)]
+  #[returns(String)]
+  GetRenderCanary,
+
+  #[opcode(1062)]
+  #[returns(String)]
+  GetInstances { cursor: u128, limit: u128 },
+
+  #[opcode(1063)]
+  #[returns(String)]
+  GetAttributesBatch { cursor: u128, limit: u128 },
+
+  #[opcode(1064)]
+  #[returns(String)]
+  GetReceipts { cursor: u128, limit: u128 },
+
+  #[opcode(1065)]
+  #[returns(u128)]
+  GetCurrentPrice { stage_id: u128 },
+
+  #[opcode(1066)]
+  #[returns(u128)]
+  GetMaxMintPerBlock { stage_id: u128 },
+
+  #[opcode(1067)]
+  #[returns(String)]
+  GetStakes { cursor: u128, limit: u128 },
+
+  #[opcode(1068)]
+  #[returns(String)]
+  GetCommitment { commit_id: u128 },
+
+  #[opcode(1069)]
+  #[returns(String)]
+  GetStorageUsage,
+
+  #[opcode(1070)]
+  #[returns(String)]
+  GetEscrow { escrow_id: u128 },
+
+  #[opcode(1071)]
+  #[returns(u128)]
+  GetMaxSupply,
+
+  #[opcode(1072)]
+  #[returns(String)]
+  GetEvents { topic: u128, start: u128, count: u128 },
+
+  #[opcode(1073)]
+  #[returns(String)]
+  GetMintStatus,
+
+  #[opcode(1074)]
+  #[returns(String)]
+  GetListeners { topic: u128 },
+
+  #[opcode(1075)]
+  #[returns(u128)]
+  GetListenerBalance { topic: u128, block: u128, tx: u128 },
+
+  #[opcode(1076)]
+  #[returns(Vec<u8>)]
+  Multicall { calls: u128 },
+
+  #[opcode(1077)]
+  #[returns(String)]
+  GetVoucher { identity_low: u128, identity_high: u128 },
+
+  #[opcode(1078)]
+  #[returns(String)]
+  GetOpcodeClasses,
+
+  #[opcode(1079)]
+  #[returns(String)]
+  GetMintedInStage { stage_id: u128 },
+
+  #[opcode(1080)]
+  #[returns(String)]
+  GetAddressMintCount { stage_id: u128, identity_low: u128, identity_high: u128 },
+
+  #[opcode(1081)]
+  #[returns(u128)]
+  GetPremineRemaining,
+
+  #[opcode(1082)]
+  #[returns(String)]
+  GetVesting,
+
+  #[opcode(1083)]
+  #[returns(String)]
+  GetTraitTables,
+
+  #[opcode(1084)]
+  #[returns(String)]
+  GetReferralRewards { code_txid_low: u128, code_txid_high: u128, code_vout: u128, block: u128, tx: u128 },
+
+  #[opcode(1085)]
+  #[returns(u128)]
+  GetRemainingSupply,
+
+  #[opcode(1086)]
+  #[returns(String)]
+  GetFinale,
+
+  #[opcode(1087)]
+  #[returns(String)]
+  GetWaitlist { ticket_txid_low: u128, ticket_txid_high: u128, ticket_vout: u128 },
+
+  #[opcode(1089)]
+  #[returns(String)]
+  GetAcceptedPayments { stage_id: u128 },
+
+  #[opcode(1090)]
+  #[returns(u128)]
+  GetTreasuryTokenBalance { block: u128, tx: u128 },
+
+  #[opcode(1091)]
+  #[returns(String)]
+  GetPriceOracle,
+
+  #[opcode(1092)]
+  #[returns(String)]
+  GetRenderEngineInfo,
+
+  #[opcode(1093)]
+  #[returns(String)]
+  GetDiscountTiers,
+
+  #[opcode(1094)]
+  #[returns(Vec<u8>)]
+  GetDataHash { index: u128 },
+
+  #[opcode(1095)]
+  #[returns(u128)]
+  GetRemainingLifetimeMints { identity_low: u128, identity_high: u128 },
+
+  #[opcode(1096)]
+  #[returns(String)]
+  GetRevenueByStage { stage_id: u128 },
+}
+
+impl Token for Collection {
+  fn name(&self) -> String {
+    let name: Arc<Vec<u8>> = self.name_pointer().get();
+    if name.is_empty() {
+      return String::from(CONTRACT_NAME);
+    }
+    String::from_utf8_lossy(&name).into_owned()
+  }
+
+  fn symbol(&self) -> String {
+    let symbol: Arc<Vec<u8>> = self.symbol_pointer().get();
+    if symbol.is_empty() {
+      return String::from(CONTRACT_SYMBOL);
+    }
+    String::from_utf8_lossy(&symbol).into_owned()
+  }
 }
 
-impl Token for Collection {
-  fn name(&self) -> String {
-    return String::from(CONTRACT_NAME)
-  }
+impl Collection {
+    /// Deploy the collection. The name is packed into two u128 parts and the symbol into
+    /// one, little endian and zero padded; zero parts keep the built in defaults. `premine`
+    /// orbitals are set aside for the owner to airdrop, 0 for none.
+    fn initialize(&self, name_part1: u128, name_part2: u128, symbol: u128, max_supply: u128, premine: u128, engine: u128) -> Result<CallResponse> {
+        self.observe_first_initialization()?;
+        self.initialize_token_identity(name_part1, name_part2, symbol)?;
+        self.initialize_render_engine(engine)?;
+        self.initialize_mint_stages()?;
+        self.initialize_premine(premine)?;
+        self.initialize_max_supply(max_supply)?;
+        self.observe_deployable_config()?;
+        let context: alkanes_support::context::Context = self.context()?;
+
+        let state: InitializationState = InitializationState {
+            initialized: true,
+            block: self.height(),
+            initializer: orbital_support::hex::encode(&self.minter_identity()?),
+            caller_block: context.caller.block,
+            caller_tx: context.caller.tx,
+            config_hash: orbital_support::hex::encode(&self.commit_config()?),
+            mode: self.commit_mode(),
+        };
+        self.set_initialization_state(&state)?;
+
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        // Collection token acts as auth token for owner operations
+        // and contract minting without any limits
+        response.alkanes.0.push(AlkaneTransfer {
+            id: context.myself.clone(),
+            value: 1u128,
+        });
+
+        Ok(response)
+    }
+
+    /// Persist the name and symbol given to `Initialize`
+    fn initialize_token_identity(&self, name_part1: u128, name_part2: u128, symbol: u128) -> Result<()> {
+        let name: String = format!("{}{}", decode_u128_to_string(name_part1), decode_u128_to_string(name_part2));
+        let symbol: String = decode_u128_to_string(symbol);
+
+        if name_part1 == 0 && name_part2 != 0 {
+            return Err(revert!(ERR_INVALID_INPUT, "Name must start in name_part1"));
+        }
+
+        if !name.is_empty() {
+            self.name_pointer().set(Arc::new(name.into_bytes()));
+        }
+
+        if !symbol.is_empty() {
+            self.symbol_pointer().set(Arc::new(symbol.into_bytes()));
+        }
+
+        Ok(())
+    }
+
+    /// Persist the art engine given to `Initialize`, one of the `render_engine` ids
+    fn initialize_render_engine(&self, engine: u128) -> Result<()> {
+        if engine >= render_engine::ENGINE_COUNT {
+            return Err(Revert::new(ERR_INVALID_INPUT, "Unknown render engine")
+                .field("engine").expected(render_engine::ENGINE_COUNT - 1).actual(engine).into());
+        }
+
+        self.render_engine_pointer().set_value::<u128>(engine);
+        Ok(())
+    }
+
+    /// Fix the collection supply for good. 0 takes the supply the stages and public mint
+    /// offer at initialization; stages added later share this supply rather than raise it.
+    fn initialize_max_supply(&self, max_supply: u128) -> Result<()> {
+        let max_supply: u128 = if max_supply == 0 { self.max_mints() } else { max_supply };
+        let art: u128 = self.svg_generator()?.trait_count();
+
+        if max_supply == 0 || max_supply > art {
+            return Err(Revert::new(ERR_INVALID_INPUT, format!("Max supply must be 1 to {}, one orbital per trait entry", art))
+                .field("max_supply").expected(art).actual(max_supply).into());
+        }
+
+        self.max_supply_pointer().set_value::<u128>(max_supply);
+        Ok(())
+    }
+
+    /// Returns the fixed collection supply as u128
+    fn get_max_supply(&self) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        response.data = self.max_supply().to_le_bytes().to_vec();
+        Ok(response)
+    }
+
+    /// Returns whether, when and by whom the collection was initialized as JSON
+    fn get_initialization_state(&self) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let state: InitializationState = self.initialization_state()?;
+        response.data = serde_json::to_vec(&state)
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to serialize initialization state"))?;
+        Ok(response)
+    }
+
+    fn get_name(&self) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        response.data = self.name().into_bytes();
+
+        Ok(response)
+    }
+
+    fn get_symbol(&self) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        response.data = self.symbol().into_bytes();
+
+        Ok(response)
+    }
+
+    fn get_total_supply(&self) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        response.data = 1u128.to_le_bytes().to_vec();
+
+        Ok(response)
+    }
+
+    fn get_orbital_count(&self) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        response.data = self.instances_count().to_le_bytes().to_vec();
+
+        Ok(response)
+    }
+
+    fn get_protocol_version(&self) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        response.data = PROTOCOL_VERSION.to_le_bytes().to_vec();
+
+        Ok(response)
+    }
+
+    fn get_attributes(&self, index: u128) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let attributes: String = if receipts::is_receipt_index(index) {
+            self.receipt_attributes(index)?
+        } else {
+            self.render_engine()?.attributes(self.art_of(index))?
+        };
+        response.data = attributes.into_bytes();
+        Ok(response)
+    }
+
+    fn get_data(&self, index: u128) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let svg: String = if receipts::is_receipt_index(index) {
+            self.receipt_data(index)?
+        } else {
+            self.render_cached(cache::CACHE_RENDERS, index)?
+        };
+        response.data = svg.into_bytes();
+        Ok(response)
+    }
+
+    /// Returns the sha256 of the `GetData` payload of an orbital (32 bytes), then what it is
+    /// rendered from: the trait entry (u128), trait table version (u128), preview flag (u8)
+    /// and whether it shows honorary art (u8). Off-chain renderers verify against it.
+    fn get_data_hash(&self, index: u128) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        if receipts::is_receipt_index(index) {
+            response.data = self.receipt_data_hash(index)?;
+            return Ok(response);
+        }
+
+        let config: RenderConfig = self.render_config_for(index)?;
+        let svg: String = self.render_cached(cache::CACHE_RENDERS, index)?;
+
+        let mut data: Vec<u8> = Vec::with_capacity(66);
+        data.extend_from_slice(&sha256::Hash::hash(svg.as_bytes()).to_byte_array());
+        data.extend_from_slice(&self.art_of(index).to_le_bytes());
+        data.extend_from_slice(&config.traits_version.to_le_bytes());
+        data.push(config.preview as u8);
+        data.push(self.has_honorary_art(index) as u8);
+        response.data = data;
+        Ok(response)
+    }
+
+    /// Render an orbital under the current configuration and under one with the proposed
+    /// preview gate. Returns the sha256 of both `GetData` payloads, current first.
+    fn dry_run_render(&self, index: u128, preview_gate: u128) -> Result<CallResponse> {
+        self.only_owner()?;
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
 
-  fn symbol(&self) -> String {
-    return String::from(CONTRACT_SYMBOL);
-  }
-}
+        let current: RenderConfig = self.render_config_for(index)?;
+        let proposed: RenderConfig = RenderConfig { preview: preview_gate != 0, ..self.render_config() };
 
-impl Collection {
-    fn initialize(&self) -> Result<CallResponse> {
-        self.observe_initialization()?;
-        self.initialize_mint_stages()?;
+        let art: u128 = self.art_of(index);
+        let mut data: Vec<u8> = Vec::with_capacity(64);
+        data.extend_from_slice(&sha256::Hash::hash(self.orbital_engine(index, current.traits_version)?.render(art, &current)?.as_bytes()).to_byte_array());
+        data.extend_from_slice(&sha256::Hash::hash(self.orbital_engine(index, proposed.traits_version)?.render(art, &proposed)?.as_bytes()).to_byte_array());
+        response.data = data;
+        Ok(response)
+    }
+
+    /// Full quality rendering, only served to the holder while previews are gated
+    fn get_full_data(&self, index: u128) -> Result<CallResponse> {
+        let config: RenderConfig = self.render_config_for(index)?;
+        if config.preview {
+            self.only_holder(index)?;
+        }
         let context: alkanes_support::context::Context = self.context()?;
         let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
 
-        if PREMINE_MINTS > 0 {
-            // Collection token acts as auth token for contract minting without any limits
-            response.alkanes.0.push(AlkaneTransfer {
-                id: context.myself.clone(),
-                value: 1u128,
-            });
+        let full: RenderConfig = RenderConfig { preview: false, ..config };
+        let rendering: String = self.orbital_engine(index, config.traits_version)?.render(self.art_of(index), &full)?;
+        response.data = rendering.into_bytes();
+        Ok(response)
+    }
+
+    /// Serve watermarked previews publicly and the full rendering only to holders
+    fn set_preview_gate(&self, enabled: u128) -> Result<CallResponse> {
+        self.only_owner()?;
+        let context: alkanes_support::context::Context = self.context()?;
+        let response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        if self.is_metadata_frozen() {
+            return Err(revert!(ERR_INVALID_STATE, "Preview gate cannot change once the metadata is frozen"));
         }
 
+        self.apply_render_config(&RenderConfig { preview: enabled != 0, ..self.render_config() })?;
         Ok(response)
     }
 
-    fn get_name(&self) -> Result<CallResponse> {
+    fn get_data_uri(&self, index: u128) -> Result<CallResponse> {
         let context: alkanes_support::context::Context = self.context()?;
         let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
 
-        response.data = self.name().into_bytes();
+        if receipts::is_receipt_index(index) {
+            response.data = self.receipt_data_uri(index)?.into_bytes();
+            return Ok(response);
+        }
 
+        let config: RenderConfig = self.render_config_for(index)?;
+        let data_uri: String = self.orbital_engine(index, config.traits_version)?.data_uri(self.art_of(index), &config)?;
+        response.data = data_uri.into_bytes();
         Ok(response)
     }
 
-    fn get_symbol(&self) -> Result<CallResponse> {
+    fn get_metadata(&self, index: u128) -> Result<CallResponse> {
         let context: alkanes_support::context::Context = self.context()?;
         let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
 
-        response.data = self.symbol().into_bytes();
+        if receipts::is_receipt_index(index) {
+            response.data = self.receipt_metadata(index)?.into_bytes();
+            return Ok(response);
+        }
 
+        let config: RenderConfig = self.render_config_for(index)?;
+        let metadata: String = self.orbital_engine(index, config.traits_version)?
+            .metadata(index, self.art_of(index), &self.name(), &config)?;
+        response.data = metadata.into_bytes();
         Ok(response)
     }
 
-    fn get_total_supply(&self) -> Result<CallResponse> {
+    fn get_thumbnail(&self, index: u128) -> Result<CallResponse> {
         let context: alkanes_support::context::Context = self.context()?;
         let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
 
-        response.data = 1u128.to_le_bytes().to_vec();
+        let svg: String = if receipts::is_receipt_index(index) {
+            self.receipt_thumbnail(index)?
+        } else {
+            self.render_cached(cache::CACHE_THUMBNAILS, index)?
+        };
+        response.data = svg.into_bytes();
+        Ok(response)
+    }
 
+    fn get_content_type(&self) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        response.data = String::from(self.render_engine()?.content_type()).into_bytes();
         Ok(response)
     }
 
-    fn get_orbital_count(&self) -> Result<CallResponse> {
+    /// Returns the frozen flag followed by the metadata version, both as u128
+    fn get_metadata_state(&self) -> Result<CallResponse> {
         let context: alkanes_support::context::Context = self.context()?;
         let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
 
-        response.data = self.instances_count().to_le_bytes().to_vec();
+        let frozen: u128 = if self.is_metadata_frozen() { 1 } else { 0 };
+
+        let mut bytes: Vec<u8> = Vec::with_capacity(32);
+        bytes.extend_from_slice(&frozen.to_le_bytes());
+        bytes.extend_from_slice(&self.metadata_version().to_le_bytes());
 
+        response.data = bytes;
         Ok(response)
     }
 
-    fn get_attributes(&self, index: u128) -> Result<CallResponse> {
+    /// Permanently lock the rendered output of every orbital
+    fn freeze_metadata(&self) -> Result<CallResponse> {
+        self.only_owner()?;
         let context: alkanes_support::context::Context = self.context()?;
-        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+        let response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
 
-        let attributes: String = SvgGenerator::get_attributes(index)?;
-        response.data = attributes.into_bytes();
+        if self.is_metadata_frozen() {
+            return Err(revert!(ERR_INVALID_STATE, "Metadata is already frozen"));
+        }
+
+        self.observe_no_live_render_canary()?;
+        self.settle_lapsed_render_canary()?;
+        self.metadata_frozen_pointer().set_value::<u8>(1);
+        self.record_metadata_change(changelog::CHANGE_FREEZE, None, 1)?;
         Ok(response)
     }
 
-    fn get_data(&self, index: u128) -> Result<CallResponse> {
+    /// Announce the block at which the art is revealed, shown in the launch timeline
+    fn set_reveal_block(&self, block: u128) -> Result<CallResponse> {
+        self.only_owner()?;
         let context: alkanes_support::context::Context = self.context()?;
-        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+        let response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
 
-        let svg: String = SvgGenerator::generate_svg(index)?;
-        response.data = svg.into_bytes();
+        if self.is_metadata_frozen() {
+            return Err(revert!(ERR_INVALID_STATE, "Metadata is frozen"));
+        }
+
+        let block: u64 = u64::try_from(block).map_err(|_| revert!(ERR_INVALID_INPUT, "Reveal block out of range"))?;
+        self.reveal_block_pointer().set_value::<u64>(block);
+        self.record_metadata_change(changelog::CHANGE_REVEAL_BLOCK, None, block as u128)?;
         Ok(response)
     }
 
@@ -209,57 +1495,216 @@ impl Collection {
 
         Ok(response)
     }
-    /// Mint from a stage
-    fn mint_in_stage(&self, stage_id: u128) -> Result<CallResponse> {
-        // @todo - determine the minter address from context instead of receiving it as a parameter
-        let mut stages: Vec<Stage> = self.get_mint_stages()?;
-        // let mut stage: Stage = self.get_mint_stage(stage_id)?;
+    /// Returns the stage as JSON, including its current soft-close extension state
+    fn get_stage_info(&self, stage_id: u128) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let stage: Stage = self.get_mint_stage(stage_id)?;
+
+        response.data = serde_json::to_vec(&stage)
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to serialize stage"))?;
+        Ok(response)
+    }
+
+    /// Returns every stage window, the reveal block and freeze status as JSON
+    fn get_timeline(&self) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let block_height: u64 = self.height();
+        let block_time: Option<u64> = self.block_time();
+
+        let stages: Vec<StageWindow> = self.get_mint_stages()?
+            .into_iter()
+            .map(|stage| StageWindow {
+                id: stage.id,
+                schedule_mode: stage.schedule_mode,
+                start_block: stage.start_block,
+                end_block: stage.end_block,
+                start_time: stage.start_time,
+                end_time: stage.end_time,
+                soft_close_window: stage.soft_close_window,
+                soft_close_extension: stage.soft_close_extension,
+                max_extension: stage.max_extension,
+                extended_by: stage.extended_by,
+                active: stage.is_active(block_height, block_time),
+            })
+            .collect();
+
+        let timeline: Timeline = Timeline {
+            current_block: block_height,
+            current_time: block_time,
+            stages,
+            reveal_block: self.reveal_block(),
+            metadata_frozen: self.is_metadata_frozen(),
+            mode: self.deployment_mode(),
+        };
+
+        response.data = serde_json::to_vec(&timeline)
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to serialize timeline"))?;
+        Ok(response)
+    }
+
+    /// Returns the IDs of the currently active stages as consecutive u128 values
+    fn get_active_stages(&self) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
         let block_height: u64 = self.height();
+        let block_time: Option<u64> = self.block_time();
+
+        let mut bytes: Vec<u8> = Vec::new();
+        for stage in self.get_mint_stages()? {
+            if stage.is_active(block_height, block_time) {
+                bytes.extend_from_slice(&stage.id.to_le_bytes());
+            }
+        }
+
+        response.data = bytes;
+        Ok(response)
+    }
+
+    /// Mint from a stage, attributing the mint to output `vout` of this transaction
+    fn mint_in_stage_to(&self, stage_id: u128, vout: u128) -> Result<CallResponse> {
+        self.designate_output(vout)?;
+        self.mint_from_stage(stage_id, MINT_IN_STAGE_TO_HEADER, None)
+    }
 
-        let stage: &mut Stage = stages.iter_mut().find(|s| s.id == stage_id).ok_or_else(|| anyhow!("stage with ID {} not found", stage_id))?;
+    /// Mint from a stage, whitelist proof nodes follow the stage ID
+    fn mint_in_stage(&self, stage_id: u128) -> Result<CallResponse> {
+        self.mint_from_stage(stage_id, MINT_IN_STAGE_HEADER, None)
+    }
+
+    /// Mint from a stage with the whitelist proof read from the inputs past `proof_header`,
+    /// rewarding the referrer of the minter if there is one
+    fn mint_from_stage(&self, stage_id: u128, proof_header: usize, referrer: Option<Ticket>) -> Result<CallResponse> {
+        let change: Vec<AlkaneTransfer> = match self.reserve_stage_mint(stage_id, proof_header)? {
+            Some((change, payment)) => {
+                if let (Some(referrer), Some((token, price))) = (referrer, payment) {
+                    self.reward_referrer(&referrer, &token, price)?;
+                }
+                change
+            }
+            None => {
+                // Declined without reverting so the hook failure stays recorded, the payment is returned
+                let context: alkanes_support::context::Context = self.context()?;
+                return Ok(CallResponse::forward(&context.incoming_alkanes));
+            }
+        };
+
+        // Proceed with minting
+        let mut response: CallResponse = CallResponse::default();
+        response.alkanes.0 = change;
+        if self.should_queue_mint(stage_id) {
+            self.enqueue_mint(stage_id)?;
+            return Ok(response);
+        }
+
+        self.observe_mint_per_block(stage_id)?;
+        let (index, transfer) = self.create_mint_transfer()?;
+        response.alkanes.0.push(transfer);
+        self.record_minter(index)?;
+        self.run_post_mint_hook(stage_id, index)?;
+        Ok(response)
+    }
 
-        if stage.start_block > block_height || stage.end_block < block_height {
-            return Err(anyhow!("Stage is not active"));
+    /// Check a stage mint for the minter of the transaction, collect its payment and count
+    /// it against the stage. Returns the change and the currency and price paid, none for a
+    /// free mint, or None when the pre-mint hook declined.
+    fn reserve_stage_mint(&self, stage_id: u128, proof_header: usize) -> Result<Option<(Vec<AlkaneTransfer>, Option<(AlkaneId, u128)>)>> {
+        self.observe_mint_open()?;
+        self.settle_stage_rollovers()?;
+        let stage: Stage = self.get_mint_stage(stage_id)?;
+        let block_height: u64 = self.height();
+
+        if !stage.is_active(block_height, self.block_time()) {
+            return Err(revert!(ERR_INVALID_STATE, "Stage is not active"));
         }
 
         if stage.total_minted + 1 > stage.max_supply {
-            return Err(anyhow!("Exceeds max supply for this stage"));
+            return Err(Revert::new(ERR_LIMIT_EXCEEDED, "Exceeds max supply for this stage")
+                .field("max_supply").expected(stage.max_supply).actual(stage.total_minted + 1).into());
         }
 
-        // @todo - Implement payment collection,
-        // Add storage for payment that did not receive
-        // orbital due to block limit or whitelist
+        let minter: [u8; 32] = self.minter_identity()?;
+        // Whitelist spots and allowances belong to the identity, so the orbital must reach it
+        if stage.whitelist_root != [0; 32] || stage.max_mints_per_address != 0 || self.max_mints_per_address() != 0 {
+            self.observe_delivered_to_minter()?;
+        }
+        self.observe_whitelist(&stage, &minter, proof_header)?;
+        self.observe_address_limit(&stage, &minter, 1)?;
 
-        // Increase total_minted for stage
-        stage.total_minted += 1;
-        // Update the stage
-        self.set_mint_stages(stages)?;
-        // Proceed with minting
-        self.mint_orbital()
+        self.observe_sybil_score(stage_id)?;
 
-    }
+        if !self.run_pre_mint_hook(stage_id, self.instances_count())? {
+            return Ok(None);
+        }
 
-    fn mint_orbital(&self) -> Result<CallResponse> {
+        // Payment goes to the treasury, any excess back to the minter
         let context: alkanes_support::context::Context = self.context()?;
-        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
-        self.observe_mint_per_block()?;
-        response.alkanes.0.push(self.create_mint_transfer()?);
+        let (price, _) = self.stage_quote(&stage, 1, block_height)?;
+        let payment: Option<(AlkaneId, u128)> = self.stage_payment(&stage, &context.incoming_alkanes, price)?;
+        self.observe_token_gate(&stage, &context.incoming_alkanes, payment)?;
+        let change: Vec<AlkaneTransfer> = match payment {
+            Some((token, price)) => {
+                self.record_currency_mint(stage_id, &token)?;
+                self.record_stage_revenue(stage_id, &token, price)?;
+                self.record_stage_sats_revenue(&stage, &token, 1, block_height)?;
+                self.collect_payment_in(&context.incoming_alkanes, &token, price)?
+            }
+            None => context.incoming_alkanes.0.clone(),
+        };
 
-        Ok(response)
+        self.record_address_mints(stage_id, &minter, 1)?;
+
+        // Count the mint against the stage as stored now, the hooks may have minted meanwhile
+        self.update_mint_stage(stage_id, |stage| stage.record_mints(1, block_height))?;
+        Ok(Some((change, payment)))
     }
 
-    fn create_mint_transfer(&self) -> Result<AlkaneTransfer> {
+    /// Deploy the next orbital, returning its index and the minted token
+    fn create_mint_transfer(&self) -> Result<(u128, AlkaneTransfer)> {
+        self.observe_mint_open()?;
         let index: u128 = self.instances_count();
+        // Orbitals held by queued mints are out of reach
+        let reserved: u128 = index + self.waitlist_pending();
 
-        if index >= self.max_mints() {
-            return Err(anyhow!("Alkane Pandas have fully minted out"));
+        if reserved >= self.max_mints() {
+            return Err(revert!(ERR_LIMIT_EXCEEDED, "Alkane Pandas have fully minted out"));
         }
 
+        if reserved >= self.max_supply() {
+            return Err(Revert::new(ERR_LIMIT_EXCEEDED, "Collection max supply reached")
+                .field("max_supply").expected(self.max_supply()).actual(reserved + 1).into());
+        }
+
+        let (orbital_id, transfer) = self.deploy_instance(index, &self.name(), &self.symbol())?;
+        self.add_instance(&orbital_id)?;
+        self.record_mint_event(&events::MintEvent {
+            index,
+            block: orbital_id.block,
+            tx: orbital_id.tx,
+            height: self.height(),
+        })?;
+        self.award_points(index, points::ACTION_MINT)?;
+        self.record_finale(index)?;
+
+        Ok((index, transfer))
+    }
+
+    /// Clone the instance template for an index and return its ID with the minted token
+    fn deploy_instance(&self, index: u128, name: &str, symbol: &str) -> Result<(AlkaneId, AlkaneTransfer)> {
+        // Names take up to 32 bytes, packed into two parts as `Initialize` takes them
+        let name_bytes: &[u8] = name.as_bytes();
+        let split: usize = name_bytes.len().min(16);
         let inputs: Vec<u128> = vec![
             0x0, 
             index, 
-            self.encode_string_to_u128(CONTRACT_NAME),
-            self.encode_string_to_u128(CONTRACT_SYMBOL)
+            self.encode_bytes_to_u128(&name_bytes[..split]),
+            self.encode_bytes_to_u128(&name_bytes[split..]),
+            self.encode_string_to_u128(symbol),
+            PROTOCOL_VERSION
         ];
 
         let cellpack: Cellpack = Cellpack {
@@ -278,46 +1723,303 @@ impl Collection {
             tx: sequence,
         };
 
-        self.add_instance(&orbital_id)?;
+        self.observe_instance_protocol(&orbital_id)?;
 
         if response.alkanes.0.len() < 1 {
-            Err(anyhow!("orbital token not returned with factory"))
+            Err(revert!(ERR_EXTERNAL_CALL, "orbital token not returned with factory"))
         } else {
-            Ok(response.alkanes.0[0])
+            Ok((orbital_id, response.alkanes.0[0]))
+        }
+    }
+
+    /// Refuse instances speaking a different protocol than this collection
+    fn observe_instance_protocol(&self, orbital_id: &AlkaneId) -> Result<()> {
+        let cellpack: Cellpack = Cellpack {
+            target: orbital_id.clone(),
+            inputs: vec![INSTANCE_GET_PROTOCOL_VERSION],
+        };
+
+        let response: CallResponse = self.staticcall(&cellpack, &AlkaneTransferParcel::default(), self.fuel())?;
+        if response.data.len() != 16 {
+            return Err(revert!(ERR_EXTERNAL_CALL, "Invalid instance protocol version response"));
         }
+
+        let version: u128 = u128::from_le_bytes(response.data[0..16].try_into().unwrap());
+        if version != PROTOCOL_VERSION {
+            return Err(Revert::new(ERR_EXTERNAL_CALL, format!(
+                "Instance protocol version {} does not match collection version {}",
+                version,
+                PROTOCOL_VERSION
+            )).field("protocol_version").expected(PROTOCOL_VERSION).actual(version).into());
+        }
+
+        Ok(())
     }
 
-    fn observe_mint_per_block(&self) -> Result<()> {
+    /// Count a mint against the collection per-block limit and the one of its stage, if any
+    fn observe_mint_per_block(&self, stage_id: u128) -> Result<()> {
         let height: u64 = self.height();
         let max_mints: u32 = self.max_mint_per_block();
 
         let hash: Vec<u8> = height.to_le_bytes().to_vec();
         let mut pointer: StoragePointer = self.seen_pointer(&hash);
 
-        let current_count: u32 = if pointer.get().len() == 0 {
-            0
-        } else {
-            pointer.get_value::<u32>()
-        };
+        let current_count: u32 = self.mints_in_block(height);
 
         if current_count < max_mints {
-            pointer.set_value::<u32>(current_count + 1);
-            Ok(())
+            counters::increment(&mut pointer, 1, max_mints as u128, "max_mint_per_block")?;
+            self.observe_stage_mint_per_block(stage_id, height)
         } else {
-            Err(anyhow!(format!(
+            Err(Revert::new(ERR_LIMIT_EXCEEDED, format!(
                 "mint limit reached for block {}",
                 hex::encode(&hash)
-            )))
+            )).field("max_mint_per_block").expected(max_mints as u128).actual(current_count as u128 + 1).into())
+        }
+    }
+
+    /// Number of orbitals minted so far at a height
+    fn mints_in_block(&self, height: u64) -> u32 {
+        let pointer: StoragePointer = self.seen_pointer(&height.to_le_bytes().to_vec());
+        if pointer.get().len() == 0 {
+            0
+        } else {
+            u32::try_from(pointer.get_value::<u128>()).unwrap_or(u32::MAX)
         }
     }
 
+    /// A second initialization fails with the block of the first one
+    fn observe_first_initialization(&self) -> Result<()> {
+        let state: InitializationState = self.initialization_state()?;
+        if state.initialized {
+            return Err(Revert::new(ERR_ALREADY_INITIALIZED, format!("Already initialized at block {}", state.block))
+                .field("initialized_at").actual(state.block as u128).into());
+        }
+
+        self.observe_initialization()
+    }
+
+    fn initialization_state_pointer(&self) -> StoragePointer {
+        StoragePointer::from_keyword("/initialization")
+    }
+
+    fn set_initialization_state(&self, state: &InitializationState) -> Result<()> {
+        let serialized: Vec<u8> = bincode::serialize(state)
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to serialize initialization state"))?;
+        self.initialization_state_pointer().set(Arc::new(serialized));
+        Ok(())
+    }
+
+    fn initialization_state(&self) -> Result<InitializationState> {
+        let stored: Arc<Vec<u8>> = self.initialization_state_pointer().get();
+        if stored.is_empty() {
+            return Ok(InitializationState::default());
+        }
+
+        bincode::deserialize(&stored).map_err(|_| revert!(ERR_STORAGE, "Failed to deserialize initialization state"))
+    }
+
+    /// Require the collection auth token among the incoming alkanes
+    fn only_owner(&self) -> Result<()> {
+        let context: alkanes_support::context::Context = self.context()?;
+
+        let has_auth_token: bool = context.incoming_alkanes.0.iter()
+            .any(|transfer| transfer.id == context.myself && transfer.value > 0);
+
+        if !has_auth_token {
+            return Err(revert!(ERR_UNAUTHORIZED, "Caller is not the collection owner"));
+        }
+
+        self.record_admin_event(context.inputs.first().copied().unwrap_or_default())
+    }
+
+    /// Require the instance token of the given index among the incoming alkanes
+    fn only_holder(&self, index: u128) -> Result<()> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let instance_id: AlkaneId = self.lookup_instance(index)?;
+
+        let holds_instance: bool = context.incoming_alkanes.0.iter()
+            .any(|transfer| transfer.id == instance_id && transfer.value > 0);
+
+        if !holds_instance {
+            return Err(revert!(ERR_UNAUTHORIZED, "Caller does not hold orbital {}", index));
+        }
+
+        Ok(())
+    }
+
+    /// Keep one unit of `orbital` out of the incoming alkanes in escrow, returning everything
+    /// else. Callers check beforehand that it was presented.
+    fn escrow_incoming_orbital(&self, incoming: &AlkaneTransferParcel, orbital: &AlkaneId) -> Vec<AlkaneTransfer> {
+        let mut rest: Vec<AlkaneTransfer> = Vec::new();
+        let mut escrowed: bool = false;
+
+        for transfer in incoming.0.iter() {
+            if transfer.id == *orbital && !escrowed {
+                escrowed = true;
+                if transfer.value > 1 {
+                    rest.push(AlkaneTransfer { id: transfer.id, value: transfer.value - 1 });
+                }
+            } else {
+                rest.push(*transfer);
+            }
+        }
+
+        rest
+    }
+
+    /// Take `amount` of `token` out of the incoming alkanes, returning everything else
+    fn take_payment(&self, incoming: &AlkaneTransferParcel, token: &AlkaneId, amount: u128) -> Result<Vec<AlkaneTransfer>> {
+        let mut outstanding: u128 = amount;
+        let mut rest: Vec<AlkaneTransfer> = Vec::new();
+
+        for transfer in incoming.0.iter() {
+            if transfer.id == *token && outstanding > 0 {
+                let used: u128 = transfer.value.min(outstanding);
+                outstanding -= used;
+                if transfer.value > used {
+                    rest.push(AlkaneTransfer { id: transfer.id, value: transfer.value - used });
+                }
+            } else {
+                rest.push(*transfer);
+            }
+        }
+
+        if outstanding > 0 {
+            return Err(Revert::new(ERR_INSUFFICIENT_PAYMENT, format!("Insufficient payment: {} short", outstanding))
+                .expected(amount).actual(amount - outstanding).into());
+        }
+
+        Ok(rest)
+    }
+
+    fn name_pointer(&self) -> StoragePointer {
+        StoragePointer::from_keyword("/name")
+    }
+
+    fn symbol_pointer(&self) -> StoragePointer {
+        StoragePointer::from_keyword("/symbol")
+    }
+
+    fn preview_gate_pointer(&self) -> StoragePointer {
+        StoragePointer::from_keyword("/preview-gate")
+    }
+
+    fn is_preview_gated(&self) -> bool {
+        self.preview_gate_pointer().get_value::<u8>() == 1
+    }
+
+    fn render_engine_pointer(&self) -> StoragePointer {
+        StoragePointer::from_keyword("/render-engine")
+    }
+
+    /// Engine id the collection was initialized with
+    pub(crate) fn render_engine_id(&self) -> u128 {
+        self.render_engine_pointer().get_value::<u128>()
+    }
+
+    /// Engine drawing the art from the active trait table
+    pub(crate) fn render_engine(&self) -> Result<Box<dyn RenderEngine>> {
+        self.render_engine_at(self.traits_version())
+    }
+
+    /// Engine drawing the art from a trait table version
+    pub(crate) fn render_engine_at(&self, version: u128) -> Result<Box<dyn RenderEngine>> {
+        render_engine::build(self.render_engine_id(), self.svg_generator_at(version)?)
+    }
+
+    fn render_config(&self) -> RenderConfig {
+        RenderConfig { preview: self.is_preview_gated(), traits_version: self.traits_version() }
+    }
+
+    /// Make a render configuration current for every orbital
+    fn apply_render_config(&self, config: &RenderConfig) -> Result<()> {
+        let enabled: u8 = if config.preview { 1 } else { 0 };
+        self.preview_gate_pointer().set_value::<u8>(enabled);
+        self.record_metadata_change(changelog::CHANGE_PREVIEW_GATE, None, enabled as u128)
+    }
+
+    fn metadata_frozen_pointer(&self) -> StoragePointer {
+        StoragePointer::from_keyword("/metadata-frozen")
+    }
+
+    fn is_metadata_frozen(&self) -> bool {
+        self.metadata_frozen_pointer().get_value::<u8>() == 1
+    }
+
+    fn reveal_block_pointer(&self) -> StoragePointer {
+        StoragePointer::from_keyword("/reveal-block")
+    }
+
+    /// Announced reveal height, 0 when not scheduled
+    fn reveal_block(&self) -> u64 {
+        self.reveal_block_pointer().get_value::<u64>()
+    }
+
+    fn metadata_version_pointer(&self) -> StoragePointer {
+        StoragePointer::from_keyword("/metadata-version")
+    }
+
+    /// Incremented by every operation that changes rendered output, a render canary that
+    /// lapsed without a call settling it counts as one
+    fn metadata_version(&self) -> u128 {
+        self.recorded_metadata_version() + self.has_lapsed_render_canary() as u128
+    }
+
+    /// Metadata version of the last logged change
+    fn recorded_metadata_version(&self) -> u128 {
+        self.metadata_version_pointer().get_value::<u128>()
+    }
+
+    /// Timestamp from the header of the block being processed.
+    /// The runtime only exposes the current block, so this stands in for median-time-past.
+    fn block_time(&self) -> Option<u64> {
+        let block: Vec<u8> = self.block();
+        if block.len() < 80 {
+            return None;
+        }
+
+        // Header layout: version (4), prev block (32), merkle root (32), time (4)
+        let time: u32 = u32::from_le_bytes(block[68..72].try_into().unwrap());
+        Some(time as u64)
+    }
+
+    /// Whether supply no queued mint holds is left for another orbital, never again once
+    /// minting was closed
+    fn can_mint_more(&self) -> bool {
+        if self.is_mint_closed() {
+            return false;
+        }
+
+        let reserved: u128 = self.instances_count() + self.waitlist_pending();
+        reserved < self.max_mints() && reserved < self.max_supply()
+    }
+
+    /// Collection supply fixed at initialization, whatever the stages add up to
+    fn max_supply(&self) -> u128 {
+        self.max_supply_pointer().get_value::<u128>()
+    }
+
+    fn max_supply_pointer(&self) -> StoragePointer {
+        StoragePointer::from_keyword("/max-supply")
+    }
+
     fn max_mints(&self) -> u128 {
-        let stages: Vec<Stage> = self.get_mint_stages().unwrap_or_default();     
-        stages.iter().map(|s| s.max_supply).sum()
+        self.stage_supply_pointer().get_value::<u128>()
+            .saturating_add(self.public_mint_supply())
+            .saturating_add(self.voucher_supply())
+            .saturating_add(self.premine())
     }
 
+    /// Collection wide per-block mint limit, the network default until the owner sets one
     fn max_mint_per_block(&self) -> u32 {
-        10
+        match self.max_mint_per_block_pointer().get_value::<u32>() {
+            0 => if self.is_canary() { 100 } else { 10 },
+            max => max,
+        }
+    }
+
+    fn max_mint_per_block_pointer(&self) -> StoragePointer {
+        StoragePointer::from_keyword("/max-mint-per-block")
     }
     
     fn seen_pointer(&self, hash: &Vec<u8>) -> StoragePointer {
@@ -339,7 +2041,7 @@ impl Collection {
     fn add_instance(&self, instance_id: &AlkaneId) -> Result<u128> {
         let count: u128 = self.instances_count();
         let new_count: u128 = count.checked_add(1)
-        .ok_or_else(|| anyhow!("instances count overflow"))?;
+        .ok_or_else(|| revert!(ERR_OVERFLOW, "instances count overflow"))?;
 
         let mut bytes: Vec<u8> = Vec::with_capacity(32);
         bytes.extend_from_slice(&instance_id.block.to_le_bytes());
@@ -348,12 +2050,24 @@ impl Collection {
         let bytes_vec: Vec<u8> = new_count.to_le_bytes().to_vec();
         let mut instance_pointer: StoragePointer = self.instances_pointer().select(&bytes_vec);
         instance_pointer.set(Arc::new(bytes));
+
+        self.instance_index_pointer(instance_id).set_value::<u128>(new_count);
         
         self.set_instances_count(new_count);
         
         Ok(new_count)
     }
         
+    /// Reverse lookup of `lookup_instance`, stored 1-based so zero means not an instance
+    fn instance_index_pointer(&self, instance_id: &AlkaneId) -> StoragePointer {
+        StoragePointer::from_keyword("/instance-index/").select(&encode_alkane_id(instance_id))
+    }
+
+    fn index_of_instance(&self, instance_id: &AlkaneId) -> Option<u128> {
+        let stored: u128 = self.instance_index_pointer(instance_id).get_value::<u128>();
+        stored.checked_sub(1)
+    }
+
     fn lookup_instance(&self, index: u128) -> Result<AlkaneId> {
         // Add 1 to index since instances are stored at 1-based indices
         let storage_index: u128 = index + 1;
@@ -363,7 +2077,7 @@ impl Collection {
         
         let bytes: Arc<Vec<u8>> = instance_pointer.get();
         if bytes.len() != 32 {
-            return Err(anyhow!("Invalid instance data length"));
+            return Err(revert!(ERR_STORAGE, "Invalid instance data length"));
         }
 
         let block_bytes: &[u8] = &bytes[..16];
@@ -374,85 +2088,178 @@ impl Collection {
 
         Ok(AlkaneId { block, tx })
     }
-    /// Storage pointer for stages
-    fn mint_stages_pointer(&self) -> StoragePointer {
-        StoragePointer::from_keyword("/stages")
+    /// Number of stage slots in use
+    fn stage_count_pointer(&self) -> StoragePointer {
+        StoragePointer::from_keyword("/stages/count")
     }
-    /// Set stages (serialized)
-    fn set_mint_stages(&self, stages: Vec<Stage>) -> Result<()> {
-        let mut stages_pointer: StoragePointer = self.mint_stages_pointer();
-        let serialized_stages: Vec<u8> = bincode::serialize(&stages)
-            .map_err(|_| anyhow!("Failed to serialize stages"))?;
-        
-        stages_pointer.set(Arc::new(serialized_stages));
+    /// Serialized stage held in a slot
+    fn stage_slot_pointer(&self, slot: u128) -> StoragePointer {
+        StoragePointer::from_keyword("/stages/slots/").select(&slot.to_le_bytes().to_vec())
+    }
+    /// Slot of a stage ID plus one, 0 when the stage does not exist
+    fn stage_slot_of_pointer(&self, stage_id: u128) -> StoragePointer {
+        StoragePointer::from_keyword("/stages/ids/").select(&stage_id.to_le_bytes().to_vec())
+    }
+    /// Sum of the max supply of every stage, kept alongside the stages
+    fn stage_supply_pointer(&self) -> StoragePointer {
+        StoragePointer::from_keyword("/stages/supply")
+    }
+
+    fn read_stage_slot(&self, slot: u128) -> Result<Stage> {
+        bincode::deserialize(&self.stage_slot_pointer(slot).get())
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to deserialize stage"))
+    }
+
+    fn write_stage_slot(&self, slot: u128, stage: &Stage) -> Result<()> {
+        let serialized: Vec<u8> = bincode::serialize(stage)
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to serialize stage"))?;
+        self.stage_slot_pointer(slot).set(Arc::new(serialized));
         Ok(())
     }
-    /// Get all stages (deserialized)
-    fn get_mint_stages(&self) -> Result<Vec<Stage>> {
-        let stages_pointer: StoragePointer = self.mint_stages_pointer();
-        let stored_data: Arc<Vec<u8>> = stages_pointer.get();
-        
-        if stored_data.is_empty() {
-            return Ok(vec![]); // No stages initialized yet
+
+    /// Create or overwrite a single stage, leaving every other stage untouched
+    fn save_mint_stage(&self, stage: &Stage) -> Result<()> {
+        let mut slot_of: StoragePointer = self.stage_slot_of_pointer(stage.id);
+        let previous_supply: u128 = match slot_of.get_value::<u128>() {
+            0 => {
+                let mut count_pointer: StoragePointer = self.stage_count_pointer();
+                let slot: u128 = count_pointer.get_value::<u128>();
+                count_pointer.set_value::<u128>(slot + 1);
+                slot_of.set_value::<u128>(slot + 1);
+                0
+            }
+            slot => self.read_stage_slot(slot - 1)?.max_supply,
+        };
+
+        self.write_stage_slot(slot_of.get_value::<u128>() - 1, stage)?;
+
+        let mut supply_pointer: StoragePointer = self.stage_supply_pointer();
+        let supply: u128 = supply_pointer.get_value::<u128>().saturating_sub(previous_supply)
+            .checked_add(stage.max_supply)
+            .ok_or_else(|| revert!(ERR_OVERFLOW, "collection supply overflow"))?;
+        supply_pointer.set_value::<u128>(supply);
+        Ok(())
+    }
+
+    /// Re-read a stage, change it and write it back with nothing running in between
+    fn update_mint_stage<F: FnOnce(&mut Stage) -> Result<()>>(&self, stage_id: u128, update: F) -> Result<Stage> {
+        let mut stage: Stage = self.get_mint_stage(stage_id)?;
+        update(&mut stage)?;
+        self.save_mint_stage(&stage)?;
+        Ok(stage)
+    }
+
+    /// Delete a stage, moving the last stage into its slot
+    fn remove_mint_stage(&self, stage_id: u128) -> Result<()> {
+        let mut slot_of: StoragePointer = self.stage_slot_of_pointer(stage_id);
+        let slot: u128 = match slot_of.get_value::<u128>() {
+            0 => return Err(revert!(ERR_NOT_FOUND, "Stage with ID {} not found", stage_id)),
+            slot => slot - 1,
+        };
+
+        let removed: Stage = self.read_stage_slot(slot)?;
+        let mut count_pointer: StoragePointer = self.stage_count_pointer();
+        let last: u128 = count_pointer.get_value::<u128>() - 1;
+        if slot != last {
+            let moved: Stage = self.read_stage_slot(last)?;
+            self.write_stage_slot(slot, &moved)?;
+            self.stage_slot_of_pointer(moved.id).set_value::<u128>(slot + 1);
         }
 
-        let stages: Vec<Stage> = bincode::deserialize(&stored_data)
-            .map_err(|_| anyhow!("Failed to deserialize stages"))?;
-        
-        Ok(stages)
+        self.stage_slot_pointer(last).set(Arc::new(vec![]));
+        slot_of.set_value::<u128>(0);
+        count_pointer.set_value::<u128>(last);
+
+        let mut supply_pointer: StoragePointer = self.stage_supply_pointer();
+        let supply: u128 = supply_pointer.get_value::<u128>().saturating_sub(removed.max_supply);
+        supply_pointer.set_value::<u128>(supply);
+        Ok(())
+    }
+
+    /// Get all stages in slot order
+    fn get_mint_stages(&self) -> Result<Vec<Stage>> {
+        let count: u128 = self.stage_count_pointer().get_value::<u128>();
+        (0..count).map(|slot| self.read_stage_slot(slot)).collect()
     }
 
     /// Retrieve a specific stage by ID
     fn get_mint_stage(&self, stage_id: u128) -> Result<Stage> {
-        let stages: Vec<Stage> = self.get_mint_stages()?;     
-        for stage in stages {
-            if stage.id == stage_id {
-                return Ok(stage);
-            }
+        match self.stage_slot_of_pointer(stage_id).get_value::<u128>() {
+            0 => Err(revert!(ERR_NOT_FOUND, "Stage with ID {} not found", stage_id)),
+            slot => self.read_stage_slot(slot - 1),
         }
-
-        Err(anyhow!("Stage with ID {} not found", stage_id))
     }
 
-    /// Initialize stages if not already set
+    /// Seed the placeholder stages of canary builds if no stage is set yet. Mainnet
+    /// collections start without stages, the owner adds them with `SetStage`.
     fn initialize_mint_stages(&self) -> Result<()> {
-        let stages: Vec<Stage> = self.get_mint_stages()?;
-
         // Only initialize if stages are not yet set
-        if stages.is_empty() {
+        if self.seeds_placeholder_stages() && self.stage_count_pointer().get_value::<u128>() == 0 {
             let initial_stages: Vec<Stage> = vec![
                 Stage {
                     id: 1,
                     price_per_item: 100,
                     max_mints_per_address: 5,
-                    whitelist: vec![],
+                    whitelist_root: [0; 32],
                     max_supply: 10,
                     start_block: 1,
                     end_block: 111905000,
                     total_minted: 0,
+                    schedule_mode: SCHEDULE_BY_HEIGHT,
+                    start_time: 0,
+                    end_time: 0,
+                    soft_close_window: 0,
+                    soft_close_extension: 0,
+                    max_extension: 0,
+                    extended_by: 0,
+                    free: false,
+                    start_price: 0,
+                    end_price: 0,
+                    decay_interval_blocks: 0,
+                    ..Default::default()
                 },
                 Stage {
                     id: 2,
                     price_per_item: 200,
                     max_mints_per_address: 3,
-                    whitelist: vec![],
+                    whitelist_root: [0; 32],
                     max_supply: 20,
                     start_block: 1,
                     end_block: 111905000,
                     total_minted: 0,
+                    schedule_mode: SCHEDULE_BY_HEIGHT,
+                    start_time: 0,
+                    end_time: 0,
+                    soft_close_window: 0,
+                    soft_close_extension: 0,
+                    max_extension: 0,
+                    extended_by: 0,
+                    free: false,
+                    start_price: 0,
+                    end_price: 0,
+                    decay_interval_blocks: 0,
+                    ..Default::default()
                 },
             ];
 
-            self.set_mint_stages(initial_stages)?;
+            for stage in initial_stages.iter() {
+                self.save_mint_stage(stage)?;
+            }
         }
 
         Ok(())
     }
 
+    /// Packs up to the first 16 bytes of a string into a little endian u128
     fn encode_string_to_u128(&self, input: &str) -> u128 {
+        self.encode_bytes_to_u128(input.as_bytes())
+    }
+
+    /// First 16 bytes packed little endian and zero padded
+    fn encode_bytes_to_u128(&self, input: &[u8]) -> u128 {
         let mut value: u128 = 0;
-        for (i, byte) in input.bytes().enumerate() {
-            value |= (byte as u128) << (8 * i);
+        for (i, byte) in input.iter().take(16).enumerate() {
+            value |= (*byte as u128) << (8 * i);
         }
         value
     }
@@ -0,0 +1,114 @@
+use metashrew_support::index_pointer::KeyValuePointer;
+use alkanes_runtime::runtime::AlkaneResponder;
+use alkanes_support::response::CallResponse;
+use serde::Serialize;
+use anyhow::Result;
+use orbital_support::{revert, errors::codes::*};
+use std::sync::Arc;
+
+use crate::Collection;
+use crate::opcodes::StoragePointer;
+use crate::roles::ROLE_METADATA;
+
+/// License codes, stable across releases since marketplaces key on them
+const LICENSE_CC0: u8 = 1;
+const LICENSE_CC_BY: u8 = 2;
+const LICENSE_CC_BY_NC: u8 = 3;
+const LICENSE_PERSONAL_USE: u8 = 4;
+const LICENSE_COMMERCIAL: u8 = 5;
+const LICENSE_ALL_RIGHTS_RESERVED: u8 = 6;
+
+/// License of an orbital, returned by `GetLicense`
+#[derive(Serialize)]
+struct LicenseView {
+    index: u128,
+    /// License code, 0 when none is assigned
+    license: u8,
+    name: &'static str,
+    /// Whether the license comes from the orbital itself, the collection default or nowhere
+    source: &'static str,
+}
+
+fn license_name(license: u8) -> Option<&'static str> {
+    match license {
+        LICENSE_CC0 => Some("CC0-1.0"),
+        LICENSE_CC_BY => Some("CC-BY-4.0"),
+        LICENSE_CC_BY_NC => Some("CC-BY-NC-4.0"),
+        LICENSE_PERSONAL_USE => Some("personal-use"),
+        LICENSE_COMMERCIAL => Some("commercial-use"),
+        LICENSE_ALL_RIGHTS_RESERVED => Some("all-rights-reserved"),
+        _ => None,
+    }
+}
+
+impl Collection {
+    /// Set the license applying to every orbital without one of its own, 0 clears it
+    pub(crate) fn set_collection_license(&self, license: u128) -> Result<CallResponse> {
+        let license: u8 = self.observe_license_change(license)?;
+        let context: alkanes_support::context::Context = self.context()?;
+        let response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        self.collection_license_pointer().set(Arc::new(vec![license]));
+        Ok(response)
+    }
+
+    /// Set the license of a single orbital, 0 falls back to the collection license
+    pub(crate) fn set_license(&self, index: u128, license: u128) -> Result<CallResponse> {
+        let license: u8 = self.observe_license_change(license)?;
+        let context: alkanes_support::context::Context = self.context()?;
+        let response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        self.lookup_instance(index)?;
+        self.license_pointer(index).set(Arc::new(vec![license]));
+        Ok(response)
+    }
+
+    /// Returns the license of an orbital as JSON
+    pub(crate) fn get_license(&self, index: u128) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let token: u8 = self.license_pointer(index).get().first().copied().unwrap_or(0);
+        let collection: u8 = self.collection_license_pointer().get().first().copied().unwrap_or(0);
+        let (license, source) = match (token, collection) {
+            (0, 0) => (0, "none"),
+            (0, collection) => (collection, "collection"),
+            (token, _) => (token, "token"),
+        };
+
+        let view: LicenseView = LicenseView {
+            index,
+            license,
+            name: license_name(license).unwrap_or("none"),
+            source,
+        };
+
+        response.data = serde_json::to_vec(&view)
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to serialize license"))?;
+        Ok(response)
+    }
+
+    /// Licenses are metadata: set by the metadata role and fixed once metadata is frozen
+    fn observe_license_change(&self, license: u128) -> Result<u8> {
+        self.only_role(ROLE_METADATA)?;
+
+        if self.is_metadata_frozen() {
+            return Err(revert!(ERR_INVALID_STATE, "Metadata is frozen"));
+        }
+
+        let license: u8 = u8::try_from(license).map_err(|_| revert!(ERR_INVALID_INPUT, "Unknown license {}", license))?;
+        if license != 0 && license_name(license).is_none() {
+            return Err(revert!(ERR_INVALID_INPUT, "Unknown license {}", license));
+        }
+
+        Ok(license)
+    }
+
+    fn collection_license_pointer(&self) -> StoragePointer {
+        StoragePointer::from_keyword("/license/collection")
+    }
+
+    fn license_pointer(&self, index: u128) -> StoragePointer {
+        StoragePointer::from_keyword("/license/tokens/").select(&index.to_le_bytes().to_vec())
+    }
+}
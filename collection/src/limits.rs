@@ -0,0 +1,249 @@
+use metashrew_support::index_pointer::KeyValuePointer;
+use alkanes_runtime::runtime::AlkaneResponder;
+use alkanes_support::response::CallResponse;
+use serde::Serialize;
+use anyhow::Result;
+use orbital_support::{revert, errors::{codes::*, Revert}};
+
+use crate::{Collection, Stage, counters};
+use crate::identity::identity_from_halves;
+use crate::opcodes::StoragePointer;
+
+/// Mints of an identity in a stage as returned by `GetAddressMintCount`
+#[derive(Serialize)]
+struct AddressMintCount {
+    stage_id: u128,
+    identity: String,
+    minted: u128,
+    /// Mints allowed per identity, 0 when unlimited
+    max_mints_per_address: u128,
+}
+
+/// Per-stage mint counters keyed on the script hash identity of the recipient
+impl Collection {
+    /// Refuse `count` more mints for an identity beyond the stage allowance, 0 means unlimited.
+    /// The collection wide allowance is checked as well.
+    pub(crate) fn observe_address_limit(&self, stage: &Stage, identity: &[u8; 32], count: u128) -> Result<()> {
+        self.observe_lifetime_limit(identity, count)?;
+        if stage.max_mints_per_address == 0 {
+            return Ok(());
+        }
+
+        let minted: u128 = self.address_mint_count(stage.id, identity);
+        let allowed: u128 = stage.max_mints_per_address as u128;
+        if minted.saturating_add(count) > allowed {
+            return Err(Revert::new(ERR_LIMIT_EXCEEDED, format!("Address mint limit of stage {} reached", stage.id))
+                .field("max_mints_per_address").expected(allowed).actual(minted.saturating_add(count)).into());
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn record_address_mints(&self, stage_id: u128, identity: &[u8; 32], count: u128) -> Result<()> {
+        let mut pointer: StoragePointer = self.address_mints_pointer(stage_id, identity);
+        counters::increment(&mut pointer, count, u128::MAX, "address_mints")?;
+        self.record_lifetime_mints(identity, count)
+    }
+
+    /// Give back allowance, used when a mint is reversed
+    pub(crate) fn release_address_mints(&self, stage_id: u128, identity: &[u8; 32], count: u128) {
+        counters::decrement(&mut self.address_mints_pointer(stage_id, identity), count);
+        counters::decrement(&mut self.lifetime_mints_pointer(identity), count);
+    }
+
+    /// Returns the mints left for an identity in a stage as u128, u128::MAX when unlimited.
+    /// The identity is the sha256 of the recipient script as two little endian halves.
+    pub(crate) fn get_remaining_mints(&self, stage_id: u128, identity_low: u128, identity_high: u128) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let stage: Stage = self.get_mint_stage(stage_id)?;
+        let identity: [u8; 32] = identity_from_halves(identity_low, identity_high);
+
+        let remaining: u128 = if stage.max_mints_per_address == 0 {
+            u128::MAX
+        } else {
+            (stage.max_mints_per_address as u128).saturating_sub(self.address_mint_count(stage_id, &identity))
+        };
+
+        response.data = remaining.to_le_bytes().to_vec();
+        Ok(response)
+    }
+
+    /// Returns what an identity minted in a stage next to the stage allowance as JSON. The
+    /// identity is the sha256 of the recipient script as two little endian halves.
+    pub(crate) fn get_address_mint_count(&self, stage_id: u128, identity_low: u128, identity_high: u128) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let stage: Stage = self.get_mint_stage(stage_id)?;
+        let identity: [u8; 32] = identity_from_halves(identity_low, identity_high);
+
+        let count: AddressMintCount = AddressMintCount {
+            stage_id,
+            identity: orbital_support::hex::encode(&identity),
+            minted: self.address_mint_count(stage_id, &identity),
+            max_mints_per_address: stage.max_mints_per_address as u128,
+        };
+
+        response.data = serde_json::to_vec(&count)
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to serialize address mint count"))?;
+        Ok(response)
+    }
+
+    pub(crate) fn address_mint_count(&self, stage_id: u128, identity: &[u8; 32]) -> u128 {
+        self.address_mints_pointer(stage_id, identity).get_value::<u128>()
+    }
+
+    fn address_mints_pointer(&self, stage_id: u128, identity: &[u8; 32]) -> StoragePointer {
+        StoragePointer::from_keyword("/address-mints/")
+            .select(&stage_id.to_le_bytes().to_vec())
+            .keyword("/")
+            .select(&identity.to_vec())
+    }
+}
+
+/// Collection wide mint counters keyed on the same identity, covering the stages, the
+/// public mint and free mint claims. Airdrops and vesting are grants of the owner and are
+/// not counted.
+impl Collection {
+    /// Set how many orbitals one identity may mint over the life of the collection, 0
+    /// removes the cap. Mints made before the cap still count against it.
+    pub(crate) fn set_max_mints_per_address(&self, max: u128) -> Result<CallResponse> {
+        self.only_owner()?;
+        let context: alkanes_support::context::Context = self.context()?;
+        let response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        self.max_mints_per_address_pointer().set_value::<u128>(max);
+        Ok(response)
+    }
+
+    /// Returns the mints left for an identity across the collection as u128, u128::MAX when
+    /// uncapped. The identity is the sha256 of the recipient script as two little endian halves.
+    pub(crate) fn get_remaining_lifetime_mints(&self, identity_low: u128, identity_high: u128) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let identity: [u8; 32] = identity_from_halves(identity_low, identity_high);
+
+        let max: u128 = self.max_mints_per_address();
+        let remaining: u128 = if max == 0 {
+            u128::MAX
+        } else {
+            max.saturating_sub(self.lifetime_mints_pointer(&identity).get_value::<u128>())
+        };
+
+        response.data = remaining.to_le_bytes().to_vec();
+        Ok(response)
+    }
+
+    /// Refuse `count` more mints for an identity beyond the collection wide allowance
+    pub(crate) fn observe_lifetime_limit(&self, identity: &[u8; 32], count: u128) -> Result<()> {
+        let max: u128 = self.max_mints_per_address();
+        if max == 0 {
+            return Ok(());
+        }
+
+        let minted: u128 = self.lifetime_mints_pointer(identity).get_value::<u128>();
+        if minted.saturating_add(count) > max {
+            return Err(Revert::new(ERR_LIMIT_EXCEEDED, "Address mint limit of the collection reached")
+                .field("max_mints_per_address").expected(max).actual(minted.saturating_add(count)).into());
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn record_lifetime_mints(&self, identity: &[u8; 32], count: u128) -> Result<()> {
+        counters::increment(&mut self.lifetime_mints_pointer(identity), count, u128::MAX, "lifetime_mints")?;
+        Ok(())
+    }
+
+    pub(crate) fn max_mints_per_address(&self) -> u128 {
+        self.max_mints_per_address_pointer().get_value::<u128>()
+    }
+
+    fn max_mints_per_address_pointer(&self) -> StoragePointer {
+        StoragePointer::from_keyword("/max-mints-per-address")
+    }
+
+    fn lifetime_mints_pointer(&self, identity: &[u8; 32]) -> StoragePointer {
+        StoragePointer::from_keyword("/lifetime-mints/").select(&identity.to_vec())
+    }
+}
+
+/// Per-block mint throughput, collection wide and per stage
+impl Collection {
+    /// Set how many orbitals may be minted in one block. Stage 0 sets the collection wide
+    /// limit, where 0 restores the network default; any other stage gets a limit of its own
+    /// on top of it, where 0 removes it.
+    pub(crate) fn set_max_mint_per_block(&self, stage_id: u128, max: u128) -> Result<CallResponse> {
+        self.only_owner()?;
+        let context: alkanes_support::context::Context = self.context()?;
+        let response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let max: u32 = u32::try_from(max).map_err(|_| Revert::new(ERR_INVALID_INPUT, "max is out of range")
+            .field("max").expected(u32::MAX as u128).actual(max))?;
+
+        if stage_id == 0 {
+            self.max_mint_per_block_pointer().set_value::<u32>(max);
+        } else {
+            self.get_mint_stage(stage_id)?;
+            self.stage_max_mint_per_block_pointer(stage_id).set_value::<u32>(max);
+        }
+
+        Ok(response)
+    }
+
+    /// Returns the per-block limit as u128, of the collection for stage 0 and of the stage
+    /// otherwise (0 when the stage has none)
+    pub(crate) fn get_max_mint_per_block(&self, stage_id: u128) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let max: u32 = if stage_id == 0 {
+            self.max_mint_per_block()
+        } else {
+            self.get_mint_stage(stage_id)?;
+            self.stage_max_mint_per_block(stage_id)
+        };
+
+        response.data = (max as u128).to_le_bytes().to_vec();
+        Ok(response)
+    }
+
+    /// Count a mint against the per-block limit of its stage, stages without one are not counted
+    pub(crate) fn observe_stage_mint_per_block(&self, stage_id: u128, height: u64) -> Result<()> {
+        let max: u32 = self.stage_max_mint_per_block(stage_id);
+        if stage_id == 0 || max == 0 {
+            return Ok(());
+        }
+
+        let mut pointer: StoragePointer = self.stage_mints_in_block_pointer(stage_id, height);
+        if pointer.get_value::<u128>() >= max as u128 {
+            return Err(Revert::new(ERR_LIMIT_EXCEEDED, format!("mint limit of stage {} reached for block {}", stage_id, height))
+                .field("max_mint_per_block").expected(max as u128).actual(max as u128 + 1).into());
+        }
+
+        counters::increment(&mut pointer, 1, max as u128, "max_mint_per_block")?;
+        Ok(())
+    }
+
+    pub(crate) fn stage_max_mint_per_block(&self, stage_id: u128) -> u32 {
+        self.stage_max_mint_per_block_pointer(stage_id).get_value::<u32>()
+    }
+
+    pub(crate) fn stage_mints_in_block(&self, stage_id: u128, height: u64) -> u128 {
+        self.stage_mints_in_block_pointer(stage_id, height).get_value::<u128>()
+    }
+
+    fn stage_max_mint_per_block_pointer(&self, stage_id: u128) -> StoragePointer {
+        StoragePointer::from_keyword("/stages/max-mint-per-block/").select(&stage_id.to_le_bytes().to_vec())
+    }
+
+    fn stage_mints_in_block_pointer(&self, stage_id: u128, height: u64) -> StoragePointer {
+        StoragePointer::from_keyword("/stages/seen/")
+            .select(&stage_id.to_le_bytes().to_vec())
+            .keyword("/")
+            .select(&height.to_le_bytes().to_vec())
+    }
+}
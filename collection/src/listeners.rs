@@ -0,0 +1,332 @@
+//! Alkanes subscribe to event topics and are called once per event appended to the topic's
+//! log. Notifications are best effort: each call gets a bounded share of fuel, and a
+//! listener that fails or runs out is skipped without affecting the operation that
+//! produced the event. Listeners read the event itself through `GetEvents` using the
+//! sequence they are handed.
+//!
+//! Every notification costs the fee set by the owner, drawn from a deposit the listener
+//! prepays in the payment token and anyone can top up. The fee is charged whether or not
+//! the call succeeds, since the fuel is spent either way, and listeners whose deposit no
+//! longer covers it are skipped until topped up.
+//!
+//! A listener runs while the collection is mid-notification, so each fee is saved before
+//! the call and the counters are applied to the list as re-read after it. Events raised
+//! from inside a notification are not delivered, and listeners cannot be dropped until
+//! the notification returns.
+
+use metashrew_support::index_pointer::KeyValuePointer;
+use alkanes_runtime::runtime::AlkaneResponder;
+use alkanes_support::{cellpack::Cellpack, id::AlkaneId, parcel::{AlkaneTransfer, AlkaneTransferParcel}, response::CallResponse};
+use serde::{Serialize, Deserialize};
+use anyhow::Result;
+use orbital_support::{revert, errors::{codes::*, Revert}};
+use std::sync::Arc;
+
+use crate::Collection;
+use crate::opcodes::StoragePointer;
+use crate::events::log_name;
+
+/// Opcode listeners implement, taking (topic, sequence)
+const LISTENER_ON_EVENT: u128 = 62;
+
+/// Fuel handed to one notification, whatever the listener would rather spend
+const LISTENER_FUEL: u64 = 100_000;
+
+/// Listeners one topic holds, each costs a call on every event of the topic
+const MAX_LISTENERS: usize = 8;
+
+#[derive(Clone, Default, Debug, Serialize, Deserialize)]
+pub(crate) struct Listener {
+    pub block: u128,
+    pub tx: u128,
+    pub registered_at: u64,
+    /// Token the deposit is held in, the payment token at registration
+    pub deposit_block: u128,
+    pub deposit_tx: u128,
+    /// Deposit left to pay notification fees from
+    pub balance: u128,
+    pub notified: u128,
+    pub failures: u128,
+    /// Notifications skipped because the deposit was short of the fee
+    pub skipped: u128,
+}
+
+impl Listener {
+    fn listener(&self) -> AlkaneId {
+        AlkaneId { block: self.block, tx: self.tx }
+    }
+
+    fn deposit_token(&self) -> AlkaneId {
+        AlkaneId { block: self.deposit_block, tx: self.deposit_tx }
+    }
+}
+
+impl Collection {
+    /// Calling alkane subscribes to a topic, prepaying `deposit` of the payment token. The
+    /// deposit must cover at least one notification.
+    pub(crate) fn register_listener(&self, topic: u128, deposit: u128) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::default();
+
+        self.observe_listener_topic(topic)?;
+        let caller: AlkaneId = context.caller;
+        if caller.block == 0 && caller.tx == 0 {
+            return Err(revert!(ERR_UNAUTHORIZED, "Only alkanes can register as listeners"));
+        }
+
+        let mut listeners: Vec<Listener> = self.listeners(topic)?;
+        if listeners.iter().any(|listener| listener.listener() == caller) {
+            return Err(revert!(ERR_INVALID_STATE, "{}:{} already listens to topic {}", caller.block, caller.tx, topic));
+        }
+
+        if listeners.len() >= MAX_LISTENERS {
+            return Err(Revert::new(ERR_LIMIT_EXCEEDED, "Topic has no listener slot left")
+                .field("listeners").expected(MAX_LISTENERS as u128).actual(listeners.len() as u128 + 1).into());
+        }
+
+        let fee: u128 = self.listener_fee();
+        if deposit < fee {
+            return Err(Revert::new(ERR_INSUFFICIENT_PAYMENT, "Deposit does not cover a notification")
+                .field("deposit").expected(fee).actual(deposit).into());
+        }
+
+        let token: AlkaneId = match self.payment_token()? {
+            Some(token) => token,
+            None if deposit == 0 => AlkaneId::default(),
+            None => return Err(revert!(ERR_INVALID_STATE, "No payment token configured")),
+        };
+        response.alkanes.0 = self.take_payment(&context.incoming_alkanes, &token, deposit)?;
+
+        listeners.push(Listener {
+            block: caller.block,
+            tx: caller.tx,
+            registered_at: self.height(),
+            deposit_block: token.block,
+            deposit_tx: token.tx,
+            balance: deposit,
+            ..Default::default()
+        });
+        self.set_listeners(topic, &listeners)?;
+        Ok(response)
+    }
+
+    /// Add `amount` to the deposit of a listener, anyone can pay for it
+    pub(crate) fn top_up_listener(&self, topic: u128, block: u128, tx: u128, amount: u128) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::default();
+
+        if amount == 0 {
+            return Err(revert!(ERR_INVALID_INPUT, "Top-up amount must be positive"));
+        }
+
+        self.observe_listener_topic(topic)?;
+        let mut listeners: Vec<Listener> = self.listeners(topic)?;
+        let listener: &mut Listener = listeners.iter_mut()
+            .find(|listener| listener.block == block && listener.tx == tx)
+            .ok_or_else(|| revert!(ERR_NOT_FOUND, "{}:{} does not listen to topic {}", block, tx, topic))?;
+
+        let token: AlkaneId = listener.deposit_token();
+        if token == AlkaneId::default() {
+            return Err(revert!(ERR_INVALID_STATE, "{}:{} registered without a deposit token", block, tx));
+        }
+
+        response.alkanes.0 = self.take_payment(&context.incoming_alkanes, &token, amount)?;
+        listener.balance = listener.balance.checked_add(amount)
+            .ok_or_else(|| revert!(ERR_OVERFLOW, "listener balance overflow"))?;
+
+        self.set_listeners(topic, &listeners)?;
+        Ok(response)
+    }
+
+    /// Set the fee every notification draws from the deposit of its listener
+    pub(crate) fn set_listener_fee(&self, fee: u128) -> Result<CallResponse> {
+        self.only_owner()?;
+        let context: alkanes_support::context::Context = self.context()?;
+        let response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        self.listener_fee_pointer().set_value::<u128>(fee);
+        Ok(response)
+    }
+
+    /// Calling alkane unsubscribes from a topic and gets the rest of its deposit back
+    pub(crate) fn unregister_listener(&self, topic: u128) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let listener: Listener = self.drop_listener(topic, &context.caller)?;
+        if listener.balance > 0 {
+            response.alkanes.0.push(AlkaneTransfer { id: listener.deposit_token(), value: listener.balance });
+        }
+
+        Ok(response)
+    }
+
+    /// Owner evicts a listener from a topic, the rest of its deposit goes to the treasury
+    pub(crate) fn remove_listener(&self, topic: u128, block: u128, tx: u128) -> Result<CallResponse> {
+        self.only_owner()?;
+        let context: alkanes_support::context::Context = self.context()?;
+        let response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let listener: Listener = self.drop_listener(topic, &AlkaneId { block, tx })?;
+        if listener.balance > 0 {
+            self.credit_treasury(&listener.deposit_token(), listener.balance)?;
+        }
+
+        Ok(response)
+    }
+
+    /// Returns the deposit a listener has left
+    pub(crate) fn get_listener_balance(&self, topic: u128, block: u128, tx: u128) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        self.observe_listener_topic(topic)?;
+        let listener: Listener = self.listeners(topic)?.into_iter()
+            .find(|listener| listener.block == block && listener.tx == tx)
+            .ok_or_else(|| revert!(ERR_NOT_FOUND, "{}:{} does not listen to topic {}", block, tx, topic))?;
+
+        response.data = listener.balance.to_le_bytes().to_vec();
+        Ok(response)
+    }
+
+    /// Returns the listeners of a topic with their delivery counters as JSON
+    pub(crate) fn get_listeners(&self, topic: u128) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        self.observe_listener_topic(topic)?;
+        response.data = serde_json::to_vec(&self.listeners(topic)?)
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to serialize listeners"))?;
+        Ok(response)
+    }
+
+    /// Call every listener of a topic about the event at `sequence`, failures are counted
+    /// and otherwise ignored
+    pub(crate) fn notify_listeners(&self, topic: u128, sequence: u128) -> Result<()> {
+        if self.is_notifying() {
+            return Ok(());
+        }
+
+        let targets: Vec<AlkaneId> = self.listeners(topic)?.iter().map(Listener::listener).collect();
+        if targets.is_empty() {
+            return Ok(());
+        }
+
+        self.notifying_pointer().set_value::<u8>(1);
+        let fee: u128 = self.listener_fee();
+        for target in targets.iter() {
+            let charged: bool = self.update_listener(topic, target, |listener| {
+                if listener.balance < fee {
+                    listener.skipped = listener.skipped.saturating_add(1);
+                    return Ok(false);
+                }
+
+                if fee > 0 {
+                    listener.balance -= fee;
+                    self.credit_treasury(&listener.deposit_token(), fee)?;
+                }
+                Ok(true)
+            })?;
+            if !charged {
+                continue;
+            }
+
+            let cellpack: Cellpack = Cellpack {
+                target: *target,
+                inputs: vec![LISTENER_ON_EVENT, topic, sequence],
+            };
+
+            let fuel: u64 = self.fuel().min(LISTENER_FUEL);
+            let delivered: bool = self.call(&cellpack, &AlkaneTransferParcel::default(), fuel).is_ok();
+            self.update_listener(topic, target, |listener| {
+                if delivered {
+                    listener.notified = listener.notified.saturating_add(1);
+                } else {
+                    listener.failures = listener.failures.saturating_add(1);
+                }
+                Ok(true)
+            })?;
+        }
+
+        self.notifying_pointer().set_value::<u8>(0);
+        Ok(())
+    }
+
+    /// Apply `update` to a listener as currently stored and save the list, returning what
+    /// `update` did or false if the listener is gone
+    fn update_listener<F>(&self, topic: u128, target: &AlkaneId, update: F) -> Result<bool>
+    where
+        F: FnOnce(&mut Listener) -> Result<bool>,
+    {
+        let mut listeners: Vec<Listener> = self.listeners(topic)?;
+        let listener: &mut Listener = match listeners.iter_mut().find(|listener| listener.listener() == *target) {
+            Some(listener) => listener,
+            None => return Ok(false),
+        };
+
+        let outcome: bool = update(listener)?;
+        self.set_listeners(topic, &listeners)?;
+        Ok(outcome)
+    }
+
+    /// Unsubscribe a listener, returning it with the deposit it had left
+    fn drop_listener(&self, topic: u128, listener: &AlkaneId) -> Result<Listener> {
+        self.observe_listener_topic(topic)?;
+        if self.is_notifying() {
+            return Err(revert!(ERR_INVALID_STATE, "Listeners cannot be dropped during a notification"));
+        }
+
+        let mut listeners: Vec<Listener> = self.listeners(topic)?;
+        let position: usize = listeners.iter()
+            .position(|registered| registered.listener() == *listener)
+            .ok_or_else(|| revert!(ERR_NOT_FOUND, "{}:{} does not listen to topic {}", listener.block, listener.tx, topic))?;
+
+        let removed: Listener = listeners.remove(position);
+        self.set_listeners(topic, &listeners)?;
+        Ok(removed)
+    }
+
+    fn listener_fee(&self) -> u128 {
+        self.listener_fee_pointer().get_value::<u128>()
+    }
+
+    fn listener_fee_pointer(&self) -> StoragePointer {
+        StoragePointer::from_keyword("/listeners/fee")
+    }
+
+    fn is_notifying(&self) -> bool {
+        self.notifying_pointer().get_value::<u8>() == 1
+    }
+
+    fn notifying_pointer(&self) -> StoragePointer {
+        StoragePointer::from_keyword("/listeners/notifying")
+    }
+
+    fn observe_listener_topic(&self, topic: u128) -> Result<()> {
+        match log_name(topic) {
+            Some(_) => Ok(()),
+            None => Err(revert!(ERR_NOT_FOUND, "Unknown event topic {}", topic)),
+        }
+    }
+
+    fn listeners(&self, topic: u128) -> Result<Vec<Listener>> {
+        let stored: Arc<Vec<u8>> = self.listeners_pointer(topic).get();
+        if stored.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        bincode::deserialize(&stored)
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to deserialize listeners"))
+    }
+
+    fn set_listeners(&self, topic: u128, listeners: &[Listener]) -> Result<()> {
+        let serialized: Vec<u8> = bincode::serialize(listeners)
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to serialize listeners"))?;
+        self.listeners_pointer(topic).set(Arc::new(serialized));
+        Ok(())
+    }
+
+    fn listeners_pointer(&self, topic: u128) -> StoragePointer {
+        StoragePointer::from_keyword("/listeners/").select(&topic.to_le_bytes().to_vec())
+    }
+}
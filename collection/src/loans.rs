@@ -0,0 +1,332 @@
+use metashrew_support::index_pointer::KeyValuePointer;
+use alkanes_runtime::runtime::AlkaneResponder;
+use alkanes_support::{id::AlkaneId, parcel::AlkaneTransfer, response::CallResponse};
+use serde::{Serialize, Deserialize};
+use anyhow::Result;
+use orbital_support::{revert, errors::codes::*};
+use std::sync::Arc;
+
+use crate::Collection;
+use crate::opcodes::StoragePointer;
+use crate::events::{MARKET_LOAN_DEFAULTED, MARKET_LOAN_FUNDED, MARKET_LOAN_REPAID};
+use crate::receipts::{RECEIPT_KIND_BORROWER, RECEIPT_KIND_LENDER};
+
+/// Collateral escrowed, waiting for a lender
+const LOAN_REQUESTED: u8 = 0;
+
+/// Principal lent, collateral locked until repayment or default
+const LOAN_FUNDED: u8 = 1;
+
+/// Borrower repaid, repayment waiting for the lender
+const LOAN_REPAID: u8 = 2;
+
+/// Borrower withdrew the request before it was funded
+const LOAN_CANCELLED: u8 = 3;
+
+/// Lender collected the repayment
+const LOAN_SETTLED: u8 = 4;
+
+/// Lender took the collateral after the deadline passed
+const LOAN_DEFAULTED: u8 = 5;
+
+/// Loan of a payment alkane against an escrowed orbital
+#[derive(Clone, Default, Debug, Serialize, Deserialize)]
+struct Loan {
+    id: u128,
+    index: u128,
+    borrower_receipt_block: u128,
+    borrower_receipt_tx: u128,
+    lender_receipt_block: u128,
+    lender_receipt_tx: u128,
+    payment_block: u128,
+    payment_tx: u128,
+    principal: u128,
+    interest_per_block: u128,
+    duration_blocks: u64,
+    funded_at: u64,
+    deadline: u64,
+    unclaimed_principal: u128,
+    repaid: u128,
+    status: u8,
+}
+
+impl Loan {
+    fn borrower_receipt(&self) -> AlkaneId {
+        AlkaneId { block: self.borrower_receipt_block, tx: self.borrower_receipt_tx }
+    }
+
+    fn lender_receipt(&self) -> AlkaneId {
+        AlkaneId { block: self.lender_receipt_block, tx: self.lender_receipt_tx }
+    }
+
+    fn payment_token(&self) -> AlkaneId {
+        AlkaneId { block: self.payment_block, tx: self.payment_tx }
+    }
+
+    /// Interest accrues every block from funding up to the deadline
+    fn amount_due(&self, height: u64) -> Result<u128> {
+        if self.status != LOAN_FUNDED {
+            return Ok(0);
+        }
+
+        let elapsed: u128 = height.min(self.deadline).saturating_sub(self.funded_at) as u128;
+        self.interest_per_block.checked_mul(elapsed)
+            .and_then(|interest| interest.checked_add(self.principal))
+            .ok_or_else(|| revert!(ERR_OVERFLOW, "Loan amount due overflow"))
+    }
+}
+
+impl Collection {
+    /// Holder escrows the orbital as collateral and receives the borrower receipt
+    pub(crate) fn request_loan(
+        &self,
+        index: u128,
+        principal: u128,
+        interest_per_block: u128,
+        duration_blocks: u128,
+        payment_block: u128,
+        payment_tx: u128,
+    ) -> Result<CallResponse> {
+        self.only_holder(index)?;
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::default();
+
+        if principal == 0 || duration_blocks == 0 {
+            return Err(revert!(ERR_INVALID_INPUT, "Loan principal and duration must be positive"));
+        }
+
+        let duration_blocks: u64 = u64::try_from(duration_blocks).map_err(|_| revert!(ERR_INVALID_INPUT, "Loan duration out of range"))?;
+        let instance_id: AlkaneId = self.lookup_instance(index)?;
+
+        // Everything but the collateral goes back to the caller
+        response.alkanes.0 = self.escrow_incoming_orbital(&context.incoming_alkanes, &instance_id);
+
+        let (receipt_id, receipt_transfer) = self.issue_receipt(index, RECEIPT_KIND_BORROWER, u64::MAX)?;
+
+        let count: u128 = self.loan_count_pointer().get_value::<u128>();
+        let loan: Loan = Loan {
+            id: count + 1,
+            index,
+            borrower_receipt_block: receipt_id.block,
+            borrower_receipt_tx: receipt_id.tx,
+            payment_block,
+            payment_tx,
+            principal,
+            interest_per_block,
+            duration_blocks,
+            status: LOAN_REQUESTED,
+            ..Default::default()
+        };
+        self.loan_count_pointer().set_value::<u128>(loan.id);
+        self.loan_of_pointer(index).set_value::<u128>(loan.id);
+        self.set_loan(&loan)?;
+
+        response.alkanes.0.push(receipt_transfer);
+        response.data = loan.id.to_le_bytes().to_vec();
+        Ok(response)
+    }
+
+    /// Borrower takes back the collateral of an unfunded request
+    pub(crate) fn cancel_loan_request(&self, loan_id: u128) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::default();
+
+        let mut loan: Loan = self.get_loan_by_id(loan_id)?;
+        let borrower_receipt: AlkaneId = loan.borrower_receipt();
+        self.only_receipt_holder(&borrower_receipt)?;
+
+        if loan.status != LOAN_REQUESTED {
+            return Err(revert!(ERR_INVALID_STATE, "Loan {} is already funded", loan_id));
+        }
+
+        // The borrower receipt is consumed, everything else goes back to the caller
+        response.alkanes.0 = without_receipt(&context.incoming_alkanes.0, &borrower_receipt);
+        response.alkanes.0.push(AlkaneTransfer { id: self.lookup_instance(loan.index)?, value: 1u128 });
+
+        loan.status = LOAN_CANCELLED;
+        self.set_loan(&loan)?;
+        self.loan_of_pointer(loan.index).set_value::<u128>(0);
+        Ok(response)
+    }
+
+    /// Lender pays the principal and receives the lender note, starting the loan term
+    pub(crate) fn fund_loan(&self, loan_id: u128) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::default();
+
+        let mut loan: Loan = self.get_loan_by_id(loan_id)?;
+        if loan.status != LOAN_REQUESTED {
+            return Err(revert!(ERR_INVALID_STATE, "Loan {} is not open for funding", loan_id));
+        }
+
+        response.alkanes.0 = self.take_payment(&context.incoming_alkanes, &loan.payment_token(), loan.principal)?;
+
+        let height: u64 = self.height();
+        let (receipt_id, receipt_transfer) = self.issue_receipt(loan.index, RECEIPT_KIND_LENDER, u64::MAX)?;
+
+        loan.lender_receipt_block = receipt_id.block;
+        loan.lender_receipt_tx = receipt_id.tx;
+        loan.funded_at = height;
+        loan.deadline = height.saturating_add(loan.duration_blocks);
+        loan.unclaimed_principal = loan.principal;
+        loan.status = LOAN_FUNDED;
+        self.set_loan(&loan)?;
+        self.record_market_event(MARKET_LOAN_FUNDED, loan.index, loan.principal)?;
+
+        response.alkanes.0.push(receipt_transfer);
+        Ok(response)
+    }
+
+    /// Borrower collects the lent principal, keeping the receipt for repayment
+    pub(crate) fn withdraw_loan_principal(&self, loan_id: u128) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let mut loan: Loan = self.get_loan_by_id(loan_id)?;
+        self.only_receipt_holder(&loan.borrower_receipt())?;
+
+        if loan.unclaimed_principal > 0 {
+            response.alkanes.0.push(AlkaneTransfer { id: loan.payment_token(), value: loan.unclaimed_principal });
+            loan.unclaimed_principal = 0;
+            self.set_loan(&loan)?;
+        }
+
+        Ok(response)
+    }
+
+    /// Borrower repays principal and accrued interest before the deadline and gets the collateral back
+    pub(crate) fn repay_loan(&self, loan_id: u128) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+
+        let mut loan: Loan = self.get_loan_by_id(loan_id)?;
+        let borrower_receipt: AlkaneId = loan.borrower_receipt();
+        self.only_receipt_holder(&borrower_receipt)?;
+
+        let height: u64 = self.height();
+        if loan.status != LOAN_FUNDED {
+            return Err(revert!(ERR_INVALID_STATE, "Loan {} is not outstanding", loan_id));
+        }
+
+        if height > loan.deadline {
+            return Err(revert!(ERR_INVALID_STATE, "Loan {} defaulted at block {}", loan_id, loan.deadline));
+        }
+
+        let amount_due: u128 = loan.amount_due(height)?;
+        let mut response: CallResponse = CallResponse::default();
+        response.alkanes.0 = self.take_payment(&context.incoming_alkanes, &loan.payment_token(), amount_due)?;
+        response.alkanes.0 = without_receipt(&response.alkanes.0, &borrower_receipt);
+
+        response.alkanes.0.push(AlkaneTransfer { id: self.lookup_instance(loan.index)?, value: 1u128 });
+        if loan.unclaimed_principal > 0 {
+            response.alkanes.0.push(AlkaneTransfer { id: loan.payment_token(), value: loan.unclaimed_principal });
+            loan.unclaimed_principal = 0;
+        }
+
+        loan.repaid = amount_due;
+        loan.status = LOAN_REPAID;
+        self.set_loan(&loan)?;
+        self.record_market_event(MARKET_LOAN_REPAID, loan.index, amount_due)?;
+        self.loan_of_pointer(loan.index).set_value::<u128>(0);
+        Ok(response)
+    }
+
+    /// Lender redeems the note for the repayment, or for the collateral once the loan defaulted
+    pub(crate) fn claim_loan(&self, loan_id: u128) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+
+        let mut loan: Loan = self.get_loan_by_id(loan_id)?;
+        let lender_receipt: AlkaneId = loan.lender_receipt();
+        self.only_receipt_holder(&lender_receipt)?;
+
+        let mut response: CallResponse = CallResponse::default();
+        response.alkanes.0 = without_receipt(&context.incoming_alkanes.0, &lender_receipt);
+
+        match loan.status {
+            LOAN_REPAID => {
+                response.alkanes.0.push(AlkaneTransfer { id: loan.payment_token(), value: loan.repaid });
+                loan.status = LOAN_SETTLED;
+            }
+            LOAN_FUNDED if self.height() > loan.deadline => {
+                response.alkanes.0.push(AlkaneTransfer { id: self.lookup_instance(loan.index)?, value: 1u128 });
+                loan.status = LOAN_DEFAULTED;
+                self.loan_of_pointer(loan.index).set_value::<u128>(0);
+                self.record_market_event(MARKET_LOAN_DEFAULTED, loan.index, 0)?;
+            }
+            LOAN_FUNDED => return Err(revert!(ERR_INVALID_STATE, "Loan {} runs until block {}", loan_id, loan.deadline)),
+            _ => return Err(revert!(ERR_INVALID_STATE, "Loan {} has nothing to claim", loan_id)),
+        }
+
+        self.set_loan(&loan)?;
+        Ok(response)
+    }
+
+    /// Returns a loan as JSON
+    pub(crate) fn get_loan(&self, loan_id: u128) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let loan: Loan = self.get_loan_by_id(loan_id)?;
+        response.data = serde_json::to_vec(&loan)
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to serialize loan"))?;
+        Ok(response)
+    }
+
+    /// Returns what repaying the loan costs at the current block, zero unless it is outstanding
+    pub(crate) fn get_loan_amount_due(&self, loan_id: u128) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let loan: Loan = self.get_loan_by_id(loan_id)?;
+        response.data = loan.amount_due(self.height())?.to_le_bytes().to_vec();
+        Ok(response)
+    }
+
+    /// Returns the id of the loan holding an index as collateral
+    pub(crate) fn get_loan_of(&self, index: u128) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let loan_id: u128 = self.loan_of_pointer(index).get_value::<u128>();
+        if loan_id == 0 {
+            return Err(revert!(ERR_NOT_FOUND, "Orbital {} is not collateral of a loan", index));
+        }
+
+        response.data = loan_id.to_le_bytes().to_vec();
+        Ok(response)
+    }
+
+    fn loan_count_pointer(&self) -> StoragePointer {
+        StoragePointer::from_keyword("/loans/count")
+    }
+
+    fn loan_of_pointer(&self, index: u128) -> StoragePointer {
+        StoragePointer::from_keyword("/loans/of/").select(&index.to_le_bytes().to_vec())
+    }
+
+    fn loan_pointer(&self, loan_id: u128) -> StoragePointer {
+        StoragePointer::from_keyword("/loans/").select(&loan_id.to_le_bytes().to_vec())
+    }
+
+    fn set_loan(&self, loan: &Loan) -> Result<()> {
+        let serialized: Vec<u8> = bincode::serialize(loan)
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to serialize loan"))?;
+        self.loan_pointer(loan.id).set(Arc::new(serialized));
+        Ok(())
+    }
+
+    fn get_loan_by_id(&self, loan_id: u128) -> Result<Loan> {
+        let stored: Arc<Vec<u8>> = self.loan_pointer(loan_id).get();
+        if stored.is_empty() {
+            return Err(revert!(ERR_NOT_FOUND, "Loan {} not found", loan_id));
+        }
+
+        bincode::deserialize(&stored).map_err(|_| revert!(ERR_STORAGE, "Failed to deserialize loan"))
+    }
+}
+
+fn without_receipt(transfers: &[AlkaneTransfer], receipt: &AlkaneId) -> Vec<AlkaneTransfer> {
+    transfers.iter()
+        .filter(|transfer| transfer.id != *receipt)
+        .copied()
+        .collect()
+}
@@ -0,0 +1,142 @@
+//! Minting can be paused and resumed at will, or closed for good. Closing is the stronger
+//! promise: nothing reopens it, and since supply can no longer grow, payments escrowed for
+//! unrevealed mints become refundable straight away.
+
+use metashrew_support::index_pointer::KeyValuePointer;
+use alkanes_runtime::runtime::AlkaneResponder;
+use alkanes_support::response::CallResponse;
+use serde::Serialize;
+use anyhow::Result;
+use orbital_support::{revert, errors::codes::*};
+
+use crate::Collection;
+use crate::opcodes::StoragePointer;
+
+/// Mint state as returned by `GetMintStatus`
+#[derive(Serialize)]
+struct MintStatus {
+    paused: bool,
+    /// Height the mint was paused at, 0 while running
+    paused_at: u64,
+    closed: bool,
+    /// Height the mint was closed at, 0 while open
+    closed_at: u64,
+    minted: u128,
+    /// Orbitals airdropped or vaulted by the owner out of the premine allowance
+    premined: u128,
+    max_supply: u128,
+    sold_out: bool,
+}
+
+impl Collection {
+    /// Halt every mint path until `ResumeMint`. Supply stays open, so escrowed payments
+    /// wait for their reveal rather than becoming refundable. Queued mints must be
+    /// processed first, since a pause would stall the queue that already took payment.
+    pub(crate) fn pause_mint(&self) -> Result<CallResponse> {
+        self.only_owner()?;
+        let context: alkanes_support::context::Context = self.context()?;
+        let response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        self.observe_mint_not_closed()?;
+        if self.is_mint_paused() {
+            return Err(revert!(ERR_INVALID_STATE, "Minting is already paused"));
+        }
+        self.observe_waitlist_drained()?;
+
+        self.mint_paused_pointer().set_value::<u8>(1);
+        self.mint_paused_at_pointer().set_value::<u64>(self.height());
+        Ok(response)
+    }
+
+    pub(crate) fn resume_mint(&self) -> Result<CallResponse> {
+        self.only_owner()?;
+        let context: alkanes_support::context::Context = self.context()?;
+        let response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        self.observe_mint_not_closed()?;
+        if !self.is_mint_paused() {
+            return Err(revert!(ERR_INVALID_STATE, "Minting is not paused"));
+        }
+
+        self.mint_paused_pointer().set_value::<u8>(0);
+        self.mint_paused_at_pointer().set_value::<u64>(0);
+        Ok(response)
+    }
+
+    /// Permanently end minting on every path, there is no opcode to reopen it. Like a
+    /// pause, it waits for the waitlist to drain.
+    pub(crate) fn close_mint(&self) -> Result<CallResponse> {
+        self.only_owner()?;
+        let context: alkanes_support::context::Context = self.context()?;
+        let response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        self.observe_mint_not_closed()?;
+        self.observe_waitlist_drained()?;
+        self.mint_closed_at_pointer().set_value::<u64>(self.height());
+        self.mint_closed_pointer().set_value::<u8>(1);
+        Ok(response)
+    }
+
+    /// Returns whether minting runs and how much supply is left as JSON
+    pub(crate) fn get_mint_status(&self) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let status: MintStatus = MintStatus {
+            paused: self.is_mint_paused(),
+            paused_at: self.mint_paused_at_pointer().get_value::<u64>(),
+            closed: self.is_mint_closed(),
+            closed_at: self.mint_closed_at_pointer().get_value::<u64>(),
+            minted: self.instances_count(),
+            premined: self.premine_minted(),
+            max_supply: self.max_supply(),
+            sold_out: !self.can_mint_more(),
+        };
+
+        response.data = serde_json::to_vec(&status)
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to serialize mint status"))?;
+        Ok(response)
+    }
+
+    /// Refuse a mint once minting was closed or while the owner has it paused
+    pub(crate) fn observe_mint_open(&self) -> Result<()> {
+        self.observe_mint_not_closed()?;
+        if self.is_mint_paused() {
+            return Err(revert!(ERR_INVALID_STATE, "Minting is paused"));
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn is_mint_closed(&self) -> bool {
+        self.mint_closed_pointer().get_value::<u8>() == 1
+    }
+
+    fn observe_mint_not_closed(&self) -> Result<()> {
+        if self.is_mint_closed() {
+            return Err(revert!(ERR_INVALID_STATE, "Minting is closed for good"));
+        }
+
+        Ok(())
+    }
+
+    fn is_mint_paused(&self) -> bool {
+        self.mint_paused_pointer().get_value::<u8>() == 1
+    }
+
+    fn mint_paused_pointer(&self) -> StoragePointer {
+        StoragePointer::from_keyword("/mint/paused")
+    }
+
+    fn mint_paused_at_pointer(&self) -> StoragePointer {
+        StoragePointer::from_keyword("/mint/paused-at")
+    }
+
+    fn mint_closed_pointer(&self) -> StoragePointer {
+        StoragePointer::from_keyword("/mint/closed")
+    }
+
+    fn mint_closed_at_pointer(&self) -> StoragePointer {
+        StoragePointer::from_keyword("/mint/closed-at")
+    }
+}
@@ -0,0 +1,155 @@
+//! Indexers usually need several views of the same token at once. `Multicall` runs a list
+//! of views inside one call and packs their answers together. Only views are accepted, and
+//! only those taking nothing but their fixed inputs, so a batch never changes state and
+//! every sub-call is delimited by the opcode table below.
+
+use alkanes_runtime::runtime::AlkaneResponder;
+use alkanes_support::response::CallResponse;
+use anyhow::Result;
+use orbital_support::{revert, errors::{codes::*, Revert}};
+
+use crate::Collection;
+
+/// Inputs ahead of the first sub-call: opcode, calls
+const MULTICALL_HEADER: usize = 2;
+
+/// Sub-calls one `Multicall` runs
+const MAX_CALLS: u128 = 16;
+
+/// Status byte ahead of each result
+const CALL_OK: u8 = 1;
+const CALL_FAILED: u8 = 0;
+
+/// Fixed inputs of a sub-call, reverting when the list ends early
+fn call_args<const N: usize>(words: &[u128], opcode: u128) -> Result<[u128; N]> {
+    words.get(..N)
+        .and_then(|args| args.try_into().ok())
+        .ok_or_else(|| Revert::new(ERR_INVALID_INPUT, format!("Sub-call {} is truncated", opcode))
+            .field("inputs").expected(N as u128).actual(words.len() as u128).into())
+}
+
+impl Collection {
+    /// Run `calls` views given in the inputs as the opcode followed by its fixed inputs.
+    /// Each result is returned as a status byte (1 ok, 0 failed), a u32 little endian length
+    /// and the response data, or the error message of a failed view, in call order.
+    pub(crate) fn multicall(&self, calls: u128) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        if calls == 0 || calls > MAX_CALLS {
+            return Err(Revert::new(ERR_LIMIT_EXCEEDED, format!("Multicalls run 1 to {} views", MAX_CALLS))
+                .field("calls").expected(MAX_CALLS).actual(calls).into());
+        }
+
+        let mut words: &[u128] = context.inputs.get(MULTICALL_HEADER..).unwrap_or(&[]);
+        let mut data: Vec<u8> = Vec::new();
+        for _ in 0..calls {
+            let (&opcode, rest) = words.split_first()
+                .ok_or_else(|| revert!(ERR_INVALID_INPUT, "Multicall lists fewer than {} calls", calls))?;
+            let (consumed, result) = self.multicall_view(opcode, rest)?;
+            words = &rest[consumed..];
+
+            let (status, bytes): (u8, Vec<u8>) = match result {
+                Ok(view) => (CALL_OK, view.data),
+                Err(e) => (CALL_FAILED, e.to_string().into_bytes()),
+            };
+            data.push(status);
+            data.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+            data.extend_from_slice(&bytes);
+        }
+
+        if !words.is_empty() {
+            return Err(Revert::new(ERR_INVALID_INPUT, "Multicall inputs continue past the last call")
+                .field("inputs").expected(0).actual(words.len() as u128).into());
+        }
+
+        response.data = data;
+        Ok(response)
+    }
+
+    /// Run one view, returning the inputs it consumed and its outcome. Opcodes outside the
+    /// table revert the whole batch since their inputs cannot be delimited.
+    fn multicall_view(&self, opcode: u128, words: &[u128]) -> Result<(usize, Result<CallResponse>)> {
+        let outcome: (usize, Result<CallResponse>) = match opcode {
+            99 => (0, self.get_name()),
+            100 => (0, self.get_symbol()),
+            101 => (0, self.get_total_supply()),
+            102 => (0, self.get_orbital_count()),
+            103 => (0, self.get_protocol_version()),
+            999 => (1, self.get_attributes(call_args::<1>(words, opcode)?[0])),
+            1000 => (1, self.get_data(call_args::<1>(words, opcode)?[0])),
+            1001 => (1, self.get_instance_alkane_id(call_args::<1>(words, opcode)?[0])),
+            1002 => (1, self.get_instance_identifier(call_args::<1>(words, opcode)?[0])),
+            1003 => (1, self.get_stage_total_minted(call_args::<1>(words, opcode)?[0])),
+            1004 => (1, self.get_data_uri(call_args::<1>(words, opcode)?[0])),
+            1005 => (1, self.get_metadata(call_args::<1>(words, opcode)?[0])),
+            1006 => (1, self.get_thumbnail(call_args::<1>(words, opcode)?[0])),
+            1007 => (0, self.get_content_type()),
+            1008 => (0, self.get_metadata_state()),
+            1009 => (0, self.get_active_stages()),
+            1010 => (1, self.get_stage_info(call_args::<1>(words, opcode)?[0])),
+            1011 => (0, self.get_timeline()),
+            1013 => {
+                let [index, sale_amount] = call_args::<2>(words, opcode)?;
+                (2, self.get_royalty_info(index, sale_amount))
+            }
+            1017 => (0, self.get_featured()),
+            1018 => (1, self.get_rental(call_args::<1>(words, opcode)?[0])),
+            1022 => (1, self.get_loan_of(call_args::<1>(words, opcode)?[0])),
+            1023 => (1, self.get_stake(call_args::<1>(words, opcode)?[0])),
+            1027 => (1, self.get_points(call_args::<1>(words, opcode)?[0])),
+            1036 => (0, self.get_initialization_state()),
+            1037 => (0, self.get_config_hash()),
+            1038 => (0, self.get_deployment_mode()),
+            1042 => (1, self.get_minted_by(call_args::<1>(words, opcode)?[0])),
+            1046 => (1, self.get_compliance(call_args::<1>(words, opcode)?[0])),
+            1048 => (1, self.get_license(call_args::<1>(words, opcode)?[0])),
+            1049 => (1, self.get_derivative_count(call_args::<1>(words, opcode)?[0])),
+            1051 => (0, self.get_payment_token()),
+            1052 => (0, self.get_treasury_balance()),
+            1055 => (0, self.get_stages()),
+            1058 => (1, self.get_metadata_changelog(call_args::<1>(words, opcode)?[0])),
+            1060 => (0, self.get_public_mint()),
+            1065 => (1, self.get_current_price(call_args::<1>(words, opcode)?[0])),
+            1066 => (1, self.get_max_mint_per_block(call_args::<1>(words, opcode)?[0])),
+            1069 => (0, self.get_storage_usage()),
+            1071 => (0, self.get_max_supply()),
+            1073 => (0, self.get_mint_status()),
+            1079 => (1, self.get_minted_in_stage(call_args::<1>(words, opcode)?[0])),
+            1080 => {
+                let [stage_id, identity_low, identity_high] = call_args::<3>(words, opcode)?;
+                (3, self.get_address_mint_count(stage_id, identity_low, identity_high))
+            }
+            1081 => (0, self.get_premine_remaining()),
+            1082 => (0, self.get_vesting()),
+            1083 => (0, self.get_trait_tables()),
+            1084 => {
+                let [code_txid_low, code_txid_high, code_vout, block, tx] = call_args::<5>(words, opcode)?;
+                (5, self.get_referral_rewards(code_txid_low, code_txid_high, code_vout, block, tx))
+            }
+            1085 => (0, self.get_remaining_supply()),
+            1086 => (0, self.get_finale()),
+            1087 => {
+                let [ticket_txid_low, ticket_txid_high, ticket_vout] = call_args::<3>(words, opcode)?;
+                (3, self.get_waitlist(ticket_txid_low, ticket_txid_high, ticket_vout))
+            }
+            1089 => (1, self.get_accepted_payments(call_args::<1>(words, opcode)?[0])),
+            1090 => {
+                let [block, tx] = call_args::<2>(words, opcode)?;
+                (2, self.get_treasury_token_balance(block, tx))
+            }
+            1091 => (0, self.get_price_oracle()),
+            1092 => (0, self.get_render_engine_info()),
+            1093 => (0, self.get_discount_tiers()),
+            1094 => (1, self.get_data_hash(call_args::<1>(words, opcode)?[0])),
+            1095 => {
+                let [identity_low, identity_high] = call_args::<2>(words, opcode)?;
+                (2, self.get_remaining_lifetime_mints(identity_low, identity_high))
+            }
+            1096 => (1, self.get_revenue_by_stage(call_args::<1>(words, opcode)?[0])),
+            _ => return Err(revert!(ERR_INVALID_INPUT, "Opcode {} cannot be multicalled", opcode)),
+        };
+
+        Ok(outcome)
+    }
+}
@@ -0,0 +1,130 @@
+//! Every opcode is either a view or a mutating call. Views are the ones marked
+//! `#[returns]` in the message table and all fall in the ranges below; they answer
+//! staticcalls, so a view reaching a write would revert whoever relied on it. Every module
+//! stores state through the `StoragePointer` below, whose writes check the class of the
+//! running opcode and abort under a view, so no path to a write is left unguarded.
+
+use metashrew_support::index_pointer::KeyValuePointer;
+use alkanes_runtime::{runtime::AlkaneResponder, storage::StoragePointer as RuntimePointer};
+use alkanes_support::response::CallResponse;
+use serde::Serialize;
+use anyhow::Result;
+use orbital_support::{revert, errors::codes::*};
+use std::sync::Arc;
+
+use crate::Collection;
+
+/// Inclusive opcode ranges of the views, everything else mutates
+const READ_ONLY_RANGES: [(u128, u128); 2] = [(99, 103), (999, 1999)];
+
+/// Opcode classes as returned by `GetOpcodeClasses`
+#[derive(Serialize)]
+struct OpcodeClasses {
+    read_only: Vec<(u128, u128)>,
+}
+
+pub(crate) fn is_read_only(opcode: u128) -> bool {
+    READ_ONLY_RANGES.iter().any(|&(first, last)| first <= opcode && opcode <= last)
+}
+
+/// Refuse to write state while `opcode` is running when it is a view
+fn observe_writable_opcode(opcode: u128) -> Result<()> {
+    if is_read_only(opcode) {
+        return Err(revert!(ERR_INVALID_STATE, "Opcode {} is read-only and cannot write state", opcode));
+    }
+
+    Ok(())
+}
+
+/// Runtime storage pointer refusing writes under a view
+#[derive(Clone)]
+pub(crate) struct StoragePointer(RuntimePointer);
+
+impl KeyValuePointer for StoragePointer {
+    fn wrap(word: &Vec<u8>) -> Self {
+        StoragePointer(RuntimePointer::wrap(word))
+    }
+
+    fn unwrap(&self) -> Arc<Vec<u8>> {
+        self.0.unwrap()
+    }
+
+    fn inherits(&mut self, from: &Self) {
+        self.0.inherits(&from.0)
+    }
+
+    fn get(&self) -> Arc<Vec<u8>> {
+        self.0.get()
+    }
+
+    fn set(&mut self, value: Arc<Vec<u8>>) {
+        // Pointer writes cannot fail, so a write under a view aborts the whole call
+        if let Err(error) = Collection::default().observe_writable() {
+            panic!("{}", error);
+        }
+
+        self.0.set(value)
+    }
+}
+
+impl Collection {
+    /// Returns the inclusive opcode ranges of the views as JSON
+    pub(crate) fn get_opcode_classes(&self) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let classes: OpcodeClasses = OpcodeClasses { read_only: READ_ONLY_RANGES.to_vec() };
+        response.data = serde_json::to_vec(&classes)
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to serialize opcode classes"))?;
+        Ok(response)
+    }
+
+    /// Refuse to write state while a view is running
+    fn observe_writable(&self) -> Result<()> {
+        let context: alkanes_support::context::Context = self.context()?;
+        observe_writable_opcode(context.inputs.first().copied().unwrap_or_default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MESSAGE_TABLE: &str = include_str!("lib.rs");
+
+    /// Every opcode of the message table and whether it is marked `#[returns]`
+    fn message_opcodes() -> Vec<(u128, bool)> {
+        let mut opcodes: Vec<(u128, bool)> = Vec::new();
+        let mut lines = MESSAGE_TABLE.lines().map(str::trim).peekable();
+        while let Some(line) = lines.next() {
+            if let Some(opcode) = line.strip_prefix("#[opcode(").and_then(|rest| rest.strip_suffix(")]")) {
+                let view: bool = lines.peek().is_some_and(|next| next.starts_with("#[returns("));
+                opcodes.push((opcode.parse().unwrap(), view));
+            }
+        }
+        opcodes
+    }
+
+    #[test]
+    fn views_are_exactly_the_read_only_opcodes() {
+        let opcodes: Vec<(u128, bool)> = message_opcodes();
+        assert!(opcodes.iter().any(|&(_, view)| view) && opcodes.iter().any(|&(_, view)| !view));
+
+        for (opcode, view) in opcodes {
+            assert_eq!(is_read_only(opcode), view, "opcode {} is classed against its message table entry", opcode);
+        }
+    }
+
+    #[test]
+    fn writes_are_refused_under_every_read_only_opcode() {
+        for (opcode, view) in message_opcodes() {
+            let result: Result<()> = observe_writable_opcode(opcode);
+            if view {
+                let error: anyhow::Error = result.unwrap_err();
+                assert_eq!(error.downcast_ref::<orbital_support::errors::Revert>().unwrap().code, ERR_INVALID_STATE);
+            } else {
+                assert!(result.is_ok(), "mutating opcode {} cannot write", opcode);
+            }
+        }
+    }
+}
@@ -0,0 +1,184 @@
+//! Stages can be priced in sats while the mint is paid in the payment token, typically an
+//! alkane stablecoin. The sats price is converted at the BTC rate an oracle alkane reports
+//! when the mint happens. A rate outside the band the owner configured, or one the oracle
+//! has not updated recently enough, reverts the mint rather than selling at a wrong price.
+
+use metashrew_support::index_pointer::KeyValuePointer;
+use alkanes_runtime::runtime::AlkaneResponder;
+use alkanes_support::{cellpack::Cellpack, id::AlkaneId, parcel::AlkaneTransferParcel, response::CallResponse};
+use serde::Serialize;
+use anyhow::Result;
+use orbital_support::{revert, errors::{codes::*, Revert}};
+use std::sync::Arc;
+
+use crate::{Collection, Stage, decode_alkane_id, encode_alkane_id};
+use crate::opcodes::StoragePointer;
+
+/// Opcode rate oracles implement: no inputs, the response data is the payment token units
+/// one BTC is worth followed by the height the rate was last updated at, both little
+/// endian u128
+const ORACLE_GET_RATE: u128 = 1000;
+
+const SATS_PER_BTC: u128 = 100_000_000;
+
+/// Oracle configuration as returned by `GetPriceOracle`
+#[derive(Serialize)]
+struct PriceOracleView {
+    /// Zero when no oracle is configured
+    oracle_block: u128,
+    oracle_tx: u128,
+    min_rate: u128,
+    max_rate: u128,
+    max_age: u128,
+    stages: Vec<u128>,
+}
+
+impl Collection {
+    /// Configure the rate oracle and the band of rates it is trusted within, in payment
+    /// token units per BTC. Rates older than `max_age` blocks are refused, zero accepts any
+    /// age. A zero oracle disables sats pricing.
+    pub(crate) fn set_price_oracle(&self, block: u128, tx: u128, min_rate: u128, max_rate: u128, max_age: u128) -> Result<CallResponse> {
+        self.only_owner()?;
+        let context: alkanes_support::context::Context = self.context()?;
+        let response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        if block == 0 && tx == 0 {
+            self.oracle_pointer().set(Arc::new(vec![]));
+            return Ok(response);
+        }
+
+        if min_rate == 0 || max_rate < min_rate {
+            return Err(Revert::new(ERR_INVALID_INPUT, "The rate band needs a positive minimum below its maximum")
+                .field("max_rate").expected(min_rate.max(1)).actual(max_rate).into());
+        }
+
+        self.oracle_pointer().set(Arc::new(encode_alkane_id(&AlkaneId { block, tx })));
+        self.oracle_min_rate_pointer().set_value::<u128>(min_rate);
+        self.oracle_max_rate_pointer().set_value::<u128>(max_rate);
+        self.oracle_max_age_pointer().set_value::<u128>(max_age);
+        Ok(response)
+    }
+
+    /// Read the prices of a stage as sats to convert at the oracle rate
+    pub(crate) fn set_stage_sats_pricing(&self, stage_id: u128, enabled: u128) -> Result<CallResponse> {
+        self.only_owner()?;
+        let context: alkanes_support::context::Context = self.context()?;
+        let response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        if enabled != 0 && self.price_oracle()?.is_none() {
+            return Err(revert!(ERR_INVALID_STATE, "No price oracle configured"));
+        }
+
+        self.update_mint_stage(stage_id, |stage| {
+            stage.priced_in_sats = enabled != 0;
+            Ok(())
+        })?;
+        Ok(response)
+    }
+
+    /// Returns the oracle, its rate band and the stages priced in sats as JSON
+    pub(crate) fn get_price_oracle(&self) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let oracle: AlkaneId = self.price_oracle()?.unwrap_or_default();
+        let view: PriceOracleView = PriceOracleView {
+            oracle_block: oracle.block,
+            oracle_tx: oracle.tx,
+            min_rate: self.oracle_min_rate_pointer().get_value::<u128>(),
+            max_rate: self.oracle_max_rate_pointer().get_value::<u128>(),
+            max_age: self.oracle_max_age_pointer().get_value::<u128>(),
+            stages: self.get_mint_stages()?.iter()
+                .filter(|stage| stage.priced_in_sats)
+                .map(|stage| stage.id)
+                .collect(),
+        };
+
+        response.data = serde_json::to_vec(&view)
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to serialize price oracle"))?;
+        Ok(response)
+    }
+
+    /// Price of `count` items of a stage in the payment token and the last block it holds
+    /// for, less the discount tier the batch reaches. Sats prices are discounted in sats and
+    /// converted at the current oracle rate, which only holds for the current block.
+    pub(crate) fn stage_quote(&self, stage: &Stage, count: u128, height: u64) -> Result<(u128, u64)> {
+        let (full_price, valid_until) = stage.quote(count, height)?;
+        let price: u128 = self.discounted_batch_price(count, full_price)?;
+        if !stage.priced_in_sats || price == 0 {
+            return Ok((price, valid_until));
+        }
+
+        let rate: u128 = self.oracle_rate()?;
+        let scaled: u128 = price.checked_mul(rate)
+            .ok_or_else(|| revert!(ERR_OVERFLOW, "price overflow"))?;
+        // Rounded up so a conversion never sells below the sats price
+        Ok((scaled.div_ceil(SATS_PER_BTC), height))
+    }
+
+    /// Staticcall the oracle for the payment token units per BTC, refusing stale rates and
+    /// rates outside the configured band
+    fn oracle_rate(&self) -> Result<u128> {
+        let oracle: AlkaneId = self.price_oracle()?
+            .ok_or_else(|| revert!(ERR_INVALID_STATE, "No price oracle configured"))?;
+
+        let cellpack: Cellpack = Cellpack {
+            target: oracle,
+            inputs: vec![ORACLE_GET_RATE],
+        };
+
+        let response: CallResponse = self.staticcall(&cellpack, &AlkaneTransferParcel::default(), self.fuel())?;
+        if response.data.len() < 32 {
+            return Err(revert!(ERR_EXTERNAL_CALL, "Invalid oracle rate response"));
+        }
+
+        let rate: u128 = u128::from_le_bytes(response.data[..16].try_into().unwrap());
+        let updated_at: u128 = u128::from_le_bytes(response.data[16..32].try_into().unwrap());
+
+        let max_age: u128 = self.oracle_max_age_pointer().get_value::<u128>();
+        let age: u128 = (self.height() as u128).saturating_sub(updated_at);
+        if max_age > 0 && age > max_age {
+            return Err(Revert::new(ERR_EXTERNAL_CALL, format!("Oracle rate is {} blocks old", age))
+                .field("rate_age").expected(max_age).actual(age).into());
+        }
+
+        let min_rate: u128 = self.oracle_min_rate_pointer().get_value::<u128>();
+        if rate < min_rate {
+            return Err(Revert::new(ERR_EXTERNAL_CALL, "Oracle rate below the trusted band")
+                .field("rate").expected(min_rate).actual(rate).into());
+        }
+
+        let max_rate: u128 = self.oracle_max_rate_pointer().get_value::<u128>();
+        if rate > max_rate {
+            return Err(Revert::new(ERR_EXTERNAL_CALL, "Oracle rate above the trusted band")
+                .field("rate").expected(max_rate).actual(rate).into());
+        }
+
+        Ok(rate)
+    }
+
+    fn price_oracle(&self) -> Result<Option<AlkaneId>> {
+        let stored: Arc<Vec<u8>> = self.oracle_pointer().get();
+        if stored.is_empty() {
+            return Ok(None);
+        }
+
+        Ok(Some(decode_alkane_id(&stored)?))
+    }
+
+    fn oracle_pointer(&self) -> StoragePointer {
+        StoragePointer::from_keyword("/oracle/alkane")
+    }
+
+    fn oracle_min_rate_pointer(&self) -> StoragePointer {
+        StoragePointer::from_keyword("/oracle/min-rate")
+    }
+
+    fn oracle_max_rate_pointer(&self) -> StoragePointer {
+        StoragePointer::from_keyword("/oracle/max-rate")
+    }
+
+    fn oracle_max_age_pointer(&self) -> StoragePointer {
+        StoragePointer::from_keyword("/oracle/max-age")
+    }
+}
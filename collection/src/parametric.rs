@@ -0,0 +1,179 @@
+//! Parametric art: a central body with ring systems, elliptical orbits carrying moons and a
+//! particle field, all drawn from a generator seeded by the encoded trait entry. The trait
+//! table only provides the seeds, so any table with one entry per orbital drives it; the
+//! traits reported are the geometry parameters the seed produced.
+
+use anyhow::Result;
+use orbital_support::rng::Rng;
+
+use crate::render_engine::RenderEngine;
+use crate::svg_generator::{RenderConfig, SvgGenerator, THUMBNAIL_SIZE};
+
+/// Salt of the generator laying out the geometry of an entry
+const PARAMETRIC_SALT: &[u8] = b"orbital/parametric/";
+
+/// Traits of the parametric art, in the order of its attributes
+const PARAMETRIC_TRAIT_TYPES: [&str; 5] = ["palette", "rings", "orbits", "eccentricity", "particles"];
+
+/// Named palettes: space, body, rings, orbits, particles
+const PALETTES: [(&str, [&str; 5]); 6] = [
+  ("nebula", ["#0b0425", "#c86bfa", "#7f5af0", "#a5b4fc", "#f0abfc"]),
+  ("solar", ["#120700", "#ffb703", "#fb8500", "#ffd166", "#fff3b0"]),
+  ("glacier", ["#020f1c", "#8ecae6", "#219ebc", "#caf0f8", "#ffffff"]),
+  ("ember", ["#1a0303", "#e63946", "#f4a261", "#ffb4a2", "#ffddd2"]),
+  ("verdant", ["#021207", "#52b788", "#2d6a4f", "#b7e4c7", "#d8f3dc"]),
+  ("monochrome", ["#000000", "#e5e5e5", "#a3a3a3", "#737373", "#fafafa"]),
+];
+
+/// Most ring bands around the body
+const MAX_RINGS: u128 = 4;
+
+/// Orbits around the body, at least one
+const MAX_ORBITS: u128 = 5;
+
+/// Widest orbit eccentricity, in hundredths
+const MAX_ECCENTRICITY: u128 = 80;
+
+/// Particle field size bounds
+const MIN_PARTICLES: u128 = 24;
+const MAX_PARTICLES: u128 = 96;
+
+/// An elliptical orbit with the body at one focus
+struct Orbit {
+  semi_major: u32,
+  /// Hundredths
+  eccentricity: u32,
+  /// Degrees the major axis is turned by
+  angle: u32,
+  moon_radius: u32,
+}
+
+/// Everything drawn for an entry, produced by one pass of the generator
+struct Geometry {
+  palette: usize,
+  body_radius: u32,
+  rings: u32,
+  /// Ring height over width, in percent
+  ring_tilt: u32,
+  orbits: Vec<Orbit>,
+  /// Position and radius in tenths of a unit
+  particles: Vec<(u32, u32, u32)>,
+}
+
+impl Geometry {
+  fn from_seed(seed: u128) -> Geometry {
+    let mut rng: Rng = Rng::new(&seed.to_le_bytes(), PARAMETRIC_SALT);
+
+    let palette: usize = rng.below(PALETTES.len() as u128) as usize;
+    let body_radius: u32 = 14 + rng.below(21) as u32;
+    let rings: u32 = rng.below(MAX_RINGS + 1) as u32;
+    let ring_tilt: u32 = 20 + rng.below(26) as u32;
+
+    let orbits: Vec<Orbit> = (0..1 + rng.below(MAX_ORBITS))
+      .map(|_| Orbit {
+        semi_major: 50 + rng.below(46) as u32,
+        eccentricity: rng.below(MAX_ECCENTRICITY + 1) as u32,
+        angle: rng.below(180) as u32,
+        moon_radius: 2 + rng.below(5) as u32,
+      })
+      .collect();
+
+    let particles: Vec<(u32, u32, u32)> = (0..MIN_PARTICLES + rng.below(MAX_PARTICLES - MIN_PARTICLES + 1))
+      .map(|_| (rng.below(200) as u32, rng.below(200) as u32, 5 + rng.below(11) as u32))
+      .collect();
+
+    Geometry { palette, body_radius, rings, ring_tilt, orbits, particles }
+  }
+
+  /// Widest eccentricity among the orbits, in hundredths
+  fn eccentricity(&self) -> u32 {
+    self.orbits.iter().map(|orbit| orbit.eccentricity).max().unwrap_or(0)
+  }
+
+  fn traits(&self) -> Vec<String> {
+    vec![
+      PALETTES[self.palette].0.to_string(),
+      self.rings.to_string(),
+      self.orbits.len().to_string(),
+      format!("{}.{:02}", self.eccentricity() / 100, self.eccentricity() % 100),
+      self.particles.len().to_string(),
+    ]
+  }
+
+  fn render_svg(&self, width: &str, height: &str) -> String {
+    let [space, body, ring, orbit_color, particle] = PALETTES[self.palette].1;
+
+    let mut svg: String = format!("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<svg width=\"{}\" height=\"{}\" viewBox=\"0 0 200 200\" xmlns=\"http://www.w3.org/2000/svg\">\n", width, height);
+    svg.push_str(&format!("<rect width=\"200\" height=\"200\" fill=\"{}\"/>\n", space));
+
+    for (x, y, radius) in &self.particles {
+      svg.push_str(&format!("<circle cx=\"{}\" cy=\"{}\" r=\"{}.{}\" fill=\"{}\" opacity=\"0.7\"/>\n", x, y, radius / 10, radius % 10, particle));
+    }
+
+    for orbit in &self.orbits {
+      // The body sits at a focus, c = a * e away from the center along the major axis
+      let semi_major: f64 = orbit.semi_major as f64;
+      let eccentricity: f64 = orbit.eccentricity as f64 / 100.0;
+      let focal: f64 = semi_major * eccentricity;
+      let semi_minor: f64 = semi_major * (1.0 - eccentricity * eccentricity).sqrt();
+      svg.push_str(&format!(
+        "<g transform=\"rotate({} 100 100)\"><ellipse cx=\"{:.1}\" cy=\"100\" rx=\"{}\" ry=\"{:.1}\" fill=\"none\" stroke=\"{}\" stroke-width=\"0.8\" opacity=\"0.8\"/><circle cx=\"{:.1}\" cy=\"100\" r=\"{}\" fill=\"{}\"/></g>\n",
+        orbit.angle, 100.0 - focal, orbit.semi_major, semi_minor, orbit_color,
+        100.0 - focal + semi_major, orbit.moon_radius, orbit_color
+      ));
+    }
+
+    svg.push_str(&format!("<circle cx=\"100\" cy=\"100\" r=\"{}\" fill=\"{}\"/>\n", self.body_radius, body));
+
+    for band in 0..self.rings {
+      let ring_width: u32 = self.body_radius * (14 + 3 * band) / 10;
+      svg.push_str(&format!(
+        "<ellipse cx=\"100\" cy=\"100\" rx=\"{}\" ry=\"{}\" fill=\"none\" stroke=\"{}\" stroke-width=\"{}\" opacity=\"0.85\"/>\n",
+        ring_width, ring_width * self.ring_tilt / 100, ring, 3 - band.min(2)
+      ));
+    }
+
+    svg.push_str("</svg>");
+    svg
+  }
+}
+
+/// Engine drawing parametric geometry from the trait table entries
+pub struct ParametricEngine {
+  generator: SvgGenerator,
+}
+
+impl ParametricEngine {
+  pub fn new(generator: SvgGenerator) -> Self {
+    ParametricEngine { generator }
+  }
+
+  fn geometry(&self, art: u128) -> Result<Geometry> {
+    Ok(Geometry::from_seed(self.generator.encoded_entry(art)?))
+  }
+}
+
+impl RenderEngine for ParametricEngine {
+  fn content_type(&self) -> &'static str {
+    "image/svg+xml"
+  }
+
+  fn trait_types(&self) -> &'static [&'static str] {
+    &PARAMETRIC_TRAIT_TYPES
+  }
+
+  fn traits(&self, art: u128) -> Result<Vec<String>> {
+    Ok(self.geometry(art)?.traits())
+  }
+
+  fn render(&self, art: u128, config: &RenderConfig) -> Result<String> {
+    let svg: String = self.geometry(art)?.render_svg("100%", "100%");
+    Ok(if config.preview { SvgGenerator::watermark(&svg) } else { svg })
+  }
+
+  fn thumbnail(&self, art: u128, config: &RenderConfig) -> Result<String> {
+    let size: String = format!("{}", THUMBNAIL_SIZE);
+    let svg: String = self.geometry(art)?.render_svg(&size, &size);
+    Ok(if config.preview { SvgGenerator::watermark(&svg) } else { svg })
+  }
+}
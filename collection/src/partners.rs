@@ -0,0 +1,423 @@
+use metashrew_support::index_pointer::KeyValuePointer;
+use alkanes_runtime::runtime::AlkaneResponder;
+use alkanes_support::{id::AlkaneId, parcel::AlkaneTransfer, response::CallResponse};
+use serde::{Serialize, Deserialize};
+use anyhow::Result;
+use orbital_support::{revert, errors::{codes::*, Revert}};
+use std::sync::Arc;
+
+use crate::{Collection, Stage};
+use crate::identity::identity_from_halves;
+use crate::opcodes::StoragePointer;
+
+/// Upper bound on orbitals per partner batch, keeps a batch within fuel
+const MAX_PARTNER_BATCH: u128 = 25;
+
+/// Inputs ahead of the attribution list: opcode, partner_id, stage_id, entries
+const PARTNER_MINT_HEADER: usize = 4;
+
+/// Inputs per attribution: identity low half, identity high half, count
+const ATTRIBUTION_WIDTH: usize = 3;
+
+/// Orbital was not minted by a partner and is final
+const CLAWBACK_NONE: u8 = 0;
+
+/// Partner minted orbital still inside its clawback window
+const CLAWBACK_REVOCABLE: u8 = 1;
+
+/// Partner minted orbital past its clawback window, irrevocable
+const CLAWBACK_FINAL: u8 = 2;
+
+/// Partner minted orbital that was reversed
+const CLAWBACK_REVERSED: u8 = 3;
+
+/// Launch partner minting on behalf of its custodial users.
+/// Authenticated by presenting its credential token.
+#[derive(Clone, Default, Debug, Serialize, Deserialize)]
+struct Partner {
+    id: u128,
+    credential_block: u128,
+    credential_tx: u128,
+    minted: u128,
+    /// Orbitals the partner may mint in total
+    quota: u128,
+    /// Wholesale price per orbital in the payment token, 0 when settled off-chain
+    price_per_item: u128,
+    payment_block: u128,
+    payment_tx: u128,
+    /// Payment collected from the partner so far
+    revenue: u128,
+    /// Blocks after a mint during which it can be reversed, 0 makes mints final at once
+    clawback_blocks: u64,
+}
+
+/// Custodial mint record of an orbital, kept for the clawback window
+#[derive(Clone, Default, Debug, Serialize, Deserialize)]
+struct CustodialMint {
+    index: u128,
+    partner_id: u128,
+    stage_id: u128,
+    identity: [u8; 32],
+    /// Wholesale price paid for the orbital, refunded on clawback
+    price: u128,
+    /// Last block at which the mint can be reversed
+    revocable_until: u64,
+    reversed: bool,
+}
+
+/// Clawback state of an orbital, returned by `GetClawbackStatus`
+#[derive(Serialize)]
+struct ClawbackStatus {
+    index: u128,
+    status: u8,
+    partner_id: u128,
+    revocable_until: u64,
+    is_final: bool,
+}
+
+impl Partner {
+    fn credential(&self) -> AlkaneId {
+        AlkaneId { block: self.credential_block, tx: self.credential_tx }
+    }
+
+    fn payment_token(&self) -> AlkaneId {
+        AlkaneId { block: self.payment_block, tx: self.payment_tx }
+    }
+
+    fn remaining_quota(&self) -> u128 {
+        self.quota.saturating_sub(self.minted)
+    }
+}
+
+impl Collection {
+    /// Register a partner or replace its credential token
+    pub(crate) fn set_partner(&self, partner_id: u128, credential_block: u128, credential_tx: u128) -> Result<CallResponse> {
+        self.only_owner()?;
+        let context: alkanes_support::context::Context = self.context()?;
+        let response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        if partner_id == 0 {
+            return Err(revert!(ERR_INVALID_INPUT, "Partner ID must be positive"));
+        }
+
+        let mut partner: Partner = self.get_partner_record(partner_id)?.unwrap_or_default();
+        partner.id = partner_id;
+        partner.credential_block = credential_block;
+        partner.credential_tx = credential_tx;
+        self.set_partner_record(&partner)?;
+        Ok(response)
+    }
+
+    /// Set the quota and wholesale terms of a partner
+    pub(crate) fn set_partner_terms(&self, partner_id: u128, quota: u128, price_per_item: u128, payment_block: u128, payment_tx: u128) -> Result<CallResponse> {
+        self.only_owner()?;
+        let context: alkanes_support::context::Context = self.context()?;
+        let response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        if price_per_item > 0 && payment_block == 0 && payment_tx == 0 {
+            return Err(revert!(ERR_INVALID_INPUT, "A wholesale price needs a payment token"));
+        }
+
+        let mut partner: Partner = self.get_partner_by_id(partner_id)?;
+        partner.quota = quota;
+        partner.price_per_item = price_per_item;
+        partner.payment_block = payment_block;
+        partner.payment_tx = payment_tx;
+        self.set_partner_record(&partner)?;
+        Ok(response)
+    }
+
+    /// Set the clawback window applied to future mints of a partner
+    pub(crate) fn set_partner_clawback(&self, partner_id: u128, window_blocks: u128) -> Result<CallResponse> {
+        self.only_owner()?;
+        let context: alkanes_support::context::Context = self.context()?;
+        let response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let mut partner: Partner = self.get_partner_by_id(partner_id)?;
+        partner.clawback_blocks = u64::try_from(window_blocks).map_err(|_| revert!(ERR_INVALID_INPUT, "Clawback window out of range"))?;
+        self.set_partner_record(&partner)?;
+        Ok(response)
+    }
+
+    pub(crate) fn remove_partner(&self, partner_id: u128) -> Result<CallResponse> {
+        self.only_owner()?;
+        let context: alkanes_support::context::Context = self.context()?;
+        let response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        self.get_partner_by_id(partner_id)?;
+        self.partner_pointer(partner_id).set(Arc::new(vec![]));
+        Ok(response)
+    }
+
+    /// Mint into a stage for the users in the attribution list that follows the fixed
+    /// inputs: `entries` triples of identity low half, identity high half and count.
+    /// Every attribution counts against the per-address limit of its identity and is
+    /// recorded as the minter of its orbitals. The batch draws on the partner quota and
+    /// is paid at the wholesale price. The partner receives the orbitals in custody.
+    pub(crate) fn partner_mint(&self, partner_id: u128, stage_id: u128, entries: u128) -> Result<CallResponse> {
+        let mut partner: Partner = self.get_partner_by_id(partner_id)?;
+        self.only_partner(&partner)?;
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::default();
+
+        let attributions: Vec<([u8; 32], u128)> = self.read_attributions(&context.inputs, entries)?;
+        let total: u128 = attributions.iter()
+            .try_fold(0u128, |total, (_, count)| total.checked_add(*count))
+            .ok_or_else(|| revert!(ERR_OVERFLOW, "batch size overflow"))?;
+
+        if total == 0 || total > MAX_PARTNER_BATCH {
+            return Err(Revert::new(ERR_LIMIT_EXCEEDED, format!("A partner batch mints between 1 and {} orbitals", MAX_PARTNER_BATCH))
+                .field("count").expected(MAX_PARTNER_BATCH).actual(total).into());
+        }
+
+        if total > partner.remaining_quota() {
+            return Err(Revert::new(ERR_LIMIT_EXCEEDED, format!("Partner {} quota exceeded", partner.id))
+                .field("quota").expected(partner.remaining_quota()).actual(total).into());
+        }
+
+        let cost: u128 = partner.price_per_item.checked_mul(total)
+            .ok_or_else(|| revert!(ERR_OVERFLOW, "price overflow"))?;
+        if cost > 0 {
+            response.alkanes.0 = self.take_payment(&context.incoming_alkanes, &partner.payment_token(), cost)?;
+            self.credit_treasury(&partner.payment_token(), cost)?;
+            self.record_stage_revenue(stage_id, &partner.payment_token(), cost)?;
+        } else {
+            response.alkanes.0 = context.incoming_alkanes.0.clone();
+        }
+
+        let stage: Stage = self.get_mint_stage(stage_id)?;
+        let height: u64 = self.height();
+
+        if !stage.is_active(height, self.block_time()) {
+            return Err(revert!(ERR_INVALID_STATE, "Stage is not active"));
+        }
+
+        if stage.total_minted.saturating_add(total) > stage.max_supply {
+            return Err(Revert::new(ERR_LIMIT_EXCEEDED, "Exceeds max supply for this stage")
+                .field("max_supply").expected(stage.max_supply).actual(stage.total_minted.saturating_add(total)).into());
+        }
+
+        // Recorded as checked so an identity listed twice is held to one allowance
+        for (identity, count) in attributions.iter() {
+            self.observe_address_limit(&stage, identity, *count)?;
+            self.record_address_mints(stage_id, identity, *count)?;
+        }
+
+        self.update_mint_stage(stage_id, |stage| stage.record_mints(total, height))?;
+
+        for (identity, count) in attributions.iter() {
+            for _ in 0..*count {
+                self.observe_mint_per_block(stage_id)?;
+                let (index, transfer) = self.create_mint_transfer()?;
+                response.alkanes.0.push(transfer);
+                self.set_minted_by(index, identity);
+                if partner.clawback_blocks > 0 {
+                    self.set_custodial_mint(&CustodialMint {
+                        index,
+                        partner_id,
+                        stage_id,
+                        identity: *identity,
+                        price: partner.price_per_item,
+                        revocable_until: height.saturating_add(partner.clawback_blocks),
+                        reversed: false,
+                    })?;
+                }
+            }
+        }
+
+        partner.minted = partner.minted.checked_add(total)
+            .ok_or_else(|| revert!(ERR_OVERFLOW, "partner mint count overflow"))?;
+        partner.revenue = partner.revenue.checked_add(cost)
+            .ok_or_else(|| revert!(ERR_OVERFLOW, "partner revenue overflow"))?;
+        self.set_partner_record(&partner)?;
+        Ok(response)
+    }
+
+    /// Reverse a fraudulent custodial mint inside its window. Needs the partner credential,
+    /// the owner auth token and the orbital itself, which stays with the collection for good.
+    /// The wholesale price goes back to the partner and the allowance back to the identity.
+    pub(crate) fn clawback(&self, index: u128) -> Result<CallResponse> {
+        let mut record: CustodialMint = self.get_custodial_mint(index)?
+            .ok_or_else(|| revert!(ERR_NOT_FOUND, "Orbital {} was not minted by a partner", index))?;
+        let mut partner: Partner = self.get_partner_by_id(record.partner_id)?;
+        self.only_owner()?;
+        self.only_partner(&partner)?;
+        self.only_holder(index)?;
+
+        if record.reversed {
+            return Err(revert!(ERR_INVALID_STATE, "Orbital {} was already clawed back", index));
+        }
+
+        if self.height() > record.revocable_until {
+            return Err(Revert::new(ERR_INVALID_STATE, format!("Orbital {} is final", index))
+                .field("revocable_until").expected(record.revocable_until as u128).actual(self.height() as u128).into());
+        }
+
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::default();
+
+        // The orbital is retained, everything else goes back to the caller
+        let instance_id: AlkaneId = self.lookup_instance(index)?;
+        let mut retained: bool = false;
+        for transfer in context.incoming_alkanes.0.iter() {
+            if transfer.id == instance_id && !retained {
+                retained = true;
+                if transfer.value > 1 {
+                    response.alkanes.0.push(AlkaneTransfer { id: transfer.id, value: transfer.value - 1 });
+                }
+            } else {
+                response.alkanes.0.push(*transfer);
+            }
+        }
+
+        if record.price > 0 {
+            self.debit_treasury(&partner.payment_token(), record.price)?;
+            self.refund_stage_revenue(record.stage_id, &partner.payment_token(), record.price)?;
+            response.alkanes.0.push(AlkaneTransfer { id: partner.payment_token(), value: record.price });
+            partner.revenue = partner.revenue.saturating_sub(record.price);
+        }
+        partner.minted = partner.minted.saturating_sub(1);
+        self.set_partner_record(&partner)?;
+
+        self.release_address_mints(record.stage_id, &record.identity, 1);
+
+        record.reversed = true;
+        self.set_custodial_mint(&record)?;
+        Ok(response)
+    }
+
+    /// Returns the clawback state of an orbital as JSON
+    pub(crate) fn get_clawback_status(&self, index: u128) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let status: ClawbackStatus = match self.get_custodial_mint(index)? {
+            None => ClawbackStatus { index, status: CLAWBACK_NONE, partner_id: 0, revocable_until: 0, is_final: true },
+            Some(record) => {
+                let status: u8 = if record.reversed {
+                    CLAWBACK_REVERSED
+                } else if self.height() > record.revocable_until {
+                    CLAWBACK_FINAL
+                } else {
+                    CLAWBACK_REVOCABLE
+                };
+
+                ClawbackStatus {
+                    index,
+                    status,
+                    partner_id: record.partner_id,
+                    revocable_until: record.revocable_until,
+                    is_final: status == CLAWBACK_FINAL,
+                }
+            }
+        };
+
+        response.data = serde_json::to_vec(&status)
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to serialize clawback status"))?;
+        Ok(response)
+    }
+
+    /// Returns a partner as JSON
+    pub(crate) fn get_partner(&self, partner_id: u128) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let partner: Partner = self.get_partner_by_id(partner_id)?;
+        response.data = serde_json::to_vec(&partner)
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to serialize partner"))?;
+        Ok(response)
+    }
+
+    /// Returns the settlement of a partner: minted, remaining quota and revenue (u128 each)
+    pub(crate) fn get_partner_settlement(&self, partner_id: u128) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let partner: Partner = self.get_partner_by_id(partner_id)?;
+        let mut data: Vec<u8> = Vec::with_capacity(48);
+        data.extend_from_slice(&partner.minted.to_le_bytes());
+        data.extend_from_slice(&partner.remaining_quota().to_le_bytes());
+        data.extend_from_slice(&partner.revenue.to_le_bytes());
+        response.data = data;
+        Ok(response)
+    }
+
+    fn read_attributions(&self, inputs: &[u128], entries: u128) -> Result<Vec<([u8; 32], u128)>> {
+        let entries: usize = usize::try_from(entries).map_err(|_| revert!(ERR_INVALID_INPUT, "Attribution count out of range"))?;
+        let expected: usize = entries.checked_mul(ATTRIBUTION_WIDTH)
+            .and_then(|width| width.checked_add(PARTNER_MINT_HEADER))
+            .ok_or_else(|| revert!(ERR_INVALID_INPUT, "Attribution count out of range"))?;
+
+        if inputs.len() < expected {
+            return Err(Revert::new(ERR_INVALID_INPUT, "Attribution list is truncated")
+                .field("inputs").expected(expected as u128).actual(inputs.len() as u128).into());
+        }
+
+        Ok(inputs[PARTNER_MINT_HEADER..expected]
+            .chunks(ATTRIBUTION_WIDTH)
+            .map(|entry| (identity_from_halves(entry[0], entry[1]), entry[2]))
+            .collect())
+    }
+
+    fn only_partner(&self, partner: &Partner) -> Result<()> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let credential: AlkaneId = partner.credential();
+        let has_credential: bool = context.incoming_alkanes.0.iter()
+            .any(|transfer| transfer.id == credential && transfer.value > 0);
+
+        if !has_credential {
+            return Err(revert!(ERR_UNAUTHORIZED, "Caller is not partner {}", partner.id));
+        }
+
+        Ok(())
+    }
+
+    fn custodial_mint_pointer(&self, index: u128) -> StoragePointer {
+        StoragePointer::from_keyword("/custodial/").select(&index.to_le_bytes().to_vec())
+    }
+
+    fn set_custodial_mint(&self, record: &CustodialMint) -> Result<()> {
+        let serialized: Vec<u8> = bincode::serialize(record)
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to serialize custodial mint"))?;
+        self.custodial_mint_pointer(record.index).set(Arc::new(serialized));
+        Ok(())
+    }
+
+    fn get_custodial_mint(&self, index: u128) -> Result<Option<CustodialMint>> {
+        let stored: Arc<Vec<u8>> = self.custodial_mint_pointer(index).get();
+        if stored.is_empty() {
+            return Ok(None);
+        }
+
+        let record: CustodialMint = bincode::deserialize(&stored)
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to deserialize custodial mint"))?;
+        Ok(Some(record))
+    }
+
+    fn partner_pointer(&self, partner_id: u128) -> StoragePointer {
+        StoragePointer::from_keyword("/partners/").select(&partner_id.to_le_bytes().to_vec())
+    }
+
+    fn set_partner_record(&self, partner: &Partner) -> Result<()> {
+        let serialized: Vec<u8> = bincode::serialize(partner)
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to serialize partner"))?;
+        self.partner_pointer(partner.id).set(Arc::new(serialized));
+        Ok(())
+    }
+
+    fn get_partner_record(&self, partner_id: u128) -> Result<Option<Partner>> {
+        let stored: Arc<Vec<u8>> = self.partner_pointer(partner_id).get();
+        if stored.is_empty() {
+            return Ok(None);
+        }
+
+        let partner: Partner = bincode::deserialize(&stored)
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to deserialize partner"))?;
+        Ok(Some(partner))
+    }
+
+    fn get_partner_by_id(&self, partner_id: u128) -> Result<Partner> {
+        self.get_partner_record(partner_id)?
+            .ok_or_else(|| revert!(ERR_NOT_FOUND, "Partner {} not found", partner_id))
+    }
+}
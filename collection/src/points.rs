@@ -0,0 +1,386 @@
+use metashrew_support::index_pointer::KeyValuePointer;
+use alkanes_runtime::runtime::AlkaneResponder;
+use alkanes_support::{id::AlkaneId, response::CallResponse};
+use serde::{Serialize, Deserialize};
+use anyhow::Result;
+use orbital_support::{revert, errors::{codes::*, Revert}};
+use std::sync::Arc;
+
+use crate::{Collection, encode_alkane_id};
+use crate::opcodes::StoragePointer;
+use crate::roles::ROLE_ACTIVITY;
+use crate::staking::Stake;
+
+/// Activities the points table assigns values to
+pub(crate) const ACTION_MINT: u128 = 0;
+const ACTION_CHECK_IN: u128 = 1;
+const ACTION_STAKING_EPOCH: u128 = 2;
+const ACTION_GOVERNANCE_VOTE: u128 = 3;
+
+/// Actions from `ACTION_GOVERNANCE_VOTE` up are reported through `RecordActivity`
+const MAX_ACTIONS: u128 = 16;
+
+/// Blocks between two check-ins of the same orbital, roughly a day
+const CHECK_IN_INTERVAL: u64 = 144;
+
+/// Check-in interval of canary deployments
+const CANARY_CHECK_IN_INTERVAL: u64 = 6;
+
+/// Blocks of continuous staking earning one staking epoch award
+const STAKING_EPOCH_BLOCKS: u64 = 144;
+
+/// Staking epoch of canary deployments
+const CANARY_STAKING_EPOCH_BLOCKS: u64 = 6;
+
+/// Indices ranked on each season leaderboard
+const MAX_LEADERBOARD: usize = 25;
+
+/// Upper bound on redeemable badges
+const MAX_BADGES: usize = 64;
+
+#[derive(Clone, Copy, Default, Debug, Serialize, Deserialize)]
+struct LeaderboardEntry {
+    index: u128,
+    points: u128,
+}
+
+#[derive(Clone, Copy, Default, Debug, Serialize, Deserialize)]
+struct BadgeReward {
+    badge_id: u128,
+    cost: u128,
+}
+
+#[derive(Serialize)]
+struct PointsInfo {
+    index: u128,
+    balance: u128,
+    season: u128,
+    season_points: u128,
+    badges: Vec<u128>,
+}
+
+#[derive(Serialize)]
+struct PointsTable {
+    season: u128,
+    season_started_at: u64,
+    actions: Vec<(u128, u128)>,
+    badges: Vec<BadgeReward>,
+}
+
+/// Points accrue per index: a spendable balance plus a score for the running season.
+impl Collection {
+    pub(crate) fn set_points_value(&self, action: u128, points: u128) -> Result<CallResponse> {
+        self.only_owner()?;
+        let context: alkanes_support::context::Context = self.context()?;
+        let response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        if action >= MAX_ACTIONS {
+            return Err(revert!(ERR_NOT_FOUND, "Unknown action {}", action));
+        }
+
+        self.points_value_pointer(action).set_value::<u128>(points);
+        Ok(response)
+    }
+
+    /// Close the running season and start ranking from zero
+    pub(crate) fn start_season(&self) -> Result<CallResponse> {
+        self.only_owner()?;
+        let context: alkanes_support::context::Context = self.context()?;
+        let response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let season: u128 = self.current_season().checked_add(1)
+            .ok_or_else(|| revert!(ERR_OVERFLOW, "season overflow"))?;
+        self.season_pointer().set_value::<u128>(season);
+        self.season_started_pointer().set_value::<u64>(self.height());
+        Ok(response)
+    }
+
+    /// Holder checks the orbital in, at most once per interval
+    pub(crate) fn check_in(&self, index: u128) -> Result<CallResponse> {
+        self.only_holder(index)?;
+        let context: alkanes_support::context::Context = self.context()?;
+        let response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let height: u64 = self.height();
+        let last: u64 = self.last_check_in_pointer(index).get_value::<u64>();
+        let interval: u64 = if self.is_canary() { CANARY_CHECK_IN_INTERVAL } else { CHECK_IN_INTERVAL };
+        if last != 0 && height < last.saturating_add(interval) {
+            return Err(revert!(ERR_INVALID_STATE, "Orbital {} can check in again at block {}", index, last + interval));
+        }
+
+        self.last_check_in_pointer(index).set_value::<u64>(height);
+        self.award_points(index, ACTION_CHECK_IN)?;
+        Ok(response)
+    }
+
+    /// Stake receipt holder claims the staking epochs completed since the last claim
+    pub(crate) fn claim_staking_points(&self, index: u128) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let stake: Stake = self.get_stake_of(index)?
+            .ok_or_else(|| revert!(ERR_NOT_FOUND, "Orbital {} is not staked", index))?;
+        self.only_receipt_holder(&stake.receipt())?;
+
+        // Epochs are counted per stake, a restake starts over
+        let mut claimed_pointer: StoragePointer = self.epochs_claimed_pointer(&stake.receipt());
+        let claimed: u64 = claimed_pointer.get_value::<u64>();
+        let epoch_blocks: u64 = if self.is_canary() { CANARY_STAKING_EPOCH_BLOCKS } else { STAKING_EPOCH_BLOCKS };
+        let epochs: u64 = stake.staked_blocks(self.height()) / epoch_blocks;
+        if epochs <= claimed {
+            return Err(revert!(ERR_INVALID_STATE, "No completed staking epoch to claim for orbital {}", index));
+        }
+
+        let points: u128 = self.points_value_pointer(ACTION_STAKING_EPOCH).get_value::<u128>()
+            .checked_mul((epochs - claimed) as u128)
+            .ok_or_else(|| revert!(ERR_OVERFLOW, "staking points overflow"))?;
+
+        claimed_pointer.set_value::<u64>(epochs);
+        self.credit_points(index, points)?;
+        Ok(response)
+    }
+
+    /// Activity role reports an externally tracked action, such as a governance vote
+    pub(crate) fn record_activity(&self, index: u128, action: u128) -> Result<CallResponse> {
+        self.only_role(ROLE_ACTIVITY)?;
+        let context: alkanes_support::context::Context = self.context()?;
+        let response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        if !(ACTION_GOVERNANCE_VOTE..MAX_ACTIONS).contains(&action) {
+            return Err(revert!(ERR_INVALID_STATE, "Action {} is not externally reported", action));
+        }
+
+        self.lookup_instance(index)?;
+        self.award_points(index, action)?;
+        Ok(response)
+    }
+
+    /// Offer a badge for a points cost, a zero cost withdraws it
+    pub(crate) fn set_badge_reward(&self, badge_id: u128, cost: u128) -> Result<CallResponse> {
+        self.only_owner()?;
+        let context: alkanes_support::context::Context = self.context()?;
+        let response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let mut rewards: Vec<BadgeReward> = self.get_badge_rewards()?;
+        rewards.retain(|reward| reward.badge_id != badge_id);
+
+        if cost > 0 {
+            if rewards.len() >= MAX_BADGES {
+                return Err(revert!(ERR_LIMIT_EXCEEDED, "At most {} badges can be offered", MAX_BADGES));
+            }
+            rewards.push(BadgeReward { badge_id, cost });
+        }
+
+        self.set_badge_rewards(&rewards)?;
+        Ok(response)
+    }
+
+    /// Holder spends points of the orbital on a badge
+    pub(crate) fn redeem_badge(&self, index: u128, badge_id: u128) -> Result<CallResponse> {
+        self.only_holder(index)?;
+        let context: alkanes_support::context::Context = self.context()?;
+        let response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let reward: BadgeReward = self.get_badge_rewards()?
+            .into_iter()
+            .find(|reward| reward.badge_id == badge_id)
+            .ok_or_else(|| revert!(ERR_NOT_FOUND, "Badge {} is not offered", badge_id))?;
+
+        let mut badges: Vec<u128> = self.get_badges(index)?;
+        if badges.contains(&badge_id) {
+            return Err(revert!(ERR_INVALID_STATE, "Orbital {} already holds badge {}", index, badge_id));
+        }
+
+        let balance: u128 = self.points_balance_pointer(index).get_value::<u128>();
+        let remaining: u128 = balance.checked_sub(reward.cost)
+            .ok_or_else(|| Revert::new(ERR_INVALID_STATE, format!("Badge {} costs {} points, orbital {} has {}", badge_id, reward.cost, index, balance))
+                .field("points").expected(reward.cost).actual(balance))?;
+
+        self.points_balance_pointer(index).set_value::<u128>(remaining);
+        badges.push(badge_id);
+        self.set_badges(index, &badges)?;
+        Ok(response)
+    }
+
+    /// Returns balance, season score and badges of an index as JSON
+    pub(crate) fn get_points(&self, index: u128) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let season: u128 = self.current_season();
+        let info: PointsInfo = PointsInfo {
+            index,
+            balance: self.points_balance_pointer(index).get_value::<u128>(),
+            season,
+            season_points: self.season_points(index),
+            badges: self.get_badges(index)?,
+        };
+
+        response.data = serde_json::to_vec(&info)
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to serialize points"))?;
+        Ok(response)
+    }
+
+    /// Returns the top indices of a season as JSON, highest score first
+    pub(crate) fn get_leaderboard(&self, season: u128) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let leaderboard: Vec<LeaderboardEntry> = self.get_leaderboard_entries(season)?;
+        response.data = serde_json::to_vec(&leaderboard)
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to serialize leaderboard"))?;
+        Ok(response)
+    }
+
+    /// Returns the current season, the points per action and the badge rewards as JSON
+    pub(crate) fn get_points_table(&self) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let table: PointsTable = PointsTable {
+            season: self.current_season(),
+            season_started_at: self.season_started_pointer().get_value::<u64>(),
+            actions: (0..MAX_ACTIONS)
+                .map(|action| (action, self.points_value_pointer(action).get_value::<u128>()))
+                .filter(|(_, points)| *points > 0)
+                .collect(),
+            badges: self.get_badge_rewards()?,
+        };
+
+        response.data = serde_json::to_vec(&table)
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to serialize points table"))?;
+        Ok(response)
+    }
+
+    /// Credit the table value of an action to an index
+    pub(crate) fn award_points(&self, index: u128, action: u128) -> Result<()> {
+        let points: u128 = self.points_value_pointer(action).get_value::<u128>();
+        self.credit_points(index, points)
+    }
+
+    /// Credit points to the balance and season score of an index
+    pub(crate) fn credit_points(&self, index: u128, points: u128) -> Result<()> {
+        if points == 0 {
+            return Ok(());
+        }
+
+        let balance: u128 = self.points_balance_pointer(index).get_value::<u128>();
+        self.points_balance_pointer(index).set_value::<u128>(
+            balance.checked_add(points).ok_or_else(|| revert!(ERR_OVERFLOW, "points balance overflow"))?
+        );
+
+        let season: u128 = self.current_season();
+        let score: u128 = self.season_points_pointer(season, index).get_value::<u128>()
+            .checked_add(points)
+            .ok_or_else(|| revert!(ERR_OVERFLOW, "season points overflow"))?;
+        self.season_points_pointer(season, index).set_value::<u128>(score);
+
+        self.update_leaderboard(season, index, score)
+    }
+
+    /// Score of an index in the running season
+    pub(crate) fn season_points(&self, index: u128) -> u128 {
+        self.season_points_pointer(self.current_season(), index).get_value::<u128>()
+    }
+
+    pub(crate) fn get_badges(&self, index: u128) -> Result<Vec<u128>> {
+        let stored: Arc<Vec<u8>> = self.badges_pointer(index).get();
+        if stored.is_empty() {
+            return Ok(vec![]);
+        }
+
+        bincode::deserialize(&stored).map_err(|_| revert!(ERR_STORAGE, "Failed to deserialize badges"))
+    }
+
+    pub(crate) fn set_badges(&self, index: u128, badges: &[u128]) -> Result<()> {
+        let serialized: Vec<u8> = bincode::serialize(badges)
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to serialize badges"))?;
+        self.badges_pointer(index).set(Arc::new(serialized));
+        Ok(())
+    }
+
+    fn update_leaderboard(&self, season: u128, index: u128, score: u128) -> Result<()> {
+        let mut leaderboard: Vec<LeaderboardEntry> = self.get_leaderboard_entries(season)?;
+        leaderboard.retain(|entry| entry.index != index);
+        leaderboard.push(LeaderboardEntry { index, points: score });
+        leaderboard.sort_by(|a, b| b.points.cmp(&a.points).then(a.index.cmp(&b.index)));
+        leaderboard.truncate(MAX_LEADERBOARD);
+
+        let serialized: Vec<u8> = bincode::serialize(&leaderboard)
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to serialize leaderboard"))?;
+        self.leaderboard_pointer(season).set(Arc::new(serialized));
+        Ok(())
+    }
+
+    fn get_leaderboard_entries(&self, season: u128) -> Result<Vec<LeaderboardEntry>> {
+        let stored: Arc<Vec<u8>> = self.leaderboard_pointer(season).get();
+        if stored.is_empty() {
+            return Ok(vec![]);
+        }
+
+        bincode::deserialize(&stored).map_err(|_| revert!(ERR_STORAGE, "Failed to deserialize leaderboard"))
+    }
+
+    fn set_badge_rewards(&self, rewards: &[BadgeReward]) -> Result<()> {
+        let serialized: Vec<u8> = bincode::serialize(rewards)
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to serialize badge rewards"))?;
+        self.badge_rewards_pointer().set(Arc::new(serialized));
+        Ok(())
+    }
+
+    fn get_badge_rewards(&self) -> Result<Vec<BadgeReward>> {
+        let stored: Arc<Vec<u8>> = self.badge_rewards_pointer().get();
+        if stored.is_empty() {
+            return Ok(vec![]);
+        }
+
+        bincode::deserialize(&stored).map_err(|_| revert!(ERR_STORAGE, "Failed to deserialize badge rewards"))
+    }
+
+    fn current_season(&self) -> u128 {
+        self.season_pointer().get_value::<u128>()
+    }
+
+    fn season_pointer(&self) -> StoragePointer {
+        StoragePointer::from_keyword("/points/season")
+    }
+
+    fn season_started_pointer(&self) -> StoragePointer {
+        StoragePointer::from_keyword("/points/season-started")
+    }
+
+    fn points_value_pointer(&self, action: u128) -> StoragePointer {
+        StoragePointer::from_keyword("/points/table/").select(&action.to_le_bytes().to_vec())
+    }
+
+    fn points_balance_pointer(&self, index: u128) -> StoragePointer {
+        StoragePointer::from_keyword("/points/balance/").select(&index.to_le_bytes().to_vec())
+    }
+
+    fn season_points_pointer(&self, season: u128, index: u128) -> StoragePointer {
+        StoragePointer::from_keyword("/points/score/")
+            .select(&season.to_le_bytes().to_vec())
+            .keyword("/")
+            .select(&index.to_le_bytes().to_vec())
+    }
+
+    fn leaderboard_pointer(&self, season: u128) -> StoragePointer {
+        StoragePointer::from_keyword("/points/leaderboard/").select(&season.to_le_bytes().to_vec())
+    }
+
+    fn last_check_in_pointer(&self, index: u128) -> StoragePointer {
+        StoragePointer::from_keyword("/points/check-in/").select(&index.to_le_bytes().to_vec())
+    }
+
+    fn epochs_claimed_pointer(&self, receipt: &AlkaneId) -> StoragePointer {
+        StoragePointer::from_keyword("/points/epochs/").select(&encode_alkane_id(receipt))
+    }
+
+    fn badge_rewards_pointer(&self) -> StoragePointer {
+        StoragePointer::from_keyword("/points/badges")
+    }
+
+    fn badges_pointer(&self, index: u128) -> StoragePointer {
+        StoragePointer::from_keyword("/badges/").select(&index.to_le_bytes().to_vec())
+    }
+}
@@ -0,0 +1,183 @@
+use metashrew_support::index_pointer::KeyValuePointer;
+use alkanes_runtime::runtime::AlkaneResponder;
+use alkanes_support::{parcel::AlkaneTransfer, response::CallResponse};
+use serde::{Serialize, Deserialize};
+use anyhow::Result;
+use orbital_support::{revert, errors::{codes::*, Revert}};
+use std::sync::Arc;
+
+use crate::Collection;
+use crate::opcodes::StoragePointer;
+
+/// Stage ID open mints are reported under to hooks, configured stages start at 1
+pub(crate) const PUBLIC_MINT_STAGE: u128 = 0;
+
+/// Orbitals a single `MintBatch` may mint until the owner sets a cap
+const DEFAULT_BATCH_CAP: u128 = 10;
+
+/// Open sale outside of any stage, closed while `max_supply` is 0
+#[derive(Clone, Default, Debug, Serialize, Deserialize)]
+struct PublicMint {
+    price_per_item: u128,
+    max_supply: u128,
+    total_minted: u128,
+}
+
+impl PublicMint {
+    fn observe_supply(&self, count: u128) -> Result<()> {
+        let minted: u128 = self.total_minted.saturating_add(count);
+        if minted > self.max_supply {
+            return Err(Revert::new(ERR_LIMIT_EXCEEDED, "Public mint is closed or sold out")
+                .field("max_supply").expected(self.max_supply).actual(minted).into());
+        }
+
+        Ok(())
+    }
+}
+
+impl Collection {
+    /// Open, reprice or close the public mint. Lowering the supply below what was already
+    /// minted is refused, a supply of 0 closes the sale.
+    pub(crate) fn set_public_mint(&self, price_per_item: u128, max_supply: u128) -> Result<CallResponse> {
+        self.only_owner()?;
+        let context: alkanes_support::context::Context = self.context()?;
+        let response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let mut config: PublicMint = self.public_mint()?;
+        if max_supply != 0 && max_supply < config.total_minted {
+            return Err(Revert::new(ERR_INVALID_INPUT, "Public mint already minted past the new supply")
+                .field("max_supply").expected(config.total_minted).actual(max_supply).into());
+        }
+
+        config.price_per_item = price_per_item;
+        config.max_supply = if max_supply == 0 { config.total_minted } else { max_supply };
+        self.save_public_mint(&config)?;
+        Ok(response)
+    }
+
+    /// Returns the public mint price, supply and minted count as JSON
+    pub(crate) fn get_public_mint(&self) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        response.data = serde_json::to_vec(&self.public_mint()?)
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to serialize public mint"))?;
+        Ok(response)
+    }
+
+    /// Set how many orbitals one `MintBatch` may mint
+    pub(crate) fn set_mint_batch_cap(&self, cap: u128) -> Result<CallResponse> {
+        self.only_owner()?;
+        let context: alkanes_support::context::Context = self.context()?;
+        let response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        if cap == 0 {
+            return Err(revert!(ERR_INVALID_INPUT, "Batch cap must be positive"));
+        }
+
+        self.batch_cap_pointer().set_value::<u128>(cap);
+        Ok(response)
+    }
+
+    /// Mint one orbital at the public price
+    pub(crate) fn mint(&self) -> Result<CallResponse> {
+        self.mint_public(1, false)
+    }
+
+    /// Mint `count` orbitals at the public price in one call, paying for all of them at
+    /// once less the discount tier the batch reaches
+    pub(crate) fn mint_batch(&self, count: u128) -> Result<CallResponse> {
+        let cap: u128 = self.mint_batch_cap();
+        if count == 0 || count > cap {
+            return Err(Revert::new(ERR_LIMIT_EXCEEDED, format!("Batches mint 1 to {} orbitals", cap))
+                .field("count").expected(cap).actual(count).into());
+        }
+
+        self.mint_public(count, true)
+    }
+
+    fn mint_public(&self, count: u128, discounted: bool) -> Result<CallResponse> {
+        let config: PublicMint = self.public_mint()?;
+        config.observe_supply(count)?;
+        let minter: [u8; 32] = self.minter_identity()?;
+
+        let first: u128 = self.instances_count();
+        for index in first..first + count {
+            if !self.run_pre_mint_hook(PUBLIC_MINT_STAGE, index)? {
+                // Declined without reverting so the hook failure stays recorded, the payment is returned
+                let context: alkanes_support::context::Context = self.context()?;
+                return Ok(CallResponse::forward(&context.incoming_alkanes));
+            }
+        }
+
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut price: u128 = config.price_per_item.checked_mul(count)
+            .ok_or_else(|| revert!(ERR_OVERFLOW, "price overflow"))?;
+        if discounted {
+            price = self.discounted_batch_price(count, price)?;
+        }
+        let change: Vec<AlkaneTransfer> = self.collect_payment(&context.incoming_alkanes, price)?;
+        if let Some(token) = self.payment_token()?.filter(|_| price > 0) {
+            self.record_stage_revenue(PUBLIC_MINT_STAGE, &token, price)?;
+        }
+
+        // Counted against the config as stored now, the hooks may have minted meanwhile
+        let mut config: PublicMint = self.public_mint()?;
+        config.observe_supply(count)?;
+        config.total_minted += count;
+        self.save_public_mint(&config)?;
+        if self.max_mints_per_address() != 0 {
+            self.observe_delivered_to_minter()?;
+        }
+        self.observe_lifetime_limit(&minter, count)?;
+        self.record_lifetime_mints(&minter, count)?;
+
+        let mut response: CallResponse = CallResponse::default();
+        response.alkanes.0 = change;
+        for _ in 0..count {
+            self.observe_mint_per_block(PUBLIC_MINT_STAGE)?;
+            let (index, transfer) = self.create_mint_transfer()?;
+            response.alkanes.0.push(transfer);
+            self.record_minter(index)?;
+            self.run_post_mint_hook(PUBLIC_MINT_STAGE, index)?;
+        }
+        Ok(response)
+    }
+
+    fn mint_batch_cap(&self) -> u128 {
+        match self.batch_cap_pointer().get_value::<u128>() {
+            0 => DEFAULT_BATCH_CAP,
+            cap => cap,
+        }
+    }
+
+    /// Orbitals the public mint may sell, part of the collection supply
+    pub(crate) fn public_mint_supply(&self) -> u128 {
+        self.public_mint().map(|config| config.max_supply).unwrap_or(0)
+    }
+
+    fn public_mint(&self) -> Result<PublicMint> {
+        let stored: Arc<Vec<u8>> = self.public_mint_pointer().get();
+        if stored.is_empty() {
+            return Ok(PublicMint::default());
+        }
+
+        bincode::deserialize(&stored)
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to deserialize public mint"))
+    }
+
+    fn save_public_mint(&self, config: &PublicMint) -> Result<()> {
+        let serialized: Vec<u8> = bincode::serialize(config)
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to serialize public mint"))?;
+        self.public_mint_pointer().set(Arc::new(serialized));
+        Ok(())
+    }
+
+    fn batch_cap_pointer(&self) -> StoragePointer {
+        StoragePointer::from_keyword("/public-mint/batch-cap")
+    }
+
+    fn public_mint_pointer(&self) -> StoragePointer {
+        StoragePointer::from_keyword("/public-mint")
+    }
+}
@@ -0,0 +1,201 @@
+use metashrew_support::index_pointer::KeyValuePointer;
+use alkanes_runtime::runtime::AlkaneResponder;
+use alkanes_support::{id::AlkaneId, response::CallResponse};
+use serde::{Serialize, Deserialize};
+use anyhow::Result;
+use orbital_support::{revert, errors::codes::*};
+use std::sync::Arc;
+
+use crate::Collection;
+use crate::opcodes::StoragePointer;
+use crate::access::{AccessPolicy, DENIED_NOT_PRESENTED};
+
+/// The orbital has been staked for at least `threshold` blocks
+const QUEST_STAKE_BLOCKS: u8 = 1;
+
+/// The caller presents at least `threshold` orbitals, the quest index among them
+const QUEST_HOLD_COUNT: u8 = 2;
+
+/// The orbital scored at least `threshold` points in the running season
+const QUEST_SEASON_POINTS: u8 = 3;
+
+/// Upper bound on defined quests, keeps the registry a single storage read
+const MAX_QUESTS: usize = 64;
+
+/// A challenge verified from on-chain state, rewarded once per index
+#[derive(Clone, Copy, Default, Debug, Serialize, Deserialize)]
+struct Quest {
+    id: u128,
+    kind: u8,
+    threshold: u128,
+    reward_points: u128,
+    /// Badge granted on completion, zero for none
+    reward_badge: u128,
+}
+
+impl Collection {
+    /// Define or replace a quest
+    pub(crate) fn set_quest(&self, quest_id: u128, kind: u128, threshold: u128, reward_points: u128, reward_badge: u128) -> Result<CallResponse> {
+        self.only_owner()?;
+        let context: alkanes_support::context::Context = self.context()?;
+        let response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let kind: u8 = match u8::try_from(kind) {
+            Ok(kind @ (QUEST_STAKE_BLOCKS | QUEST_HOLD_COUNT | QUEST_SEASON_POINTS)) => kind,
+            _ => return Err(revert!(ERR_NOT_FOUND, "Unknown quest kind {}", kind)),
+        };
+
+        if kind == QUEST_STAKE_BLOCKS && u64::try_from(threshold).is_err() {
+            return Err(revert!(ERR_INVALID_INPUT, "Staking threshold out of range"));
+        }
+
+        if reward_points == 0 && reward_badge == 0 {
+            return Err(revert!(ERR_INVALID_INPUT, "Quest must reward points or a badge"));
+        }
+
+        let quest: Quest = Quest { id: quest_id, kind, threshold, reward_points, reward_badge };
+
+        let mut quests: Vec<Quest> = self.get_quest_list()?;
+        match quests.iter_mut().find(|existing| existing.id == quest_id) {
+            Some(existing) => *existing = quest,
+            None => {
+                if quests.len() >= MAX_QUESTS {
+                    return Err(revert!(ERR_LIMIT_EXCEEDED, "At most {} quests can be defined", MAX_QUESTS));
+                }
+                quests.push(quest);
+            }
+        }
+
+        self.set_quest_list(&quests)?;
+        Ok(response)
+    }
+
+    pub(crate) fn remove_quest(&self, quest_id: u128) -> Result<CallResponse> {
+        self.only_owner()?;
+        let context: alkanes_support::context::Context = self.context()?;
+        let response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let mut quests: Vec<Quest> = self.get_quest_list()?;
+        let before: usize = quests.len();
+        quests.retain(|quest| quest.id != quest_id);
+
+        if quests.len() == before {
+            return Err(revert!(ERR_NOT_FOUND, "Unknown quest {}", quest_id));
+        }
+
+        self.set_quest_list(&quests)?;
+        Ok(response)
+    }
+
+    /// Verify the quest condition for an index against the presented tokens and grant the reward
+    pub(crate) fn complete_quest(&self, index: u128, quest_id: u128) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let quest: Quest = self.get_quest_list()?
+            .into_iter()
+            .find(|quest| quest.id == quest_id)
+            .ok_or_else(|| revert!(ERR_NOT_FOUND, "Unknown quest {}", quest_id))?;
+
+        if self.quest_completed_pointer(index, quest_id).get_value::<u8>() == 1 {
+            return Err(revert!(ERR_INVALID_STATE, "Orbital {} already completed quest {}", index, quest_id));
+        }
+
+        match quest.kind {
+            QUEST_STAKE_BLOCKS => {
+                let policy: AccessPolicy = AccessPolicy {
+                    allow_staker: true,
+                    min_staked_blocks: quest.threshold as u64,
+                    ..Default::default()
+                };
+                if self.evaluate_access(index, &policy)? >= DENIED_NOT_PRESENTED {
+                    return Err(revert!(ERR_INVALID_STATE, "Orbital {} has not been staked for {} blocks", index, quest.threshold));
+                }
+            }
+            QUEST_HOLD_COUNT => {
+                self.only_holder(index)?;
+
+                let mut held: Vec<AlkaneId> = Vec::new();
+                for transfer in context.incoming_alkanes.0.iter() {
+                    if transfer.value > 0 && self.index_of_instance(&transfer.id).is_some() && !held.contains(&transfer.id) {
+                        held.push(transfer.id);
+                    }
+                }
+
+                if (held.len() as u128) < quest.threshold {
+                    return Err(revert!(ERR_INVALID_STATE, "Quest {} requires holding {} orbitals", quest_id, quest.threshold));
+                }
+            }
+            QUEST_SEASON_POINTS => {
+                if self.evaluate_access(index, &self.default_access_policy())? >= DENIED_NOT_PRESENTED {
+                    return Err(revert!(ERR_UNAUTHORIZED, "Caller does not hold, rent or stake orbital {}", index));
+                }
+
+                if self.season_points(index) < quest.threshold {
+                    return Err(revert!(ERR_INVALID_STATE, "Quest {} requires {} season points", quest_id, quest.threshold));
+                }
+            }
+            _ => return Err(revert!(ERR_NOT_FOUND, "Unknown quest kind {}", quest.kind)),
+        }
+
+        self.quest_completed_pointer(index, quest_id).set_value::<u8>(1);
+        self.credit_points(index, quest.reward_points)?;
+
+        if quest.reward_badge != 0 {
+            let mut badges: Vec<u128> = self.get_badges(index)?;
+            if !badges.contains(&quest.reward_badge) {
+                badges.push(quest.reward_badge);
+                self.set_badges(index, &badges)?;
+            }
+        }
+
+        Ok(response)
+    }
+
+    /// Returns every quest as a JSON array
+    pub(crate) fn get_quests(&self) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let quests: Vec<Quest> = self.get_quest_list()?;
+        response.data = serde_json::to_vec(&quests)
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to serialize quests"))?;
+        Ok(response)
+    }
+
+    pub(crate) fn is_quest_completed(&self, index: u128, quest_id: u128) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let completed: u128 = self.quest_completed_pointer(index, quest_id).get_value::<u8>() as u128;
+        response.data = completed.to_le_bytes().to_vec();
+        Ok(response)
+    }
+
+    fn quests_pointer(&self) -> StoragePointer {
+        StoragePointer::from_keyword("/quests")
+    }
+
+    fn quest_completed_pointer(&self, index: u128, quest_id: u128) -> StoragePointer {
+        StoragePointer::from_keyword("/quests/completed/")
+            .select(&index.to_le_bytes().to_vec())
+            .keyword("/")
+            .select(&quest_id.to_le_bytes().to_vec())
+    }
+
+    fn set_quest_list(&self, quests: &[Quest]) -> Result<()> {
+        let serialized: Vec<u8> = bincode::serialize(quests)
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to serialize quests"))?;
+        self.quests_pointer().set(Arc::new(serialized));
+        Ok(())
+    }
+
+    fn get_quest_list(&self) -> Result<Vec<Quest>> {
+        let stored: Arc<Vec<u8>> = self.quests_pointer().get();
+        if stored.is_empty() {
+            return Ok(vec![]);
+        }
+
+        bincode::deserialize(&stored).map_err(|_| revert!(ERR_STORAGE, "Failed to deserialize quests"))
+    }
+}
@@ -0,0 +1,232 @@
+use metashrew_support::index_pointer::KeyValuePointer;
+use alkanes_runtime::runtime::AlkaneResponder;
+use alkanes_support::{id::AlkaneId, parcel::AlkaneTransfer, response::CallResponse};
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use bitcoin::hashes::{sha256, Hash};
+use serde::{Serialize, Deserialize};
+use serde_json::{json, Value};
+use anyhow::Result;
+use orbital_support::{revert, errors::codes::*};
+use std::sync::Arc;
+
+use crate::{Collection, encode_alkane_id, decode_alkane_id};
+use crate::opcodes::StoragePointer;
+
+/// Redeems an orbital escrowed for rent and its rental proceeds
+pub(crate) const RECEIPT_KIND_RENTAL_CLAIM: u8 = 1;
+
+/// Grants time-limited use of a rented orbital
+pub(crate) const RECEIPT_KIND_RENTER: u8 = 2;
+
+/// Redeems collateral and loan principal for the borrower
+pub(crate) const RECEIPT_KIND_BORROWER: u8 = 3;
+
+/// Redeems repayment or defaulted collateral for the lender
+pub(crate) const RECEIPT_KIND_LENDER: u8 = 4;
+
+/// Redeems a staked orbital and proves how long it has been staked
+pub(crate) const RECEIPT_KIND_STAKER: u8 = 5;
+
+/// Symbol shared by every receipt token
+const RECEIPT_SYMBOL: &str = "RCPT";
+
+/// Receipt tokens are deployed under their issue position offset by this flag, so data
+/// requests from a receipt are told apart from those of the orbital it stands for
+pub(crate) const RECEIPT_INDEX_FLAG: u128 = 1 << 127;
+
+/// Whether an instance index is that of a receipt token
+pub(crate) fn is_receipt_index(index: u128) -> bool {
+    index & RECEIPT_INDEX_FLAG != 0
+}
+
+fn receipt_name(kind: u8) -> Result<&'static str> {
+    match kind {
+        RECEIPT_KIND_RENTAL_CLAIM => Ok("Rental Claim"),
+        RECEIPT_KIND_RENTER => Ok("Rental Receipt"),
+        RECEIPT_KIND_BORROWER => Ok("Loan Claim"),
+        RECEIPT_KIND_LENDER => Ok("Loan Note"),
+        RECEIPT_KIND_STAKER => Ok("Stake Receipt"),
+        _ => Err(revert!(ERR_NOT_FOUND, "Unknown receipt kind {}", kind)),
+    }
+}
+
+/// Card a receipt token shows in place of art: what it is, the orbital it stands for and
+/// when it expires
+fn receipt_card(receipt: &Receipt) -> Result<String> {
+    let expiry: String = if receipt.expires_at == u64::MAX {
+        String::from("No expiry")
+    } else {
+        format!("Expires at block {}", receipt.expires_at)
+    };
+
+    Ok(format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"0 0 400 400\" width=\"400\" height=\"400\"><rect width=\"400\" height=\"400\" fill=\"#101418\"/><text x=\"200\" y=\"170\" font-family=\"monospace\" font-size=\"28\" fill=\"#ffffff\" text-anchor=\"middle\">{}</text><text x=\"200\" y=\"215\" font-family=\"monospace\" font-size=\"20\" fill=\"#9aa4ad\" text-anchor=\"middle\">Orbital #{}</text><text x=\"200\" y=\"250\" font-family=\"monospace\" font-size=\"16\" fill=\"#9aa4ad\" text-anchor=\"middle\">{}</text></svg>",
+        receipt_name(receipt.kind)?,
+        receipt.index,
+        expiry
+    ))
+}
+
+/// What a receipt token stands for
+#[derive(Clone, Copy, Default, Debug, Serialize, Deserialize)]
+pub(crate) struct Receipt {
+    pub index: u128,
+    pub kind: u8,
+    pub expires_at: u64,
+}
+
+/// Receipts are tokens cloned from the instance template and registered here,
+/// so whoever holds one can prove a claim on escrowed orbitals or balances.
+impl Collection {
+    /// Deploy a receipt token for an index and register what it stands for
+    pub(crate) fn issue_receipt(&self, index: u128, kind: u8, expires_at: u64) -> Result<(AlkaneId, AlkaneTransfer)> {
+        let name: &str = receipt_name(kind)?;
+
+        // Issue order is what batch queries page through, and what the token is deployed under
+        let mut count_pointer: StoragePointer = self.receipts_issued_pointer();
+        let position: u128 = count_pointer.get_value::<u128>();
+        let (receipt_id, transfer) = self.deploy_instance(RECEIPT_INDEX_FLAG | position, name, RECEIPT_SYMBOL)?;
+
+        let receipt: Receipt = Receipt { index, kind, expires_at };
+        let serialized: Vec<u8> = bincode::serialize(&receipt)
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to serialize receipt"))?;
+        self.receipt_pointer(&receipt_id).set(Arc::new(serialized));
+
+        self.receipt_order_pointer(position).set(Arc::new(encode_alkane_id(&receipt_id)));
+        count_pointer.set_value::<u128>(position + 1);
+
+        Ok((receipt_id, transfer))
+    }
+
+    /// Returns what a receipt token stands for as JSON
+    pub(crate) fn get_receipt_info(&self, block: u128, tx: u128) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let receipt: Receipt = self.get_receipt(&AlkaneId { block, tx })?
+            .ok_or_else(|| revert!(ERR_NOT_FOUND, "{}:{} is not a receipt of this collection", block, tx))?;
+
+        response.data = serde_json::to_vec(&receipt)
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to serialize receipt"))?;
+        Ok(response)
+    }
+
+    pub(crate) fn get_receipt(&self, receipt_id: &AlkaneId) -> Result<Option<Receipt>> {
+        let stored: Arc<Vec<u8>> = self.receipt_pointer(receipt_id).get();
+        if stored.is_empty() {
+            return Ok(None);
+        }
+
+        let receipt: Receipt = bincode::deserialize(&stored)
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to deserialize receipt"))?;
+        Ok(Some(receipt))
+    }
+
+    /// What the receipt token deployed under instance index `index` stands for
+    fn receipt_of_index(&self, index: u128) -> Result<Receipt> {
+        let receipt_id: AlkaneId = self.receipt_at(index & !RECEIPT_INDEX_FLAG)?;
+        self.get_receipt(&receipt_id)?
+            .ok_or_else(|| revert!(ERR_NOT_FOUND, "{}:{} is not a receipt of this collection", receipt_id.block, receipt_id.tx))
+    }
+
+    /// `GetData` of a receipt token: its card, framed as a page when the collection serves HTML
+    pub(crate) fn receipt_data(&self, index: u128) -> Result<String> {
+        let card: String = receipt_card(&self.receipt_of_index(index)?)?;
+        if self.render_engine()?.content_type() == "text/html" {
+            return Ok(format!("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n</head>\n<body>\n{}\n</body>\n</html>", card));
+        }
+
+        Ok(card)
+    }
+
+    /// `GetThumbnail` of a receipt token, always its SVG card
+    pub(crate) fn receipt_thumbnail(&self, index: u128) -> Result<String> {
+        receipt_card(&self.receipt_of_index(index)?)
+    }
+
+    /// `GetAttributes` of a receipt token as a JSON object
+    pub(crate) fn receipt_attributes(&self, index: u128) -> Result<String> {
+        let receipt: Receipt = self.receipt_of_index(index)?;
+        Ok(json!({
+            "Receipt": receipt_name(receipt.kind)?,
+            "Orbital": receipt.index.to_string(),
+            "Expires": receipt.expires_at.to_string(),
+        }).to_string())
+    }
+
+    /// `GetDataUri` of a receipt token
+    pub(crate) fn receipt_data_uri(&self, index: u128) -> Result<String> {
+        let content_type: &str = self.render_engine()?.content_type();
+        Ok(format!("data:{};base64,{}", content_type, STANDARD.encode(self.receipt_data(index)?.as_bytes())))
+    }
+
+    /// `GetMetadata` of a receipt token, named after what it stands for
+    pub(crate) fn receipt_metadata(&self, index: u128) -> Result<String> {
+        let receipt: Receipt = self.receipt_of_index(index)?;
+        let attributes: Vec<Value> = vec![
+            json!({ "trait_type": "Receipt", "value": receipt_name(receipt.kind)? }),
+            json!({ "trait_type": "Orbital", "value": receipt.index.to_string() }),
+            json!({ "trait_type": "Expires", "value": receipt.expires_at.to_string() }),
+        ];
+
+        Ok(json!({
+            "name": format!("{} #{}", receipt_name(receipt.kind)?, receipt.index),
+            "image": self.receipt_data_uri(index)?,
+            "attributes": attributes,
+        }).to_string())
+    }
+
+    /// `GetDataHash` of a receipt token: the sha256 of its `GetData` payload, then its
+    /// instance index in place of a trait entry and zeros for the rest
+    pub(crate) fn receipt_data_hash(&self, index: u128) -> Result<Vec<u8>> {
+        let mut data: Vec<u8> = Vec::with_capacity(66);
+        data.extend_from_slice(&sha256::Hash::hash(self.receipt_data(index)?.as_bytes()).to_byte_array());
+        data.extend_from_slice(&index.to_le_bytes());
+        data.extend_from_slice(&0u128.to_le_bytes());
+        data.extend_from_slice(&[0, 0]);
+        Ok(data)
+    }
+
+    /// Number of receipts issued so far
+    pub(crate) fn receipts_issued(&self) -> u128 {
+        self.receipts_issued_pointer().get_value::<u128>()
+    }
+
+    /// Receipt issued at `position` in issue order
+    pub(crate) fn receipt_at(&self, position: u128) -> Result<AlkaneId> {
+        decode_alkane_id(&self.receipt_order_pointer(position).get())
+    }
+
+    /// Receipts issued and the approximate bytes of their records and issue order
+    pub(crate) fn receipts_usage(&self) -> (u128, u128) {
+        let record_bytes: u64 = bincode::serialized_size(&Receipt::default()).unwrap_or(0) + 32;
+        let issued: u128 = self.receipts_issued();
+        (issued, issued.saturating_mul(record_bytes as u128))
+    }
+
+    /// Require the receipt token among the incoming alkanes
+    pub(crate) fn only_receipt_holder(&self, receipt: &AlkaneId) -> Result<()> {
+        let context: alkanes_support::context::Context = self.context()?;
+
+        let presented: bool = context.incoming_alkanes.0.iter()
+            .any(|transfer| transfer.id == *receipt && transfer.value > 0);
+
+        if !presented {
+            return Err(revert!(ERR_UNAUTHORIZED, "Receipt {}:{} not presented", receipt.block, receipt.tx));
+        }
+
+        Ok(())
+    }
+
+    fn receipt_pointer(&self, receipt: &AlkaneId) -> StoragePointer {
+        StoragePointer::from_keyword("/receipts/").select(&encode_alkane_id(receipt))
+    }
+
+    fn receipts_issued_pointer(&self) -> StoragePointer {
+        StoragePointer::from_keyword("/receipt-order/count")
+    }
+
+    fn receipt_order_pointer(&self, position: u128) -> StoragePointer {
+        StoragePointer::from_keyword("/receipt-order/").select(&position.to_le_bytes().to_vec())
+    }
+}
@@ -0,0 +1,174 @@
+//! A staged mint can name the referral code of whoever referred the minter: an outpoint
+//! the referrer controls. A share of the price, set by the owner in basis points, moves
+//! from the treasury to a balance kept under the code. The referrer claims it by spending
+//! the outpoint, after which the attributed output of the claiming transaction controls
+//! the code, so it can be handed out once and claimed under many times. Balances are kept
+//! per payment token, so a change of payment token leaves earlier rewards claimable.
+
+use metashrew_support::index_pointer::KeyValuePointer;
+use alkanes_runtime::runtime::AlkaneResponder;
+use alkanes_support::{id::AlkaneId, parcel::AlkaneTransfer, response::CallResponse};
+use serde::Serialize;
+use anyhow::Result;
+use orbital_support::{revert, errors::{codes::*, Revert}};
+use std::sync::Arc;
+
+use crate::{Collection, counters, encode_alkane_id};
+use crate::opcodes::StoragePointer;
+use crate::tickets::Ticket;
+
+/// Inputs ahead of the whitelist proof of `MintInStageReferred`: opcode, stage_id,
+/// referrer_txid_low, referrer_txid_high, referrer_vout
+pub(crate) const MINT_IN_STAGE_REFERRED_HEADER: usize = 5;
+
+/// Basis points in a whole price
+const BPS_DENOMINATOR: u128 = 10_000;
+
+/// Upper bound on the referral share, in basis points
+const MAX_REFERRAL_BPS: u128 = 2_000;
+
+/// Referral state of a code as returned by `GetReferralRewards`
+#[derive(Serialize)]
+struct ReferralView {
+    code_txid: String,
+    code_vout: u32,
+    /// Outpoint a claim must spend
+    controller_txid: String,
+    controller_vout: u32,
+    token_block: u128,
+    token_tx: u128,
+    balance: u128,
+    /// Mints rewarded to the code, in any token
+    referred: u128,
+}
+
+/// Share of `price` paid for a referral, rounded down
+fn referral_share(price: u128, bps: u128) -> u128 {
+    (price / BPS_DENOMINATOR) * bps + (price % BPS_DENOMINATOR) * bps / BPS_DENOMINATOR
+}
+
+impl Collection {
+    /// Mint from a stage on referral of a code, its txid given as two little endian halves,
+    /// whitelist proof nodes follow the code
+    pub(crate) fn mint_in_stage_referred(&self, stage_id: u128, referrer_txid_low: u128, referrer_txid_high: u128, referrer_vout: u128) -> Result<CallResponse> {
+        let referrer: Ticket = Ticket::from_halves(referrer_txid_low, referrer_txid_high, referrer_vout)?;
+        self.mint_from_stage(stage_id, MINT_IN_STAGE_REFERRED_HEADER, Some(referrer))
+    }
+
+    /// Set the share of a referred mint price paid to the referrer, 0 ends rewards
+    pub(crate) fn set_referral_rate(&self, bps: u128) -> Result<CallResponse> {
+        self.only_owner()?;
+        let context: alkanes_support::context::Context = self.context()?;
+        let response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        if bps > MAX_REFERRAL_BPS {
+            return Err(Revert::new(ERR_INVALID_INPUT, format!("Referral rate must be at most {} bps", MAX_REFERRAL_BPS))
+                .field("bps").expected(MAX_REFERRAL_BPS).actual(bps).into());
+        }
+
+        self.referral_rate_pointer().set_value::<u128>(bps);
+        Ok(response)
+    }
+
+    /// Pay the referral balance of a code in a token to the transaction spending the
+    /// outpoint that controls the code, handing control to its attributed output
+    pub(crate) fn claim_referral_rewards(&self, code_txid_low: u128, code_txid_high: u128, code_vout: u128, block: u128, tx: u128) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let code: Ticket = Ticket::from_halves(code_txid_low, code_txid_high, code_vout)?;
+        self.observe_ticket_spent(&self.referral_controller(&code)?)?;
+
+        let token: AlkaneId = AlkaneId { block, tx };
+        let mut balance_pointer: StoragePointer = self.referral_balance_pointer(&code, &token);
+        let balance: u128 = balance_pointer.get_value::<u128>();
+        if balance == 0 {
+            return Err(revert!(ERR_NOT_FOUND, "No referral rewards in {}:{}", block, tx));
+        }
+
+        balance_pointer.set_value::<u128>(0);
+        self.save_referral_controller(&code, &self.attributed_ticket()?)?;
+        response.alkanes.0.push(AlkaneTransfer { id: token, value: balance });
+        Ok(response)
+    }
+
+    /// Returns the referral balance of a code in a token, the outpoint controlling it and
+    /// its rewarded mints as JSON
+    pub(crate) fn get_referral_rewards(&self, code_txid_low: u128, code_txid_high: u128, code_vout: u128, block: u128, tx: u128) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let code: Ticket = Ticket::from_halves(code_txid_low, code_txid_high, code_vout)?;
+        let controller: Ticket = self.referral_controller(&code)?;
+        let view: ReferralView = ReferralView {
+            code_txid: orbital_support::hex::encode(&code.txid),
+            code_vout: code.vout,
+            controller_txid: orbital_support::hex::encode(&controller.txid),
+            controller_vout: controller.vout,
+            token_block: block,
+            token_tx: tx,
+            balance: self.referral_balance_pointer(&code, &AlkaneId { block, tx }).get_value::<u128>(),
+            referred: self.referred_pointer(&code).get_value::<u128>(),
+        };
+
+        response.data = serde_json::to_vec(&view)
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to serialize referral rewards"))?;
+        Ok(response)
+    }
+
+    /// Move the referral share of a price collected in `token` from the treasury to the
+    /// referral code. Nothing is paid while rewards are off, for free mints or for a code
+    /// on an output of the minting transaction itself.
+    pub(crate) fn reward_referrer(&self, referrer: &Ticket, token: &AlkaneId, price: u128) -> Result<()> {
+        let bps: u128 = self.referral_rate_pointer().get_value::<u128>();
+        if bps == 0 || price == 0 || referrer.txid == [0; 32] || referrer.txid == self.attributed_ticket()?.txid {
+            return Ok(());
+        }
+
+        let share: u128 = referral_share(price, bps);
+        if share > 0 {
+            self.debit_treasury(token, share)?;
+            counters::increment(&mut self.referral_balance_pointer(referrer, token), share, u128::MAX, "referral_balance")?;
+        }
+
+        counters::increment(&mut self.referred_pointer(referrer), 1, u128::MAX, "referred")?;
+        Ok(())
+    }
+
+    fn referral_rate_pointer(&self) -> StoragePointer {
+        StoragePointer::from_keyword("/referrals/rate")
+    }
+
+    /// Outpoint a claim under `code` must spend: the code itself until its first claim
+    fn referral_controller(&self, code: &Ticket) -> Result<Ticket> {
+        let stored: Arc<Vec<u8>> = self.referral_controller_pointer(code).get();
+        if stored.is_empty() {
+            return Ok(*code);
+        }
+
+        bincode::deserialize(&stored)
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to deserialize referral controller"))
+    }
+
+    fn save_referral_controller(&self, code: &Ticket, controller: &Ticket) -> Result<()> {
+        let serialized: Vec<u8> = bincode::serialize(controller)
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to serialize referral controller"))?;
+        self.referral_controller_pointer(code).set(Arc::new(serialized));
+        Ok(())
+    }
+
+    fn referral_controller_pointer(&self, code: &Ticket) -> StoragePointer {
+        StoragePointer::from_keyword("/referrals/controller/").select(&code.to_bytes())
+    }
+
+    fn referral_balance_pointer(&self, code: &Ticket, token: &AlkaneId) -> StoragePointer {
+        StoragePointer::from_keyword("/referrals/balance/")
+            .select(&code.to_bytes())
+            .keyword("/")
+            .select(&encode_alkane_id(token))
+    }
+
+    fn referred_pointer(&self, code: &Ticket) -> StoragePointer {
+        StoragePointer::from_keyword("/referrals/referred/").select(&code.to_bytes())
+    }
+}
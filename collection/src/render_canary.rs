@@ -0,0 +1,223 @@
+use metashrew_support::index_pointer::KeyValuePointer;
+use alkanes_runtime::runtime::AlkaneResponder;
+use alkanes_support::response::CallResponse;
+use serde::{Serialize, Deserialize};
+use anyhow::Result;
+use orbital_support::{revert, errors::{codes::*, Revert}};
+use std::sync::Arc;
+
+use crate::{Collection, changelog};
+use crate::opcodes::StoragePointer;
+use crate::svg_generator::RenderConfig;
+
+/// Inputs ahead of the canary indices of `StartRenderCanary`: opcode, preview_gate, probation_blocks, count
+const CANARY_HEADER: usize = 4;
+
+/// Upper bound on canary indices per trial
+const MAX_CANARIES: usize = 16;
+
+/// Proposed render configuration served to a few orbitals before the rest of the collection
+#[derive(Clone, Default, Debug, Serialize, Deserialize)]
+struct RenderCanary {
+    proposed: RenderConfig,
+    indices: Vec<u128>,
+    started_at: u64,
+    /// Last block the canaries render under the proposal, the trial lapses afterwards
+    expires_at: u64,
+}
+
+impl RenderCanary {
+    fn is_live(&self, height: u64) -> bool {
+        height <= self.expires_at
+    }
+}
+
+/// Canary trial as returned by `GetRenderCanary`
+#[derive(Serialize)]
+struct RenderCanaryView {
+    preview: bool,
+    indices: Vec<u128>,
+    started_at: u64,
+    expires_at: u64,
+    /// "live" during probation, "lapsed" once it passed without confirmation
+    status: &'static str,
+}
+
+impl Collection {
+    /// Serve a proposed preview gate to `count` canary orbitals, listed after the fixed
+    /// inputs, for `probation_blocks` blocks. Unless confirmed in that window the canaries
+    /// fall back to the current configuration on their own.
+    pub(crate) fn start_render_canary(&self, preview_gate: u128, probation_blocks: u128, count: u128) -> Result<CallResponse> {
+        self.only_owner()?;
+        let context: alkanes_support::context::Context = self.context()?;
+        let response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        self.observe_canary_mutable()?;
+        let height: u64 = self.height();
+        if self.render_canary()?.is_some_and(|canary| canary.is_live(height)) {
+            return Err(revert!(ERR_INVALID_STATE, "A render canary is already on probation"));
+        }
+        self.settle_lapsed_render_canary()?;
+
+        let count: usize = usize::try_from(count).unwrap_or(usize::MAX);
+        if count == 0 || count > MAX_CANARIES {
+            return Err(Revert::new(ERR_INVALID_INPUT, format!("Render canaries take 1 to {} indices", MAX_CANARIES))
+                .field("count").expected(MAX_CANARIES as u128).actual(count as u128).into());
+        }
+
+        if context.inputs.len() < CANARY_HEADER + count {
+            return Err(revert!(ERR_INVALID_INPUT, "Canary index list is truncated"));
+        }
+
+        let probation: u64 = u64::try_from(probation_blocks)
+            .ok()
+            .filter(|blocks| *blocks > 0)
+            .ok_or_else(|| revert!(ERR_INVALID_INPUT, "Probation must last at least one block"))?;
+
+        let mut indices: Vec<u128> = context.inputs[CANARY_HEADER..CANARY_HEADER + count].to_vec();
+        indices.sort_unstable();
+        indices.dedup();
+        for index in indices.iter() {
+            self.lookup_instance(*index)?;
+        }
+
+        let canary: RenderCanary = RenderCanary {
+            proposed: RenderConfig { preview: preview_gate != 0, ..self.render_config() },
+            indices,
+            started_at: height,
+            expires_at: height.saturating_add(probation),
+        };
+
+        for index in canary.indices.iter() {
+            self.record_metadata_change(changelog::CHANGE_RENDER_CANARY, Some(*index), canary.expires_at as u128)?;
+        }
+        self.save_render_canary(Some(&canary))?;
+        Ok(response)
+    }
+
+    /// Apply the configuration on probation to every orbital
+    pub(crate) fn confirm_render_canary(&self) -> Result<CallResponse> {
+        self.only_owner()?;
+        let context: alkanes_support::context::Context = self.context()?;
+        let response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        self.observe_canary_mutable()?;
+        let canary: RenderCanary = self.render_canary()?
+            .filter(|canary| canary.is_live(self.height()))
+            .ok_or_else(|| revert!(ERR_INVALID_STATE, "No render canary on probation"))?;
+
+        self.apply_render_config(&canary.proposed)?;
+        self.save_render_canary(None)?;
+        Ok(response)
+    }
+
+    /// Stop a trial early, the canaries return to the current configuration
+    pub(crate) fn cancel_render_canary(&self) -> Result<CallResponse> {
+        self.only_owner()?;
+        let context: alkanes_support::context::Context = self.context()?;
+        let response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let canary: RenderCanary = self.render_canary()?
+            .ok_or_else(|| revert!(ERR_NOT_FOUND, "No render canary to cancel"))?;
+
+        self.end_render_canary(&canary)?;
+        Ok(response)
+    }
+
+    /// Whether a trial passed its probation unconfirmed and was not settled yet. The
+    /// canaries already render the current configuration again, so the metadata version
+    /// counts the lapse as one change until a mutating call logs it.
+    pub(crate) fn has_lapsed_render_canary(&self) -> bool {
+        matches!(self.render_canary(), Ok(Some(canary)) if !canary.is_live(self.height()))
+    }
+
+    /// Log the lapse of an unconfirmed trial and clear it, nothing to do while it is live
+    pub(crate) fn settle_lapsed_render_canary(&self) -> Result<()> {
+        match self.render_canary()? {
+            Some(canary) if !canary.is_live(self.height()) => self.end_render_canary(&canary),
+            _ => Ok(()),
+        }
+    }
+
+    /// Refuse to freeze the output while canaries render a proposal that could still lapse
+    pub(crate) fn observe_no_live_render_canary(&self) -> Result<()> {
+        if self.render_canary()?.is_some_and(|canary| canary.is_live(self.height())) {
+            return Err(revert!(ERR_INVALID_STATE, "Confirm or cancel the render canary before freezing the metadata"));
+        }
+
+        Ok(())
+    }
+
+    fn observe_canary_mutable(&self) -> Result<()> {
+        if self.is_metadata_frozen() {
+            return Err(revert!(ERR_INVALID_STATE, "Render canaries cannot run once the metadata is frozen"));
+        }
+
+        Ok(())
+    }
+
+    /// Return the canaries to the current configuration, logging 0 as the end of probation
+    fn end_render_canary(&self, canary: &RenderCanary) -> Result<()> {
+        self.save_render_canary(None)?;
+        for index in canary.indices.iter() {
+            self.record_metadata_change(changelog::CHANGE_RENDER_CANARY, Some(*index), 0)?;
+        }
+        Ok(())
+    }
+
+    /// Returns the current canary trial, or a lapsed one no call settled yet, as JSON, null
+    /// when there is none
+    pub(crate) fn get_render_canary(&self) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let height: u64 = self.height();
+        let view: Option<RenderCanaryView> = self.render_canary()?.map(|canary| RenderCanaryView {
+            preview: canary.proposed.preview,
+            status: if canary.is_live(height) { "live" } else { "lapsed" },
+            indices: canary.indices,
+            started_at: canary.started_at,
+            expires_at: canary.expires_at,
+        });
+
+        response.data = serde_json::to_vec(&view)
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to serialize render canary"))?;
+        Ok(response)
+    }
+
+    /// Configuration an orbital renders under, the proposal while it is a live canary
+    pub(crate) fn render_config_for(&self, index: u128) -> Result<RenderConfig> {
+        if let Some(canary) = self.render_canary()? {
+            if canary.is_live(self.height()) && canary.indices.binary_search(&index).is_ok() {
+                return Ok(canary.proposed);
+            }
+        }
+
+        Ok(self.render_config())
+    }
+
+    fn render_canary(&self) -> Result<Option<RenderCanary>> {
+        let stored: Arc<Vec<u8>> = self.render_canary_pointer().get();
+        if stored.is_empty() {
+            return Ok(None);
+        }
+
+        let canary: RenderCanary = bincode::deserialize(&stored)
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to deserialize render canary"))?;
+        Ok(Some(canary))
+    }
+
+    fn save_render_canary(&self, canary: Option<&RenderCanary>) -> Result<()> {
+        let serialized: Vec<u8> = match canary {
+            Some(canary) => bincode::serialize(canary)
+                .map_err(|_| revert!(ERR_STORAGE, "Failed to serialize render canary"))?,
+            None => vec![],
+        };
+        self.render_canary_pointer().set(Arc::new(serialized));
+        Ok(())
+    }
+
+    fn render_canary_pointer(&self) -> StoragePointer {
+        StoragePointer::from_keyword("/render-canary")
+    }
+}
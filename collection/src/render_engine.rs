@@ -0,0 +1,315 @@
+//! Art engines a collection can draw its orbitals with. Every engine reads the same trait
+//! table, so supply, reveal and trait gated features work alike whatever the art looks
+//! like; the engine decides how an entry is drawn and which traits it reports. A collection
+//! picks its engine once, at `Initialize`. Whatever the engine, its SVG is held to the
+//! `orbital_support::svg` policy before any of it is served or cached.
+
+use serde_json::{Map, Value, json};
+use anyhow::Result;
+use orbital_support::{revert, errors::{codes::*, Revert}, hex, rng::Rng, sha256, svg};
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+
+use crate::parametric::ParametricEngine;
+use crate::svg_generator::{RenderConfig, SvgGenerator, THUMBNAIL_SIZE};
+
+/// Layered SVG portraits assembled from the templates
+pub const ENGINE_LAYERED: u128 = 0;
+
+/// Mirrored sprites on a coarse grid, colored by trait
+pub const ENGINE_PIXEL: u128 = 1;
+
+/// Self contained HTML page around the layered portrait
+pub const ENGINE_HTML: u128 = 2;
+
+/// Orbits, rings and particles generated from the encoded entries
+pub const ENGINE_PARAMETRIC: u128 = 3;
+
+/// Number of built in engines, engine ids run below it
+pub const ENGINE_COUNT: u128 = 4;
+
+/// Revision of what the engines draw, bumped with every intended change of the art along
+/// with the render fixtures of `render_verify`
+pub const RENDER_VERSION: u128 = 1;
+
+/// Traits of the layered portrait, in the order of its attributes
+const PORTRAIT_TRAIT_TYPES: [&str; 6] = ["species", "background", "body", "head", "eyes", "mouth"];
+
+/// Cells along each side of a pixel sprite, 10 units each in the 200x200 viewBox
+const PIXEL_GRID: u32 = 20;
+
+/// Salt of the generator laying out a pixel sprite
+const PIXEL_SALT: &[u8] = b"orbital/pixel/";
+
+/// Draws trait entries
+pub trait RenderEngine {
+  /// MIME type of what `render` returns
+  fn content_type(&self) -> &'static str;
+
+  /// Names of the traits the engine reports, addressed by position
+  fn trait_types(&self) -> &'static [&'static str];
+
+  /// Trait values of entry `art`, in the order of `trait_types`
+  fn traits(&self, art: u128) -> Result<Vec<String>>;
+
+  /// Full rendering of entry `art`, watermarked under a preview configuration
+  fn render(&self, art: u128, config: &RenderConfig) -> Result<String>;
+
+  /// SVG at list view size, whatever `content_type` is
+  fn thumbnail(&self, art: u128, config: &RenderConfig) -> Result<String>;
+
+  /// Traits of entry `art` as a JSON object
+  fn attributes(&self, art: u128) -> Result<String> {
+    let attributes: Map<String, Value> = self.trait_types().iter()
+      .zip(self.traits(art)?)
+      .map(|(trait_type, value)| (trait_type.to_string(), Value::String(value)))
+      .collect();
+    Ok(Value::Object(attributes).to_string())
+  }
+
+  /// Value of a single trait, by its position in `trait_types`
+  fn trait_value(&self, art: u128, trait_type: u128) -> Result<String> {
+    let position: usize = usize::try_from(trait_type).unwrap_or(usize::MAX);
+    self.traits(art)?
+      .into_iter()
+      .nth(position)
+      .ok_or_else(|| revert!(ERR_NOT_FOUND, "Unknown trait type {}", trait_type))
+  }
+
+  /// Base64 data URI of the full rendering, ready to be used as an image source
+  fn data_uri(&self, art: u128, config: &RenderConfig) -> Result<String> {
+    let rendering: String = self.render(art, config)?;
+    Ok(format!("data:{};base64,{}", self.content_type(), STANDARD.encode(rendering.as_bytes())))
+  }
+
+  /// Marketplace style metadata document of orbital `index` drawing entry `art`, with the
+  /// rendering inlined as a data URI
+  fn metadata(&self, index: u128, art: u128, name: &str, config: &RenderConfig) -> Result<String> {
+    let attributes: Vec<Value> = self.trait_types().iter()
+      .zip(self.traits(art)?)
+      .map(|(trait_type, value)| json!({ "trait_type": trait_type, "value": value }))
+      .collect();
+
+    let metadata = json!({
+      "name": format!("{} #{}", name, index),
+      "image": self.data_uri(art, config)?,
+      "attributes": attributes,
+    });
+
+    Ok(metadata.to_string())
+  }
+}
+
+/// Engine `engine` drawing from the table of `generator`
+pub fn build(engine: u128, generator: SvgGenerator) -> Result<Box<dyn RenderEngine>> {
+  let engine: Box<dyn RenderEngine> = match engine {
+    ENGINE_LAYERED => Box::new(generator),
+    ENGINE_PIXEL => Box::new(PixelEngine { generator }),
+    ENGINE_HTML => Box::new(HtmlEngine { generator }),
+    ENGINE_PARAMETRIC => Box::new(ParametricEngine::new(generator)),
+    _ => return Err(Revert::new(ERR_INVALID_INPUT, "Unknown render engine")
+      .field("engine").expected(ENGINE_COUNT - 1).actual(engine).into()),
+  };
+  Ok(Box::new(PolicyChecked { engine }))
+}
+
+/// Name of a built in engine
+pub fn engine_name(engine: u128) -> &'static str {
+  match engine {
+    ENGINE_LAYERED => "layered",
+    ENGINE_PIXEL => "pixel",
+    ENGINE_HTML => "html",
+    ENGINE_PARAMETRIC => "parametric",
+    _ => "unknown",
+  }
+}
+
+/// Refuse to hand out SVG violating the policy. A violation in generated art is a broken
+/// engine or table rather than a bad request.
+pub fn observe_svg_policy(rendering: &str) -> Result<()> {
+  svg::validate(rendering).map_err(|violation| Revert { code: ERR_INVALID_STATE, ..*violation }.into())
+}
+
+/// Checks every SVG another engine renders. HTML pages pass as they are, the portrait
+/// they frame is checked as their thumbnail.
+struct PolicyChecked {
+  engine: Box<dyn RenderEngine>,
+}
+
+impl RenderEngine for PolicyChecked {
+  fn content_type(&self) -> &'static str {
+    self.engine.content_type()
+  }
+
+  fn trait_types(&self) -> &'static [&'static str] {
+    self.engine.trait_types()
+  }
+
+  fn traits(&self, art: u128) -> Result<Vec<String>> {
+    self.engine.traits(art)
+  }
+
+  fn render(&self, art: u128, config: &RenderConfig) -> Result<String> {
+    let rendering: String = self.engine.render(art, config)?;
+    if self.engine.content_type() == "image/svg+xml" {
+      observe_svg_policy(&rendering)?;
+    }
+    Ok(rendering)
+  }
+
+  fn thumbnail(&self, art: u128, config: &RenderConfig) -> Result<String> {
+    let thumbnail: String = self.engine.thumbnail(art, config)?;
+    observe_svg_policy(&thumbnail)?;
+    Ok(thumbnail)
+  }
+}
+
+/// Portrait traits of an entry, in the order of `PORTRAIT_TRAIT_TYPES`
+fn portrait_traits(generator: &SvgGenerator, art: u128) -> Result<Vec<String>> {
+  let (species, background, body_acc, head_acc, eyes, mouth, _nose, _outer_eyes) = generator.decode_traits(art)?;
+  Ok(vec![species, background, body_acc, head_acc, eyes, mouth])
+}
+
+impl RenderEngine for SvgGenerator {
+  fn content_type(&self) -> &'static str {
+    "image/svg+xml"
+  }
+
+  fn trait_types(&self) -> &'static [&'static str] {
+    &PORTRAIT_TRAIT_TYPES
+  }
+
+  fn traits(&self, art: u128) -> Result<Vec<String>> {
+    portrait_traits(self, art)
+  }
+
+  fn render(&self, art: u128, config: &RenderConfig) -> Result<String> {
+    let svg: String = self.generate_svg(art)?;
+    Ok(if config.preview { SvgGenerator::watermark(&svg) } else { svg })
+  }
+
+  fn thumbnail(&self, art: u128, config: &RenderConfig) -> Result<String> {
+    let svg: String = self.generate_thumbnail(art)?;
+    Ok(if config.preview { SvgGenerator::watermark(&svg) } else { svg })
+  }
+}
+
+/// Sprites mirrored around the vertical axis. The trait names pick the colors and seed the
+/// body shape, so entries sharing every trait share a sprite.
+pub struct PixelEngine {
+  generator: SvgGenerator,
+}
+
+impl PixelEngine {
+  /// Color a trait name is drawn in, from the leading bytes of its hash
+  fn color(name: &str) -> String {
+    format!("#{}", hex::encode(&sha256::hash(name.as_bytes())[..3]))
+  }
+
+  fn cell(svg: &mut String, x: u32, y: u32, fill: &str) {
+    svg.push_str(&format!("<rect x=\"{}\" y=\"{}\" width=\"10\" height=\"10\" fill=\"{}\"/>\n", x * 10, y * 10, fill));
+  }
+
+  fn render_svg(&self, art: u128, width: &str, height: &str) -> Result<String> {
+    let traits: Vec<String> = portrait_traits(&self.generator, art)?;
+    let (species, background, body_acc, head_acc, eyes, mouth) =
+      (&traits[0], &traits[1], &traits[2], &traits[3], &traits[4], &traits[5]);
+
+    let mut svg: String = format!("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<svg width=\"{}\" height=\"{}\" viewBox=\"0 0 200 200\" shape-rendering=\"crispEdges\" xmlns=\"http://www.w3.org/2000/svg\">\n", width, height);
+    svg.push_str(&format!("<rect width=\"200\" height=\"200\" fill=\"{}\"/>\n", Self::color(background)));
+
+    // Body: the left half of an 8x9 block drawn at random and mirrored, over a solid core
+    let mut rng: Rng = Rng::new(traits.join("/").as_bytes(), PIXEL_SALT);
+    let body: String = Self::color(species);
+    for y in 6..15 {
+      for offset in 0..4 {
+        if offset == 0 || rng.below(3) > 0 {
+          Self::cell(&mut svg, PIXEL_GRID / 2 - 1 - offset, y, &body);
+          Self::cell(&mut svg, PIXEL_GRID / 2 + offset, y, &body);
+        }
+      }
+    }
+
+    if head_acc != "none" {
+      let fill: String = Self::color(head_acc);
+      for x in 7..13 {
+        Self::cell(&mut svg, x, 5, &fill);
+      }
+    }
+
+    if body_acc != "none" {
+      let fill: String = Self::color(body_acc);
+      for x in 6..14 {
+        Self::cell(&mut svg, x, 14, &fill);
+      }
+    }
+
+    let eye: String = Self::color(eyes);
+    Self::cell(&mut svg, 8, 8, &eye);
+    Self::cell(&mut svg, 11, 8, &eye);
+
+    let lips: String = Self::color(mouth);
+    Self::cell(&mut svg, 9, 11, &lips);
+    Self::cell(&mut svg, 10, 11, &lips);
+
+    svg.push_str("</svg>");
+    Ok(svg)
+  }
+}
+
+impl RenderEngine for PixelEngine {
+  fn content_type(&self) -> &'static str {
+    "image/svg+xml"
+  }
+
+  fn trait_types(&self) -> &'static [&'static str] {
+    &PORTRAIT_TRAIT_TYPES
+  }
+
+  fn traits(&self, art: u128) -> Result<Vec<String>> {
+    portrait_traits(&self.generator, art)
+  }
+
+  fn render(&self, art: u128, config: &RenderConfig) -> Result<String> {
+    let svg: String = self.render_svg(art, "100%", "100%")?;
+    Ok(if config.preview { SvgGenerator::watermark(&svg) } else { svg })
+  }
+
+  fn thumbnail(&self, art: u128, config: &RenderConfig) -> Result<String> {
+    let size: String = format!("{}", THUMBNAIL_SIZE);
+    let svg: String = self.render_svg(art, &size, &size)?;
+    Ok(if config.preview { SvgGenerator::watermark(&svg) } else { svg })
+  }
+}
+
+/// Page showing the layered portrait full screen, for venues that display HTML. The
+/// thumbnail stays the plain portrait so list views get an image.
+pub struct HtmlEngine {
+  generator: SvgGenerator,
+}
+
+impl RenderEngine for HtmlEngine {
+  fn content_type(&self) -> &'static str {
+    "text/html"
+  }
+
+  fn trait_types(&self) -> &'static [&'static str] {
+    &PORTRAIT_TRAIT_TYPES
+  }
+
+  fn traits(&self, art: u128) -> Result<Vec<String>> {
+    portrait_traits(&self.generator, art)
+  }
+
+  fn render(&self, art: u128, config: &RenderConfig) -> Result<String> {
+    let svg: String = self.generator.render(art, config)?;
+    let body: &str = svg.find("<svg").map_or(svg.as_str(), |start| &svg[start..]);
+    Ok(format!(
+      "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n<style>html,body{{margin:0;height:100%;background:#000000}}svg{{display:block;width:100vmin;height:100vmin;margin:auto}}</style>\n</head>\n<body>\n{}\n</body>\n</html>",
+      body
+    ))
+  }
+
+  fn thumbnail(&self, art: u128, config: &RenderConfig) -> Result<String> {
+    self.generator.thumbnail(art, config)
+  }
+}
@@ -0,0 +1,71 @@
+//! Everything an off-chain tool needs to redraw the art and compare it with what the
+//! collection serves, in one query: the engine and the revision of its output, the trait
+//! table the entries come from, how reveals shuffle entries between orbitals, and whether
+//! any of it can still change.
+
+use alkanes_runtime::runtime::AlkaneResponder;
+use alkanes_support::response::CallResponse;
+use serde::Serialize;
+use anyhow::Result;
+use orbital_support::{revert, errors::codes::*};
+
+use crate::Collection;
+use crate::commit_reveal::REVEAL_SALT;
+use crate::render_engine;
+
+/// Render setup as returned by `GetRenderEngineInfo`
+#[derive(Serialize)]
+struct RenderEngineInfo {
+    engine: u128,
+    engine_name: &'static str,
+    content_type: &'static str,
+    /// Revision of the engine output, see `render_engine::RENDER_VERSION`
+    render_version: u128,
+    /// Trait table version the art is drawn from, 0 for the built in table
+    trait_table_version: u128,
+    trait_entries: u128,
+    shuffle: ShuffleInfo,
+    preview_gated: bool,
+    reveal_block: u64,
+    metadata_frozen: bool,
+    metadata_version: u128,
+}
+
+/// Reveals draw an entry for their orbital from a stream seeded by the first 80 bytes of
+/// the reveal block and salted with `salt` followed by the commitment ID
+#[derive(Serialize)]
+struct ShuffleInfo {
+    salt: String,
+    /// Reveals that shuffled so far, 0 means every orbital draws the entry at its index
+    shuffled: u128,
+}
+
+impl Collection {
+    /// Returns the engine, trait table, shuffle and freeze state of the art as JSON
+    pub(crate) fn get_render_engine_info(&self) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let engine: u128 = self.render_engine_id();
+        let info: RenderEngineInfo = RenderEngineInfo {
+            engine,
+            engine_name: render_engine::engine_name(engine),
+            content_type: self.render_engine()?.content_type(),
+            render_version: render_engine::RENDER_VERSION,
+            trait_table_version: self.traits_version(),
+            trait_entries: self.svg_generator()?.trait_count(),
+            shuffle: ShuffleInfo {
+                salt: String::from_utf8_lossy(REVEAL_SALT).into_owned(),
+                shuffled: self.shuffled_reveals(),
+            },
+            preview_gated: self.is_preview_gated(),
+            reveal_block: self.reveal_block(),
+            metadata_frozen: self.is_metadata_frozen(),
+            metadata_version: self.metadata_version(),
+        };
+
+        response.data = serde_json::to_vec(&info)
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to serialize render engine info"))?;
+        Ok(response)
+    }
+}
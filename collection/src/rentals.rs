@@ -0,0 +1,220 @@
+use metashrew_support::index_pointer::KeyValuePointer;
+use alkanes_runtime::runtime::AlkaneResponder;
+use alkanes_support::{id::AlkaneId, parcel::AlkaneTransfer, response::CallResponse};
+use serde::{Serialize, Deserialize};
+use anyhow::Result;
+use orbital_support::{revert, errors::codes::*};
+use std::sync::Arc;
+
+use crate::Collection;
+use crate::opcodes::StoragePointer;
+use crate::events::{MARKET_RENTAL_ENDED, MARKET_RENTAL_OFFERED, MARKET_RENTED};
+use crate::receipts::{Receipt, RECEIPT_KIND_RENTAL_CLAIM, RECEIPT_KIND_RENTER};
+
+/// Rental terms of an escrowed orbital
+#[derive(Clone, Default, Debug, Serialize, Deserialize)]
+struct Rental {
+    index: u128,
+    owner_receipt_block: u128,
+    owner_receipt_tx: u128,
+    payment_block: u128,
+    payment_tx: u128,
+    price: u128,
+    duration_blocks: u64,
+    renter_receipt_block: u128,
+    renter_receipt_tx: u128,
+    rented_until: u64,
+    proceeds: u128,
+}
+
+impl Rental {
+    fn is_rented(&self, height: u64) -> bool {
+        height <= self.rented_until
+    }
+
+    fn owner_receipt(&self) -> AlkaneId {
+        AlkaneId { block: self.owner_receipt_block, tx: self.owner_receipt_tx }
+    }
+
+    fn renter_receipt(&self) -> AlkaneId {
+        AlkaneId { block: self.renter_receipt_block, tx: self.renter_receipt_tx }
+    }
+
+    fn has_renter(&self, height: u64) -> bool {
+        self.renter_receipt_block != 0 && self.is_rented(height)
+    }
+
+    fn payment_token(&self) -> AlkaneId {
+        AlkaneId { block: self.payment_block, tx: self.payment_tx }
+    }
+}
+
+impl Collection {
+    /// Holder escrows the orbital for rent and receives the owner claim receipt
+    pub(crate) fn offer_rental(&self, index: u128, price: u128, duration_blocks: u128, payment_block: u128, payment_tx: u128) -> Result<CallResponse> {
+        self.only_holder(index)?;
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::default();
+
+        if price == 0 || duration_blocks == 0 {
+            return Err(revert!(ERR_INVALID_INPUT, "Rental price and duration must be positive"));
+        }
+
+        let duration_blocks: u64 = u64::try_from(duration_blocks).map_err(|_| revert!(ERR_INVALID_INPUT, "Rental duration out of range"))?;
+        let instance_id: AlkaneId = self.lookup_instance(index)?;
+
+        // Everything but the escrowed orbital goes back to the caller
+        response.alkanes.0 = self.escrow_incoming_orbital(&context.incoming_alkanes, &instance_id);
+
+        let (receipt_id, receipt_transfer) = self.issue_receipt(index, RECEIPT_KIND_RENTAL_CLAIM, u64::MAX)?;
+
+        let rental: Rental = Rental {
+            index,
+            owner_receipt_block: receipt_id.block,
+            owner_receipt_tx: receipt_id.tx,
+            payment_block,
+            payment_tx,
+            price,
+            duration_blocks,
+            ..Default::default()
+        };
+        self.set_rental(&rental)?;
+        self.record_market_event(MARKET_RENTAL_OFFERED, index, price)?;
+
+        response.alkanes.0.push(receipt_transfer);
+        Ok(response)
+    }
+
+    /// Pay the rental price and receive a usage receipt valid for the rental duration
+    pub(crate) fn rent(&self, index: u128) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::default();
+
+        let mut rental: Rental = self.get_rental_terms(index)?;
+        let height: u64 = self.height();
+        if rental.has_renter(height) {
+            return Err(revert!(ERR_INVALID_STATE, "Orbital {} is already rented", index));
+        }
+
+        response.alkanes.0 = self.take_payment(&context.incoming_alkanes, &rental.payment_token(), rental.price)?;
+
+        let rented_until: u64 = height.saturating_add(rental.duration_blocks);
+        let (receipt_id, receipt_transfer) = self.issue_receipt(index, RECEIPT_KIND_RENTER, rented_until)?;
+
+        rental.renter_receipt_block = receipt_id.block;
+        rental.renter_receipt_tx = receipt_id.tx;
+        rental.rented_until = rented_until;
+        rental.proceeds = rental.proceeds.checked_add(rental.price)
+            .ok_or_else(|| revert!(ERR_OVERFLOW, "rental proceeds overflow"))?;
+        self.set_rental(&rental)?;
+        self.record_market_event(MARKET_RENTED, index, rental.price)?;
+
+        response.alkanes.0.push(receipt_transfer);
+        Ok(response)
+    }
+
+    /// Owner collects the rental proceeds accrued so far, keeping the orbital up for rent
+    pub(crate) fn withdraw_rental_proceeds(&self, index: u128) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let mut rental: Rental = self.get_rental_terms(index)?;
+        self.only_receipt_holder(&rental.owner_receipt())?;
+
+        if rental.proceeds > 0 {
+            response.alkanes.0.push(AlkaneTransfer { id: rental.payment_token(), value: rental.proceeds });
+            rental.proceeds = 0;
+            self.set_rental(&rental)?;
+        }
+
+        Ok(response)
+    }
+
+    /// Once no rental is running, the claim receipt redeems the orbital and remaining proceeds
+    pub(crate) fn end_rental(&self, index: u128) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::default();
+
+        let rental: Rental = self.get_rental_terms(index)?;
+        let owner_receipt: AlkaneId = rental.owner_receipt();
+        self.only_receipt_holder(&owner_receipt)?;
+
+        if rental.has_renter(self.height()) {
+            return Err(revert!(ERR_INVALID_STATE, "Orbital {} is rented until block {}", index, rental.rented_until));
+        }
+
+        // The claim receipt is consumed, everything else goes back to the caller
+        for transfer in context.incoming_alkanes.0.iter() {
+            if transfer.id != owner_receipt {
+                response.alkanes.0.push(*transfer);
+            }
+        }
+
+        response.alkanes.0.push(AlkaneTransfer { id: self.lookup_instance(index)?, value: 1u128 });
+        if rental.proceeds > 0 {
+            response.alkanes.0.push(AlkaneTransfer { id: rental.payment_token(), value: rental.proceeds });
+        }
+
+        self.rental_pointer(index).set(Arc::new(vec![]));
+        self.record_market_event(MARKET_RENTAL_ENDED, index, 0)?;
+        Ok(response)
+    }
+
+    /// Orbital up for rent that a presented receipt stands in for as its user, with the
+    /// receipt kind: the renter receipt while its rental runs, the claim receipt while none
+    /// does. Use goes back to the owner the block a rental expires, without a transaction.
+    pub(crate) fn rental_user_of(&self, token: &AlkaneId, height: u64) -> Result<Option<(u128, u8)>> {
+        let receipt: Receipt = match self.get_receipt(token)? {
+            Some(receipt) if receipt.kind == RECEIPT_KIND_RENTER || receipt.kind == RECEIPT_KIND_RENTAL_CLAIM => receipt,
+            _ => return Ok(None),
+        };
+
+        // Receipts of rentals since ended or offered again no longer count
+        if self.rental_pointer(receipt.index).get().is_empty() {
+            return Ok(None);
+        }
+        let rental: Rental = self.get_rental_terms(receipt.index)?;
+        let uses: bool = match receipt.kind {
+            RECEIPT_KIND_RENTER => rental.renter_receipt() == *token && rental.has_renter(height),
+            _ => rental.owner_receipt() == *token && !rental.has_renter(height),
+        };
+
+        Ok(uses.then_some((receipt.index, receipt.kind)))
+    }
+
+    /// Height the rental of an index runs until
+    pub(crate) fn rented_until(&self, index: u128) -> Result<u64> {
+        Ok(self.get_rental_terms(index)?.rented_until)
+    }
+
+    /// Returns the rental terms of an index as JSON
+    pub(crate) fn get_rental(&self, index: u128) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let rental: Rental = self.get_rental_terms(index)?;
+        response.data = serde_json::to_vec(&rental)
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to serialize rental"))?;
+        Ok(response)
+    }
+
+    fn rental_pointer(&self, index: u128) -> StoragePointer {
+        StoragePointer::from_keyword("/rentals/").select(&index.to_le_bytes().to_vec())
+    }
+
+    fn set_rental(&self, rental: &Rental) -> Result<()> {
+        let serialized: Vec<u8> = bincode::serialize(rental)
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to serialize rental"))?;
+        self.rental_pointer(rental.index).set(Arc::new(serialized));
+        Ok(())
+    }
+
+    fn get_rental_terms(&self, index: u128) -> Result<Rental> {
+        let stored: Arc<Vec<u8>> = self.rental_pointer(index).get();
+        if stored.is_empty() {
+            return Err(revert!(ERR_NOT_FOUND, "Orbital {} is not up for rent", index));
+        }
+
+        bincode::deserialize(&stored).map_err(|_| revert!(ERR_STORAGE, "Failed to deserialize rental"))
+    }
+}
@@ -0,0 +1,144 @@
+//! Mint payments attributed to the stage they were paid in, per currency, so a revenue
+//! split negotiated for one phase can be checked against what that phase brought in.
+//! Public mints are attributed to stage 0. Receipts are counted gross of referral rewards;
+//! payments handed back, by an escrow refund or a partner clawback, are counted as refunded.
+//! Stages priced in sats are paid in the payment token at the oracle rate; the token amount
+//! is counted like any other payment and the sats price it settled is counted next to it.
+
+use metashrew_support::index_pointer::KeyValuePointer;
+use alkanes_runtime::runtime::AlkaneResponder;
+use alkanes_support::{id::AlkaneId, response::CallResponse};
+use serde::{Serialize, Deserialize};
+use anyhow::Result;
+use orbital_support::{revert, errors::codes::*};
+use std::sync::Arc;
+
+use crate::{Collection, Stage, counters};
+use crate::opcodes::StoragePointer;
+use crate::public_mint::PUBLIC_MINT_STAGE;
+
+/// What a stage received in one currency
+#[derive(Clone, Default, Debug, Serialize, Deserialize)]
+struct StageRevenue {
+    block: u128,
+    tx: u128,
+    received: u128,
+    refunded: u128,
+}
+
+/// Revenue of a currency as returned by `GetRevenueByStage`
+#[derive(Serialize)]
+struct StageRevenueView {
+    block: u128,
+    tx: u128,
+    received: u128,
+    refunded: u128,
+    /// Received less refunded
+    net: u128,
+}
+
+/// Revenue of a stage as returned by `GetRevenueByStage`
+#[derive(Serialize)]
+struct StageRevenueReport {
+    stage_id: u128,
+    currencies: Vec<StageRevenueView>,
+    /// Sats prices settled in the payment token, 0 unless the stage is priced in sats.
+    /// Refunds are only counted in the token they were paid in.
+    received_sats: u128,
+}
+
+impl Collection {
+    /// Returns what a stage received and refunded in every currency it was paid in as JSON,
+    /// stage 0 being the public mint
+    pub(crate) fn get_revenue_by_stage(&self, stage_id: u128) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        if stage_id != PUBLIC_MINT_STAGE {
+            self.get_mint_stage(stage_id)?;
+        }
+
+        let report: StageRevenueReport = StageRevenueReport {
+            stage_id,
+            currencies: self.stage_revenue(stage_id)?.into_iter()
+                .map(|revenue| StageRevenueView {
+                    block: revenue.block,
+                    tx: revenue.tx,
+                    received: revenue.received,
+                    refunded: revenue.refunded,
+                    net: revenue.received.saturating_sub(revenue.refunded),
+                })
+                .collect(),
+            received_sats: self.stage_sats_revenue_pointer(stage_id).get_value::<u128>(),
+        };
+
+        response.data = serde_json::to_vec(&report)
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to serialize stage revenue"))?;
+        Ok(response)
+    }
+
+    /// Attribute a payment of `amount` in `token` to a stage
+    pub(crate) fn record_stage_revenue(&self, stage_id: u128, token: &AlkaneId, amount: u128) -> Result<()> {
+        self.update_stage_revenue(stage_id, token, |revenue| {
+            revenue.received = revenue.received.checked_add(amount)
+                .ok_or_else(|| revert!(ERR_OVERFLOW, "stage revenue overflow"))?;
+            Ok(())
+        })
+    }
+
+    /// Attribute the sats price of `count` items to a sats priced stage paid in `token`.
+    /// Accepted payments carry a price of their own and settle no sats.
+    pub(crate) fn record_stage_sats_revenue(&self, stage: &Stage, token: &AlkaneId, count: u128, height: u64) -> Result<()> {
+        if !stage.priced_in_sats || stage.accepted_payments.iter().any(|payment| payment.token() == *token) {
+            return Ok(());
+        }
+
+        let (sats, _) = stage.quote(count, height)?;
+        counters::increment(&mut self.stage_sats_revenue_pointer(stage.id), sats, u128::MAX, "stage_sats_revenue")?;
+        Ok(())
+    }
+
+    /// Count `amount` in `token` a stage received as handed back to the payer
+    pub(crate) fn refund_stage_revenue(&self, stage_id: u128, token: &AlkaneId, amount: u128) -> Result<()> {
+        self.update_stage_revenue(stage_id, token, |revenue| {
+            revenue.refunded = revenue.refunded.checked_add(amount)
+                .ok_or_else(|| revert!(ERR_OVERFLOW, "stage refund overflow"))?;
+            Ok(())
+        })
+    }
+
+    fn update_stage_revenue<F: FnOnce(&mut StageRevenue) -> Result<()>>(&self, stage_id: u128, token: &AlkaneId, update: F) -> Result<()> {
+        let mut revenues: Vec<StageRevenue> = self.stage_revenue(stage_id)?;
+        let position: usize = match revenues.iter().position(|revenue| revenue.block == token.block && revenue.tx == token.tx) {
+            Some(position) => position,
+            None => {
+                revenues.push(StageRevenue { block: token.block, tx: token.tx, ..Default::default() });
+                revenues.len() - 1
+            }
+        };
+        update(&mut revenues[position])?;
+
+        let serialized: Vec<u8> = bincode::serialize(&revenues)
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to serialize stage revenue"))?;
+        self.stage_revenue_pointer(stage_id).set(Arc::new(serialized));
+        Ok(())
+    }
+
+    fn stage_revenue(&self, stage_id: u128) -> Result<Vec<StageRevenue>> {
+        let stored: Arc<Vec<u8>> = self.stage_revenue_pointer(stage_id).get();
+        if stored.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        bincode::deserialize(&stored)
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to deserialize stage revenue"))
+    }
+
+    fn stage_revenue_pointer(&self, stage_id: u128) -> StoragePointer {
+        StoragePointer::from_keyword("/revenue/stages/").select(&stage_id.to_le_bytes().to_vec())
+    }
+
+    fn stage_sats_revenue_pointer(&self, stage_id: u128) -> StoragePointer {
+        StoragePointer::from_keyword("/revenue/sats/").select(&stage_id.to_le_bytes().to_vec())
+    }
+}
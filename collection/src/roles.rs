@@ -0,0 +1,86 @@
+use metashrew_support::index_pointer::KeyValuePointer;
+use alkanes_runtime::runtime::AlkaneResponder;
+use alkanes_support::{id::AlkaneId, response::CallResponse};
+use anyhow::Result;
+use orbital_support::{revert, errors::codes::*};
+use std::sync::Arc;
+
+use crate::{Collection, decode_alkane_id, encode_alkane_id};
+use crate::opcodes::StoragePointer;
+
+/// Role allowed to manage per-index metadata such as royalty overrides
+pub(crate) const ROLE_METADATA: u128 = 1;
+
+/// Role allowed to report externally tracked activity, such as governance votes
+pub(crate) const ROLE_ACTIVITY: u128 = 2;
+
+/// A role is held by whoever presents its credential token.
+/// The owner (collection auth token) implicitly holds every role.
+impl Collection {
+    /// Assign the alkane token acting as credential for a role
+    pub(crate) fn grant_role(&self, role: u128, block: u128, tx: u128) -> Result<CallResponse> {
+        self.only_owner()?;
+        let context: alkanes_support::context::Context = self.context()?;
+        let response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        self.observe_known_role(role)?;
+
+        let credential: AlkaneId = AlkaneId { block, tx };
+        self.role_pointer(role).set(Arc::new(encode_alkane_id(&credential)));
+        Ok(response)
+    }
+
+    pub(crate) fn revoke_role(&self, role: u128) -> Result<CallResponse> {
+        self.only_owner()?;
+        let context: alkanes_support::context::Context = self.context()?;
+        let response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        self.observe_known_role(role)?;
+
+        self.role_pointer(role).set(Arc::new(vec![]));
+        Ok(response)
+    }
+
+    /// Returns the credential alkane ID of a role, empty when unassigned
+    pub(crate) fn get_role_credential(&self, role: u128) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        response.data = self.role_pointer(role).get().as_ref().clone();
+        Ok(response)
+    }
+
+    /// Require either the owner auth token or the role credential among the incoming alkanes
+    pub(crate) fn only_role(&self, role: u128) -> Result<()> {
+        if self.only_owner().is_ok() {
+            return Ok(());
+        }
+
+        let context: alkanes_support::context::Context = self.context()?;
+        let stored: Arc<Vec<u8>> = self.role_pointer(role).get();
+        if stored.len() == 0 {
+            return Err(revert!(ERR_UNAUTHORIZED, "Caller does not hold role {}", role));
+        }
+
+        let credential: AlkaneId = decode_alkane_id(&stored)?;
+        let has_credential: bool = context.incoming_alkanes.0.iter()
+            .any(|transfer| transfer.id == credential && transfer.value > 0);
+
+        if !has_credential {
+            return Err(revert!(ERR_UNAUTHORIZED, "Caller does not hold role {}", role));
+        }
+
+        Ok(())
+    }
+
+    fn observe_known_role(&self, role: u128) -> Result<()> {
+        match role {
+            ROLE_METADATA | ROLE_ACTIVITY => Ok(()),
+            _ => Err(revert!(ERR_NOT_FOUND, "Unknown role {}", role)),
+        }
+    }
+
+    fn role_pointer(&self, role: u128) -> StoragePointer {
+        StoragePointer::from_keyword("/roles/").select(&role.to_le_bytes().to_vec())
+    }
+}
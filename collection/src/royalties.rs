@@ -0,0 +1,130 @@
+use metashrew_support::index_pointer::KeyValuePointer;
+use alkanes_runtime::runtime::AlkaneResponder;
+use alkanes_support::{id::AlkaneId, response::CallResponse};
+use anyhow::Result;
+use orbital_support::{revert, errors::{codes::*, Revert}};
+use std::sync::Arc;
+
+use crate::{Collection, decode_alkane_id, encode_alkane_id};
+use crate::opcodes::StoragePointer;
+use crate::roles::ROLE_METADATA;
+
+/// Royalties are expressed in basis points of the sale amount
+const MAX_ROYALTY_BPS: u128 = 10_000;
+
+/// Royalty recipient and rate
+#[derive(Clone, Copy, Default, Debug)]
+pub(crate) struct Royalty {
+    pub recipient: AlkaneId,
+    pub bps: u128,
+}
+
+impl Royalty {
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes: Vec<u8> = encode_alkane_id(&self.recipient);
+        bytes.extend_from_slice(&self.bps.to_le_bytes());
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Royalty> {
+        if bytes.len() != 48 {
+            return Err(revert!(ERR_INVALID_INPUT, "Invalid royalty data length"));
+        }
+
+        Ok(Royalty {
+            recipient: decode_alkane_id(&bytes[..32])?,
+            bps: u128::from_le_bytes(bytes[32..48].try_into().unwrap()),
+        })
+    }
+}
+
+impl Collection {
+    /// Set the collection wide royalty applied to every index without an override
+    pub(crate) fn set_default_royalty(&self, recipient_block: u128, recipient_tx: u128, bps: u128) -> Result<CallResponse> {
+        self.only_owner()?;
+        let context: alkanes_support::context::Context = self.context()?;
+        let response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let royalty: Royalty = self.new_royalty(recipient_block, recipient_tx, bps)?;
+        self.default_royalty_pointer().set(Arc::new(royalty.to_bytes()));
+        Ok(response)
+    }
+
+    /// Give a single minted index (honorary, collab) its own recipient and rate
+    pub(crate) fn set_royalty_override(&self, index: u128, recipient_block: u128, recipient_tx: u128, bps: u128) -> Result<CallResponse> {
+        self.only_role(ROLE_METADATA)?;
+        let context: alkanes_support::context::Context = self.context()?;
+        let response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        if index >= self.instances_count() {
+            return Err(Revert::new(ERR_NOT_FOUND, "No minted orbital under this index")
+                .field("index").expected(self.instances_count().saturating_sub(1)).actual(index).into());
+        }
+
+        let royalty: Royalty = self.new_royalty(recipient_block, recipient_tx, bps)?;
+        self.royalty_override_pointer(index).set(Arc::new(royalty.to_bytes()));
+        Ok(response)
+    }
+
+    pub(crate) fn clear_royalty_override(&self, index: u128) -> Result<CallResponse> {
+        self.only_role(ROLE_METADATA)?;
+        let context: alkanes_support::context::Context = self.context()?;
+        let response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        self.royalty_override_pointer(index).set(Arc::new(vec![]));
+        Ok(response)
+    }
+
+    /// Returns recipient block, recipient tx and royalty amount for the sale, each as u128
+    pub(crate) fn get_royalty_info(&self, index: u128, sale_amount: u128) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let royalty: Royalty = self.royalty_for(index)?;
+        let amount: u128 = sale_amount
+            .checked_mul(royalty.bps)
+            .ok_or_else(|| revert!(ERR_OVERFLOW, "royalty amount overflow"))?
+            / MAX_ROYALTY_BPS;
+
+        let mut bytes: Vec<u8> = encode_alkane_id(&royalty.recipient);
+        bytes.extend_from_slice(&amount.to_le_bytes());
+
+        response.data = bytes;
+        Ok(response)
+    }
+
+    /// Effective royalty of an index: its override if any, else the collection default
+    pub(crate) fn royalty_for(&self, index: u128) -> Result<Royalty> {
+        let override_data: Arc<Vec<u8>> = self.royalty_override_pointer(index).get();
+        if override_data.len() > 0 {
+            return Royalty::from_bytes(&override_data);
+        }
+
+        let default_data: Arc<Vec<u8>> = self.default_royalty_pointer().get();
+        if default_data.len() > 0 {
+            return Royalty::from_bytes(&default_data);
+        }
+
+        Ok(Royalty::default())
+    }
+
+    fn new_royalty(&self, recipient_block: u128, recipient_tx: u128, bps: u128) -> Result<Royalty> {
+        if bps > MAX_ROYALTY_BPS {
+            return Err(Revert::new(ERR_INVALID_INPUT, format!("Royalty of {} bps exceeds {}", bps, MAX_ROYALTY_BPS))
+                .field("bps").expected(MAX_ROYALTY_BPS).actual(bps).into());
+        }
+
+        Ok(Royalty {
+            recipient: AlkaneId { block: recipient_block, tx: recipient_tx },
+            bps,
+        })
+    }
+
+    fn default_royalty_pointer(&self) -> StoragePointer {
+        StoragePointer::from_keyword("/royalty/default")
+    }
+
+    fn royalty_override_pointer(&self, index: u128) -> StoragePointer {
+        StoragePointer::from_keyword("/royalty/override/").select(&index.to_le_bytes().to_vec())
+    }
+}
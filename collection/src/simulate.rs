@@ -0,0 +1,99 @@
+use alkanes_runtime::runtime::AlkaneResponder;
+use alkanes_support::{id::AlkaneId, response::CallResponse};
+use anyhow::Result;
+use orbital_support::{revert, errors::codes::*};
+
+use crate::{Collection, Stage};
+
+/// Inputs ahead of the whitelist proof of `SimulateMint`: opcode, stage_id, count
+const SIMULATE_MINT_HEADER: usize = 3;
+
+/// Rough fuel cost of deploying one orbital instance and registering it,
+/// added to the fuel measured while validating
+const FUEL_PER_MINT: u64 = 250_000;
+
+impl Collection {
+    /// Run every mint check for `count` orbitals in a stage without changing state,
+    /// whitelist proof nodes follow the fixed inputs as for `MintInStage`. Returns the price (u128) followed by the estimated fuel (u128).
+    pub(crate) fn simulate_mint(&self, stage_id: u128, count: u128) -> Result<CallResponse> {
+        let fuel_start: u64 = self.fuel();
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        if count == 0 {
+            return Err(revert!(ERR_INVALID_INPUT, "Mint count must be positive"));
+        }
+
+        self.observe_mint_open()?;
+        let stage: Stage = self.current_mint_stage(stage_id)?;
+        let height: u64 = self.height();
+
+        if !stage.is_active(height, self.block_time()) {
+            return Err(revert!(ERR_INVALID_STATE, "Stage is not active"));
+        }
+
+        if stage.total_minted.saturating_add(count) > stage.max_supply {
+            return Err(revert!(ERR_LIMIT_EXCEEDED, "Exceeds max supply for this stage"));
+        }
+
+        let index: u128 = self.instances_count();
+        if index.saturating_add(count) > self.max_mints().min(self.max_supply()) {
+            return Err(revert!(ERR_LIMIT_EXCEEDED, "Exceeds the remaining supply of the collection"));
+        }
+
+        let minted_in_block: u128 = self.mints_in_block(height) as u128;
+        if minted_in_block.saturating_add(count) > self.max_mint_per_block() as u128 {
+            return Err(revert!(ERR_LIMIT_EXCEEDED, "mint limit reached for block {}", height));
+        }
+
+        let stage_limit: u128 = self.stage_max_mint_per_block(stage_id) as u128;
+        if stage_limit > 0 && self.stage_mints_in_block(stage_id, height).saturating_add(count) > stage_limit {
+            return Err(revert!(ERR_LIMIT_EXCEEDED, "mint limit of stage {} reached for block {}", stage_id, height));
+        }
+
+        let minter: [u8; 32] = self.minter_identity()?;
+        self.observe_whitelist(&stage, &minter, SIMULATE_MINT_HEADER)?;
+        self.observe_address_limit(&stage, &minter, count)?;
+        self.observe_sybil_score(stage_id)?;
+        self.check_pre_mint_hook(stage_id, index)?;
+
+        let (price, _) = self.stage_quote(&stage, count, height)?;
+        let payment: Option<(AlkaneId, u128)> = self.payment_token()?.filter(|_| price > 0).map(|token| (token, price));
+        self.observe_token_gate(&stage, &context.incoming_alkanes, payment)?;
+
+        let validation_fuel: u64 = fuel_start.saturating_sub(self.fuel());
+        let fuel: u128 = (validation_fuel as u128).saturating_add(FUEL_PER_MINT as u128 * count);
+
+        let mut data: Vec<u8> = Vec::with_capacity(32);
+        data.extend_from_slice(&price.to_le_bytes());
+        data.extend_from_slice(&fuel.to_le_bytes());
+        response.data = data;
+        Ok(response)
+    }
+
+    /// Quote `count` orbitals of a stage. Returns the price (u128) and the first and
+    /// last block (u128 each) at which a mint pays exactly that price.
+    pub(crate) fn get_quote(&self, stage_id: u128, count: u128) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        if count == 0 {
+            return Err(revert!(ERR_INVALID_INPUT, "Mint count must be positive"));
+        }
+
+        let stage: Stage = self.get_mint_stage(stage_id)?;
+        if stage.total_minted.saturating_add(count) > stage.max_supply {
+            return Err(revert!(ERR_LIMIT_EXCEEDED, "Exceeds max supply for this stage"));
+        }
+
+        let height: u64 = self.height();
+        let (price, valid_until) = self.stage_quote(&stage, count, height)?;
+
+        let mut data: Vec<u8> = Vec::with_capacity(48);
+        data.extend_from_slice(&price.to_le_bytes());
+        data.extend_from_slice(&(height as u128).to_le_bytes());
+        data.extend_from_slice(&(valid_until as u128).to_le_bytes());
+        response.data = data;
+        Ok(response)
+    }
+}
@@ -0,0 +1,272 @@
+use alkanes_runtime::runtime::AlkaneResponder;
+use alkanes_support::response::CallResponse;
+use serde::Serialize;
+use anyhow::Result;
+use orbital_support::{revert, errors::{codes::*, Revert}};
+
+use crate::{Collection, Stage, SCHEDULE_BY_HEIGHT, SCHEDULE_BY_TIME};
+
+/// Mint progress of a stage as returned by `GetMintedInStage`
+#[derive(Serialize)]
+struct StageProgress {
+    stage_id: u128,
+    minted: u128,
+    max_supply: u128,
+    remaining: u128,
+    active: bool,
+}
+
+fn narrow<T: TryFrom<u128>>(field: &str, value: u128) -> Result<T> {
+    T::try_from(value).map_err(|_| Revert::new(ERR_INVALID_INPUT, format!("{} is out of range", field))
+        .field(field).actual(value).into())
+}
+
+/// Post-deployment stage administration
+impl Collection {
+    /// Create a stage or update the schedule, price, supply and whitelist root of an
+    /// existing one. Mint counters and soft-close state of an existing stage are kept.
+    /// The whitelist root is given as two little endian u128 halves, zero for an open stage.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn set_stage(
+        &self,
+        stage_id: u128,
+        price_per_item: u128,
+        max_mints_per_address: u128,
+        max_supply: u128,
+        schedule_mode: u128,
+        start_block: u128,
+        end_block: u128,
+        start_time: u128,
+        end_time: u128,
+        whitelist_low: u128,
+        whitelist_high: u128,
+        free: u128,
+    ) -> Result<CallResponse> {
+        self.only_owner()?;
+        let context: alkanes_support::context::Context = self.context()?;
+        let response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        if stage_id == 0 {
+            return Err(revert!(ERR_INVALID_INPUT, "Stage ID must be positive"));
+        }
+
+        let schedule_mode: u8 = narrow("schedule_mode", schedule_mode)?;
+        if schedule_mode != SCHEDULE_BY_HEIGHT && schedule_mode != SCHEDULE_BY_TIME {
+            return Err(revert!(ERR_INVALID_INPUT, "Unknown schedule mode {}", schedule_mode));
+        }
+
+        let mut whitelist_root: [u8; 32] = [0; 32];
+        whitelist_root[..16].copy_from_slice(&whitelist_low.to_le_bytes());
+        whitelist_root[16..].copy_from_slice(&whitelist_high.to_le_bytes());
+
+        let existing: Option<Stage> = self.get_mint_stage(stage_id).ok();
+        let previous_start: Option<u64> = existing.as_ref().map(|existing| existing.start_block);
+        let mut stage: Stage = existing.unwrap_or_else(|| Stage { id: stage_id, ..Default::default() });
+        stage.price_per_item = narrow("price_per_item", price_per_item)?;
+        stage.max_mints_per_address = narrow("max_mints_per_address", max_mints_per_address)?;
+        stage.max_supply = max_supply;
+        stage.schedule_mode = schedule_mode;
+        stage.start_block = narrow("start_block", start_block)?;
+        stage.end_block = narrow("end_block", end_block)?;
+        stage.start_time = narrow("start_time", start_time)?;
+        stage.end_time = narrow("end_time", end_time)?;
+        stage.whitelist_root = whitelist_root;
+        stage.free = free != 0;
+
+        if stage.max_supply < stage.total_minted {
+            return Err(Revert::new(ERR_INVALID_INPUT, format!("Stage {} already minted past the new supply", stage_id))
+                .field("max_supply").expected(stage.total_minted).actual(stage.max_supply).into());
+        }
+
+        if stage.end_block < stage.start_block || (schedule_mode == SCHEDULE_BY_TIME && stage.end_time < stage.start_time) {
+            return Err(revert!(ERR_INVALID_INPUT, "Stage {} ends before it starts", stage_id));
+        }
+
+        if stage.price_per_item == 0 && !stage.free {
+            return Err(revert!(ERR_INVALID_INPUT, "Stage {} has a zero price without being flagged free", stage_id));
+        }
+
+        if stage.decay_interval_blocks > 0 && schedule_mode != SCHEDULE_BY_HEIGHT {
+            return Err(revert!(ERR_INVALID_INPUT, "Stage {} has a pricing curve and must stay height scheduled", stage_id));
+        }
+
+        if stage.rollover && schedule_mode != SCHEDULE_BY_HEIGHT {
+            return Err(revert!(ERR_INVALID_INPUT, "Stage {} rolls over and must stay height scheduled", stage_id));
+        }
+
+        // Windows already under way may be edited, a new or moved start must lie ahead
+        self.observe_deployable_stage(&stage, previous_start != Some(stage.start_block))?;
+
+        self.save_mint_stage(&stage)?;
+        Ok(response)
+    }
+
+    /// Remove a stage nothing was minted in yet, end a stage that has mints instead
+    pub(crate) fn remove_stage(&self, stage_id: u128) -> Result<CallResponse> {
+        self.only_owner()?;
+        let context: alkanes_support::context::Context = self.context()?;
+        let response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let stage: Stage = self.get_mint_stage(stage_id)?;
+        if stage.total_minted > 0 {
+            return Err(Revert::new(ERR_INVALID_STATE, format!("Stage {} has mints and can only be ended", stage_id))
+                .field("total_minted").expected(0).actual(stage.total_minted).into());
+        }
+
+        self.remove_mint_stage(stage_id)?;
+        Ok(response)
+    }
+
+    /// Turn a height scheduled stage into a descending Dutch auction, or back to its fixed
+    /// price with a decay interval of 0. Already minted items keep the price they paid.
+    pub(crate) fn set_stage_pricing_curve(&self, stage_id: u128, start_price: u128, end_price: u128, decay_interval_blocks: u128) -> Result<CallResponse> {
+        self.only_owner()?;
+        let context: alkanes_support::context::Context = self.context()?;
+        let response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let start_price: u64 = narrow("start_price", start_price)?;
+        let end_price: u64 = narrow("end_price", end_price)?;
+        let decay_interval_blocks: u64 = narrow("decay_interval_blocks", decay_interval_blocks)?;
+
+        let mut stage: Stage = self.get_mint_stage(stage_id)?;
+        if decay_interval_blocks > 0 {
+            if stage.schedule_mode != SCHEDULE_BY_HEIGHT {
+                return Err(revert!(ERR_INVALID_INPUT, "Stage {} is time scheduled, curves step by block", stage_id));
+            }
+
+            if start_price < end_price {
+                return Err(Revert::new(ERR_INVALID_INPUT, "Dutch auction prices must not rise")
+                    .field("end_price").expected(start_price as u128).actual(end_price as u128).into());
+            }
+
+            if end_price == 0 && !stage.free {
+                return Err(revert!(ERR_INVALID_INPUT, "Stage {} has a zero price without being flagged free", stage_id));
+            }
+        }
+
+        stage.start_price = start_price;
+        stage.end_price = end_price;
+        stage.decay_interval_blocks = decay_interval_blocks;
+        self.save_mint_stage(&stage)?;
+        Ok(response)
+    }
+
+    /// Have the supply a stage leaves unsold move to the stage starting next after it ends.
+    /// The next stage is the height scheduled stage with the earliest start past the end of
+    /// this one, picked when it starts, so stages added meanwhile are taken into account.
+    pub(crate) fn set_stage_rollover(&self, stage_id: u128, enabled: u128) -> Result<CallResponse> {
+        self.only_owner()?;
+        let context: alkanes_support::context::Context = self.context()?;
+        let response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let mut stage: Stage = self.get_mint_stage(stage_id)?;
+        if stage.rolled_into != 0 {
+            return Err(revert!(ERR_INVALID_STATE, "Stage {} already rolled over into stage {}", stage_id, stage.rolled_into));
+        }
+
+        if enabled != 0 && stage.schedule_mode != SCHEDULE_BY_HEIGHT {
+            return Err(revert!(ERR_INVALID_INPUT, "Stage {} is time scheduled, rollovers follow block heights", stage_id));
+        }
+
+        stage.rollover = enabled != 0;
+        self.save_mint_stage(&stage)?;
+        Ok(response)
+    }
+
+    /// Store every rollover due at the current height
+    pub(crate) fn settle_stage_rollovers(&self) -> Result<()> {
+        for stage in self.rolled_over_stages()? {
+            self.save_mint_stage(&stage)?;
+        }
+
+        Ok(())
+    }
+
+    /// A stage as it stands once the rollovers due at the current height happened, without
+    /// storing them
+    pub(crate) fn current_mint_stage(&self, stage_id: u128) -> Result<Stage> {
+        match self.rolled_over_stages()?.into_iter().find(|stage| stage.id == stage_id) {
+            Some(stage) => Ok(stage),
+            None => self.get_mint_stage(stage_id),
+        }
+    }
+
+    /// Stages changed by the rollovers due at the current height, in start order so a stage
+    /// receiving supply can pass it on in the same pass
+    fn rolled_over_stages(&self) -> Result<Vec<Stage>> {
+        let height: u64 = self.height();
+        let mut stages: Vec<Stage> = self.get_mint_stages()?.into_iter()
+            .filter(|stage| stage.schedule_mode == SCHEDULE_BY_HEIGHT)
+            .collect();
+        stages.sort_by_key(|stage| (stage.start_block, stage.id));
+
+        let mut changed: Vec<usize> = Vec::new();
+        for from in 0..stages.len() {
+            let stage: &Stage = &stages[from];
+            if !stage.rollover || stage.rolled_into != 0 || height <= stage.end_block {
+                continue;
+            }
+
+            let end_block: u64 = stage.end_block;
+            let into: usize = match (from + 1..stages.len()).find(|&next| stages[next].start_block > end_block) {
+                Some(into) if stages[into].start_block <= height => into,
+                _ => continue,
+            };
+
+            let unsold: u128 = stages[from].max_supply.saturating_sub(stages[from].total_minted);
+            stages[into].max_supply = stages[into].max_supply.checked_add(unsold)
+                .ok_or_else(|| revert!(ERR_OVERFLOW, "stage supply overflow"))?;
+            stages[from].max_supply = stages[from].total_minted;
+            stages[from].rolled_into = stages[into].id;
+
+            for index in [from, into] {
+                if !changed.contains(&index) {
+                    changed.push(index);
+                }
+            }
+        }
+
+        Ok(changed.into_iter().map(|index| stages[index].clone()).collect())
+    }
+
+    /// Returns the per item price of a stage at the current height, in the payment token
+    pub(crate) fn get_current_price(&self, stage_id: u128) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let stage: Stage = self.get_mint_stage(stage_id)?;
+        let (price, _) = self.stage_quote(&stage, 1, self.height())?;
+        response.data = price.to_le_bytes().to_vec();
+        Ok(response)
+    }
+
+    /// Returns every stage as a JSON array
+    pub(crate) fn get_stages(&self) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        response.data = serde_json::to_vec(&self.get_mint_stages()?)
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to serialize stages"))?;
+        Ok(response)
+    }
+
+    /// Returns the minted count, supply and what is left of a stage as JSON, counting any
+    /// rollover due at the current height
+    pub(crate) fn get_minted_in_stage(&self, stage_id: u128) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let stage: Stage = self.current_mint_stage(stage_id)?;
+        let progress: StageProgress = StageProgress {
+            stage_id,
+            minted: stage.total_minted,
+            max_supply: stage.max_supply,
+            remaining: stage.max_supply.saturating_sub(stage.total_minted),
+            active: stage.is_active(self.height(), self.block_time()),
+        };
+
+        response.data = serde_json::to_vec(&progress)
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to serialize stage progress"))?;
+        Ok(response)
+    }
+}
@@ -0,0 +1,169 @@
+use metashrew_support::index_pointer::KeyValuePointer;
+use alkanes_runtime::runtime::AlkaneResponder;
+use alkanes_support::{id::AlkaneId, parcel::{AlkaneTransfer, AlkaneTransferParcel}, response::CallResponse};
+use serde::{Serialize, Deserialize};
+use anyhow::Result;
+use orbital_support::{revert, errors::codes::*};
+use std::sync::Arc;
+
+use crate::Collection;
+use crate::opcodes::StoragePointer;
+use crate::batch::Page;
+use crate::events::{STAKING_STAKED, STAKING_UNSTAKED};
+use crate::receipts::{RECEIPT_KIND_RENTER, RECEIPT_KIND_STAKER};
+
+/// An orbital locked in the collection, tracked from the block it was staked. A renter
+/// stakes its renter receipt instead, and the stake stops counting when the rental ends.
+#[derive(Clone, Default, Debug, Serialize, Deserialize)]
+pub(crate) struct Stake {
+    pub index: u128,
+    pub receipt_block: u128,
+    pub receipt_tx: u128,
+    pub staked_at: u64,
+    /// Token locked by the stake, the orbital or a renter receipt
+    pub asset_block: u128,
+    pub asset_tx: u128,
+    /// Last block the stake counts for
+    pub until: u64,
+}
+
+impl Stake {
+    pub(crate) fn receipt(&self) -> AlkaneId {
+        AlkaneId { block: self.receipt_block, tx: self.receipt_tx }
+    }
+
+    fn asset(&self) -> AlkaneId {
+        AlkaneId { block: self.asset_block, tx: self.asset_tx }
+    }
+
+    pub(crate) fn staked_blocks(&self, height: u64) -> u64 {
+        height.min(self.until).saturating_sub(self.staked_at)
+    }
+}
+
+impl Collection {
+    /// Holder locks the orbital in the collection, or the renter its renter receipt for
+    /// the rest of the rental, and receives the stake receipt
+    pub(crate) fn stake(&self, index: u128) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::default();
+
+        let (asset, until): (AlkaneId, u64) = self.stakeable_asset(index, &context.incoming_alkanes)?;
+        if let Some(previous) = self.get_stake_of(index)? {
+            // A stake of a renter receipt outlived by its rental gives way
+            if previous.until >= self.height() {
+                return Err(revert!(ERR_INVALID_STATE, "Orbital {} is already staked", index));
+            }
+        }
+
+        // Everything but the staked token goes back to the caller
+        response.alkanes.0 = self.escrow_incoming_orbital(&context.incoming_alkanes, &asset);
+
+        let (receipt_id, receipt_transfer) = self.issue_receipt(index, RECEIPT_KIND_STAKER, u64::MAX)?;
+
+        let stake: Stake = Stake {
+            index,
+            receipt_block: receipt_id.block,
+            receipt_tx: receipt_id.tx,
+            staked_at: self.height(),
+            asset_block: asset.block,
+            asset_tx: asset.tx,
+            until,
+        };
+        self.set_stake(&stake)?;
+        self.record_staking_event(STAKING_STAKED, index)?;
+
+        response.alkanes.0.push(receipt_transfer);
+        Ok(response)
+    }
+
+    /// The stake receipt redeems the orbital and ends the stake
+    pub(crate) fn unstake(&self, index: u128) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::default();
+
+        let stake: Stake = self.get_stake_of(index)?
+            .ok_or_else(|| revert!(ERR_NOT_FOUND, "Orbital {} is not staked", index))?;
+        let receipt: AlkaneId = stake.receipt();
+        self.only_receipt_holder(&receipt)?;
+
+        // The stake receipt is consumed, everything else goes back to the caller
+        for transfer in context.incoming_alkanes.0.iter() {
+            if transfer.id != receipt {
+                response.alkanes.0.push(*transfer);
+            }
+        }
+
+        response.alkanes.0.push(AlkaneTransfer { id: stake.asset(), value: 1u128 });
+
+        self.stake_pointer(index).set(Arc::new(vec![]));
+        self.record_staking_event(STAKING_UNSTAKED, index)?;
+        Ok(response)
+    }
+
+    /// Returns the stake of an index as JSON
+    pub(crate) fn get_stake(&self, index: u128) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let stake: Stake = self.get_stake_of(index)?
+            .ok_or_else(|| revert!(ERR_NOT_FOUND, "Orbital {} is not staked", index))?;
+        response.data = serde_json::to_vec(&stake)
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to serialize stake"))?;
+        Ok(response)
+    }
+
+    /// Returns the stakes among a page of orbital indices as JSON, indices that are not
+    /// staked are skipped
+    pub(crate) fn get_stakes(&self, cursor: u128, limit: u128) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let page: Page<Stake> = self.page(cursor, limit, self.instances_count(), |index| self.get_stake_of(index))?;
+        response.data = serde_json::to_vec(&page)
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to serialize stakes"))?;
+        Ok(response)
+    }
+
+    pub(crate) fn get_stake_of(&self, index: u128) -> Result<Option<Stake>> {
+        let stored: Arc<Vec<u8>> = self.stake_pointer(index).get();
+        if stored.is_empty() {
+            return Ok(None);
+        }
+
+        let stake: Stake = bincode::deserialize(&stored)
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to deserialize stake"))?;
+        Ok(Some(stake))
+    }
+
+    /// Token a stake of `index` locks and the last block it counts for: the orbital for
+    /// good, or a presented renter receipt until its rental ends
+    fn stakeable_asset(&self, index: u128, incoming: &AlkaneTransferParcel) -> Result<(AlkaneId, u64)> {
+        let instance_id: AlkaneId = self.lookup_instance(index)?;
+        if incoming.0.iter().any(|transfer| transfer.id == instance_id && transfer.value > 0) {
+            return Ok((instance_id, u64::MAX));
+        }
+
+        let height: u64 = self.height();
+        for transfer in incoming.0.iter().filter(|transfer| transfer.value > 0) {
+            if let Some((rented, RECEIPT_KIND_RENTER)) = self.rental_user_of(&transfer.id, height)? {
+                if rented == index {
+                    return Ok((transfer.id, self.rented_until(index)?));
+                }
+            }
+        }
+
+        Err(revert!(ERR_UNAUTHORIZED, "Caller does not hold orbital {} or a running rental of it", index))
+    }
+
+    fn stake_pointer(&self, index: u128) -> StoragePointer {
+        StoragePointer::from_keyword("/stakes/").select(&index.to_le_bytes().to_vec())
+    }
+
+    fn set_stake(&self, stake: &Stake) -> Result<()> {
+        let serialized: Vec<u8> = bincode::serialize(stake)
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to serialize stake"))?;
+        self.stake_pointer(stake.index).set(Arc::new(serialized));
+        Ok(())
+    }
+}
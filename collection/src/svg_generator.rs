@@ -1,118 +1,261 @@
-use serde_json::{Value, json};
-use anyhow::{anyhow, Result};
+use serde::{Serialize, Deserialize};
+use serde_json::Value;
+use anyhow::Result;
+use orbital_support::{revert, errors::{codes::*, Revert}};
 
 const ENCODED_TRAITS_JSON: &str = include_str!("encoded_traits.json");
 const SVG_TEMPLATES_JSON: &str = include_str!("svg-templates.json");
 
-pub struct SvgGenerator;
+/// Edge length in pixels of the thumbnail rendering
+pub const THUMBNAIL_SIZE: u32 = 64;
 
-impl SvgGenerator {
-  fn get_encoded_traits() -> Value {
+/// Overlay drawn over preview renderings, in the 200x200 viewBox of the art
+const WATERMARK: &str = "<g opacity=\"0.55\"><rect x=\"0\" y=\"84\" width=\"200\" height=\"32\" fill=\"#000000\"/><text x=\"100\" y=\"107\" font-family=\"monospace\" font-size=\"20\" fill=\"#ffffff\" text-anchor=\"middle\">PREVIEW</text></g>";
+
+/// Settings deciding what `GetData` serves, compared by `DryRunRender`
+#[derive(Clone, Copy, Default, Debug, Serialize, Deserialize)]
+pub struct RenderConfig {
+  /// Watermark the art for the public
+  pub preview: bool,
+  /// Trait table the art is drawn from, 0 for the built in one
+  pub traits_version: u128,
+}
+
+/// Bits each layer takes in an encoded trait entry, lowest layer first: background, outer
+/// eyes, nose, mouth, eyes, head accessory, body accessory, species
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TraitFormat {
+  pub species_bits: u32,
+  pub body_acc_bits: u32,
+  pub head_acc_bits: u32,
+  pub eyes_bits: u32,
+  pub mouth_bits: u32,
+  pub nose_bits: u32,
+  pub outer_eyes_bits: u32,
+  pub bg_bits: u32,
+}
+
+/// Trait names per layer, addressed by the codes of an encoded entry
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TraitIndices {
+  pub species: Vec<String>,
+  pub body_accessories: Vec<String>,
+  pub head_accessories: Vec<String>,
+  pub eyes: Vec<String>,
+  pub mouth: Vec<String>,
+  pub nose: Vec<String>,
+  pub outer_eyes: Vec<String>,
+  pub background: Vec<String>,
+}
+
+/// Trait table the art is drawn from: one encoded entry per orbital, in draw order, as
+/// decimal strings of the packed layer codes
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TraitTable {
+  pub format: TraitFormat,
+  pub indices: TraitIndices,
+  pub traits: Vec<String>,
+}
+
+impl TraitTable {
+  /// Table compiled into the contract
+  pub fn builtin() -> TraitTable {
     serde_json::from_str(ENCODED_TRAITS_JSON).unwrap()
   }
 
+  /// Table from its JSON document, in the layout of `encoded_traits.json`
+  pub fn parse(json: &[u8]) -> Result<TraitTable> {
+    serde_json::from_slice(json)
+      .map_err(|e| revert!(ERR_INVALID_INPUT, "Invalid trait table: {}", e))
+  }
+
+  /// Layers in the order their codes are packed, lowest bits first: the template group
+  /// drawing them, the bits they take and their names
+  fn layers(&self) -> [(&'static str, u32, &[String]); 8] {
+    let (format, indices) = (&self.format, &self.indices);
+    [
+      ("background", format.bg_bits, indices.background.as_slice()),
+      ("outerEyes", format.outer_eyes_bits, indices.outer_eyes.as_slice()),
+      ("nose", format.nose_bits, indices.nose.as_slice()),
+      ("mouth", format.mouth_bits, indices.mouth.as_slice()),
+      ("eyes", format.eyes_bits, indices.eyes.as_slice()),
+      ("headAccessories", format.head_acc_bits, indices.head_accessories.as_slice()),
+      ("bodyAccessories", format.body_acc_bits, indices.body_accessories.as_slice()),
+      ("species", format.species_bits, indices.species.as_slice()),
+    ]
+  }
+
+  /// Packed layer codes of an entry
+  fn entry(&self, position: usize) -> Result<u128> {
+    self.traits.get(position)
+      .ok_or_else(|| revert!(ERR_INVALID_INPUT, "Invalid trait index"))?
+      .parse::<u128>()
+      .map_err(|e| Revert::new(ERR_INVALID_INPUT, format!("Failed to parse encoded trait: {}", e))
+        .field("traits").actual(position as u128).into())
+  }
+
+  /// Layer codes of an entry, in packing order
+  fn entry_codes(&self, position: usize) -> Result<[u128; 8]> {
+    let encoded: u128 = self.entry(position)?;
+
+    let mut codes: [u128; 8] = [0; 8];
+    let mut shift: u32 = 0;
+    for (code, (_, bits, _)) in codes.iter_mut().zip(self.layers()) {
+      *code = encoded.checked_shr(shift).unwrap_or(0) & 1u128.checked_shl(bits).map_or(u128::MAX, |bound| bound - 1);
+      shift = shift.saturating_add(bits);
+    }
+
+    Ok(codes)
+  }
+
+  /// Refuse a table that would not render: a layout wider than an entry, a layer with
+  /// more names than its bits address or a name without a template, and entries that do
+  /// not parse or address a name past the end of their layer. The first violation is
+  /// reported with the offending field, the bound and the value found.
+  pub fn validate(&self) -> Result<()> {
+    let templates: Value = SvgGenerator::get_svg_templates();
+    let layers = self.layers();
+
+    let width: u32 = layers.iter().fold(0u32, |width, (_, bits, _)| width.saturating_add(*bits));
+    if width > u128::BITS {
+      return Err(Revert::new(ERR_INVALID_INPUT, "Trait layout is wider than an entry")
+        .field("format").expected(u128::BITS as u128).actual(width as u128).into());
+    }
+
+    for (layer, bits, names) in layers {
+      let addressable: u128 = 1u128.checked_shl(bits).unwrap_or(u128::MAX);
+      if names.is_empty() || names.len() as u128 > addressable {
+        return Err(Revert::new(ERR_INVALID_INPUT, format!("Layer {} holds 1 to {} names", layer, addressable))
+          .field(layer).expected(addressable).actual(names.len() as u128).into());
+      }
+
+      for (code, name) in names.iter().enumerate() {
+        if !Self::has_template(&templates, layer, name) {
+          return Err(Revert::new(ERR_INVALID_INPUT, format!("No {} template for {}", layer, name))
+            .field(layer).actual(code as u128).into());
+        }
+      }
+    }
+
+    for position in 0..self.traits.len() {
+      for (code, (layer, _, names)) in self.entry_codes(position)?.iter().zip(layers) {
+        if *code >= names.len() as u128 {
+          return Err(Revert::new(ERR_INVALID_INPUT, format!("Entry {} addresses a {} past the layer", position, layer))
+            .field(layer).expected(names.len() as u128 - 1).actual(*code).into());
+        }
+      }
+    }
+
+    Ok(())
+  }
+
+  /// Whether the templates can draw a layer name, accessories may be "none"
+  fn has_template(templates: &Value, layer: &str, name: &str) -> bool {
+    match layer {
+      "headAccessories" | "bodyAccessories" if name == "none" => true,
+      "species" => ["body", "ears", "head"].iter().all(|part| templates[layer][name][*part].is_string()),
+      _ => templates[layer][name].is_string(),
+    }
+  }
+}
+
+/// Renders the orbitals of one trait table
+pub struct SvgGenerator {
+  traits: TraitTable,
+}
+
+impl SvgGenerator {
+  pub fn new(traits: TraitTable) -> Self {
+    SvgGenerator { traits }
+  }
+
   fn get_svg_templates() -> Value {
     serde_json::from_str(SVG_TEMPLATES_JSON).unwrap()
   }
 
-  pub fn decode_traits(index: u128) -> Result<(String, String, String, String, String, String, String, String)> {
-    let encoded_traits = Self::get_encoded_traits();
-    let traits_array = encoded_traits["traits"].as_array()
-      .ok_or_else(|| anyhow!("Invalid traits array"))?;
-    let encoded_trait = traits_array.get(index as usize)
-      .ok_or_else(|| anyhow!("Invalid trait index"))?
-      .as_str()
-      .ok_or_else(|| anyhow!("Invalid trait format"))?;
-
-    let encoded = encoded_trait.parse::<u128>()
-      .map_err(|e| anyhow!("Failed to parse encoded trait: {}", e))?;
-
-    let format = &encoded_traits["format"];
-    let bg_bits = format["bgBits"].as_u64().unwrap() as u32;
-    let outer_eyes_bits = format["outerEyesBits"].as_u64().unwrap() as u32;
-    let nose_bits = format["noseBits"].as_u64().unwrap() as u32;
-    let mouth_bits = format["mouthBits"].as_u64().unwrap() as u32;
-    let eyes_bits = format["eyesBits"].as_u64().unwrap() as u32;
-    let head_acc_bits = format["headAccBits"].as_u64().unwrap() as u32;
-    let body_acc_bits = format["bodyAccBits"].as_u64().unwrap() as u32;
-    let species_bits = format["speciesBits"].as_u64().unwrap() as u32;
-
-    let bg_code = (encoded & ((1u128 << bg_bits) - 1)) as usize;
-    let outer_eyes_code = ((encoded >> bg_bits) & ((1u128 << outer_eyes_bits) - 1)) as usize;
-    let nose_code = ((encoded >> (bg_bits + outer_eyes_bits)) & ((1u128 << nose_bits) - 1)) as usize;
-    let mouth_code = ((encoded >> (bg_bits + outer_eyes_bits + nose_bits)) & ((1u128 << mouth_bits) - 1)) as usize;
-    let eyes_code = ((encoded >> (bg_bits + outer_eyes_bits + nose_bits + mouth_bits)) & ((1u128 << eyes_bits) - 1)) as usize;
-    let head_acc_code = ((encoded >> (bg_bits + outer_eyes_bits + nose_bits + mouth_bits + eyes_bits)) & ((1u128 << head_acc_bits) - 1)) as usize;
-    let body_acc_code = ((encoded >> (bg_bits + outer_eyes_bits + nose_bits + mouth_bits + eyes_bits + head_acc_bits)) & ((1u128 << body_acc_bits) - 1)) as usize;
-    let species_code = ((encoded >> (bg_bits + outer_eyes_bits + nose_bits + mouth_bits + eyes_bits + head_acc_bits + body_acc_bits)) & ((1u128 << species_bits) - 1)) as usize;
-
-    let indices = &encoded_traits["indices"];
-    let species = indices["species"][species_code].as_str().unwrap().to_string();
-    let body_acc = indices["bodyAccessories"][body_acc_code].as_str().unwrap().to_string();
-    let head_acc = indices["headAccessories"][head_acc_code].as_str().unwrap().to_string();
-    let eyes = indices["eyes"][eyes_code].as_str().unwrap().to_string();
-    let mouth = indices["mouth"][mouth_code].as_str().unwrap().to_string();
-    let nose = indices["nose"][nose_code].as_str().unwrap().to_string();
-    let outer_eyes = indices["outerEyes"][outer_eyes_code].as_str().unwrap().to_string();
-    let background = indices["background"][bg_code].as_str().unwrap().to_string();
-
-    Ok((species, background, body_acc, head_acc, eyes, mouth, nose, outer_eyes))
-  }
-
-  pub fn get_attributes(index: u128) -> Result<String> {
-    let (species, background, body_acc, head_acc, eyes, mouth, _nose, _outer_eyes) = Self::decode_traits(index)?;
-
-    let attributes = json!({
-      "species": species,
-      "background": background,
-      "body": body_acc,
-      "head": head_acc,
-      "eyes": eyes,
-      "mouth": mouth,
-    });
-
-    Ok(attributes.to_string())
-  }
-
-  pub fn generate_svg(index: u128) -> Result<String> {
-    let (species, background, body_acc, head_acc, eyes, mouth, nose, outer_eyes) = Self::decode_traits(index)?;
+  /// Number of trait entries, the art an orbital can be assigned
+  pub fn trait_count(&self) -> u128 {
+    self.traits.traits.len() as u128
+  }
+
+  /// Encoded trait entry `index` as stored in the table
+  pub fn encoded_entry(&self, index: u128) -> Result<u128> {
+    self.traits.entry(index as usize)
+  }
+
+  #[allow(clippy::type_complexity)]
+  pub fn decode_traits(&self, index: u128) -> Result<(String, String, String, String, String, String, String, String)> {
+    let codes: [u128; 8] = self.traits.entry_codes(index as usize)?;
+    let layers = self.traits.layers();
+    let name = |layer: usize| -> Result<String> {
+      let (group, _, names) = layers[layer];
+      names.get(codes[layer] as usize)
+        .cloned()
+        .ok_or_else(|| revert!(ERR_INVALID_INPUT, "No {} trait with code {}", group, codes[layer]))
+    };
+
+    Ok((name(7)?, name(0)?, name(6)?, name(5)?, name(4)?, name(3)?, name(2)?, name(1)?))
+  }
+
+  pub fn generate_svg(&self, index: u128) -> Result<String> {
+    self.render_svg(index, "100%", "100%")
+  }
+
+  /// Same layers as `generate_svg` with fixed pixel dimensions for list views
+  pub fn generate_thumbnail(&self, index: u128) -> Result<String> {
+    let size: String = format!("{}", THUMBNAIL_SIZE);
+    self.render_svg(index, &size, &size)
+  }
+
+  /// Insert the watermark as the last layer of a rendering
+  pub fn watermark(svg: &str) -> String {
+    match svg.rfind("</svg>") {
+      Some(end) => format!("{}{}\n{}", &svg[..end], WATERMARK, &svg[end..]),
+      None => svg.to_string(),
+    }
+  }
+
+  fn render_svg(&self, index: u128, width: &str, height: &str) -> Result<String> {
+    let (species, background, body_acc, head_acc, eyes, mouth, nose, outer_eyes) = self.decode_traits(index)?;
 
     let svg_templates = Self::get_svg_templates();
 
-    let mut svg = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<svg width=\"100%\" height=\"100%\" viewBox=\"0 0 200 200\" xmlns=\"http://www.w3.org/2000/svg\">\n");
+    let mut svg = format!("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<svg width=\"{}\" height=\"{}\" viewBox=\"0 0 200 200\" xmlns=\"http://www.w3.org/2000/svg\">\n", width, height);
     
     svg.push_str(svg_templates["background"][&background].as_str().unwrap());
-    svg.push_str("\n");
+    svg.push('\n');
     
     let species_template = &svg_templates["species"][&species];
     svg.push_str(species_template["body"].as_str().unwrap());
-    svg.push_str("\n");
+    svg.push('\n');
     svg.push_str(svg_templates["nipples"]["normal"].as_str().unwrap());
-    svg.push_str("\n");
+    svg.push('\n');
     
     if body_acc != "none" {
       svg.push_str(svg_templates["bodyAccessories"][&body_acc].as_str().unwrap());
-      svg.push_str("\n");
+      svg.push('\n');
     }
     
     svg.push_str(species_template["ears"].as_str().unwrap());
-    svg.push_str("\n");
+    svg.push('\n');
     svg.push_str(species_template["head"].as_str().unwrap());
-    svg.push_str("\n");
+    svg.push('\n');
     
     if head_acc != "none" {
       svg.push_str(svg_templates["headAccessories"][&head_acc].as_str().unwrap());
-      svg.push_str("\n");
+      svg.push('\n');
     }
     
     svg.push_str(svg_templates["nose"][&nose].as_str().unwrap());
-    svg.push_str("\n");
+    svg.push('\n');
     svg.push_str(svg_templates["outerEyes"][&outer_eyes].as_str().unwrap());
-    svg.push_str("\n");
+    svg.push('\n');
     svg.push_str(svg_templates["eyes"][&eyes].as_str().unwrap());
-    svg.push_str("\n");
+    svg.push('\n');
     svg.push_str(svg_templates["mouth"][&mouth].as_str().unwrap());
-    svg.push_str("\n");
+    svg.push('\n');
     
     svg.push_str("</svg>");
 
@@ -0,0 +1,139 @@
+use metashrew_support::index_pointer::KeyValuePointer;
+use alkanes_runtime::runtime::AlkaneResponder;
+use alkanes_support::{cellpack::Cellpack, id::AlkaneId, parcel::AlkaneTransferParcel, response::CallResponse};
+use serde::Serialize;
+use anyhow::Result;
+use orbital_support::{revert, errors::{codes::*, Revert}};
+use std::sync::Arc;
+
+use crate::{Collection, Stage, decode_alkane_id, encode_alkane_id};
+use crate::opcodes::StoragePointer;
+
+/// Opcode scoring alkanes implement: inputs are the identity as two little endian u128 halves,
+/// the response data is the score as a little endian u128
+const SCORER_GET_SCORE: u128 = 50;
+
+#[derive(Serialize)]
+struct SybilGate {
+    /// Zero when no scorer is configured
+    scorer_block: u128,
+    scorer_tx: u128,
+    threshold: u128,
+    stages: Vec<u128>,
+}
+
+impl Collection {
+    /// Configure the scoring alkane and the minimum score, a zero scorer disables the gate
+    pub(crate) fn set_sybil_gate(&self, block: u128, tx: u128, threshold: u128) -> Result<CallResponse> {
+        self.only_owner()?;
+        let context: alkanes_support::context::Context = self.context()?;
+        let response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        if block == 0 && tx == 0 {
+            self.sybil_scorer_pointer().set(Arc::new(vec![]));
+        } else {
+            self.sybil_scorer_pointer().set(Arc::new(encode_alkane_id(&AlkaneId { block, tx })));
+        }
+
+        self.sybil_threshold_pointer().set_value::<u128>(threshold);
+        Ok(response)
+    }
+
+    /// Designate whether mints in a stage must pass the scoring gate
+    pub(crate) fn set_stage_sybil_gate(&self, stage_id: u128, enabled: u128) -> Result<CallResponse> {
+        self.only_owner()?;
+        let context: alkanes_support::context::Context = self.context()?;
+        let response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        self.get_mint_stage(stage_id)?;
+        self.sybil_stage_pointer(stage_id).set_value::<u8>(if enabled != 0 { 1 } else { 0 });
+        Ok(response)
+    }
+
+    /// Returns the scorer, threshold and gated stages as JSON
+    pub(crate) fn get_sybil_gate(&self) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let stages: Vec<Stage> = self.get_mint_stages()?;
+        let scorer: AlkaneId = self.sybil_scorer()?.unwrap_or_default();
+        let gate: SybilGate = SybilGate {
+            scorer_block: scorer.block,
+            scorer_tx: scorer.tx,
+            threshold: self.sybil_threshold_pointer().get_value::<u128>(),
+            stages: stages.iter()
+                .map(|stage| stage.id)
+                .filter(|stage_id| self.sybil_stage_pointer(*stage_id).get_value::<u8>() == 1)
+                .collect(),
+        };
+
+        response.data = serde_json::to_vec(&gate)
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to serialize sybil gate"))?;
+        Ok(response)
+    }
+
+    /// Pre-mint hook: in gated stages the minter identity must score at least the threshold
+    /// and receive the orbital
+    pub(crate) fn observe_sybil_score(&self, stage_id: u128) -> Result<()> {
+        if self.sybil_stage_pointer(stage_id).get_value::<u8>() != 1 {
+            return Ok(());
+        }
+
+        let scorer: AlkaneId = match self.sybil_scorer()? {
+            Some(scorer) => scorer,
+            None => return Ok(()),
+        };
+
+        // The score is of the identity, so the orbital must reach it
+        self.observe_delivered_to_minter()?;
+        let identity: [u8; 32] = self.minter_identity()?;
+        let cellpack: Cellpack = Cellpack {
+            target: scorer,
+            inputs: vec![
+                SCORER_GET_SCORE,
+                u128::from_le_bytes(identity[..16].try_into().unwrap()),
+                u128::from_le_bytes(identity[16..].try_into().unwrap()),
+            ],
+        };
+
+        let response: CallResponse = self.staticcall(&cellpack, &AlkaneTransferParcel::default(), self.fuel())?;
+        if response.data.len() < 16 {
+            return Err(revert!(ERR_EXTERNAL_CALL, "Invalid score response"));
+        }
+
+        let score: u128 = u128::from_le_bytes(response.data[..16].try_into().unwrap());
+        let threshold: u128 = self.sybil_threshold_pointer().get_value::<u128>();
+        if score < threshold {
+            return Err(Revert::new(ERR_UNAUTHORIZED, format!("Minter score {} is below the required {}", score, threshold))
+                .field("score").expected(threshold).actual(score).into());
+        }
+
+        Ok(())
+    }
+
+    /// Scorer and threshold of the gate
+    pub(crate) fn sybil_settings(&self) -> Result<(Option<AlkaneId>, u128)> {
+        Ok((self.sybil_scorer()?, self.sybil_threshold_pointer().get_value::<u128>()))
+    }
+
+    fn sybil_scorer(&self) -> Result<Option<AlkaneId>> {
+        let stored: Arc<Vec<u8>> = self.sybil_scorer_pointer().get();
+        if stored.is_empty() {
+            return Ok(None);
+        }
+
+        Ok(Some(decode_alkane_id(&stored)?))
+    }
+
+    fn sybil_scorer_pointer(&self) -> StoragePointer {
+        StoragePointer::from_keyword("/sybil/scorer")
+    }
+
+    fn sybil_threshold_pointer(&self) -> StoragePointer {
+        StoragePointer::from_keyword("/sybil/threshold")
+    }
+
+    fn sybil_stage_pointer(&self, stage_id: u128) -> StoragePointer {
+        StoragePointer::from_keyword("/sybil/stages/").select(&stage_id.to_le_bytes().to_vec())
+    }
+}
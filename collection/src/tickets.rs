@@ -0,0 +1,70 @@
+//! Claims the collection holds for someone absent, such as queued mints, are bound to an
+//! outpoint of the transaction that paid for them. Spending that outpoint in a later
+//! transaction proves control over it, which the identity of an output script cannot:
+//! anyone can put any script on an output of their own transaction.
+
+use bitcoin::{hashes::Hash, Transaction};
+use serde::{Serialize, Deserialize};
+use anyhow::Result;
+use orbital_support::{revert, errors::codes::*};
+
+use crate::Collection;
+use crate::identity::identity_from_halves;
+
+/// Outpoint created by the transaction a claim was recorded in
+#[derive(Clone, Copy, Default, Debug, PartialEq, Serialize, Deserialize)]
+pub(crate) struct Ticket {
+    pub txid: [u8; 32],
+    pub vout: u32,
+}
+
+impl Ticket {
+    /// Ticket from its txid, given as two little endian u128 halves, and output index
+    pub(crate) fn from_halves(txid_low: u128, txid_high: u128, vout: u128) -> Result<Ticket> {
+        Ok(Ticket {
+            txid: identity_from_halves(txid_low, txid_high),
+            vout: u32::try_from(vout).map_err(|_| revert!(ERR_INVALID_INPUT, "Ticket output out of range"))?,
+        })
+    }
+
+    pub(crate) fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes: Vec<u8> = self.txid.to_vec();
+        bytes.extend_from_slice(&self.vout.to_le_bytes());
+        bytes
+    }
+}
+
+impl Collection {
+    /// Ticket for the attributed output of the current transaction, the output the minted
+    /// orbital lands on and whose script the minter identity is taken from
+    pub(crate) fn attributed_ticket(&self) -> Result<Ticket> {
+        let (vout, _, _) = self.attributed_output()?;
+        let tx: Transaction = self.decode_transaction()?;
+
+        Ok(Ticket {
+            txid: tx.compute_txid().to_byte_array(),
+            vout,
+        })
+    }
+
+    /// Tickets the current transaction spends, one per input
+    pub(crate) fn spent_tickets(&self) -> Result<Vec<Ticket>> {
+        let tx: Transaction = self.decode_transaction()?;
+
+        Ok(tx.input.iter()
+            .map(|input| Ticket {
+                txid: input.previous_output.txid.to_byte_array(),
+                vout: input.previous_output.vout,
+            })
+            .collect())
+    }
+
+    /// Require the current transaction to spend the ticket outpoint
+    pub(crate) fn observe_ticket_spent(&self, ticket: &Ticket) -> Result<()> {
+        if !self.spent_tickets()?.contains(ticket) {
+            return Err(revert!(ERR_UNAUTHORIZED, "Transaction does not spend the ticket outpoint"));
+        }
+
+        Ok(())
+    }
+}
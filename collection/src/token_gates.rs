@@ -0,0 +1,71 @@
+//! A gated stage admits callers presenting a minimum balance of some alkane, such as the
+//! orbitals of another collection. The gate token is only shown, never taken: it comes back
+//! with the change of the mint. A gate on an orbital of this collection also admits the
+//! rental receipt holding its use, the renter receipt while rented and the claim receipt
+//! otherwise.
+
+use alkanes_runtime::runtime::AlkaneResponder;
+use alkanes_support::{id::AlkaneId, parcel::AlkaneTransferParcel, response::CallResponse};
+use anyhow::Result;
+use orbital_support::{revert, errors::{codes::*, Revert}};
+
+use crate::{Collection, Stage};
+
+impl Collection {
+    /// Require `min_balance` of an alkane among the incoming alkanes to mint in a stage, a
+    /// zero ID removes the gate
+    pub(crate) fn set_stage_token_gate(&self, stage_id: u128, block: u128, tx: u128, min_balance: u128) -> Result<CallResponse> {
+        self.only_owner()?;
+        let context: alkanes_support::context::Context = self.context()?;
+        let response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let gated: bool = block != 0 || tx != 0;
+        if gated && min_balance == 0 {
+            return Err(revert!(ERR_INVALID_INPUT, "Token gates need a positive minimum balance"));
+        }
+
+        if gated && (AlkaneId { block, tx }) == context.myself {
+            return Err(revert!(ERR_INVALID_INPUT, "The collection auth token cannot gate a stage"));
+        }
+
+        self.update_mint_stage(stage_id, |stage| {
+            stage.gate_block = block;
+            stage.gate_tx = tx;
+            stage.gate_min_balance = if gated { min_balance } else { 0 };
+            Ok(())
+        })?;
+        Ok(response)
+    }
+
+    /// Require the gate token of a stage among the incoming alkanes. When the gate token is
+    /// also the currency the mint pays in, the balance must cover the gate on top of the price.
+    pub(crate) fn observe_token_gate(&self, stage: &Stage, incoming: &AlkaneTransferParcel, payment: Option<(AlkaneId, u128)>) -> Result<()> {
+        let gate: AlkaneId = match stage.token_gate() {
+            Some(gate) => gate,
+            None => return Ok(()),
+        };
+
+        let height: u64 = self.height();
+        let gated_index: Option<u128> = self.index_of_instance(&gate);
+        let mut presented: u128 = 0;
+        for transfer in incoming.0.iter().filter(|transfer| transfer.value > 0) {
+            if transfer.id == gate {
+                presented = presented.saturating_add(transfer.value);
+            } else if gated_index.is_some() && self.rental_user_of(&transfer.id, height)?.map(|(index, _)| index) == gated_index {
+                presented = presented.saturating_add(1);
+            }
+        }
+
+        let required: u128 = match payment {
+            Some((token, price)) if token == gate => stage.gate_min_balance.saturating_add(price),
+            _ => stage.gate_min_balance,
+        };
+
+        if presented < required {
+            return Err(Revert::new(ERR_UNAUTHORIZED, format!("Stage {} is gated by {}:{}", stage.id, gate.block, gate.tx))
+                .field("gate_balance").expected(required).actual(presented).into());
+        }
+
+        Ok(())
+    }
+}
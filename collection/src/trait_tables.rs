@@ -0,0 +1,207 @@
+//! The trait table the art is drawn from can be replaced until the metadata is frozen, to
+//! fix trait names or rebalance the entries before reveal without a new build. Tables are
+//! uploaded in chunks of the `compress::pack` encoding of their JSON document, in the layout
+//! of `encoded_traits.json`, under the next version; activating a version seals it. Sealed
+//! versions stay stored, so activating an earlier one, or version 0 for the built in table,
+//! rolls a change back.
+
+use metashrew_support::index_pointer::KeyValuePointer;
+use alkanes_runtime::runtime::AlkaneResponder;
+use alkanes_support::response::CallResponse;
+use serde::Serialize;
+use anyhow::Result;
+use orbital_support::{revert, compress, errors::{codes::*, Revert}};
+use std::sync::Arc;
+
+use crate::{Collection, changelog, counters};
+use crate::opcodes::StoragePointer;
+use crate::svg_generator::{SvgGenerator, TraitTable};
+use crate::unlockables::read_words;
+
+/// Inputs ahead of the chunk bytes of `UploadTraitTableChunk`: opcode, version, chunk, length
+const TRAIT_CHUNK_HEADER: usize = 4;
+
+/// Largest chunk one upload carries, in bytes
+const MAX_TRAIT_CHUNK_BYTES: usize = 1024;
+
+/// Chunks a packed table spans at most
+const MAX_TRAIT_CHUNKS: u128 = 512;
+
+/// Trait table state as returned by `GetTraitTables`
+#[derive(Serialize)]
+struct TraitTablesView {
+    /// Version the art is drawn from, 0 for the built in table
+    active: u128,
+    /// Versions sealed so far
+    sealed: u128,
+    /// Chunks uploaded under the next version
+    pending_chunks: u128,
+    frozen: bool,
+}
+
+impl Collection {
+    /// Upload chunk `chunk` of the next table version, `length` bytes packed into the
+    /// words after the fixed inputs. Chunks are uploaded in order; re-uploading one
+    /// replaces it, and uploading chunk 0 again starts the table over.
+    pub(crate) fn upload_trait_table_chunk(&self, version: u128, chunk: u128, length: u128) -> Result<CallResponse> {
+        self.only_owner()?;
+        let context: alkanes_support::context::Context = self.context()?;
+        let response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        self.observe_traits_mutable()?;
+        let pending: u128 = self.sealed_traits_versions() + 1;
+        if version != pending {
+            return Err(Revert::new(ERR_INVALID_STATE, "Chunks are uploaded under the next table version")
+                .field("version").expected(pending).actual(version).into());
+        }
+
+        if chunk >= MAX_TRAIT_CHUNKS {
+            return Err(Revert::new(ERR_LIMIT_EXCEEDED, format!("Trait tables hold at most {} chunks", MAX_TRAIT_CHUNKS))
+                .field("chunk").expected(MAX_TRAIT_CHUNKS - 1).actual(chunk).into());
+        }
+
+        let length: usize = usize::try_from(length).unwrap_or(usize::MAX);
+        if length == 0 || length > MAX_TRAIT_CHUNK_BYTES {
+            return Err(Revert::new(ERR_INVALID_INPUT, format!("Chunks hold 1 to {} bytes", MAX_TRAIT_CHUNK_BYTES))
+                .field("length").expected(MAX_TRAIT_CHUNK_BYTES as u128).actual(length as u128).into());
+        }
+
+        let words: usize = length.div_ceil(16);
+        if context.inputs.len() < TRAIT_CHUNK_HEADER + words {
+            return Err(revert!(ERR_INVALID_INPUT, "Trait table chunk is truncated"));
+        }
+
+        let mut bytes: Vec<u8> = read_words(&context.inputs, TRAIT_CHUNK_HEADER, words);
+        bytes.truncate(length);
+
+        let mut count: StoragePointer = self.trait_chunk_count_pointer(version);
+        if chunk == 0 {
+            count.set_value::<u128>(0);
+        }
+
+        if chunk > count.get_value::<u128>() {
+            return Err(Revert::new(ERR_INVALID_INPUT, "Chunks are uploaded in order")
+                .field("chunk").expected(count.get_value::<u128>()).actual(chunk).into());
+        }
+
+        self.trait_chunk_pointer(version, chunk).set(Arc::new(bytes));
+        if chunk == count.get_value::<u128>() {
+            count.set_value::<u128>(chunk + 1);
+        }
+
+        Ok(response)
+    }
+
+    /// Draw the art from a table version. Activating the next version seals its chunks
+    /// once the table passes `TraitTable::validate` and holds an entry for every orbital
+    /// of the supply.
+    pub(crate) fn activate_trait_table(&self, version: u128) -> Result<CallResponse> {
+        self.only_owner()?;
+        let context: alkanes_support::context::Context = self.context()?;
+        let response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        self.observe_traits_mutable()?;
+        let sealed: u128 = self.sealed_traits_versions();
+        if version > sealed + 1 {
+            return Err(Revert::new(ERR_NOT_FOUND, "No trait table under this version")
+                .field("version").expected(sealed + 1).actual(version).into());
+        }
+
+        let table: TraitTable = self.trait_table_at(version)?;
+        table.validate()?;
+        let entries: u128 = table.traits.len() as u128;
+        if entries < self.max_supply() {
+            return Err(Revert::new(ERR_INVALID_INPUT, "Trait tables hold one entry per orbital of the supply")
+                .field("traits").expected(self.max_supply()).actual(entries).into());
+        }
+
+        if version == sealed + 1 {
+            counters::increment(&mut self.sealed_traits_pointer(), 1, u128::MAX, "trait_tables")?;
+        }
+
+        self.active_traits_pointer().set_value::<u128>(version);
+        self.record_metadata_change(changelog::CHANGE_TRAIT_TABLE, None, version)?;
+        Ok(response)
+    }
+
+    /// Returns the active table version, how many are sealed and the pending upload as JSON
+    pub(crate) fn get_trait_tables(&self) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let sealed: u128 = self.sealed_traits_versions();
+        let view: TraitTablesView = TraitTablesView {
+            active: self.traits_version(),
+            sealed,
+            pending_chunks: self.trait_chunk_count_pointer(sealed + 1).get_value::<u128>(),
+            frozen: self.is_metadata_frozen(),
+        };
+
+        response.data = serde_json::to_vec(&view)
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to serialize trait tables"))?;
+        Ok(response)
+    }
+
+    /// Table version the art is drawn from, 0 for the built in table
+    pub(crate) fn traits_version(&self) -> u128 {
+        self.active_traits_pointer().get_value::<u128>()
+    }
+
+    /// Generator drawing from the active table
+    pub(crate) fn svg_generator(&self) -> Result<SvgGenerator> {
+        self.svg_generator_at(self.traits_version())
+    }
+
+    /// Generator drawing from a table version
+    pub(crate) fn svg_generator_at(&self, version: u128) -> Result<SvgGenerator> {
+        Ok(SvgGenerator::new(self.trait_table_at(version)?))
+    }
+
+    fn trait_table_at(&self, version: u128) -> Result<TraitTable> {
+        if version == 0 {
+            return Ok(TraitTable::builtin());
+        }
+
+        let chunks: u128 = self.trait_chunk_count_pointer(version).get_value::<u128>();
+        if chunks == 0 {
+            return Err(revert!(ERR_NOT_FOUND, "Trait table {} has no chunks", version));
+        }
+
+        let packed: Vec<u8> = (0..chunks)
+            .flat_map(|chunk| self.trait_chunk_pointer(version, chunk).get().as_ref().clone())
+            .collect();
+        let json: Vec<u8> = compress::unpack(&packed)?;
+        TraitTable::parse(&json)
+    }
+
+    fn observe_traits_mutable(&self) -> Result<()> {
+        if self.is_metadata_frozen() {
+            return Err(revert!(ERR_INVALID_STATE, "Trait tables cannot change once the metadata is frozen"));
+        }
+
+        Ok(())
+    }
+
+    fn sealed_traits_versions(&self) -> u128 {
+        self.sealed_traits_pointer().get_value::<u128>()
+    }
+
+    fn active_traits_pointer(&self) -> StoragePointer {
+        StoragePointer::from_keyword("/traits/active")
+    }
+
+    fn sealed_traits_pointer(&self) -> StoragePointer {
+        StoragePointer::from_keyword("/traits/sealed")
+    }
+
+    fn trait_chunk_count_pointer(&self, version: u128) -> StoragePointer {
+        StoragePointer::from_keyword("/traits/chunk-count/").select(&version.to_le_bytes().to_vec())
+    }
+
+    fn trait_chunk_pointer(&self, version: u128, chunk: u128) -> StoragePointer {
+        StoragePointer::from_keyword("/traits/chunks/")
+            .select(&version.to_le_bytes().to_vec())
+            .keyword("/")
+            .select(&chunk.to_le_bytes().to_vec())
+    }
+}
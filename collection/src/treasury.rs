@@ -0,0 +1,146 @@
+use metashrew_support::index_pointer::KeyValuePointer;
+use alkanes_runtime::runtime::AlkaneResponder;
+use alkanes_support::{
+  id::AlkaneId,
+  parcel::{AlkaneTransfer, AlkaneTransferParcel},
+  response::CallResponse
+};
+use anyhow::Result;
+use orbital_support::{revert, errors::{codes::*, Revert}};
+use std::sync::Arc;
+
+use crate::{Collection, decode_alkane_id, encode_alkane_id};
+use crate::opcodes::StoragePointer;
+
+/// Mint proceeds held by the collection, accounted per payment token
+impl Collection {
+    /// Set the alkane stage prices are paid in
+    pub(crate) fn set_payment_token(&self, block: u128, tx: u128) -> Result<CallResponse> {
+        self.only_owner()?;
+        let context: alkanes_support::context::Context = self.context()?;
+        let response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        if block == 0 && tx == 0 {
+            return Err(revert!(ERR_INVALID_INPUT, "Payment token must be set"));
+        }
+
+        self.payment_token_pointer().set(Arc::new(encode_alkane_id(&AlkaneId { block, tx })));
+        Ok(response)
+    }
+
+    /// Returns the 32 byte payment token ID, empty when unset
+    pub(crate) fn get_payment_token(&self) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        response.data = self.payment_token_pointer().get().as_ref().clone();
+        Ok(response)
+    }
+
+    pub(crate) fn payment_token(&self) -> Result<Option<AlkaneId>> {
+        let stored: Arc<Vec<u8>> = self.payment_token_pointer().get();
+        if stored.is_empty() {
+            return Ok(None);
+        }
+
+        Ok(Some(decode_alkane_id(&stored)?))
+    }
+
+    /// Owner withdraws `amount` of the payment token from the treasury
+    pub(crate) fn withdraw_proceeds(&self, amount: u128) -> Result<CallResponse> {
+        let token: AlkaneId = self.payment_token()?
+            .ok_or_else(|| revert!(ERR_INVALID_STATE, "No payment token configured"))?;
+        self.withdraw_treasury_token(token.block, token.tx, amount)
+    }
+
+    /// Owner withdraws `amount` of any currency the treasury holds
+    pub(crate) fn withdraw_treasury_token(&self, block: u128, tx: u128, amount: u128) -> Result<CallResponse> {
+        self.only_owner()?;
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        if amount == 0 {
+            return Err(revert!(ERR_INVALID_INPUT, "Withdrawal amount must be positive"));
+        }
+
+        let token: AlkaneId = AlkaneId { block, tx };
+        let balance: u128 = self.treasury_balance(&token);
+        if amount > balance {
+            return Err(Revert::new(ERR_INSUFFICIENT_PAYMENT, format!("Treasury holds {}", balance))
+                .field("amount").expected(balance).actual(amount).into());
+        }
+
+        self.debit_treasury(&token, amount)?;
+        response.alkanes.0.push(AlkaneTransfer { id: token, value: amount });
+        Ok(response)
+    }
+
+    /// Returns the treasury balance in the payment token as u128
+    pub(crate) fn get_treasury_balance(&self) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let balance: u128 = match self.payment_token()? {
+            Some(token) => self.treasury_balance(&token),
+            None => 0,
+        };
+
+        response.data = balance.to_le_bytes().to_vec();
+        Ok(response)
+    }
+
+    /// Take a mint price in the payment token into the treasury, returning the change
+    pub(crate) fn collect_payment(&self, incoming: &AlkaneTransferParcel, price: u128) -> Result<Vec<AlkaneTransfer>> {
+        if price == 0 {
+            return Ok(incoming.0.clone());
+        }
+
+        let token: AlkaneId = self.payment_token()?
+            .ok_or_else(|| revert!(ERR_INVALID_STATE, "No payment token configured"))?;
+        self.collect_payment_in(incoming, &token, price)
+    }
+
+    /// Take a mint price in `token` into the treasury, returning the change
+    pub(crate) fn collect_payment_in(&self, incoming: &AlkaneTransferParcel, token: &AlkaneId, price: u128) -> Result<Vec<AlkaneTransfer>> {
+        let change: Vec<AlkaneTransfer> = self.take_payment(incoming, token, price)?;
+        self.credit_treasury(token, price)?;
+        Ok(change)
+    }
+
+    /// Returns the treasury balance in any currency as u128
+    pub(crate) fn get_treasury_token_balance(&self, block: u128, tx: u128) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        response.data = self.treasury_balance(&AlkaneId { block, tx }).to_le_bytes().to_vec();
+        Ok(response)
+    }
+
+    pub(crate) fn credit_treasury(&self, token: &AlkaneId, amount: u128) -> Result<()> {
+        let mut pointer: StoragePointer = self.treasury_pointer(token);
+        let balance: u128 = pointer.get_value::<u128>().checked_add(amount)
+            .ok_or_else(|| revert!(ERR_OVERFLOW, "treasury balance overflow"))?;
+        pointer.set_value::<u128>(balance);
+        Ok(())
+    }
+
+    pub(crate) fn debit_treasury(&self, token: &AlkaneId, amount: u128) -> Result<()> {
+        let mut pointer: StoragePointer = self.treasury_pointer(token);
+        let balance: u128 = pointer.get_value::<u128>().checked_sub(amount)
+            .ok_or_else(|| revert!(ERR_INSUFFICIENT_PAYMENT, "Treasury holds less than {}", amount))?;
+        pointer.set_value::<u128>(balance);
+        Ok(())
+    }
+
+    pub(crate) fn treasury_balance(&self, token: &AlkaneId) -> u128 {
+        self.treasury_pointer(token).get_value::<u128>()
+    }
+
+    fn payment_token_pointer(&self) -> StoragePointer {
+        StoragePointer::from_keyword("/payment-token")
+    }
+
+    fn treasury_pointer(&self, token: &AlkaneId) -> StoragePointer {
+        StoragePointer::from_keyword("/treasury/").select(&encode_alkane_id(token))
+    }
+}
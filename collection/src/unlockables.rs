@@ -0,0 +1,383 @@
+use metashrew_support::index_pointer::KeyValuePointer;
+use alkanes_runtime::runtime::AlkaneResponder;
+use alkanes_support::response::CallResponse;
+use serde::{Serialize, Deserialize};
+use anyhow::Result;
+use orbital_support::{revert, errors::{codes::*, Revert}};
+use std::sync::Arc;
+
+use crate::Collection;
+use crate::opcodes::StoragePointer;
+
+/// Inputs ahead of the payload words of `SetUnlockable`: opcode, index, payload_words, key_words
+const UNLOCKABLE_HEADER: usize = 4;
+
+/// Inputs ahead of the key words of `RotateUnlockableKey`: opcode, index, key_words
+const ROTATION_HEADER: usize = 3;
+
+/// Upper bound on ciphertext words, 16 bytes each
+const MAX_PAYLOAD_WORDS: usize = 64;
+
+/// Upper bound on key reference words, 16 bytes each
+const MAX_KEY_WORDS: usize = 4;
+
+/// Inputs ahead of the chunk words of `UploadUnlockableChunk`: opcode, index, epoch, chunk, length
+const CHUNK_HEADER: usize = 5;
+
+/// Inputs ahead of the key words of `RotateContentKey`: opcode, key_words
+const CONTENT_KEY_HEADER: usize = 2;
+
+/// Upper bound on bytes per payload chunk
+const MAX_CHUNK_BYTES: usize = MAX_PAYLOAD_WORDS * 16;
+
+/// Upper bound on chunks per orbital and epoch
+const MAX_CHUNKS: u128 = 256;
+
+/// Encrypted content attached to an orbital. Contract storage is public, so `key` is meant
+/// to be a wrapped key or locator that a key service honours once a release is recorded.
+#[derive(Clone, Default, Debug, Serialize, Deserialize)]
+struct Unlockable {
+    ciphertext: Vec<u8>,
+    key: Vec<u8>,
+    /// Bumped on every payload or key change so stale releases can be told apart
+    key_version: u64,
+    set_at: u64,
+}
+
+/// Public part of an unlockable, returned by `GetUnlockable`
+#[derive(Serialize)]
+struct UnlockableView {
+    index: u128,
+    /// Hex ciphertext
+    ciphertext: String,
+    key_version: u64,
+    set_at: u64,
+    /// Hex script hash identity the current key version was last released to
+    released_to: Option<String>,
+}
+
+/// Chunked payload state of an orbital, returned by `GetContentKeyState`
+#[derive(Serialize)]
+struct ContentKeyState {
+    index: u128,
+    /// Current epoch of the collection content key, 0 before the first key
+    current_epoch: u128,
+    /// Epoch the orbital's chunks were last uploaded under, 0 when none were
+    payload_epoch: u128,
+    chunks: u128,
+    /// Whether the payload still needs re-encrypting under the current key
+    stale: bool,
+}
+
+pub(crate) fn read_words(inputs: &[u128], from: usize, count: usize) -> Vec<u8> {
+    inputs[from..from + count].iter().flat_map(|word| word.to_le_bytes()).collect()
+}
+
+impl Collection {
+    /// Attach encrypted content to an orbital. `payload_words` u128 words of ciphertext
+    /// follow the fixed inputs, then `key_words` words of key reference.
+    /// Replacing an existing unlockable rotates its key version.
+    pub(crate) fn set_unlockable(&self, index: u128, payload_words: u128, key_words: u128) -> Result<CallResponse> {
+        self.only_owner()?;
+        let context: alkanes_support::context::Context = self.context()?;
+        let response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        self.lookup_instance(index)?;
+        let payload_words: usize = self.observe_word_count("payload_words", payload_words, MAX_PAYLOAD_WORDS)?;
+        let key_words: usize = self.observe_word_count("key_words", key_words, MAX_KEY_WORDS)?;
+        if payload_words == 0 || key_words == 0 {
+            return Err(revert!(ERR_INVALID_INPUT, "Unlockable needs a payload and a key"));
+        }
+
+        if context.inputs.len() < UNLOCKABLE_HEADER + payload_words + key_words {
+            return Err(revert!(ERR_INVALID_INPUT, "Unlockable payload is truncated"));
+        }
+
+        let previous: Option<Unlockable> = self.unlockable(index)?;
+        let unlockable: Unlockable = Unlockable {
+            ciphertext: read_words(&context.inputs, UNLOCKABLE_HEADER, payload_words),
+            key: read_words(&context.inputs, UNLOCKABLE_HEADER + payload_words, key_words),
+            key_version: previous.map(|previous| previous.key_version + 1).unwrap_or(1),
+            set_at: self.height(),
+        };
+
+        self.save_unlockable(index, &unlockable)?;
+        Ok(response)
+    }
+
+    /// Replace the key reference of an unlockable, for content re-wrapped off-chain
+    /// after a key was exposed. `key_words` words of key reference follow the fixed inputs.
+    pub(crate) fn rotate_unlockable_key(&self, index: u128, key_words: u128) -> Result<CallResponse> {
+        self.only_owner()?;
+        let context: alkanes_support::context::Context = self.context()?;
+        let response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let key_words: usize = self.observe_word_count("key_words", key_words, MAX_KEY_WORDS)?;
+        if key_words == 0 {
+            return Err(revert!(ERR_INVALID_INPUT, "Key reference must be set"));
+        }
+
+        if context.inputs.len() < ROTATION_HEADER + key_words {
+            return Err(revert!(ERR_INVALID_INPUT, "Key reference is truncated"));
+        }
+
+        let mut unlockable: Unlockable = self.unlockable(index)?
+            .ok_or_else(|| revert!(ERR_NOT_FOUND, "Orbital {} has no unlockable content", index))?;
+        unlockable.key = read_words(&context.inputs, ROTATION_HEADER, key_words);
+        unlockable.key_version += 1;
+        unlockable.set_at = self.height();
+
+        self.save_unlockable(index, &unlockable)?;
+        Ok(response)
+    }
+
+    /// Holder presents the orbital and receives the key reference back in the response
+    /// data, prefixed with the key version as u128. The release is recorded per version.
+    pub(crate) fn release_unlockable_key(&self, index: u128) -> Result<CallResponse> {
+        self.only_holder(index)?;
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let unlockable: Unlockable = self.unlockable(index)?
+            .ok_or_else(|| revert!(ERR_NOT_FOUND, "Orbital {} has no unlockable content", index))?;
+
+        let holder: [u8; 32] = self.minter_identity()?;
+        self.released_pointer(index, unlockable.key_version).set(Arc::new(holder.to_vec()));
+
+        let mut data: Vec<u8> = (unlockable.key_version as u128).to_le_bytes().to_vec();
+        data.extend_from_slice(&unlockable.key);
+        response.data = data;
+        Ok(response)
+    }
+
+    /// Returns the ciphertext and key version of an orbital's unlockable as JSON
+    pub(crate) fn get_unlockable(&self, index: u128) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let unlockable: Unlockable = self.unlockable(index)?
+            .ok_or_else(|| revert!(ERR_NOT_FOUND, "Orbital {} has no unlockable content", index))?;
+
+        let released: Arc<Vec<u8>> = self.released_pointer(index, unlockable.key_version).get();
+        let view: UnlockableView = UnlockableView {
+            index,
+            ciphertext: orbital_support::hex::encode(&unlockable.ciphertext),
+            key_version: unlockable.key_version,
+            set_at: unlockable.set_at,
+            released_to: if released.is_empty() { None } else { Some(orbital_support::hex::encode(&released)) },
+        };
+
+        response.data = serde_json::to_vec(&view)
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to serialize unlockable"))?;
+        Ok(response)
+    }
+
+    /// Start a new epoch of the collection content key. `key_words` words of key reference
+    /// follow the fixed inputs. Payloads uploaded under earlier epochs are reported stale
+    /// until re-encrypted and uploaded again under the new one.
+    pub(crate) fn rotate_content_key(&self, key_words: u128) -> Result<CallResponse> {
+        self.only_owner()?;
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let key_words: usize = self.observe_word_count("key_words", key_words, MAX_KEY_WORDS)?;
+        if key_words == 0 {
+            return Err(revert!(ERR_INVALID_INPUT, "Key reference must be set"));
+        }
+
+        if context.inputs.len() < CONTENT_KEY_HEADER + key_words {
+            return Err(revert!(ERR_INVALID_INPUT, "Key reference is truncated"));
+        }
+
+        let epoch: u128 = self.content_epoch() + 1;
+        self.content_key_pointer(epoch).set(Arc::new(read_words(&context.inputs, CONTENT_KEY_HEADER, key_words)));
+        self.content_epoch_pointer().set_value::<u128>(epoch);
+
+        response.data = epoch.to_le_bytes().to_vec();
+        Ok(response)
+    }
+
+    /// Upload one chunk of an orbital's payload encrypted under the current content key.
+    /// `length` bytes follow the fixed inputs packed into little endian u128 words.
+    /// Uploading under a new epoch starts the orbital's chunk list over.
+    pub(crate) fn upload_unlockable_chunk(&self, index: u128, epoch: u128, chunk: u128, length: u128) -> Result<CallResponse> {
+        self.only_owner()?;
+        let context: alkanes_support::context::Context = self.context()?;
+        let response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        self.lookup_instance(index)?;
+        let current: u128 = self.content_epoch();
+        if epoch == 0 || epoch != current {
+            return Err(Revert::new(ERR_INVALID_STATE, "Chunks are uploaded under the current content key")
+                .field("epoch").expected(current).actual(epoch).into());
+        }
+
+        if chunk >= MAX_CHUNKS {
+            return Err(Revert::new(ERR_LIMIT_EXCEEDED, format!("Payloads hold at most {} chunks", MAX_CHUNKS))
+                .field("chunk").expected(MAX_CHUNKS - 1).actual(chunk).into());
+        }
+
+        let length: usize = usize::try_from(length).unwrap_or(usize::MAX);
+        if length == 0 || length > MAX_CHUNK_BYTES {
+            return Err(Revert::new(ERR_INVALID_INPUT, format!("Chunks hold 1 to {} bytes", MAX_CHUNK_BYTES))
+                .field("length").expected(MAX_CHUNK_BYTES as u128).actual(length as u128).into());
+        }
+
+        let words: usize = length.div_ceil(16);
+        if context.inputs.len() < CHUNK_HEADER + words {
+            return Err(revert!(ERR_INVALID_INPUT, "Payload chunk is truncated"));
+        }
+
+        let mut bytes: Vec<u8> = read_words(&context.inputs, CHUNK_HEADER, words);
+        bytes.truncate(length);
+
+        let mut payload_epoch: StoragePointer = self.payload_epoch_pointer(index);
+        let mut count: StoragePointer = self.chunk_count_pointer(index);
+        if payload_epoch.get_value::<u128>() != epoch {
+            payload_epoch.set_value::<u128>(epoch);
+            count.set_value::<u128>(0);
+        }
+
+        if chunk > count.get_value::<u128>() {
+            return Err(Revert::new(ERR_INVALID_INPUT, "Chunks are uploaded in order")
+                .field("chunk").expected(count.get_value::<u128>()).actual(chunk).into());
+        }
+
+        self.chunk_pointer(index, epoch, chunk).set(Arc::new(bytes));
+        if chunk == count.get_value::<u128>() {
+            count.set_value::<u128>(chunk + 1);
+        }
+
+        Ok(response)
+    }
+
+    /// Returns the raw bytes of a payload chunk
+    pub(crate) fn get_unlockable_chunk(&self, index: u128, epoch: u128, chunk: u128) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let stored: Arc<Vec<u8>> = self.chunk_pointer(index, epoch, chunk).get();
+        if stored.is_empty() {
+            return Err(revert!(ERR_NOT_FOUND, "Orbital {} has no chunk {} under epoch {}", index, chunk, epoch));
+        }
+
+        response.data = stored.as_ref().clone();
+        Ok(response)
+    }
+
+    /// Returns the content key epoch and chunked payload state of an orbital as JSON
+    pub(crate) fn get_content_key_state(&self, index: u128) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let current_epoch: u128 = self.content_epoch();
+        let payload_epoch: u128 = self.payload_epoch_pointer(index).get_value::<u128>();
+        let state: ContentKeyState = ContentKeyState {
+            index,
+            current_epoch,
+            payload_epoch,
+            chunks: self.chunk_count_pointer(index).get_value::<u128>(),
+            stale: payload_epoch != 0 && payload_epoch != current_epoch,
+        };
+
+        response.data = serde_json::to_vec(&state)
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to serialize content key state"))?;
+        Ok(response)
+    }
+
+    /// Holder presents the orbital and receives the content key its chunks are encrypted
+    /// under, prefixed with that epoch as u128
+    pub(crate) fn release_content_key(&self, index: u128) -> Result<CallResponse> {
+        self.only_holder(index)?;
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let epoch: u128 = self.payload_epoch_pointer(index).get_value::<u128>();
+        if epoch == 0 {
+            return Err(revert!(ERR_NOT_FOUND, "Orbital {} has no chunked payload", index));
+        }
+
+        let holder: [u8; 32] = self.minter_identity()?;
+        self.content_released_pointer(index, epoch).set(Arc::new(holder.to_vec()));
+
+        let mut data: Vec<u8> = epoch.to_le_bytes().to_vec();
+        data.extend_from_slice(&self.content_key_pointer(epoch).get());
+        response.data = data;
+        Ok(response)
+    }
+
+    fn observe_word_count(&self, field: &str, words: u128, max: usize) -> Result<usize> {
+        let count: usize = usize::try_from(words).unwrap_or(usize::MAX);
+        if count > max {
+            return Err(Revert::new(ERR_LIMIT_EXCEEDED, format!("At most {} {}", max, field))
+                .field(field).expected(max as u128).actual(words).into());
+        }
+
+        Ok(count)
+    }
+
+    fn unlockable(&self, index: u128) -> Result<Option<Unlockable>> {
+        let stored: Arc<Vec<u8>> = self.unlockable_pointer(index).get();
+        if stored.is_empty() {
+            return Ok(None);
+        }
+
+        let unlockable: Unlockable = bincode::deserialize(&stored)
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to deserialize unlockable"))?;
+        Ok(Some(unlockable))
+    }
+
+    fn save_unlockable(&self, index: u128, unlockable: &Unlockable) -> Result<()> {
+        let serialized: Vec<u8> = bincode::serialize(unlockable)
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to serialize unlockable"))?;
+        self.unlockable_pointer(index).set(Arc::new(serialized));
+        Ok(())
+    }
+
+    fn unlockable_pointer(&self, index: u128) -> StoragePointer {
+        StoragePointer::from_keyword("/unlockables/").select(&index.to_le_bytes().to_vec())
+    }
+
+    fn content_epoch(&self) -> u128 {
+        self.content_epoch_pointer().get_value::<u128>()
+    }
+
+    fn content_epoch_pointer(&self) -> StoragePointer {
+        StoragePointer::from_keyword("/unlockables/content-epoch")
+    }
+
+    fn content_key_pointer(&self, epoch: u128) -> StoragePointer {
+        StoragePointer::from_keyword("/unlockables/content-keys/").select(&epoch.to_le_bytes().to_vec())
+    }
+
+    fn payload_epoch_pointer(&self, index: u128) -> StoragePointer {
+        StoragePointer::from_keyword("/unlockables/payload-epoch/").select(&index.to_le_bytes().to_vec())
+    }
+
+    fn chunk_count_pointer(&self, index: u128) -> StoragePointer {
+        StoragePointer::from_keyword("/unlockables/chunk-count/").select(&index.to_le_bytes().to_vec())
+    }
+
+    fn chunk_pointer(&self, index: u128, epoch: u128, chunk: u128) -> StoragePointer {
+        StoragePointer::from_keyword("/unlockables/chunks/")
+            .select(&index.to_le_bytes().to_vec())
+            .keyword("/")
+            .select(&epoch.to_le_bytes().to_vec())
+            .keyword("/")
+            .select(&chunk.to_le_bytes().to_vec())
+    }
+
+    fn content_released_pointer(&self, index: u128, epoch: u128) -> StoragePointer {
+        StoragePointer::from_keyword("/unlockables/content-released/")
+            .select(&index.to_le_bytes().to_vec())
+            .keyword("/")
+            .select(&epoch.to_le_bytes().to_vec())
+    }
+
+    fn released_pointer(&self, index: u128, key_version: u64) -> StoragePointer {
+        StoragePointer::from_keyword("/unlockables/released/")
+            .select(&index.to_le_bytes().to_vec())
+            .keyword("/")
+            .select(&key_version.to_le_bytes().to_vec())
+    }
+}
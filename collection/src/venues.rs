@@ -0,0 +1,150 @@
+use metashrew_support::index_pointer::KeyValuePointer;
+use alkanes_runtime::runtime::AlkaneResponder;
+use alkanes_support::{id::AlkaneId, response::CallResponse};
+use anyhow::Result;
+use orbital_support::{revert, errors::codes::*};
+use std::sync::Arc;
+
+use crate::{Collection, decode_alkane_id, encode_alkane_id};
+use crate::opcodes::StoragePointer;
+
+/// Approved marketplace/escrow venues. While enforcement is enabled,
+/// escrow acknowledgments refuse any other venue. A venue records an orbital
+/// as escrowed when it takes it in and releases the record when it hands it out.
+impl Collection {
+    pub(crate) fn set_venue_enforcement(&self, enabled: u128) -> Result<CallResponse> {
+        self.only_owner()?;
+        let context: alkanes_support::context::Context = self.context()?;
+        let response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let flag: u8 = if enabled == 0 { 0 } else { 1 };
+        self.venue_enforcement_pointer().set_value::<u8>(flag);
+        Ok(response)
+    }
+
+    pub(crate) fn approve_venue(&self, block: u128, tx: u128) -> Result<CallResponse> {
+        self.only_owner()?;
+        let context: alkanes_support::context::Context = self.context()?;
+        let response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let venue: AlkaneId = AlkaneId { block, tx };
+        let mut venues: Vec<AlkaneId> = self.approved_venues()?;
+        if !venues.contains(&venue) {
+            venues.push(venue);
+            self.set_approved_venues(&venues);
+        }
+
+        Ok(response)
+    }
+
+    pub(crate) fn remove_venue(&self, block: u128, tx: u128) -> Result<CallResponse> {
+        self.only_owner()?;
+        let context: alkanes_support::context::Context = self.context()?;
+        let response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let venue: AlkaneId = AlkaneId { block, tx };
+        let mut venues: Vec<AlkaneId> = self.approved_venues()?;
+        venues.retain(|approved| *approved != venue);
+        self.set_approved_venues(&venues);
+
+        Ok(response)
+    }
+
+    /// Called by a venue holding the orbital to record that it is escrowed there
+    pub(crate) fn acknowledge_escrow_deposit(&self, index: u128) -> Result<CallResponse> {
+        self.only_holder(index)?;
+        let context: alkanes_support::context::Context = self.context()?;
+        let response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        self.observe_venue(&context.caller)?;
+
+        self.escrow_venue_pointer(index).set(Arc::new(encode_alkane_id(&context.caller)));
+        Ok(response)
+    }
+
+    /// Called by the venue the orbital is escrowed at, still holding it, when it hands the orbital out
+    pub(crate) fn release_escrow_deposit(&self, index: u128) -> Result<CallResponse> {
+        self.only_holder(index)?;
+        let context: alkanes_support::context::Context = self.context()?;
+        let response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let escrowed_at: Arc<Vec<u8>> = self.escrow_venue_pointer(index).get();
+        if escrowed_at.as_slice() != encode_alkane_id(&context.caller).as_slice() {
+            return Err(revert!(ERR_UNAUTHORIZED, "Orbital {} is not escrowed at {}:{}", index, context.caller.block, context.caller.tx));
+        }
+
+        self.escrow_venue_pointer(index).set(Arc::new(vec![]));
+        Ok(response)
+    }
+
+    /// Returns 1 when the venue is accepted by cooperating flows, 0 otherwise
+    pub(crate) fn is_approved_venue(&self, block: u128, tx: u128) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let approved: u128 = if self.observe_venue(&AlkaneId { block, tx }).is_ok() { 1 } else { 0 };
+        response.data = approved.to_le_bytes().to_vec();
+        Ok(response)
+    }
+
+    /// Returns the enforcement flag as u128 followed by every approved venue ID
+    pub(crate) fn get_venues(&self) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let enforced: u128 = if self.is_venue_enforcement_enabled() { 1 } else { 0 };
+        let mut bytes: Vec<u8> = enforced.to_le_bytes().to_vec();
+        bytes.extend_from_slice(&self.approved_venues_pointer().get());
+
+        response.data = bytes;
+        Ok(response)
+    }
+
+    /// Returns the venue the orbital is escrowed at, 32 bytes, zeroed when it is not in escrow
+    pub(crate) fn get_venue_state(&self, index: u128) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let stored: Arc<Vec<u8>> = self.escrow_venue_pointer(index).get();
+        response.data = if stored.len() == 32 { stored.to_vec() } else { vec![0u8; 32] };
+        Ok(response)
+    }
+
+    fn observe_venue(&self, venue: &AlkaneId) -> Result<()> {
+        if !self.is_venue_enforcement_enabled() {
+            return Ok(());
+        }
+
+        if !self.approved_venues()?.contains(venue) {
+            return Err(revert!(ERR_UNAUTHORIZED, "Venue {}:{} is not approved", venue.block, venue.tx));
+        }
+
+        Ok(())
+    }
+
+    fn is_venue_enforcement_enabled(&self) -> bool {
+        self.venue_enforcement_pointer().get_value::<u8>() == 1
+    }
+
+    fn approved_venues(&self) -> Result<Vec<AlkaneId>> {
+        let stored: Arc<Vec<u8>> = self.approved_venues_pointer().get();
+        stored.chunks(32).map(decode_alkane_id).collect()
+    }
+
+    fn set_approved_venues(&self, venues: &[AlkaneId]) {
+        let bytes: Vec<u8> = venues.iter().flat_map(encode_alkane_id).collect();
+        self.approved_venues_pointer().set(Arc::new(bytes));
+    }
+
+    fn venue_enforcement_pointer(&self) -> StoragePointer {
+        StoragePointer::from_keyword("/venues/enforced")
+    }
+
+    fn approved_venues_pointer(&self) -> StoragePointer {
+        StoragePointer::from_keyword("/venues/approved")
+    }
+
+    fn escrow_venue_pointer(&self, index: u128) -> StoragePointer {
+        StoragePointer::from_keyword("/venues/escrow/").select(&index.to_le_bytes().to_vec())
+    }
+}
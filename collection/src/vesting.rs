@@ -0,0 +1,187 @@
+//! The owner can lock part of the premine in a vault kept by the collection instead of
+//! airdropping it. Vaulted orbitals are minted up front, so anyone can see them, and are
+//! released in equal tranches on a block schedule fixed when the vault was filled. The
+//! schedule cannot be changed afterwards, whatever the owner does.
+
+use metashrew_support::index_pointer::KeyValuePointer;
+use alkanes_runtime::runtime::AlkaneResponder;
+use alkanes_support::{parcel::AlkaneTransfer, response::CallResponse};
+use serde::{Serialize, Deserialize};
+use anyhow::Result;
+use orbital_support::{revert, errors::{codes::*, Revert}};
+use std::sync::Arc;
+
+use crate::Collection;
+use crate::opcodes::StoragePointer;
+use crate::public_mint::PUBLIC_MINT_STAGE;
+
+/// Vault of premined orbitals, a contiguous run of indices
+#[derive(Clone, Default, Debug, Serialize, Deserialize)]
+struct Vesting {
+    first_index: u128,
+    total: u128,
+    claimed: u128,
+    /// The first tranche is released at start_block, one more every interval_blocks
+    start_block: u64,
+    interval_blocks: u64,
+    tranches: u64,
+}
+
+impl Vesting {
+    /// Orbitals released by the schedule at a height, claimed or not
+    fn vested_at(&self, height: u64) -> u128 {
+        if height < self.start_block {
+            return 0;
+        }
+
+        let elapsed: u64 = (height - self.start_block) / self.interval_blocks.max(1) + 1;
+        let released: u128 = elapsed.min(self.tranches) as u128;
+        self.total.saturating_mul(released) / self.tranches as u128
+    }
+
+    /// Block releasing the next tranche, None once every tranche is out
+    fn next_release_at(&self, height: u64) -> Option<u64> {
+        if height < self.start_block {
+            return Some(self.start_block);
+        }
+
+        let elapsed: u64 = (height - self.start_block) / self.interval_blocks.max(1) + 1;
+        if elapsed >= self.tranches {
+            return None;
+        }
+
+        self.start_block.checked_add(elapsed.checked_mul(self.interval_blocks)?)
+    }
+}
+
+/// Vault state as returned by `GetVesting`
+#[derive(Serialize)]
+struct VestingView {
+    first_index: u128,
+    total: u128,
+    claimed: u128,
+    vested: u128,
+    start_block: u64,
+    interval_blocks: u64,
+    tranches: u64,
+    next_release_block: Option<u64>,
+}
+
+impl Collection {
+    /// Mint `count` orbitals of the premine into the vault, released in `tranches` equal
+    /// parts from `start_block` on, one every `interval_blocks`. A collection has one vault.
+    pub(crate) fn vest_premine(&self, count: u128, start_block: u128, interval_blocks: u128, tranches: u128) -> Result<CallResponse> {
+        self.only_owner()?;
+        let context: alkanes_support::context::Context = self.context()?;
+        let response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        if self.vesting()?.is_some() {
+            return Err(revert!(ERR_INVALID_STATE, "The premine vault is already filled"));
+        }
+
+        if count == 0 {
+            return Err(revert!(ERR_INVALID_INPUT, "Vaults hold at least one orbital"));
+        }
+
+        let start_block: u64 = u64::try_from(start_block).map_err(|_| Revert::new(ERR_INVALID_INPUT, "start_block is out of range")
+            .field("start_block").expected(u64::MAX as u128).actual(start_block))?;
+        let interval_blocks: u64 = u64::try_from(interval_blocks).map_err(|_| Revert::new(ERR_INVALID_INPUT, "interval_blocks is out of range")
+            .field("interval_blocks").expected(u64::MAX as u128).actual(interval_blocks))?;
+        if tranches == 0 || tranches > count {
+            return Err(Revert::new(ERR_INVALID_INPUT, "Tranches must be 1 to the vaulted count")
+                .field("tranches").expected(count).actual(tranches).into());
+        }
+
+        if tranches > 1 && interval_blocks == 0 {
+            return Err(revert!(ERR_INVALID_INPUT, "Tranches need a positive interval"));
+        }
+
+        self.reserve_premine(count)?;
+        let first_index: u128 = self.instances_count();
+        for _ in 0..count {
+            self.observe_mint_per_block(PUBLIC_MINT_STAGE)?;
+            // The transfer is dropped, the minted orbital stays with the collection
+            self.create_mint_transfer()?;
+        }
+
+        self.save_vesting(&Vesting {
+            first_index,
+            total: count,
+            claimed: 0,
+            start_block,
+            interval_blocks,
+            tranches: tranches as u64,
+        })?;
+        Ok(response)
+    }
+
+    /// Release `count` vested orbitals of the vault to the owner, oldest index first
+    pub(crate) fn claim_vested(&self, count: u128) -> Result<CallResponse> {
+        self.only_owner()?;
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let mut vesting: Vesting = self.vesting()?
+            .ok_or_else(|| revert!(ERR_NOT_FOUND, "No premine vault"))?;
+        let claimable: u128 = vesting.vested_at(self.height()).saturating_sub(vesting.claimed);
+        if count == 0 || count > claimable {
+            return Err(Revert::new(ERR_LIMIT_EXCEEDED, "Claim exceeds the vested orbitals")
+                .field("count").expected(claimable).actual(count).into());
+        }
+
+        let first: u128 = vesting.first_index + vesting.claimed;
+        for index in first..first + count {
+            response.alkanes.0.push(AlkaneTransfer { id: self.lookup_instance(index)?, value: 1u128 });
+        }
+
+        vesting.claimed += count;
+        self.save_vesting(&vesting)?;
+        Ok(response)
+    }
+
+    /// Returns the vault, what the schedule released so far and the next release as JSON
+    pub(crate) fn get_vesting(&self) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let vesting: Vesting = self.vesting()?
+            .ok_or_else(|| revert!(ERR_NOT_FOUND, "No premine vault"))?;
+        let height: u64 = self.height();
+        let view: VestingView = VestingView {
+            first_index: vesting.first_index,
+            total: vesting.total,
+            claimed: vesting.claimed,
+            vested: vesting.vested_at(height),
+            start_block: vesting.start_block,
+            interval_blocks: vesting.interval_blocks,
+            tranches: vesting.tranches,
+            next_release_block: vesting.next_release_at(height),
+        };
+
+        response.data = serde_json::to_vec(&view)
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to serialize vesting"))?;
+        Ok(response)
+    }
+
+    fn vesting(&self) -> Result<Option<Vesting>> {
+        let stored: Arc<Vec<u8>> = self.vesting_pointer().get();
+        if stored.is_empty() {
+            return Ok(None);
+        }
+
+        bincode::deserialize(&stored)
+            .map(Some)
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to deserialize vesting"))
+    }
+
+    fn save_vesting(&self, vesting: &Vesting) -> Result<()> {
+        let serialized: Vec<u8> = bincode::serialize(vesting)
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to serialize vesting"))?;
+        self.vesting_pointer().set(Arc::new(serialized));
+        Ok(())
+    }
+
+    fn vesting_pointer(&self) -> StoragePointer {
+        StoragePointer::from_keyword("/vesting")
+    }
+}
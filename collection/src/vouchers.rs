@@ -0,0 +1,199 @@
+//! Free mint allocations granted by the owner, either one by one per identity or as a
+//! Merkle root over `merkle::voucher_identity(identity, count)`. An allocation is a total
+//! an identity may claim across any number of `ClaimFreeMint` calls; what was claimed is
+//! tracked per identity, so no allocation is claimed twice. Allocated supply is reserved on
+//! top of stage and public supply.
+
+use metashrew_support::index_pointer::KeyValuePointer;
+use alkanes_runtime::runtime::AlkaneResponder;
+use alkanes_support::response::CallResponse;
+use serde::Serialize;
+use anyhow::Result;
+use orbital_support::{revert, errors::{codes::*, Revert}, merkle};
+use std::sync::Arc;
+
+use crate::{Collection, counters};
+use crate::identity::identity_from_halves;
+use crate::opcodes::StoragePointer;
+use crate::public_mint::PUBLIC_MINT_STAGE;
+
+/// Inputs ahead of the voucher proof of `ClaimFreeMint`: opcode, count, allocation
+const CLAIM_FREE_MINT_HEADER: usize = 3;
+
+/// Orbitals one `ClaimFreeMint` mints
+const MAX_CLAIM: u128 = 10;
+
+/// Voucher state of an identity as returned by `GetVoucher`
+#[derive(Serialize)]
+struct VoucherView {
+    identity: String,
+    /// Allocation granted directly, Merkle allocations are only known from their proof
+    allocation: u128,
+    claimed: u128,
+}
+
+impl Collection {
+    /// Grant an identity `count` free mints in total, given as two little endian u128 halves.
+    /// A count of 0 withdraws the grant; lowering it below what was claimed is refused.
+    pub(crate) fn set_voucher(&self, identity_low: u128, identity_high: u128, count: u128) -> Result<CallResponse> {
+        self.only_owner()?;
+        let context: alkanes_support::context::Context = self.context()?;
+        let response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let identity: [u8; 32] = identity_from_halves(identity_low, identity_high);
+        let claimed: u128 = self.voucher_claimed_pointer(&identity).get_value::<u128>();
+        if count != 0 && count < claimed {
+            return Err(Revert::new(ERR_INVALID_INPUT, "Allocation below what was already claimed")
+                .field("count").expected(claimed).actual(count).into());
+        }
+
+        let mut allocation_pointer: StoragePointer = self.voucher_allocation_pointer(&identity);
+        let previous: u128 = allocation_pointer.get_value::<u128>();
+        // A withdrawn grant keeps the claimed part reserved, it was minted
+        let reserved: u128 = if count == 0 { claimed } else { count };
+
+        let mut allocated_pointer: StoragePointer = self.vouchers_allocated_pointer();
+        counters::decrement(&mut allocated_pointer, previous);
+        counters::increment(&mut allocated_pointer, reserved, u128::MAX, "voucher_supply")?;
+        allocation_pointer.set_value::<u128>(reserved);
+        Ok(response)
+    }
+
+    /// Set the root of the Merkle vouchers and the total they allocate, a zero root ends
+    /// Merkle claims while keeping `total` reserved for what they already claimed
+    pub(crate) fn set_voucher_root(&self, root_low: u128, root_high: u128, total: u128) -> Result<CallResponse> {
+        self.only_owner()?;
+        let context: alkanes_support::context::Context = self.context()?;
+        let response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let claimed: u128 = self.merkle_vouchers_claimed_pointer().get_value::<u128>();
+        if total < claimed {
+            return Err(Revert::new(ERR_INVALID_INPUT, "Voucher total below what was already claimed")
+                .field("total").expected(claimed).actual(total).into());
+        }
+
+        let root: [u8; 32] = identity_from_halves(root_low, root_high);
+        self.voucher_root_pointer().set(Arc::new(root.to_vec()));
+        self.merkle_vouchers_total_pointer().set_value::<u128>(total);
+        Ok(response)
+    }
+
+    /// Mint `count` orbitals for free against the allocation of the minter. `allocation` is
+    /// the count a Merkle voucher was issued for, its proof following the fixed inputs; it is
+    /// ignored for identities granted an allocation directly.
+    pub(crate) fn claim_free_mint(&self, count: u128, allocation: u128) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+
+        if count == 0 || count > MAX_CLAIM {
+            return Err(Revert::new(ERR_LIMIT_EXCEEDED, format!("Claims mint 1 to {} orbitals", MAX_CLAIM))
+                .field("count").expected(MAX_CLAIM).actual(count).into());
+        }
+
+        // The allocation belongs to the identity, so the orbitals must reach it
+        self.observe_delivered_to_minter()?;
+        let identity: [u8; 32] = self.minter_identity()?;
+        let direct: u128 = self.voucher_allocation_pointer(&identity).get_value::<u128>();
+        let allocation: u128 = if direct > 0 {
+            direct
+        } else {
+            self.observe_merkle_voucher(&identity, allocation)?;
+            allocation
+        };
+
+        let mut claimed_pointer: StoragePointer = self.voucher_claimed_pointer(&identity);
+        let claimed: u128 = claimed_pointer.get_value::<u128>();
+        if claimed.saturating_add(count) > allocation {
+            return Err(Revert::new(ERR_LIMIT_EXCEEDED, "Exceeds the free mint allocation")
+                .field("allocation").expected(allocation).actual(claimed.saturating_add(count)).into());
+        }
+
+        let first: u128 = self.instances_count();
+        for index in first..first + count {
+            if !self.run_pre_mint_hook(PUBLIC_MINT_STAGE, index)? {
+                // Declined without reverting so the hook failure stays recorded
+                return Ok(CallResponse::forward(&context.incoming_alkanes));
+            }
+        }
+
+        counters::increment(&mut claimed_pointer, count, allocation, "voucher_claimed")?;
+        if direct == 0 {
+            let total: u128 = self.merkle_vouchers_total_pointer().get_value::<u128>();
+            counters::increment(&mut self.merkle_vouchers_claimed_pointer(), count, total, "merkle_vouchers_claimed")?;
+        }
+        self.observe_lifetime_limit(&identity, count)?;
+        self.record_lifetime_mints(&identity, count)?;
+
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+        for _ in 0..count {
+            self.observe_mint_per_block(PUBLIC_MINT_STAGE)?;
+            let (index, transfer) = self.create_mint_transfer()?;
+            response.alkanes.0.push(transfer);
+            self.record_minter(index)?;
+            self.run_post_mint_hook(PUBLIC_MINT_STAGE, index)?;
+        }
+        Ok(response)
+    }
+
+    /// Returns the direct allocation of an identity and what it claimed as JSON
+    pub(crate) fn get_voucher(&self, identity_low: u128, identity_high: u128) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let identity: [u8; 32] = identity_from_halves(identity_low, identity_high);
+        let view: VoucherView = VoucherView {
+            identity: orbital_support::hex::encode(&identity),
+            allocation: self.voucher_allocation_pointer(&identity).get_value::<u128>(),
+            claimed: self.voucher_claimed_pointer(&identity).get_value::<u128>(),
+        };
+
+        response.data = serde_json::to_vec(&view)
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to serialize voucher"))?;
+        Ok(response)
+    }
+
+    /// Free mints reserved by every voucher, counted into the collection supply
+    pub(crate) fn voucher_supply(&self) -> u128 {
+        self.vouchers_allocated_pointer().get_value::<u128>()
+            .saturating_add(self.merkle_vouchers_total_pointer().get_value::<u128>())
+    }
+
+    fn observe_merkle_voucher(&self, identity: &[u8; 32], allocation: u128) -> Result<()> {
+        let stored: Arc<Vec<u8>> = self.voucher_root_pointer().get();
+        let root: [u8; 32] = stored.as_slice().try_into().unwrap_or([0; 32]);
+        if root == [0; 32] {
+            return Err(revert!(ERR_UNAUTHORIZED, "No free mint voucher for the recipient"));
+        }
+
+        let proof: Vec<[u8; 32]> = self.merkle_proof(CLAIM_FREE_MINT_HEADER)?;
+        if !merkle::verify(&root, &merkle::voucher_identity(identity, allocation), &proof) {
+            return Err(revert!(ERR_UNAUTHORIZED, "Voucher proof does not match an allocation of {}", allocation));
+        }
+
+        Ok(())
+    }
+
+    fn voucher_allocation_pointer(&self, identity: &[u8; 32]) -> StoragePointer {
+        StoragePointer::from_keyword("/vouchers/allocations/").select(&identity.to_vec())
+    }
+
+    fn voucher_claimed_pointer(&self, identity: &[u8; 32]) -> StoragePointer {
+        StoragePointer::from_keyword("/vouchers/claimed/").select(&identity.to_vec())
+    }
+
+    /// Sum of the direct allocations
+    fn vouchers_allocated_pointer(&self) -> StoragePointer {
+        StoragePointer::from_keyword("/vouchers/allocated")
+    }
+
+    fn voucher_root_pointer(&self) -> StoragePointer {
+        StoragePointer::from_keyword("/vouchers/root")
+    }
+
+    fn merkle_vouchers_total_pointer(&self) -> StoragePointer {
+        StoragePointer::from_keyword("/vouchers/root-total")
+    }
+
+    fn merkle_vouchers_claimed_pointer(&self) -> StoragePointer {
+        StoragePointer::from_keyword("/vouchers/root-claimed")
+    }
+}
@@ -0,0 +1,247 @@
+//! With the waitlist on, a staged mint arriving once the per-block limits are used up is
+//! paid and queued rather than reverted. Anyone can process the queue in a later block; the
+//! orbitals are minted in arrival order, within the limits of the block processing them,
+//! and kept by the collection until claimed by spending the ticket outpoint of the
+//! transaction that paid. Queued entries hold their share of the supply, so direct mints
+//! cannot take it from them, and minting cannot be paused or closed while any are pending:
+//! their payments are already in the treasury and only the queue can deliver for them.
+
+use metashrew_support::index_pointer::KeyValuePointer;
+use alkanes_runtime::runtime::AlkaneResponder;
+use alkanes_support::{parcel::AlkaneTransfer, response::CallResponse};
+use serde::{Serialize, Deserialize};
+use anyhow::Result;
+use orbital_support::{revert, errors::{codes::*, Revert}};
+use std::sync::Arc;
+
+use crate::Collection;
+use crate::opcodes::StoragePointer;
+use crate::public_mint::PUBLIC_MINT_STAGE;
+use crate::tickets::Ticket;
+
+/// A paid staged mint waiting for room in a block
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct QueueEntry {
+    stage_id: u128,
+    identity: [u8; 32],
+    /// Attributed outpoint of the paying transaction, spent to claim the orbital
+    ticket: Ticket,
+    queued_at: u64,
+}
+
+/// Waitlist state as returned by `GetWaitlist`
+#[derive(Serialize)]
+struct WaitlistView {
+    enabled: bool,
+    pending: u128,
+    /// Position of the next entry processed
+    head: u128,
+    ticket_txid: String,
+    ticket_vout: u32,
+    /// Indices minted for the ticket and not claimed yet
+    held: Vec<u128>,
+}
+
+impl Collection {
+    /// Queue staged mints past the per-block limits instead of reverting them
+    pub(crate) fn set_waitlist(&self, enabled: u128) -> Result<CallResponse> {
+        self.only_owner()?;
+        let context: alkanes_support::context::Context = self.context()?;
+        let response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        self.waitlist_enabled_pointer().set_value::<u8>(if enabled != 0 { 1 } else { 0 });
+        Ok(response)
+    }
+
+    /// Mint up to `count` queued entries, oldest first, stopping at an entry queued in this
+    /// block or one the limits of this block have no room for. Returns the entries
+    /// processed as u128.
+    pub(crate) fn process_queue(&self, count: u128) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let height: u64 = self.height();
+        let mut processed: u128 = 0;
+        while processed < count && self.waitlist_pending() > 0 {
+            let head: u128 = self.waitlist_head_pointer().get_value::<u128>();
+            let entry: QueueEntry = self.queue_entry(head)?;
+            if entry.queued_at >= height || !self.has_mint_capacity(entry.stage_id) {
+                break;
+            }
+
+            // Leaving the queue first releases the supply the entry held
+            self.waitlist_head_pointer().set_value::<u128>(head + 1);
+            self.observe_mint_per_block(entry.stage_id)?;
+            // The transfer is dropped, the orbital stays with the collection until claimed
+            let (index, _) = self.create_mint_transfer()?;
+            self.set_minted_by(index, &entry.identity);
+            self.assign_finale_winner(index, &entry.identity, &entry.ticket)?;
+
+            let mut held: Vec<u128> = self.held_mints(&entry.ticket)?;
+            held.push(index);
+            self.save_held_mints(&entry.ticket, &held)?;
+
+            self.run_post_mint_hook(entry.stage_id, index)?;
+            processed += 1;
+        }
+
+        response.data = processed.to_le_bytes().to_vec();
+        Ok(response)
+    }
+
+    /// Release `count` orbitals the queue minted for the tickets this transaction spends,
+    /// oldest first within each ticket
+    pub(crate) fn claim_queued_mints(&self, count: u128) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let tickets: Vec<Ticket> = self.spent_tickets()?;
+        let mut available: u128 = 0;
+        for ticket in tickets.iter() {
+            available += self.held_mints(ticket)?.len() as u128;
+        }
+
+        if count == 0 || count > available {
+            return Err(Revert::new(ERR_LIMIT_EXCEEDED, "Claim exceeds the orbitals held for the spent tickets")
+                .field("count").expected(available).actual(count).into());
+        }
+
+        let mut remaining: usize = count as usize;
+        for ticket in tickets.iter() {
+            let mut held: Vec<u128> = self.held_mints(ticket)?;
+            if remaining == 0 || held.is_empty() {
+                continue;
+            }
+
+            let take: usize = remaining.min(held.len());
+            for index in held.drain(..take) {
+                response.alkanes.0.push(AlkaneTransfer { id: self.lookup_instance(index)?, value: 1u128 });
+            }
+
+            self.save_held_mints(ticket, &held)?;
+            remaining -= take;
+        }
+
+        Ok(response)
+    }
+
+    /// Returns whether the waitlist is on, the entries pending and what the queue minted
+    /// for a ticket as JSON, the txid given as its two little endian halves
+    pub(crate) fn get_waitlist(&self, ticket_txid_low: u128, ticket_txid_high: u128, ticket_vout: u128) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let ticket: Ticket = Ticket::from_halves(ticket_txid_low, ticket_txid_high, ticket_vout)?;
+        let view: WaitlistView = WaitlistView {
+            enabled: self.waitlist_enabled(),
+            pending: self.waitlist_pending(),
+            head: self.waitlist_head_pointer().get_value::<u128>(),
+            ticket_txid: orbital_support::hex::encode(&ticket.txid),
+            ticket_vout: ticket.vout,
+            held: self.held_mints(&ticket)?,
+        };
+
+        response.data = serde_json::to_vec(&view)
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to serialize waitlist"))?;
+        Ok(response)
+    }
+
+    /// Whether a paid staged mint should be queued rather than minted in this block
+    pub(crate) fn should_queue_mint(&self, stage_id: u128) -> bool {
+        self.waitlist_enabled() && !self.has_mint_capacity(stage_id)
+    }
+
+    /// Queue a paid staged mint for the minter identity, claimable with the attributed ticket
+    pub(crate) fn enqueue_mint(&self, stage_id: u128) -> Result<()> {
+        let reserved: u128 = self.instances_count() + self.waitlist_pending();
+        let cap: u128 = self.max_mints().min(self.max_supply());
+        if reserved >= cap {
+            return Err(Revert::new(ERR_LIMIT_EXCEEDED, "Collection max supply reached")
+                .field("max_supply").expected(cap).actual(reserved + 1).into());
+        }
+
+        let entry: QueueEntry = QueueEntry {
+            stage_id,
+            identity: self.minter_identity()?,
+            ticket: self.attributed_ticket()?,
+            queued_at: self.height(),
+        };
+        let tail: u128 = self.waitlist_tail_pointer().get_value::<u128>();
+        let serialized: Vec<u8> = bincode::serialize(&entry)
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to serialize queue entry"))?;
+        self.queue_entry_pointer(tail).set(Arc::new(serialized));
+        self.waitlist_tail_pointer().set_value::<u128>(tail + 1);
+        Ok(())
+    }
+
+    /// Queued entries not minted yet, each holding one orbital of the supply
+    pub(crate) fn waitlist_pending(&self) -> u128 {
+        self.waitlist_tail_pointer().get_value::<u128>()
+            .saturating_sub(self.waitlist_head_pointer().get_value::<u128>())
+    }
+
+    /// Refuse to pause or close minting while queued entries wait on the queue to mint them
+    pub(crate) fn observe_waitlist_drained(&self) -> Result<()> {
+        let pending: u128 = self.waitlist_pending();
+        if pending > 0 {
+            return Err(Revert::new(ERR_INVALID_STATE, "Queued mints are pending, process the queue first")
+                .field("pending").expected(0u128).actual(pending).into());
+        }
+
+        Ok(())
+    }
+
+    /// Whether the collection and stage per-block limits leave room for another mint
+    fn has_mint_capacity(&self, stage_id: u128) -> bool {
+        let height: u64 = self.height();
+        let stage_max: u32 = self.stage_max_mint_per_block(stage_id);
+        self.mints_in_block(height) < self.max_mint_per_block()
+            && (stage_id == PUBLIC_MINT_STAGE || stage_max == 0 || self.stage_mints_in_block(stage_id, height) < stage_max as u128)
+    }
+
+    fn waitlist_enabled(&self) -> bool {
+        self.waitlist_enabled_pointer().get_value::<u8>() == 1
+    }
+
+    fn queue_entry(&self, position: u128) -> Result<QueueEntry> {
+        bincode::deserialize(&self.queue_entry_pointer(position).get())
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to deserialize queue entry {}", position))
+    }
+
+    fn held_mints(&self, ticket: &Ticket) -> Result<Vec<u128>> {
+        let stored: Arc<Vec<u8>> = self.held_mints_pointer(ticket).get();
+        if stored.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        bincode::deserialize(&stored)
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to deserialize held mints"))
+    }
+
+    fn save_held_mints(&self, ticket: &Ticket, held: &[u128]) -> Result<()> {
+        let serialized: Vec<u8> = bincode::serialize(held)
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to serialize held mints"))?;
+        self.held_mints_pointer(ticket).set(Arc::new(serialized));
+        Ok(())
+    }
+
+    fn waitlist_enabled_pointer(&self) -> StoragePointer {
+        StoragePointer::from_keyword("/waitlist/enabled")
+    }
+
+    fn waitlist_head_pointer(&self) -> StoragePointer {
+        StoragePointer::from_keyword("/waitlist/head")
+    }
+
+    fn waitlist_tail_pointer(&self) -> StoragePointer {
+        StoragePointer::from_keyword("/waitlist/tail")
+    }
+
+    fn queue_entry_pointer(&self, position: u128) -> StoragePointer {
+        StoragePointer::from_keyword("/waitlist/entries/").select(&position.to_le_bytes().to_vec())
+    }
+
+    fn held_mints_pointer(&self, ticket: &Ticket) -> StoragePointer {
+        StoragePointer::from_keyword("/waitlist/held/").select(&ticket.to_bytes())
+    }
+}
@@ -0,0 +1,109 @@
+use alkanes_runtime::runtime::AlkaneResponder;
+use alkanes_support::response::CallResponse;
+use serde::Serialize;
+use anyhow::Result;
+use orbital_support::{revert, errors::{codes::*, Revert}, merkle};
+
+use crate::{Collection, Stage};
+
+/// Deepest proof accepted, enough for 2^32 whitelisted identities
+const MAX_PROOF_DEPTH: usize = 32;
+
+/// Machine readable description of how whitelist roots and proofs are built,
+/// returned by `GetWhitelistScheme`
+#[derive(Serialize)]
+struct WhitelistScheme {
+    stage_id: u128,
+    scheme: &'static str,
+    /// Hex root, all zeros when the stage is open to everyone
+    root: String,
+    hash: &'static str,
+    /// What a leaf commits to: the sha256 of the recipient output script
+    identity: &'static str,
+    leaf: &'static str,
+    leaf_prefix: u8,
+    node: &'static str,
+    node_prefix: u8,
+    /// Leaves are sorted ascending and deduplicated before building the tree
+    leaf_order: &'static str,
+    /// A node without a sibling moves up a level unchanged
+    odd_node: &'static str,
+    proof: &'static str,
+}
+
+impl Collection {
+    /// Require a proof that the identity is in the stage whitelist, open stages pass.
+    /// The proof follows the `header` fixed inputs as two little endian u128 halves per
+    /// sibling, low half first.
+    pub(crate) fn observe_whitelist(&self, stage: &Stage, identity: &[u8; 32], header: usize) -> Result<()> {
+        if stage.whitelist_root == [0; 32] {
+            return Ok(());
+        }
+
+        let proof: Vec<[u8; 32]> = self.merkle_proof(header)?;
+        if !merkle::verify(&stage.whitelist_root, identity, &proof) {
+            return Err(revert!(ERR_UNAUTHORIZED, "Recipient is not whitelisted for stage {}", stage.id));
+        }
+
+        Ok(())
+    }
+
+    /// Merkle proof following the `header` fixed inputs
+    pub(crate) fn merkle_proof(&self, header: usize) -> Result<Vec<[u8; 32]>> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let words: &[u128] = context.inputs.get(header..).unwrap_or(&[]);
+        if words.len() % 2 != 0 {
+            return Err(revert!(ERR_INVALID_INPUT, "Whitelist proof is truncated"));
+        }
+
+        if words.len() / 2 > MAX_PROOF_DEPTH {
+            return Err(Revert::new(ERR_LIMIT_EXCEEDED, format!("Whitelist proofs hold at most {} nodes", MAX_PROOF_DEPTH))
+                .field("proof").expected(MAX_PROOF_DEPTH as u128).actual((words.len() / 2) as u128).into());
+        }
+
+        Ok(words.chunks_exact(2)
+            .map(|halves| {
+                let mut node: [u8; 32] = [0; 32];
+                node[..16].copy_from_slice(&halves[0].to_le_bytes());
+                node[16..].copy_from_slice(&halves[1].to_le_bytes());
+                node
+            })
+            .collect())
+    }
+
+    /// Returns the 32 byte whitelist root of a stage, all zeros when the stage is open
+    pub(crate) fn get_whitelist_root(&self, stage_id: u128) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let stage: Stage = self.get_mint_stage(stage_id)?;
+        response.data = stage.whitelist_root.to_vec();
+        Ok(response)
+    }
+
+    /// Returns the whitelist root of a stage with the leaf and hashing scheme as JSON
+    pub(crate) fn get_whitelist_scheme(&self, stage_id: u128) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let stage: Stage = self.get_mint_stage(stage_id)?;
+        let scheme: WhitelistScheme = WhitelistScheme {
+            stage_id,
+            scheme: merkle::SCHEME,
+            root: orbital_support::hex::encode(&stage.whitelist_root),
+            hash: "sha256",
+            identity: "sha256(script_pubkey)",
+            leaf: "sha256(leaf_prefix || identity)",
+            leaf_prefix: merkle::LEAF_PREFIX,
+            node: "sha256(node_prefix || min(a, b) || max(a, b))",
+            node_prefix: merkle::NODE_PREFIX,
+            leaf_order: "sorted-dedup",
+            odd_node: "promote",
+            proof: "sibling hashes from leaf to root as trailing mint inputs, two u128 LE halves each",
+        };
+
+        response.data = serde_json::to_vec(&scheme)
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to serialize whitelist scheme"))?;
+        Ok(response)
+    }
+}
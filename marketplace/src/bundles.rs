@@ -0,0 +1,198 @@
+use metashrew_support::index_pointer::KeyValuePointer;
+use alkanes_runtime::{runtime::AlkaneResponder, storage::StoragePointer};
+use alkanes_support::{id::AlkaneId, parcel::AlkaneTransfer, response::CallResponse};
+use serde::{Serialize, Deserialize};
+use anyhow::Result;
+use orbital_support::{revert, errors::codes::*};
+use std::sync::Arc;
+
+use crate::{Marketplace, TokenRef, STATUS_ACTIVE, STATUS_CANCELLED, STATUS_SETTLED};
+use crate::tickets::Ticket;
+
+/// Smallest number of orbitals that makes a bundle
+const MIN_BUNDLE_SIZE: usize = 2;
+
+/// Upper bound on orbitals per bundle, keeps settlement within fuel
+const MAX_BUNDLE_SIZE: usize = 25;
+
+/// One escrowed orbital of a bundle
+#[derive(Clone, Copy, Default, Debug, Serialize, Deserialize)]
+struct BundleItem {
+    instance: TokenRef,
+    collection: TokenRef,
+    index: u128,
+}
+
+/// Set of orbitals sold together at one price, all or nothing
+#[derive(Clone, Default, Debug, Serialize, Deserialize)]
+struct Bundle {
+    id: u128,
+    items: Vec<BundleItem>,
+    payment_token: TokenRef,
+    price: u128,
+    seller: Ticket,
+    created_block: u64,
+    status: u8,
+}
+
+impl Bundle {
+    /// Share of the price attributed to an item for royalty purposes.
+    /// The price is split evenly, the last item absorbs the rounding remainder.
+    fn item_share(&self, position: usize) -> u128 {
+        let count: u128 = self.items.len() as u128;
+        let share: u128 = self.price / count;
+
+        if position + 1 == self.items.len() {
+            self.price - share * (count - 1)
+        } else {
+            share
+        }
+    }
+}
+
+impl Marketplace {
+    /// Escrow every incoming orbital as a single bundle
+    pub(crate) fn list_bundle(&self, price: u128, payment_block: u128, payment_tx: u128, ticket_vout: u128) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::default();
+
+        let payment_token: AlkaneId = AlkaneId { block: payment_block, tx: payment_tx };
+        self.observe_payment_token(&payment_token)?;
+
+        if price == 0 {
+            return Err(revert!(ERR_INVALID_INPUT, "Bundle price must be positive"));
+        }
+
+        let count: usize = context.incoming_alkanes.0.len();
+        if count < MIN_BUNDLE_SIZE || count > MAX_BUNDLE_SIZE {
+            return Err(revert!(ERR_LIMIT_EXCEEDED, "A bundle holds between {} and {} orbitals", MIN_BUNDLE_SIZE, MAX_BUNDLE_SIZE));
+        }
+
+        let mut items: Vec<BundleItem> = Vec::with_capacity(count);
+        for transfer in context.incoming_alkanes.0.iter() {
+            if transfer.value != 1 {
+                return Err(revert!(ERR_INVALID_INPUT, "Bundles only hold orbitals"));
+            }
+
+            let (collection, index) = self.resolve_orbital(&transfer.id)?;
+            self.acknowledge_deposit(&collection, index, &transfer.id)?;
+
+            items.push(BundleItem {
+                instance: TokenRef::from_id(&transfer.id),
+                collection: TokenRef::from_id(&collection),
+                index,
+            });
+        }
+
+        let bundle: Bundle = Bundle {
+            id: self.next_id(self.bundle_count_pointer())?,
+            items,
+            payment_token: TokenRef::from_id(&payment_token),
+            price,
+            seller: self.ticket_for_output(ticket_vout)?,
+            created_block: self.height(),
+            status: STATUS_ACTIVE,
+        };
+
+        self.set_bundle(&bundle)?;
+
+        response.data = bundle.id.to_le_bytes().to_vec();
+        Ok(response)
+    }
+
+    pub(crate) fn cancel_bundle(&self, bundle_id: u128) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let mut bundle: Bundle = self.get_active_bundle(bundle_id)?;
+        self.observe_ticket_spent(&bundle.seller)?;
+
+        self.release_bundle(&bundle)?;
+        bundle.status = STATUS_CANCELLED;
+        self.set_bundle(&bundle)?;
+
+        response.alkanes.0.extend(self.bundle_transfers(&bundle));
+        Ok(response)
+    }
+
+    /// Pay the bundle price and receive every orbital in it, royalties settled per item
+    pub(crate) fn buy_bundle(&self, bundle_id: u128) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::default();
+
+        let mut bundle: Bundle = self.get_active_bundle(bundle_id)?;
+        let payment_token: AlkaneId = bundle.payment_token.id();
+
+        response.alkanes.0 = self.take_payment(&context.incoming_alkanes, &payment_token, bundle.price)?;
+
+        let mut proceeds: u128 = 0;
+        for (position, item) in bundle.items.iter().enumerate() {
+            proceeds += self.settle_royalty(&item.collection.id(), item.index, &payment_token, bundle.item_share(position))?;
+        }
+        self.credit_ticket(&bundle.seller, &payment_token, proceeds)?;
+
+        self.release_bundle(&bundle)?;
+        bundle.status = STATUS_SETTLED;
+        self.set_bundle(&bundle)?;
+
+        response.alkanes.0.extend(self.bundle_transfers(&bundle));
+        Ok(response)
+    }
+
+    pub(crate) fn get_bundle(&self, bundle_id: u128) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let bundle: Bundle = self.get_bundle_by_id(bundle_id)?;
+        response.data = serde_json::to_vec(&bundle)
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to serialize bundle"))?;
+        Ok(response)
+    }
+
+    fn release_bundle(&self, bundle: &Bundle) -> Result<()> {
+        for item in bundle.items.iter() {
+            self.release_deposit(&item.collection.id(), item.index, &item.instance.id())?;
+        }
+
+        Ok(())
+    }
+
+    fn bundle_transfers(&self, bundle: &Bundle) -> Vec<AlkaneTransfer> {
+        bundle.items.iter()
+            .map(|item| AlkaneTransfer { id: item.instance.id(), value: 1u128 })
+            .collect()
+    }
+
+    fn bundle_count_pointer(&self) -> StoragePointer {
+        StoragePointer::from_keyword("/bundles/count")
+    }
+
+    fn bundle_pointer(&self, bundle_id: u128) -> StoragePointer {
+        StoragePointer::from_keyword("/bundles/").select(&bundle_id.to_le_bytes().to_vec())
+    }
+
+    fn set_bundle(&self, bundle: &Bundle) -> Result<()> {
+        let serialized: Vec<u8> = bincode::serialize(bundle)
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to serialize bundle"))?;
+        self.bundle_pointer(bundle.id).set(Arc::new(serialized));
+        Ok(())
+    }
+
+    fn get_bundle_by_id(&self, bundle_id: u128) -> Result<Bundle> {
+        let stored: Arc<Vec<u8>> = self.bundle_pointer(bundle_id).get();
+        if stored.is_empty() {
+            return Err(revert!(ERR_NOT_FOUND, "Bundle {} not found", bundle_id));
+        }
+
+        bincode::deserialize(&stored).map_err(|_| revert!(ERR_STORAGE, "Failed to deserialize bundle"))
+    }
+
+    fn get_active_bundle(&self, bundle_id: u128) -> Result<Bundle> {
+        let bundle: Bundle = self.get_bundle_by_id(bundle_id)?;
+        if bundle.status != STATUS_ACTIVE {
+            return Err(revert!(ERR_INVALID_STATE, "Bundle {} is not active", bundle_id));
+        }
+
+        Ok(bundle)
+    }
+}
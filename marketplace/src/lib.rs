@@ -0,0 +1,871 @@
+use metashrew_support::index_pointer::KeyValuePointer;
+use metashrew_support::compat::to_arraybuffer_layout;
+
+use alkanes_runtime::{
+  declare_alkane, message::MessageDispatch, storage::StoragePointer,
+  runtime::AlkaneResponder
+};
+
+use alkanes_support::{
+  cellpack::Cellpack, id::AlkaneId,
+  parcel::{AlkaneTransfer, AlkaneTransferParcel}, response::CallResponse
+};
+
+use serde::{Serialize, Deserialize};
+use anyhow::Result;
+use orbital_support::{revert, errors::{codes::*, Revert}, pagination::{self, PageBounds}, pricing::DutchCurve};
+use std::sync::Arc;
+mod bundles;
+mod orbitals;
+use orbitals::RoyaltyDue;
+mod otc;
+mod tickets;
+use tickets::Ticket;
+
+/// Oracle opcode returning the value of one base unit of a token in reference units
+const ORACLE_GET_RATE: u128 = 100;
+
+/// Fixed point scale of oracle rates
+const RATE_SCALE: u128 = 100_000_000;
+
+/// Listing and offer lifecycle
+const STATUS_ACTIVE: u8 = 0;
+const STATUS_SETTLED: u8 = 1;
+const STATUS_CANCELLED: u8 = 2;
+
+/// Serializable alkane ID
+#[derive(Clone, Copy, Default, Debug, PartialEq, Serialize, Deserialize)]
+struct TokenRef {
+    block: u128,
+    tx: u128,
+}
+
+impl TokenRef {
+    fn from_id(id: &AlkaneId) -> TokenRef {
+        TokenRef { block: id.block, tx: id.tx }
+    }
+
+    fn id(&self) -> AlkaneId {
+        AlkaneId { block: self.block, tx: self.tx }
+    }
+
+    fn is_zero(&self) -> bool {
+        self.block == 0 && self.tx == 0
+    }
+}
+
+/// Alkane accepted as payment, with an optional oracle used to compare offers across tokens
+#[derive(Clone, Copy, Default, Debug, Serialize, Deserialize)]
+struct PaymentToken {
+    token: TokenRef,
+    oracle: Option<TokenRef>,
+}
+
+/// Orbital escrowed for sale. Fixed price listings have no decay, dutch listings decline
+/// from `price` towards `floor_price` along the curve of dutch mint stages, one step per block.
+#[derive(Clone, Default, Debug, Serialize, Deserialize)]
+struct Listing {
+    id: u128,
+    instance: TokenRef,
+    collection: TokenRef,
+    index: u128,
+    payment_token: TokenRef,
+    price: u128,
+    floor_price: u128,
+    decay_per_block: u128,
+    seller: Ticket,
+    created_block: u64,
+    status: u8,
+}
+
+impl Listing {
+    fn current_price(&self, height: u64) -> u128 {
+        DutchCurve::per_block(self.price, self.floor_price, self.decay_per_block, self.created_block).price_at(height).0
+    }
+}
+
+/// Payment escrowed as a bid on a collection index
+#[derive(Clone, Default, Debug, Serialize, Deserialize)]
+struct Offer {
+    id: u128,
+    collection: TokenRef,
+    index: u128,
+    payment_token: TokenRef,
+    amount: u128,
+    expiry_block: u64,
+    bidder: Ticket,
+    created_block: u64,
+    status: u8,
+}
+
+impl Offer {
+    fn is_open(&self, height: u64) -> bool {
+        self.status == STATUS_ACTIVE && height <= self.expiry_block
+    }
+}
+
+/// Offer together with its oracle normalized value
+#[derive(Serialize)]
+struct RankedOffer {
+    offer: Offer,
+    normalized_value: Option<u128>,
+}
+
+/// One page of a listing query, cursors follow `orbital_support::pagination`
+#[derive(Serialize)]
+struct Page<T> {
+    items: Vec<T>,
+    next: Option<u128>,
+    total: u128,
+}
+
+/// Deployment facts recorded by `Initialize`, returned by `GetInitializationState`
+#[derive(Default, Serialize, Deserialize)]
+struct InitializationState {
+    initialized: bool,
+    block: u64,
+    /// Script hash of the first spendable output of the initializing transaction
+    initializer: String,
+    caller_block: u128,
+    caller_tx: u128,
+}
+
+#[derive(Default)]
+pub struct Marketplace(());
+
+impl AlkaneResponder for Marketplace {}
+
+#[derive(MessageDispatch)]
+enum MarketplaceMessage {
+  #[opcode(0)]
+  Initialize,
+
+  #[opcode(10)]
+  ListOrbital { price: u128, payment_block: u128, payment_tx: u128, ticket_vout: u128 },
+
+  #[opcode(11)]
+  CancelListing { listing_id: u128 },
+
+  #[opcode(12)]
+  BuyListing { listing_id: u128 },
+
+  #[opcode(13)]
+  ListBundle { price: u128, payment_block: u128, payment_tx: u128, ticket_vout: u128 },
+
+  #[opcode(14)]
+  CancelBundle { bundle_id: u128 },
+
+  #[opcode(15)]
+  BuyBundle { bundle_id: u128 },
+
+  #[opcode(16)]
+  ListDutch { start_price: u128, floor_price: u128, decay_per_block: u128, payment_block: u128, payment_tx: u128, ticket_vout: u128 },
+
+  #[opcode(20)]
+  MakeOffer { collection_block: u128, collection_tx: u128, index: u128, expiry_block: u128, ticket_vout: u128 },
+
+  #[opcode(21)]
+  CancelOffer { offer_id: u128 },
+
+  #[opcode(22)]
+  AcceptOffer { offer_id: u128 },
+
+  #[opcode(30)]
+  Claim,
+
+  #[opcode(31)]
+  ClaimRoyalties { recipient_block: u128, recipient_tx: u128 },
+
+  #[opcode(40)]
+  SetPaymentToken { block: u128, tx: u128, oracle_block: u128, oracle_tx: u128 },
+
+  #[opcode(41)]
+  RemovePaymentToken { block: u128, tx: u128 },
+
+  #[opcode(50)]
+  ProposeDeal {
+    taker_txid_low: u128,
+    taker_txid_high: u128,
+    taker_vout: u128,
+    expiry_block: u128,
+    arbiter_block: u128,
+    arbiter_tx: u128,
+    ticket_vout: u128
+  },
+
+  #[opcode(51)]
+  AcceptDeal { deal_id: u128 },
+
+  #[opcode(52)]
+  ConfirmDeal { deal_id: u128 },
+
+  #[opcode(53)]
+  CancelDeal { deal_id: u128 },
+
+  #[opcode(54)]
+  ResolveDeal { deal_id: u128, side: u128 },
+
+  #[opcode(55)]
+  RefundDeal { deal_id: u128 },
+
+  #[opcode(100)]
+  #[returns(String)]
+  GetListing { listing_id: u128 },
+
+  #[opcode(101)]
+  #[returns(String)]
+  GetOffer { offer_id: u128 },
+
+  #[opcode(102)]
+  #[returns(String)]
+  GetBestOffer { collection_block: u128, collection_tx: u128, index: u128 },
+
+  #[opcode(103)]
+  #[returns(String)]
+  GetBestOfferInToken { collection_block: u128, collection_tx: u128, index: u128, token_block: u128, token_tx: u128 },
+
+  #[opcode(104)]
+  #[returns(String)]
+  GetPaymentTokens,
+
+  #[opcode(105)]
+  #[returns(String)]
+  GetBundle { bundle_id: u128 },
+
+  #[opcode(106)]
+  #[returns(u128)]
+  GetListingPrice { listing_id: u128 },
+
+  #[opcode(107)]
+  #[returns(String)]
+  GetDeal { deal_id: u128 },
+
+  #[opcode(108)]
+  #[returns(String)]
+  GetInitializationState,
+
+  #[opcode(109)]
+  #[returns(String)]
+  GetListings { cursor: u128, limit: u128 },
+}
+
+impl Marketplace {
+    fn initialize(&self) -> Result<CallResponse> {
+        self.observe_first_initialization()?;
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let state: InitializationState = InitializationState {
+            initialized: true,
+            block: self.height(),
+            initializer: orbital_support::hex::encode(&self.sender_identity()?),
+            caller_block: context.caller.block,
+            caller_tx: context.caller.tx,
+        };
+        self.set_initialization_state(&state)?;
+
+        // Marketplace token acts as auth token for owner operations
+        response.alkanes.0.push(AlkaneTransfer {
+            id: context.myself.clone(),
+            value: 1u128,
+        });
+
+        Ok(response)
+    }
+
+    /// Returns whether, when and by whom the marketplace was initialized as JSON
+    fn get_initialization_state(&self) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let state: InitializationState = self.initialization_state()?;
+        response.data = serde_json::to_vec(&state)
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to serialize initialization state"))?;
+        Ok(response)
+    }
+
+    /// A second initialization fails with the block of the first one
+    fn observe_first_initialization(&self) -> Result<()> {
+        let state: InitializationState = self.initialization_state()?;
+        if state.initialized {
+            return Err(Revert::new(ERR_ALREADY_INITIALIZED, format!("Already initialized at block {}", state.block))
+                .field("initialized_at").actual(state.block as u128).into());
+        }
+
+        self.observe_initialization()
+    }
+
+    fn initialization_state_pointer(&self) -> StoragePointer {
+        StoragePointer::from_keyword("/initialization")
+    }
+
+    fn set_initialization_state(&self, state: &InitializationState) -> Result<()> {
+        let serialized: Vec<u8> = bincode::serialize(state)
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to serialize initialization state"))?;
+        self.initialization_state_pointer().set(Arc::new(serialized));
+        Ok(())
+    }
+
+    fn initialization_state(&self) -> Result<InitializationState> {
+        let stored: Arc<Vec<u8>> = self.initialization_state_pointer().get();
+        if stored.is_empty() {
+            return Ok(InitializationState::default());
+        }
+
+        bincode::deserialize(&stored).map_err(|_| revert!(ERR_STORAGE, "Failed to deserialize initialization state"))
+    }
+
+    /// Escrow the incoming orbital for sale. `ticket_vout` designates the output
+    /// of this transaction the seller later spends to cancel or collect proceeds.
+    fn list_orbital(&self, price: u128, payment_block: u128, payment_tx: u128, ticket_vout: u128) -> Result<CallResponse> {
+        self.open_listing(price, price, 0, payment_block, payment_tx, ticket_vout)
+    }
+
+    /// Escrow the incoming orbital at a price declining every block down to a floor
+    fn list_dutch(&self, start_price: u128, floor_price: u128, decay_per_block: u128, payment_block: u128, payment_tx: u128, ticket_vout: u128) -> Result<CallResponse> {
+        if floor_price > start_price {
+            return Err(revert!(ERR_INVALID_INPUT, "Floor price exceeds start price"));
+        }
+
+        if decay_per_block == 0 {
+            return Err(revert!(ERR_INVALID_INPUT, "Dutch listings need a positive decay"));
+        }
+
+        self.open_listing(start_price, floor_price, decay_per_block, payment_block, payment_tx, ticket_vout)
+    }
+
+    fn open_listing(&self, price: u128, floor_price: u128, decay_per_block: u128, payment_block: u128, payment_tx: u128, ticket_vout: u128) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::default();
+
+        let payment_token: AlkaneId = AlkaneId { block: payment_block, tx: payment_tx };
+        self.observe_payment_token(&payment_token)?;
+
+        if floor_price == 0 {
+            return Err(revert!(ERR_INVALID_INPUT, "Listing price must be positive"));
+        }
+
+        if context.incoming_alkanes.0.len() != 1 || context.incoming_alkanes.0[0].value != 1 {
+            return Err(revert!(ERR_INVALID_INPUT, "Exactly one orbital must be sent to list"));
+        }
+
+        let instance: AlkaneId = context.incoming_alkanes.0[0].id;
+        let (collection, index) = self.resolve_orbital(&instance)?;
+        self.acknowledge_deposit(&collection, index, &instance)?;
+
+        let listing: Listing = Listing {
+            id: self.next_id(self.listing_count_pointer())?,
+            instance: TokenRef::from_id(&instance),
+            collection: TokenRef::from_id(&collection),
+            index,
+            payment_token: TokenRef::from_id(&payment_token),
+            price,
+            floor_price,
+            decay_per_block,
+            seller: self.ticket_for_output(ticket_vout)?,
+            created_block: self.height(),
+            status: STATUS_ACTIVE,
+        };
+
+        self.set_listing(&listing)?;
+
+        response.data = listing.id.to_le_bytes().to_vec();
+        Ok(response)
+    }
+
+    fn cancel_listing(&self, listing_id: u128) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let mut listing: Listing = self.get_active_listing(listing_id)?;
+        self.observe_ticket_spent(&listing.seller)?;
+
+        self.release_deposit(&listing.collection.id(), listing.index, &listing.instance.id())?;
+        listing.status = STATUS_CANCELLED;
+        self.set_listing(&listing)?;
+
+        response.alkanes.0.push(AlkaneTransfer {
+            id: listing.instance.id(),
+            value: 1u128,
+        });
+
+        Ok(response)
+    }
+
+    fn buy_listing(&self, listing_id: u128) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::default();
+
+        let mut listing: Listing = self.get_active_listing(listing_id)?;
+        let payment_token: AlkaneId = listing.payment_token.id();
+        let price: u128 = listing.current_price(self.height());
+
+        response.alkanes.0 = self.take_payment(&context.incoming_alkanes, &payment_token, price)?;
+
+        let proceeds: u128 = self.settle_royalty(&listing.collection.id(), listing.index, &payment_token, price)?;
+        self.credit_ticket(&listing.seller, &payment_token, proceeds)?;
+
+        self.release_deposit(&listing.collection.id(), listing.index, &listing.instance.id())?;
+        listing.status = STATUS_SETTLED;
+        self.set_listing(&listing)?;
+
+        response.alkanes.0.push(AlkaneTransfer {
+            id: listing.instance.id(),
+            value: 1u128,
+        });
+
+        Ok(response)
+    }
+
+    /// Escrow the incoming payment as a bid on a collection index. `ticket_vout`
+    /// designates the output the bidder later spends to cancel or collect the orbital.
+    fn make_offer(&self, collection_block: u128, collection_tx: u128, index: u128, expiry_block: u128, ticket_vout: u128) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::default();
+
+        if context.incoming_alkanes.0.len() != 1 || context.incoming_alkanes.0[0].value == 0 {
+            return Err(revert!(ERR_INVALID_INPUT, "Exactly one payment token must be sent to make an offer"));
+        }
+
+        let payment: AlkaneTransfer = context.incoming_alkanes.0[0];
+        self.observe_payment_token(&payment.id)?;
+
+        let expiry_block: u64 = u64::try_from(expiry_block).map_err(|_| revert!(ERR_INVALID_INPUT, "Expiry block out of range"))?;
+        if expiry_block <= self.height() {
+            return Err(revert!(ERR_INVALID_INPUT, "Offer expiry must be in the future"));
+        }
+
+        let collection: AlkaneId = AlkaneId { block: collection_block, tx: collection_tx };
+        // Fails when the index has not been minted
+        self.instance_of(&collection, index)?;
+
+        let offer: Offer = Offer {
+            id: self.next_id(self.offer_count_pointer())?,
+            collection: TokenRef::from_id(&collection),
+            index,
+            payment_token: TokenRef::from_id(&payment.id),
+            amount: payment.value,
+            expiry_block,
+            bidder: self.ticket_for_output(ticket_vout)?,
+            created_block: self.height(),
+            status: STATUS_ACTIVE,
+        };
+
+        self.set_offer(&offer)?;
+        self.index_offer(&offer)?;
+
+        response.data = offer.id.to_le_bytes().to_vec();
+        Ok(response)
+    }
+
+    /// Refund an offer to the bidder, allowed at any time including after expiry
+    fn cancel_offer(&self, offer_id: u128) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let mut offer: Offer = self.get_offer_by_id(offer_id)?;
+        if offer.status != STATUS_ACTIVE {
+            return Err(revert!(ERR_INVALID_STATE, "Offer {} is not active", offer_id));
+        }
+
+        self.observe_ticket_spent(&offer.bidder)?;
+
+        offer.status = STATUS_CANCELLED;
+        self.set_offer(&offer)?;
+
+        response.alkanes.0.push(AlkaneTransfer {
+            id: offer.payment_token.id(),
+            value: offer.amount,
+        });
+
+        Ok(response)
+    }
+
+    /// Holder sells the orbital to the bidder, receiving the payment less royalties
+    fn accept_offer(&self, offer_id: u128) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::default();
+
+        let mut offer: Offer = self.get_offer_by_id(offer_id)?;
+        if !offer.is_open(self.height()) {
+            return Err(revert!(ERR_INVALID_STATE, "Offer {} is not open", offer_id));
+        }
+
+        let collection: AlkaneId = offer.collection.id();
+        let instance: AlkaneId = self.instance_of(&collection, offer.index)?;
+        response.alkanes.0 = self.take_payment(&context.incoming_alkanes, &instance, 1)?;
+
+        let payment_token: AlkaneId = offer.payment_token.id();
+        let proceeds: u128 = self.settle_royalty(&collection, offer.index, &payment_token, offer.amount)?;
+        self.credit_ticket(&offer.bidder, &instance, 1)?;
+
+        offer.status = STATUS_SETTLED;
+        self.set_offer(&offer)?;
+
+        response.alkanes.0.push(AlkaneTransfer {
+            id: payment_token,
+            value: proceeds,
+        });
+
+        Ok(response)
+    }
+
+    fn set_payment_token(&self, block: u128, tx: u128, oracle_block: u128, oracle_tx: u128) -> Result<CallResponse> {
+        self.only_owner()?;
+        let context: alkanes_support::context::Context = self.context()?;
+        let response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let token: TokenRef = TokenRef { block, tx };
+        let oracle: TokenRef = TokenRef { block: oracle_block, tx: oracle_tx };
+        let entry: PaymentToken = PaymentToken {
+            token,
+            oracle: if oracle.is_zero() { None } else { Some(oracle) },
+        };
+
+        let mut tokens: Vec<PaymentToken> = self.get_payment_token_list()?;
+        tokens.retain(|accepted| accepted.token != token);
+        tokens.push(entry);
+        self.set_payment_token_list(&tokens)?;
+
+        Ok(response)
+    }
+
+    fn remove_payment_token(&self, block: u128, tx: u128) -> Result<CallResponse> {
+        self.only_owner()?;
+        let context: alkanes_support::context::Context = self.context()?;
+        let response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let token: TokenRef = TokenRef { block, tx };
+        let mut tokens: Vec<PaymentToken> = self.get_payment_token_list()?;
+        tokens.retain(|accepted| accepted.token != token);
+        self.set_payment_token_list(&tokens)?;
+
+        Ok(response)
+    }
+
+    fn get_listing(&self, listing_id: u128) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let listing: Listing = self.get_listing_by_id(listing_id)?;
+        response.data = serde_json::to_vec(&listing)
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to serialize listing"))?;
+        Ok(response)
+    }
+
+    /// Returns a page of listings in ID order as JSON, settled and cancelled ones included
+    fn get_listings(&self, cursor: u128, limit: u128) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let bounds: PageBounds = pagination::page_bounds(cursor, limit, self.listing_count_pointer().get_value::<u128>())?;
+        // Listing IDs start at 1
+        let items: Vec<Listing> = bounds.positions()
+            .map(|position| self.get_listing_by_id(position + 1))
+            .collect::<Result<Vec<Listing>>>()?;
+
+        let page: Page<Listing> = Page { items, next: bounds.next, total: bounds.total };
+        response.data = serde_json::to_vec(&page)
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to serialize listings"))?;
+        Ok(response)
+    }
+
+    /// Price a buyer pays for the listing in the current block
+    fn get_listing_price(&self, listing_id: u128) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let listing: Listing = self.get_listing_by_id(listing_id)?;
+        response.data = listing.current_price(self.height()).to_le_bytes().to_vec();
+        Ok(response)
+    }
+
+    fn get_offer(&self, offer_id: u128) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let offer: Offer = self.get_offer_by_id(offer_id)?;
+        response.data = serde_json::to_vec(&offer)
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to serialize offer"))?;
+        Ok(response)
+    }
+
+    /// Highest open offer across tokens, compared by oracle normalized value.
+    /// Offers in tokens without an oracle cannot be compared and are skipped.
+    fn get_best_offer(&self, collection_block: u128, collection_tx: u128, index: u128) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let collection: TokenRef = TokenRef { block: collection_block, tx: collection_tx };
+        let mut best: Option<RankedOffer> = None;
+
+        for offer in self.open_offers_for(&collection, index)? {
+            let normalized: Option<u128> = self.normalized_value(&offer.payment_token, offer.amount)?;
+            let value: u128 = match normalized {
+                Some(value) => value,
+                None => continue,
+            };
+
+            let improves: bool = match &best {
+                Some(current) => value > current.normalized_value.unwrap_or(0),
+                None => true,
+            };
+
+            if improves {
+                best = Some(RankedOffer { offer, normalized_value: Some(value) });
+            }
+        }
+
+        response.data = serde_json::to_vec(&best)
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to serialize offer"))?;
+        Ok(response)
+    }
+
+    /// Highest open offer denominated in a specific token
+    fn get_best_offer_in_token(&self, collection_block: u128, collection_tx: u128, index: u128, token_block: u128, token_tx: u128) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let collection: TokenRef = TokenRef { block: collection_block, tx: collection_tx };
+        let token: TokenRef = TokenRef { block: token_block, tx: token_tx };
+
+        let best: Option<Offer> = self.open_offers_for(&collection, index)?
+            .into_iter()
+            .filter(|offer| offer.payment_token == token)
+            .max_by_key(|offer| offer.amount);
+
+        let ranked: Option<RankedOffer> = match best {
+            Some(offer) => {
+                let normalized_value: Option<u128> = self.normalized_value(&offer.payment_token, offer.amount)?;
+                Some(RankedOffer { offer, normalized_value })
+            }
+            None => None,
+        };
+
+        response.data = serde_json::to_vec(&ranked)
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to serialize offer"))?;
+        Ok(response)
+    }
+
+    fn get_payment_tokens(&self) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let tokens: Vec<PaymentToken> = self.get_payment_token_list()?;
+        response.data = serde_json::to_vec(&tokens)
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to serialize payment tokens"))?;
+        Ok(response)
+    }
+
+    /// Credit the collection royalty for a sale and return what is left for the seller
+    fn settle_royalty(&self, collection: &AlkaneId, index: u128, payment_token: &AlkaneId, sale_amount: u128) -> Result<u128> {
+        let royalty: RoyaltyDue = self.royalty_due(collection, index, sale_amount)?;
+        if royalty.amount == 0 || TokenRef::from_id(&royalty.recipient).is_zero() {
+            return Ok(sale_amount);
+        }
+
+        self.credit_royalty(&royalty.recipient, payment_token, royalty.amount)?;
+        Ok(sale_amount - royalty.amount)
+    }
+
+    /// Split the incoming alkanes into the amount owed and whatever is returned to the caller
+    fn take_payment(&self, incoming: &AlkaneTransferParcel, token: &AlkaneId, amount: u128) -> Result<Vec<AlkaneTransfer>> {
+        let mut outstanding: u128 = amount;
+        let mut rest: Vec<AlkaneTransfer> = Vec::new();
+
+        for transfer in incoming.0.iter() {
+            if transfer.id == *token && outstanding > 0 {
+                let used: u128 = transfer.value.min(outstanding);
+                outstanding -= used;
+                if transfer.value > used {
+                    rest.push(AlkaneTransfer { id: transfer.id, value: transfer.value - used });
+                }
+            } else {
+                rest.push(*transfer);
+            }
+        }
+
+        if outstanding > 0 {
+            return Err(Revert::new(ERR_INSUFFICIENT_PAYMENT, format!("Insufficient payment: {} short", outstanding))
+                .expected(amount).actual(amount - outstanding).into());
+        }
+
+        Ok(rest)
+    }
+
+    /// Value of an amount in oracle reference units, None when the token has no oracle
+    fn normalized_value(&self, token: &TokenRef, amount: u128) -> Result<Option<u128>> {
+        let oracle: TokenRef = match self.get_payment_token_list()?
+            .into_iter()
+            .find(|accepted| accepted.token == *token)
+            .and_then(|accepted| accepted.oracle)
+        {
+            Some(oracle) => oracle,
+            None => return Ok(None),
+        };
+
+        let cellpack: Cellpack = Cellpack {
+            target: oracle.id(),
+            inputs: vec![ORACLE_GET_RATE, token.block, token.tx],
+        };
+
+        let oracle_response: CallResponse = self.staticcall(&cellpack, &AlkaneTransferParcel::default(), self.fuel())?;
+        if oracle_response.data.len() != 16 {
+            return Err(revert!(ERR_EXTERNAL_CALL, "Invalid oracle rate response"));
+        }
+
+        let rate: u128 = u128::from_le_bytes(oracle_response.data[0..16].try_into().unwrap());
+        Ok(Some(amount.saturating_mul(rate) / RATE_SCALE))
+    }
+
+    fn observe_payment_token(&self, token: &AlkaneId) -> Result<()> {
+        let token_ref: TokenRef = TokenRef::from_id(token);
+        let accepted: bool = self.get_payment_token_list()?
+            .iter()
+            .any(|payment_token| payment_token.token == token_ref);
+
+        if !accepted {
+            return Err(revert!(ERR_INVALID_STATE, "Payment token {}:{} is not accepted", token.block, token.tx));
+        }
+
+        Ok(())
+    }
+
+    /// Require the marketplace auth token among the incoming alkanes
+    fn only_owner(&self) -> Result<()> {
+        let context: alkanes_support::context::Context = self.context()?;
+
+        let has_auth_token: bool = context.incoming_alkanes.0.iter()
+            .any(|transfer| transfer.id == context.myself && transfer.value > 0);
+
+        if !has_auth_token {
+            return Err(revert!(ERR_UNAUTHORIZED, "Caller is not the marketplace owner"));
+        }
+
+        Ok(())
+    }
+
+    fn next_id(&self, mut pointer: StoragePointer) -> Result<u128> {
+        let id: u128 = pointer.get_value::<u128>().checked_add(1)
+            .ok_or_else(|| revert!(ERR_OVERFLOW, "id overflow"))?;
+        pointer.set_value::<u128>(id);
+        Ok(id)
+    }
+
+    fn listing_count_pointer(&self) -> StoragePointer {
+        StoragePointer::from_keyword("/listings/count")
+    }
+
+    fn listing_pointer(&self, listing_id: u128) -> StoragePointer {
+        StoragePointer::from_keyword("/listings/").select(&listing_id.to_le_bytes().to_vec())
+    }
+
+    fn set_listing(&self, listing: &Listing) -> Result<()> {
+        let serialized: Vec<u8> = bincode::serialize(listing)
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to serialize listing"))?;
+        self.listing_pointer(listing.id).set(Arc::new(serialized));
+        Ok(())
+    }
+
+    fn get_listing_by_id(&self, listing_id: u128) -> Result<Listing> {
+        let stored: Arc<Vec<u8>> = self.listing_pointer(listing_id).get();
+        if stored.is_empty() {
+            return Err(revert!(ERR_NOT_FOUND, "Listing {} not found", listing_id));
+        }
+
+        bincode::deserialize(&stored).map_err(|_| revert!(ERR_STORAGE, "Failed to deserialize listing"))
+    }
+
+    fn get_active_listing(&self, listing_id: u128) -> Result<Listing> {
+        let listing: Listing = self.get_listing_by_id(listing_id)?;
+        if listing.status != STATUS_ACTIVE {
+            return Err(revert!(ERR_INVALID_STATE, "Listing {} is not active", listing_id));
+        }
+
+        Ok(listing)
+    }
+
+    fn offer_count_pointer(&self) -> StoragePointer {
+        StoragePointer::from_keyword("/offers/count")
+    }
+
+    fn offer_pointer(&self, offer_id: u128) -> StoragePointer {
+        StoragePointer::from_keyword("/offers/").select(&offer_id.to_le_bytes().to_vec())
+    }
+
+    fn set_offer(&self, offer: &Offer) -> Result<()> {
+        let serialized: Vec<u8> = bincode::serialize(offer)
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to serialize offer"))?;
+        self.offer_pointer(offer.id).set(Arc::new(serialized));
+        Ok(())
+    }
+
+    fn get_offer_by_id(&self, offer_id: u128) -> Result<Offer> {
+        let stored: Arc<Vec<u8>> = self.offer_pointer(offer_id).get();
+        if stored.is_empty() {
+            return Err(revert!(ERR_NOT_FOUND, "Offer {} not found", offer_id));
+        }
+
+        bincode::deserialize(&stored).map_err(|_| revert!(ERR_STORAGE, "Failed to deserialize offer"))
+    }
+
+    /// Offer IDs per collection index, stored as consecutive u128 values
+    fn target_offers_pointer(&self, collection: &TokenRef, index: u128) -> StoragePointer {
+        let mut key: Vec<u8> = Vec::with_capacity(48);
+        key.extend_from_slice(&collection.block.to_le_bytes());
+        key.extend_from_slice(&collection.tx.to_le_bytes());
+        key.extend_from_slice(&index.to_le_bytes());
+
+        StoragePointer::from_keyword("/offers/target/").select(&key)
+    }
+
+    fn index_offer(&self, offer: &Offer) -> Result<()> {
+        let mut pointer: StoragePointer = self.target_offers_pointer(&offer.collection, offer.index);
+        let mut ids: Vec<u8> = pointer.get().as_ref().clone();
+        ids.extend_from_slice(&offer.id.to_le_bytes());
+        pointer.set(Arc::new(ids));
+        Ok(())
+    }
+
+    fn open_offers_for(&self, collection: &TokenRef, index: u128) -> Result<Vec<Offer>> {
+        let height: u64 = self.height();
+        let ids: Arc<Vec<u8>> = self.target_offers_pointer(collection, index).get();
+
+        let mut offers: Vec<Offer> = Vec::new();
+        for chunk in ids.chunks(16) {
+            let offer_id: u128 = u128::from_le_bytes(chunk.try_into().unwrap());
+            let offer: Offer = self.get_offer_by_id(offer_id)?;
+            if offer.is_open(height) {
+                offers.push(offer);
+            }
+        }
+
+        Ok(offers)
+    }
+
+    fn payment_tokens_pointer(&self) -> StoragePointer {
+        StoragePointer::from_keyword("/payment-tokens")
+    }
+
+    fn get_payment_token_list(&self) -> Result<Vec<PaymentToken>> {
+        let stored: Arc<Vec<u8>> = self.payment_tokens_pointer().get();
+        if stored.is_empty() {
+            return Ok(vec![]);
+        }
+
+        bincode::deserialize(&stored).map_err(|_| revert!(ERR_STORAGE, "Failed to deserialize payment tokens"))
+    }
+
+    fn set_payment_token_list(&self, tokens: &Vec<PaymentToken>) -> Result<()> {
+        let serialized: Vec<u8> = bincode::serialize(tokens)
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to serialize payment tokens"))?;
+        self.payment_tokens_pointer().set(Arc::new(serialized));
+        Ok(())
+    }
+}
+
+declare_alkane! {
+  impl AlkaneResponder for Marketplace {
+    type Message = MarketplaceMessage;
+  }
+}
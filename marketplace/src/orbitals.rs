@@ -0,0 +1,136 @@
+use alkanes_runtime::runtime::AlkaneResponder;
+use alkanes_support::{
+  cellpack::Cellpack, id::AlkaneId,
+  parcel::{AlkaneTransfer, AlkaneTransferParcel}, response::CallResponse
+};
+use anyhow::Result;
+use orbital_support::{revert, errors::codes::*};
+
+use crate::Marketplace;
+
+/// Instance opcodes
+const INSTANCE_GET_COLLECTION_IDENTIFIER: u128 = 998;
+const INSTANCE_GET_NFT_INDEX: u128 = 999;
+
+/// Collection opcodes
+const COLLECTION_GET_INSTANCE_ALKANE_ID: u128 = 1001;
+const COLLECTION_GET_ROYALTY_INFO: u128 = 1013;
+const COLLECTION_ACKNOWLEDGE_ESCROW_DEPOSIT: u128 = 234;
+const COLLECTION_RELEASE_ESCROW_DEPOSIT: u128 = 235;
+
+/// Royalty owed on a sale as reported by the collection
+pub(crate) struct RoyaltyDue {
+    pub recipient: AlkaneId,
+    pub amount: u128,
+}
+
+/// Talking to orbital instances and their collection
+impl Marketplace {
+    /// Resolve an orbital instance to its collection and index, confirmed with the collection itself
+    pub(crate) fn resolve_orbital(&self, instance: &AlkaneId) -> Result<(AlkaneId, u128)> {
+        let identifier: Vec<u8> = self.static_query(instance, vec![INSTANCE_GET_COLLECTION_IDENTIFIER])?;
+        let collection: AlkaneId = parse_alkane_identifier(&identifier)?;
+
+        let index_bytes: Vec<u8> = self.static_query(instance, vec![INSTANCE_GET_NFT_INDEX])?;
+        if index_bytes.len() != 16 {
+            return Err(revert!(ERR_EXTERNAL_CALL, "Invalid orbital index response"));
+        }
+        let index: u128 = u128::from_le_bytes(index_bytes[0..16].try_into().unwrap());
+
+        if self.instance_of(&collection, index)? != *instance {
+            return Err(revert!(ERR_NOT_FOUND, "Orbital {}:{} is not an instance of its collection", instance.block, instance.tx));
+        }
+
+        Ok((collection, index))
+    }
+
+    /// Collection and index of a deposited alkane when it is an orbital instance, None for any other token
+    pub(crate) fn orbital_of(&self, token: &AlkaneId, amount: u128) -> Option<(AlkaneId, u128)> {
+        if amount != 1 {
+            return None;
+        }
+
+        self.resolve_orbital(token).ok()
+    }
+
+    /// Instance alkane ID of a collection index
+    pub(crate) fn instance_of(&self, collection: &AlkaneId, index: u128) -> Result<AlkaneId> {
+        let bytes: Vec<u8> = self.static_query(collection, vec![COLLECTION_GET_INSTANCE_ALKANE_ID, index])?;
+        if bytes.len() != 32 {
+            return Err(revert!(ERR_EXTERNAL_CALL, "Invalid instance ID response"));
+        }
+
+        Ok(AlkaneId {
+            block: u128::from_le_bytes(bytes[0..16].try_into().unwrap()),
+            tx: u128::from_le_bytes(bytes[16..32].try_into().unwrap()),
+        })
+    }
+
+    /// Royalty the collection asks for on a sale of the given index
+    pub(crate) fn royalty_due(&self, collection: &AlkaneId, index: u128, sale_amount: u128) -> Result<RoyaltyDue> {
+        let bytes: Vec<u8> = self.static_query(collection, vec![COLLECTION_GET_ROYALTY_INFO, index, sale_amount])?;
+        if bytes.len() != 48 {
+            return Err(revert!(ERR_EXTERNAL_CALL, "Invalid royalty info response"));
+        }
+
+        let amount: u128 = u128::from_le_bytes(bytes[32..48].try_into().unwrap());
+
+        Ok(RoyaltyDue {
+            recipient: AlkaneId {
+                block: u128::from_le_bytes(bytes[0..16].try_into().unwrap()),
+                tx: u128::from_le_bytes(bytes[16..32].try_into().unwrap()),
+            },
+            amount: amount.min(sale_amount),
+        })
+    }
+
+    /// Tell the collection the orbital is now escrowed here; refused when this venue is not approved
+    pub(crate) fn acknowledge_deposit(&self, collection: &AlkaneId, index: u128, instance: &AlkaneId) -> Result<()> {
+        self.escrow_call(collection, COLLECTION_ACKNOWLEDGE_ESCROW_DEPOSIT, index, instance)
+    }
+
+    /// Tell the collection the orbital leaves escrow here, before handing it out
+    pub(crate) fn release_deposit(&self, collection: &AlkaneId, index: u128, instance: &AlkaneId) -> Result<()> {
+        self.escrow_call(collection, COLLECTION_RELEASE_ESCROW_DEPOSIT, index, instance)
+    }
+
+    fn escrow_call(&self, collection: &AlkaneId, opcode: u128, index: u128, instance: &AlkaneId) -> Result<()> {
+        let cellpack: Cellpack = Cellpack {
+            target: collection.clone(),
+            inputs: vec![opcode, index],
+        };
+
+        // The orbital proves we hold it and is forwarded straight back
+        let parcel: AlkaneTransferParcel = AlkaneTransferParcel(vec![AlkaneTransfer {
+            id: instance.clone(),
+            value: 1u128,
+        }]);
+
+        self.call(&cellpack, &parcel, self.fuel())?;
+        Ok(())
+    }
+
+    fn static_query(&self, target: &AlkaneId, inputs: Vec<u128>) -> Result<Vec<u8>> {
+        let cellpack: Cellpack = Cellpack {
+            target: target.clone(),
+            inputs,
+        };
+
+        let response: CallResponse = self.staticcall(&cellpack, &AlkaneTransferParcel::default(), self.fuel())?;
+        Ok(response.data)
+    }
+}
+
+/// Parse a "block:tx" identifier as returned by `GetCollectionIdentifier`
+fn parse_alkane_identifier(bytes: &[u8]) -> Result<AlkaneId> {
+    let identifier: &str = std::str::from_utf8(bytes)
+        .map_err(|_| revert!(ERR_INVALID_INPUT, "Invalid collection identifier"))?;
+
+    let (block, tx) = identifier.split_once(':')
+        .ok_or_else(|| revert!(ERR_INVALID_INPUT, "Invalid collection identifier"))?;
+
+    Ok(AlkaneId {
+        block: block.parse::<u128>().map_err(|_| revert!(ERR_INVALID_INPUT, "Invalid collection identifier"))?,
+        tx: tx.parse::<u128>().map_err(|_| revert!(ERR_INVALID_INPUT, "Invalid collection identifier"))?,
+    })
+}
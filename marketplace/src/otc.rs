@@ -0,0 +1,394 @@
+use metashrew_support::index_pointer::KeyValuePointer;
+use alkanes_runtime::{runtime::AlkaneResponder, storage::StoragePointer};
+use alkanes_support::{
+  id::AlkaneId,
+  parcel::{AlkaneTransfer, AlkaneTransferParcel}, response::CallResponse
+};
+use serde::{Serialize, Deserialize};
+use anyhow::Result;
+use orbital_support::{revert, errors::codes::*};
+use std::sync::Arc;
+
+use crate::{Marketplace, TokenRef, STATUS_ACTIVE, STATUS_CANCELLED, STATUS_SETTLED};
+use crate::tickets::Ticket;
+
+/// Both sides are escrowed, waiting for the maker to confirm or the arbiter to resolve
+const DEAL_FUNDED: u8 = 3;
+
+/// Arbiter released the whole escrow to one side
+const DEAL_RESOLVED: u8 = 4;
+
+/// Both sides were refunded after expiry
+const DEAL_REFUNDED: u8 = 5;
+
+/// Side selectors for `ResolveDeal`
+const SIDE_MAKER: u128 = 0;
+const SIDE_TAKER: u128 = 1;
+
+/// Position in the cellpack inputs (opcode included) where the requested taker assets start
+const PROPOSE_DEAL_ASSETS_OFFSET: usize = 8;
+
+/// Upper bound on assets per side, keeps settlement within fuel
+const MAX_DEAL_ASSETS: usize = 25;
+
+#[derive(Clone, Copy, Default, Debug, Serialize, Deserialize)]
+struct DealAsset {
+    token: TokenRef,
+    amount: u128,
+    /// Collection and index when the asset is an orbital, resolved once it is escrowed
+    orbital: Option<(TokenRef, u128)>,
+}
+
+/// Over-the-counter trade between a maker and a named taker
+#[derive(Clone, Default, Debug, Serialize, Deserialize)]
+struct Deal {
+    id: u128,
+    maker: Ticket,
+    taker: Ticket,
+    maker_assets: Vec<DealAsset>,
+    taker_assets: Vec<DealAsset>,
+    arbiter: Option<TokenRef>,
+    expiry_block: u64,
+    created_block: u64,
+    status: u8,
+}
+
+impl Marketplace {
+    /// Escrow the incoming alkanes as the maker side of a deal.
+    /// The taker is named by an outpoint they control (txid as two little endian u128 halves).
+    /// The requested taker assets follow the fixed inputs as (block, tx, amount) triples.
+    /// Orbitals on either side are acknowledged with their collection, which may refuse this venue,
+    /// and the other side pays their royalties when the swap settles.
+    pub(crate) fn propose_deal(
+        &self,
+        taker_txid_low: u128,
+        taker_txid_high: u128,
+        taker_vout: u128,
+        expiry_block: u128,
+        arbiter_block: u128,
+        arbiter_tx: u128,
+        ticket_vout: u128,
+    ) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::default();
+
+        let mut maker_assets: Vec<DealAsset> = context.incoming_alkanes.0.iter()
+            .map(|transfer| DealAsset { token: TokenRef::from_id(&transfer.id), amount: transfer.value, orbital: None })
+            .collect();
+
+        let taker_assets: Vec<DealAsset> = self.requested_assets(&context.inputs)?;
+
+        if maker_assets.is_empty() || taker_assets.is_empty() {
+            return Err(revert!(ERR_INVALID_INPUT, "Both sides of a deal need at least one asset"));
+        }
+
+        if maker_assets.len() > MAX_DEAL_ASSETS {
+            return Err(revert!(ERR_LIMIT_EXCEEDED, "A deal side holds at most {} assets", MAX_DEAL_ASSETS));
+        }
+
+        let expiry_block: u64 = u64::try_from(expiry_block).map_err(|_| revert!(ERR_INVALID_INPUT, "Expiry block out of range"))?;
+        if expiry_block <= self.height() {
+            return Err(revert!(ERR_INVALID_INPUT, "Deal expiry must be in the future"));
+        }
+
+        self.acknowledge_orbitals(&mut maker_assets)?;
+
+        let mut taker_txid: [u8; 32] = [0u8; 32];
+        taker_txid[..16].copy_from_slice(&taker_txid_low.to_le_bytes());
+        taker_txid[16..].copy_from_slice(&taker_txid_high.to_le_bytes());
+
+        let arbiter: TokenRef = TokenRef { block: arbiter_block, tx: arbiter_tx };
+
+        let deal: Deal = Deal {
+            id: self.next_id(self.deal_count_pointer())?,
+            maker: self.ticket_for_output(ticket_vout)?,
+            taker: Ticket {
+                txid: taker_txid,
+                vout: u32::try_from(taker_vout).map_err(|_| revert!(ERR_INVALID_INPUT, "Taker output out of range"))?,
+            },
+            maker_assets,
+            taker_assets,
+            arbiter: if arbiter.is_zero() { None } else { Some(arbiter) },
+            expiry_block,
+            created_block: self.height(),
+            status: STATUS_ACTIVE,
+        };
+
+        self.set_deal(&deal)?;
+
+        response.data = deal.id.to_le_bytes().to_vec();
+        Ok(response)
+    }
+
+    /// Taker escrows their side. Without an arbiter the swap settles immediately,
+    /// with one it waits for the maker to confirm.
+    pub(crate) fn accept_deal(&self, deal_id: u128) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::default();
+
+        let mut deal: Deal = self.get_deal_by_id(deal_id)?;
+        if deal.status != STATUS_ACTIVE || self.height() > deal.expiry_block {
+            return Err(revert!(ERR_INVALID_STATE, "Deal {} is not open", deal_id));
+        }
+
+        self.observe_ticket_spent(&deal.taker)?;
+
+        let mut rest: Vec<AlkaneTransfer> = context.incoming_alkanes.0.clone();
+        for asset in deal.taker_assets.iter() {
+            let parcel: AlkaneTransferParcel = AlkaneTransferParcel(rest);
+            rest = self.take_payment(&parcel, &asset.token.id(), asset.amount)?;
+        }
+        response.alkanes.0 = rest;
+        self.acknowledge_orbitals(&mut deal.taker_assets)?;
+
+        if deal.arbiter.is_none() {
+            let (to_maker, to_taker) = self.settle_swap(&deal)?;
+            self.credit_assets(&deal.maker, &to_maker)?;
+            response.alkanes.0.extend(asset_transfers(&to_taker));
+            deal.status = STATUS_SETTLED;
+        } else {
+            deal.status = DEAL_FUNDED;
+        }
+
+        self.set_deal(&deal)?;
+        Ok(response)
+    }
+
+    /// Maker confirms a funded deal, releasing each side to the other
+    pub(crate) fn confirm_deal(&self, deal_id: u128) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let mut deal: Deal = self.get_deal_by_id(deal_id)?;
+        if deal.status != DEAL_FUNDED {
+            return Err(revert!(ERR_INVALID_STATE, "Deal {} is not funded", deal_id));
+        }
+
+        self.observe_ticket_spent(&deal.maker)?;
+
+        let (to_maker, to_taker) = self.settle_swap(&deal)?;
+        self.credit_assets(&deal.taker, &to_taker)?;
+        response.alkanes.0.extend(asset_transfers(&to_maker));
+
+        deal.status = STATUS_SETTLED;
+        self.set_deal(&deal)?;
+        Ok(response)
+    }
+
+    /// Maker withdraws an unaccepted deal, at any time before or after expiry
+    pub(crate) fn cancel_deal(&self, deal_id: u128) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let mut deal: Deal = self.get_deal_by_id(deal_id)?;
+        if deal.status != STATUS_ACTIVE {
+            return Err(revert!(ERR_INVALID_STATE, "Deal {} is not open", deal_id));
+        }
+
+        self.observe_ticket_spent(&deal.maker)?;
+
+        self.release_orbitals(&deal.maker_assets)?;
+        response.alkanes.0.extend(asset_transfers(&deal.maker_assets));
+
+        deal.status = STATUS_CANCELLED;
+        self.set_deal(&deal)?;
+        Ok(response)
+    }
+
+    /// Arbiter releases everything escrowed in a funded deal to one side
+    pub(crate) fn resolve_deal(&self, deal_id: u128, side: u128) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let mut deal: Deal = self.get_deal_by_id(deal_id)?;
+        if deal.status != DEAL_FUNDED {
+            return Err(revert!(ERR_INVALID_STATE, "Deal {} is not funded", deal_id));
+        }
+
+        let arbiter: AlkaneId = deal.arbiter
+            .ok_or_else(|| revert!(ERR_INVALID_STATE, "Deal {} has no arbiter", deal_id))?
+            .id();
+
+        let presented: bool = context.incoming_alkanes.0.iter()
+            .any(|transfer| transfer.id == arbiter && transfer.value > 0);
+        if !presented {
+            return Err(revert!(ERR_UNAUTHORIZED, "Arbiter token not presented"));
+        }
+
+        let beneficiary: Ticket = match side {
+            SIDE_MAKER => deal.maker,
+            SIDE_TAKER => deal.taker,
+            _ => return Err(revert!(ERR_NOT_FOUND, "Unknown deal side {}", side)),
+        };
+
+        // An award is not a sale, no royalties are due on it
+        self.release_orbitals(&deal.maker_assets)?;
+        self.release_orbitals(&deal.taker_assets)?;
+        self.credit_assets(&beneficiary, &deal.maker_assets)?;
+        self.credit_assets(&beneficiary, &deal.taker_assets)?;
+
+        deal.status = DEAL_RESOLVED;
+        self.set_deal(&deal)?;
+        Ok(response)
+    }
+
+    /// After expiry an unresolved funded deal returns each side's deposit, callable by anyone
+    pub(crate) fn refund_deal(&self, deal_id: u128) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let mut deal: Deal = self.get_deal_by_id(deal_id)?;
+        if deal.status != DEAL_FUNDED || self.height() <= deal.expiry_block {
+            return Err(revert!(ERR_INVALID_STATE, "Deal {} cannot be refunded yet", deal_id));
+        }
+
+        self.release_orbitals(&deal.maker_assets)?;
+        self.release_orbitals(&deal.taker_assets)?;
+        self.credit_assets(&deal.maker, &deal.maker_assets)?;
+        self.credit_assets(&deal.taker, &deal.taker_assets)?;
+
+        deal.status = DEAL_REFUNDED;
+        self.set_deal(&deal)?;
+        Ok(response)
+    }
+
+    pub(crate) fn get_deal(&self, deal_id: u128) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let deal: Deal = self.get_deal_by_id(deal_id)?;
+        response.data = serde_json::to_vec(&deal)
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to serialize deal"))?;
+        Ok(response)
+    }
+
+    fn requested_assets(&self, inputs: &[u128]) -> Result<Vec<DealAsset>> {
+        let trailing: &[u128] = inputs.get(PROPOSE_DEAL_ASSETS_OFFSET..).unwrap_or(&[]);
+        if trailing.len() % 3 != 0 {
+            return Err(revert!(ERR_INVALID_INPUT, "Requested assets must be (block, tx, amount) triples"));
+        }
+
+        if trailing.len() / 3 > MAX_DEAL_ASSETS {
+            return Err(revert!(ERR_LIMIT_EXCEEDED, "A deal side holds at most {} assets", MAX_DEAL_ASSETS));
+        }
+
+        trailing.chunks(3)
+            .map(|triple| {
+                if triple[2] == 0 {
+                    return Err(revert!(ERR_INVALID_INPUT, "Requested asset amounts must be positive"));
+                }
+                Ok(DealAsset { token: TokenRef { block: triple[0], tx: triple[1] }, amount: triple[2], orbital: None })
+            })
+            .collect()
+    }
+
+    /// Resolve the orbitals among freshly escrowed assets and acknowledge each deposit with its collection
+    fn acknowledge_orbitals(&self, assets: &mut [DealAsset]) -> Result<()> {
+        for asset in assets.iter_mut() {
+            let token: AlkaneId = asset.token.id();
+            asset.orbital = self.orbital_of(&token, asset.amount)
+                .map(|(collection, index)| (TokenRef::from_id(&collection), index));
+
+            if let Some((collection, index)) = asset.orbital {
+                self.acknowledge_deposit(&collection.id(), index, &token)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn release_orbitals(&self, assets: &[DealAsset]) -> Result<()> {
+        for asset in assets.iter() {
+            if let Some((collection, index)) = asset.orbital {
+                self.release_deposit(&collection.id(), index, &asset.token.id())?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Release every orbital of a deal and return what goes to the maker and to the taker,
+    /// each side paying the royalties of the orbitals it receives
+    fn settle_swap(&self, deal: &Deal) -> Result<(Vec<DealAsset>, Vec<DealAsset>)> {
+        self.release_orbitals(&deal.maker_assets)?;
+        self.release_orbitals(&deal.taker_assets)?;
+
+        Ok((
+            self.pay_royalties(&deal.taker_assets, &deal.maker_assets)?,
+            self.pay_royalties(&deal.maker_assets, &deal.taker_assets)?,
+        ))
+    }
+
+    /// Payment assets less the royalties of the orbitals they buy. Each payment asset is
+    /// split evenly over those orbitals, the last absorbing the rounding remainder.
+    fn pay_royalties(&self, payment: &[DealAsset], bought: &[DealAsset]) -> Result<Vec<DealAsset>> {
+        let orbitals: Vec<(TokenRef, u128)> = bought.iter().filter_map(|asset| asset.orbital).collect();
+        if orbitals.is_empty() {
+            return Ok(payment.to_vec());
+        }
+
+        let count: u128 = orbitals.len() as u128;
+        let mut proceeds: Vec<DealAsset> = Vec::with_capacity(payment.len());
+        for asset in payment.iter() {
+            if asset.orbital.is_some() {
+                proceeds.push(*asset);
+                continue;
+            }
+
+            let token: AlkaneId = asset.token.id();
+            let share: u128 = asset.amount / count;
+            let mut amount: u128 = 0;
+            for (position, (collection, index)) in orbitals.iter().enumerate() {
+                let sale_amount: u128 = if position + 1 == orbitals.len() {
+                    asset.amount - share * (count - 1)
+                } else {
+                    share
+                };
+                amount += self.settle_royalty(&collection.id(), *index, &token, sale_amount)?;
+            }
+
+            proceeds.push(DealAsset { amount, ..*asset });
+        }
+
+        Ok(proceeds)
+    }
+
+    fn credit_assets(&self, ticket: &Ticket, assets: &[DealAsset]) -> Result<()> {
+        for asset in assets.iter() {
+            self.credit_ticket(ticket, &asset.token.id(), asset.amount)?;
+        }
+
+        Ok(())
+    }
+
+    fn deal_count_pointer(&self) -> StoragePointer {
+        StoragePointer::from_keyword("/deals/count")
+    }
+
+    fn deal_pointer(&self, deal_id: u128) -> StoragePointer {
+        StoragePointer::from_keyword("/deals/").select(&deal_id.to_le_bytes().to_vec())
+    }
+
+    fn set_deal(&self, deal: &Deal) -> Result<()> {
+        let serialized: Vec<u8> = bincode::serialize(deal)
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to serialize deal"))?;
+        self.deal_pointer(deal.id).set(Arc::new(serialized));
+        Ok(())
+    }
+
+    fn get_deal_by_id(&self, deal_id: u128) -> Result<Deal> {
+        let stored: Arc<Vec<u8>> = self.deal_pointer(deal_id).get();
+        if stored.is_empty() {
+            return Err(revert!(ERR_NOT_FOUND, "Deal {} not found", deal_id));
+        }
+
+        bincode::deserialize(&stored).map_err(|_| revert!(ERR_STORAGE, "Failed to deserialize deal"))
+    }
+}
+
+fn asset_transfers(assets: &[DealAsset]) -> Vec<AlkaneTransfer> {
+    assets.iter()
+        .filter(|asset| asset.amount > 0)
+        .map(|asset| AlkaneTransfer { id: asset.token.id(), value: asset.amount })
+        .collect()
+}
@@ -0,0 +1,183 @@
+use metashrew_support::index_pointer::KeyValuePointer;
+use alkanes_runtime::{runtime::AlkaneResponder, storage::StoragePointer};
+use alkanes_support::{id::AlkaneId, parcel::AlkaneTransfer, response::CallResponse};
+use bitcoin::{consensus::deserialize, hashes::{sha256, Hash}, Transaction};
+use serde::{Serialize, Deserialize};
+use anyhow::Result;
+use orbital_support::{revert, errors::codes::*};
+use std::sync::Arc;
+
+use crate::{Marketplace, TokenRef};
+
+/// Outpoint created by the transaction that opened a listing or offer.
+/// Spending it in a later transaction proves control over what was escrowed
+/// for the absent party, since only the key holder of that output can do so.
+#[derive(Clone, Copy, Default, Debug, PartialEq, Serialize, Deserialize)]
+pub(crate) struct Ticket {
+    pub txid: [u8; 32],
+    pub vout: u32,
+}
+
+impl Ticket {
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes: Vec<u8> = self.txid.to_vec();
+        bytes.extend_from_slice(&self.vout.to_le_bytes());
+        bytes
+    }
+}
+
+/// Balance of a single alkane owed to a ticket or royalty recipient
+#[derive(Clone, Copy, Default, Debug, Serialize, Deserialize)]
+struct Claimable {
+    token: TokenRef,
+    amount: u128,
+}
+
+impl Marketplace {
+    /// Pay out everything owed to the tickets spent by this transaction
+    pub(crate) fn claim(&self) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let tx: Transaction = self.current_transaction()?;
+        for input in tx.input.iter() {
+            let ticket: Ticket = Ticket {
+                txid: input.previous_output.txid.to_byte_array(),
+                vout: input.previous_output.vout,
+            };
+            response.alkanes.0.extend(self.drain(self.ticket_claims_pointer(&ticket))?);
+        }
+
+        Ok(response)
+    }
+
+    /// Pay out royalties accrued for a recipient, who must present its token
+    pub(crate) fn claim_royalties(&self, recipient_block: u128, recipient_tx: u128) -> Result<CallResponse> {
+        let context: alkanes_support::context::Context = self.context()?;
+        let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+        let recipient: AlkaneId = AlkaneId { block: recipient_block, tx: recipient_tx };
+        let presented: bool = context.incoming_alkanes.0.iter()
+            .any(|transfer| transfer.id == recipient && transfer.value > 0);
+
+        if !presented {
+            return Err(revert!(ERR_UNAUTHORIZED, "Royalty recipient token not presented"));
+        }
+
+        response.alkanes.0.extend(self.drain(self.royalty_claims_pointer(&recipient))?);
+        Ok(response)
+    }
+
+    pub(crate) fn current_transaction(&self) -> Result<Transaction> {
+        deserialize::<Transaction>(&self.transaction())
+            .map_err(|_| revert!(ERR_FAILED, "Failed to decode transaction"))
+    }
+
+    /// Script hash of the first spendable output of the current transaction
+    pub(crate) fn sender_identity(&self) -> Result<[u8; 32]> {
+        let tx: Transaction = self.current_transaction()?;
+        let output = tx.output.iter()
+            .find(|output| !output.script_pubkey.is_op_return())
+            .ok_or_else(|| revert!(ERR_INVALID_INPUT, "Transaction has no spendable output"))?;
+
+        Ok(sha256::Hash::hash(output.script_pubkey.as_bytes()).to_byte_array())
+    }
+
+    /// Ticket for an output of the current transaction
+    pub(crate) fn ticket_for_output(&self, vout: u128) -> Result<Ticket> {
+        let tx: Transaction = self.current_transaction()?;
+        let vout: u32 = u32::try_from(vout).map_err(|_| revert!(ERR_INVALID_INPUT, "Ticket output out of range"))?;
+
+        let output = tx.output.get(vout as usize)
+            .ok_or_else(|| revert!(ERR_INVALID_INPUT, "Ticket output {} does not exist", vout))?;
+
+        if output.script_pubkey.is_op_return() {
+            return Err(revert!(ERR_INVALID_INPUT, "Ticket output {} is an OP_RETURN", vout));
+        }
+
+        Ok(Ticket {
+            txid: tx.compute_txid().to_byte_array(),
+            vout,
+        })
+    }
+
+    /// Require the current transaction to spend the ticket outpoint
+    pub(crate) fn observe_ticket_spent(&self, ticket: &Ticket) -> Result<()> {
+        let tx: Transaction = self.current_transaction()?;
+
+        let spent: bool = tx.input.iter().any(|input| {
+            input.previous_output.txid.to_byte_array() == ticket.txid
+                && input.previous_output.vout == ticket.vout
+        });
+
+        if !spent {
+            return Err(revert!(ERR_UNAUTHORIZED, "Transaction does not spend the ticket outpoint"));
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn credit_ticket(&self, ticket: &Ticket, token: &AlkaneId, amount: u128) -> Result<()> {
+        self.credit(self.ticket_claims_pointer(ticket), token, amount)
+    }
+
+    pub(crate) fn credit_royalty(&self, recipient: &AlkaneId, token: &AlkaneId, amount: u128) -> Result<()> {
+        self.credit(self.royalty_claims_pointer(recipient), token, amount)
+    }
+
+    fn credit(&self, mut pointer: StoragePointer, token: &AlkaneId, amount: u128) -> Result<()> {
+        if amount == 0 {
+            return Ok(());
+        }
+
+        let mut claimables: Vec<Claimable> = self.read_claimables(&pointer)?;
+        let token_ref: TokenRef = TokenRef::from_id(token);
+
+        match claimables.iter_mut().find(|claimable| claimable.token == token_ref) {
+            Some(claimable) => {
+                claimable.amount = claimable.amount.checked_add(amount)
+                    .ok_or_else(|| revert!(ERR_OVERFLOW, "claimable balance overflow"))?;
+            }
+            None => claimables.push(Claimable { token: token_ref, amount }),
+        }
+
+        let serialized: Vec<u8> = bincode::serialize(&claimables)
+            .map_err(|_| revert!(ERR_STORAGE, "Failed to serialize claimables"))?;
+        pointer.set(Arc::new(serialized));
+        Ok(())
+    }
+
+    fn drain(&self, mut pointer: StoragePointer) -> Result<Vec<AlkaneTransfer>> {
+        let claimables: Vec<Claimable> = self.read_claimables(&pointer)?;
+        if claimables.is_empty() {
+            return Ok(vec![]);
+        }
+
+        pointer.set(Arc::new(vec![]));
+
+        Ok(claimables.iter()
+            .map(|claimable| AlkaneTransfer { id: claimable.token.id(), value: claimable.amount })
+            .collect())
+    }
+
+    fn read_claimables(&self, pointer: &StoragePointer) -> Result<Vec<Claimable>> {
+        let stored: Arc<Vec<u8>> = pointer.get();
+        if stored.is_empty() {
+            return Ok(vec![]);
+        }
+
+        bincode::deserialize(&stored).map_err(|_| revert!(ERR_STORAGE, "Failed to deserialize claimables"))
+    }
+
+    fn ticket_claims_pointer(&self, ticket: &Ticket) -> StoragePointer {
+        StoragePointer::from_keyword("/claims/").select(&ticket.to_bytes())
+    }
+
+    fn royalty_claims_pointer(&self, recipient: &AlkaneId) -> StoragePointer {
+        let mut bytes: Vec<u8> = Vec::with_capacity(32);
+        bytes.extend_from_slice(&recipient.block.to_le_bytes());
+        bytes.extend_from_slice(&recipient.tx.to_le_bytes());
+
+        StoragePointer::from_keyword("/royalties/").select(&bytes)
+    }
+}
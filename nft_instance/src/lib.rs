@@ -12,9 +12,33 @@ use alkanes_support::{
 };
 
 use anyhow::Result;
+use orbital_support::{revert, errors::{codes::*, Revert}};
 // use protorune_support::balance_sheet::IntoString;
 use std::sync::Arc;
 
+/// Version of the collection <-> instance protocol implemented by this instance
+const PROTOCOL_VERSION: u128 = 2;
+
+/// Oldest collection protocol version this instance can talk to
+const MIN_SUPPORTED_PROTOCOL_VERSION: u128 = 2;
+
+/// Collection opcodes the instance forwards to
+const COLLECTION_GET_ATTRIBUTES: u128 = 999;
+const COLLECTION_GET_DATA: u128 = 1000;
+const COLLECTION_GET_DATA_URI: u128 = 1004;
+const COLLECTION_GET_METADATA: u128 = 1005;
+const COLLECTION_GET_THUMBNAIL: u128 = 1006;
+const COLLECTION_GET_CONTENT_TYPE: u128 = 1007;
+const COLLECTION_GET_METADATA_STATE: u128 = 1008;
+const COLLECTION_GET_DATA_HASH: u128 = 1094;
+const COLLECTION_GET_PROTOCOL_VERSION: u128 = 103;
+
+/// Formats accepted by `GetDataAs`
+const FORMAT_SVG: u128 = 0;
+const FORMAT_DATA_URI: u128 = 1;
+const FORMAT_JSON_METADATA: u128 = 2;
+const FORMAT_THUMBNAIL: u128 = 3;
+
 #[derive(Default)]
 pub struct OrbitalInstance(());
 
@@ -25,8 +49,10 @@ enum OrbitalInstanceMessage {
   #[opcode(0)]
   Initialize {
     index: u128,
-    name: u128,
-    symbol: u128
+    name_part1: u128,
+    name_part2: u128,
+    symbol: u128,
+    protocol_version: u128
   },
 
   #[opcode(99)]
@@ -41,6 +67,10 @@ enum OrbitalInstanceMessage {
   #[returns(u128)]
   GetTotalSupply,
 
+  #[opcode(103)]
+  #[returns(u128)]
+  GetProtocolVersion,
+
   #[opcode(998)]
   #[returns(String)]
   GetCollectionIdentifier,
@@ -60,6 +90,17 @@ enum OrbitalInstanceMessage {
   #[opcode(1002)]
   #[returns(String)]
   GetAttributes,
+
+  #[opcode(1003)]
+  #[returns(Vec<u8>)]
+  GetDataAs { format: u128 },
+
+  #[opcode(1004)]
+  #[returns(Vec<u8>)]
+  GetPreview,
+
+  #[opcode(1005)]
+  RefreshCache,
 }
 
 impl Token for OrbitalInstance {
@@ -77,15 +118,17 @@ impl Token for OrbitalInstance {
 impl OrbitalInstance {
   /// Initialize the NFT instance with a given index
   /// Opcode: 0
-  fn initialize(&self, index: u128, name: u128, symbol: u128) -> Result<CallResponse> {
+  fn initialize(&self, index: u128, name_part1: u128, name_part2: u128, symbol: u128, protocol_version: u128) -> Result<CallResponse> {
     let context = self.context()?;
     let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
 
     self.observe_initialization()?;
+    self.observe_protocol_version(protocol_version)?;
 
     self.set_collection_alkane_id(&context.caller);
+    self.set_collection_protocol_version(protocol_version);
     self.set_index(index);
-    let _ = self.save_name_to_pointer(self.decode_u128_to_string(name));
+    let _ = self.save_name_to_pointer(self.decode_name(name_part1, name_part2));
     let _ = self.save_symbol_to_pointer(self.decode_u128_to_string(symbol));
 
     response.alkanes.0.push(AlkaneTransfer {
@@ -129,6 +172,17 @@ impl OrbitalInstance {
     Ok(response)
   }
 
+  /// Get the protocol version implemented by this instance
+  /// Opcode: 103
+  fn get_protocol_version(&self) -> Result<CallResponse> {
+    let context: alkanes_support::context::Context = self.context()?;
+    let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+    response.data = PROTOCOL_VERSION.to_le_bytes().to_vec();
+
+    Ok(response)
+  }
+
   /// Get the collection identifier
   /// Opcode: 998
   fn get_collection_identifier(&self) -> Result<CallResponse> {
@@ -158,20 +212,7 @@ impl OrbitalInstance {
     let context: alkanes_support::context::Context = self.context()?;
     let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
 
-    let collection_id: AlkaneId = self.collection_ref();
-
-    let cellpack: Cellpack = Cellpack {
-      target: collection_id,
-      inputs: vec![1000, self.index()],
-    };
-
-    let call_response: CallResponse = self.staticcall(
-      &cellpack,
-      &AlkaneTransferParcel::default(),
-      self.fuel()
-    )?;
-
-    response.data = call_response.data;
+    response.data = self.call_collection(COLLECTION_GET_DATA)?;
 
     Ok(response)
   }
@@ -182,7 +223,7 @@ impl OrbitalInstance {
     let context: alkanes_support::context::Context = self.context()?;
     let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
 
-    response.data = String::from("image/svg+xml").into_bytes().to_vec();
+    response.data = self.content_type()?;
 
     Ok(response)
   }
@@ -193,11 +234,90 @@ impl OrbitalInstance {
     let context: alkanes_support::context::Context = self.context()?;
     let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
 
-    let collection_id: AlkaneId = self.collection_ref();
+    response.data = self.call_collection_cached(COLLECTION_GET_ATTRIBUTES)?;
+
+    Ok(response)
+  }
+
+  /// Get the NFT data in the requested format
+  /// Opcode: 1003
+  fn get_data_as(&self, format: u128) -> Result<CallResponse> {
+    let context: alkanes_support::context::Context = self.context()?;
+    let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+    let opcode: u128 = self.collection_opcode_for_format(format)?;
+    response.data = self.call_collection(opcode)?;
+
+    Ok(response)
+  }
+
+  /// Get what a wallet list view shows without rendering: the index, the collection block
+  /// and tx as u128 little endian, the 32 byte SHA-256 of the data, then the content type and
+  /// the name, each behind a u16 little endian length. The hash is the one the collection
+  /// reports through `GetDataHash`.
+  /// Opcode: 1004
+  fn get_preview(&self) -> Result<CallResponse> {
+    let context: alkanes_support::context::Context = self.context()?;
+    let mut response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+    let collection: AlkaneId = self.collection_ref();
+    let content_type: Vec<u8> = self.content_type()?;
+    let name: String = self.name();
+
+    let mut data: Vec<u8> = Vec::with_capacity(48 + 32 + 4 + content_type.len() + name.len());
+    data.extend_from_slice(&self.index().to_le_bytes());
+    data.extend_from_slice(&collection.block.to_le_bytes());
+    data.extend_from_slice(&collection.tx.to_le_bytes());
+    data.extend_from_slice(&self.data_hash()?);
+    data.extend_from_slice(&(content_type.len() as u16).to_le_bytes());
+    data.extend_from_slice(&content_type);
+    data.extend_from_slice(&(name.len() as u16).to_le_bytes());
+    data.extend_from_slice(name.as_bytes());
+    response.data = data;
+
+    Ok(response)
+  }
+
+  /// Store the collection answers that cannot change under its current metadata version:
+  /// the content type, and once the collection froze its frozen version and the attributes.
+  /// Views serve them from here and call through while nothing matching is stored. Anyone
+  /// can refresh.
+  /// Opcode: 1005
+  fn refresh_cache(&self) -> Result<CallResponse> {
+    let context: alkanes_support::context::Context = self.context()?;
+    let response: CallResponse = CallResponse::forward(&context.incoming_alkanes);
+
+    // Cached answers outlive the collection, make sure they were produced by the version we expect
+    self.observe_collection_protocol()?;
+
+    let content_type: Vec<u8> = self.call_collection(COLLECTION_GET_CONTENT_TYPE)?;
+    self.content_type_cache_pointer().set(Arc::new(content_type));
+
+    if let Some(version) = self.collection_freeze()? {
+      // A freeze is final, later views read it from here instead of asking the collection
+      self.frozen_version_pointer().set_value::<u128>(version);
+      let attributes: Vec<u8> = self.call_collection(COLLECTION_GET_ATTRIBUTES)?;
+      self.answer_cache_pointer(version, COLLECTION_GET_ATTRIBUTES).set(Arc::new(attributes));
+    }
+
+    Ok(response)
+  }
 
+  // Helper functions
+  /// Refuse collections speaking a protocol version this instance does not understand
+  fn observe_protocol_version(&self, version: u128) -> Result<()> {
+    if version < MIN_SUPPORTED_PROTOCOL_VERSION || version > PROTOCOL_VERSION {
+      return Err(revert!(ERR_EXTERNAL_CALL, "unsupported collection protocol version {}", version));
+    }
+
+    Ok(())
+  }
+
+  /// Re-check the live collection version against the one announced at initialization
+  fn observe_collection_protocol(&self) -> Result<()> {
     let cellpack: Cellpack = Cellpack {
-      target: collection_id,
-      inputs: vec![1002, self.index()],
+      target: self.collection_ref(),
+      inputs: vec![COLLECTION_GET_PROTOCOL_VERSION],
     };
 
     let call_response: CallResponse = self.staticcall(
@@ -206,12 +326,131 @@ impl OrbitalInstance {
       self.fuel()
     )?;
 
-    response.data = call_response.data;
+    if call_response.data.len() != 16 {
+      return Err(revert!(ERR_EXTERNAL_CALL, "Invalid collection protocol version response"));
+    }
 
-    Ok(response)
+    let version: u128 = u128::from_le_bytes(call_response.data[0..16].try_into().unwrap());
+    if version != self.collection_protocol_version() {
+      return Err(Revert::new(ERR_EXTERNAL_CALL, format!(
+        "collection protocol version changed from {} to {}",
+        self.collection_protocol_version(),
+        version
+      )).field("protocol_version").expected(self.collection_protocol_version()).actual(version).into());
+    }
+
+    Ok(())
+  }
+
+  /// Protocol version announced by the collection at initialization
+  fn collection_protocol_version(&self) -> u128 {
+    self.collection_protocol_version_pointer().get_value::<u128>()
+  }
+
+  fn set_collection_protocol_version(&self, version: u128) {
+    self.collection_protocol_version_pointer().set_value::<u128>(version);
+  }
+
+  fn collection_protocol_version_pointer(&self) -> StoragePointer {
+    StoragePointer::from_keyword("/collection-protocol-version")
+  }
+
+  /// Map a `GetDataAs` format to the collection opcode serving it
+  fn collection_opcode_for_format(&self, format: u128) -> Result<u128> {
+    match format {
+      FORMAT_SVG => Ok(COLLECTION_GET_DATA),
+      FORMAT_DATA_URI => Ok(COLLECTION_GET_DATA_URI),
+      FORMAT_JSON_METADATA => Ok(COLLECTION_GET_METADATA),
+      FORMAT_THUMBNAIL => Ok(COLLECTION_GET_THUMBNAIL),
+      _ => Err(revert!(ERR_INVALID_INPUT, "unsupported data format {}", format)),
+    }
+  }
+
+  /// Content type never changes for a collection, served from the cache once refreshed
+  fn content_type(&self) -> Result<Vec<u8>> {
+    let cached: Arc<Vec<u8>> = self.content_type_cache_pointer().get();
+    if cached.len() > 0 {
+      return Ok(cached.as_ref().clone());
+    }
+
+    self.call_collection(COLLECTION_GET_CONTENT_TYPE)
+  }
+
+  /// Serve a collection answer cached under its live metadata version, calling through otherwise
+  fn call_collection_cached(&self, opcode: u128) -> Result<Vec<u8>> {
+    if let Some(version) = self.collection_freeze()? {
+      let cached: Arc<Vec<u8>> = self.answer_cache_pointer(version, opcode).get();
+      if cached.len() > 0 {
+        return Ok(cached.as_ref().clone());
+      }
+    }
+
+    self.call_collection(opcode)
+  }
+
+  /// Metadata version of the collection when it is frozen, None while it can still change.
+  /// Once `RefreshCache` stored the freeze, the collection is not asked again.
+  fn collection_freeze(&self) -> Result<Option<u128>> {
+    let stored: StoragePointer = self.frozen_version_pointer();
+    if stored.get().len() > 0 {
+      return Ok(Some(stored.get_value::<u128>()));
+    }
+
+    let data: Vec<u8> = self.call_collection(COLLECTION_GET_METADATA_STATE)?;
+    if data.len() != 32 {
+      return Err(revert!(ERR_INVALID_INPUT, "Invalid metadata state length"));
+    }
+
+    let frozen: u128 = u128::from_le_bytes(data[0..16].try_into().unwrap());
+    let version: u128 = u128::from_le_bytes(data[16..32].try_into().unwrap());
+
+    if frozen == 0 {
+      return Ok(None);
+    }
+
+    Ok(Some(version))
+  }
+
+  /// Cached answers are keyed by the frozen metadata version, so a version change invalidates them
+  fn answer_cache_pointer(&self, version: u128, opcode: u128) -> StoragePointer {
+    StoragePointer::from_keyword("/cache/")
+      .select(&version.to_le_bytes().to_vec())
+      .keyword("/")
+      .select(&opcode.to_le_bytes().to_vec())
+  }
+
+  /// SHA-256 of the data this orbital serves, the first 32 bytes of the collection's `GetDataHash`
+  fn data_hash(&self) -> Result<[u8; 32]> {
+    let data: Vec<u8> = self.call_collection(COLLECTION_GET_DATA_HASH)?;
+    data.get(0..32)
+      .and_then(|hash| hash.try_into().ok())
+      .ok_or_else(|| revert!(ERR_EXTERNAL_CALL, "Invalid collection data hash response"))
+  }
+
+  fn content_type_cache_pointer(&self) -> StoragePointer {
+    StoragePointer::from_keyword("/cache/content-type")
+  }
+
+  fn frozen_version_pointer(&self) -> StoragePointer {
+    StoragePointer::from_keyword("/cache/frozen-version")
+  }
+
+  /// Staticcall a collection opcode for this index and return the response data
+  fn call_collection(&self, opcode: u128) -> Result<Vec<u8>> {
+    let cellpack: Cellpack = Cellpack {
+      target: self.collection_ref(),
+      inputs: vec![opcode, self.index()],
+    };
+
+    let call_response: CallResponse = self.staticcall(
+      &cellpack,
+      &AlkaneTransferParcel::default(),
+      self.fuel()
+    )?;
+
+    Ok(call_response.data)
   }
 
-  // Helper functions
   /// Set the collection Alkane ID
   fn set_collection_alkane_id(&self, id: &AlkaneId) {
     let mut bytes: Vec<u8> = Vec::with_capacity(32);
@@ -358,6 +597,15 @@ impl OrbitalInstance {
   }
 
   /// Converts a u128 to a string
+  /// Name packed into two parts by the collection, the second only counts when the first is full
+  fn decode_name(&self, part1: u128, part2: u128) -> String {
+    let mut name: String = self.decode_u128_to_string(part1);
+    if name.chars().count() == 16 {
+      name.push_str(&self.decode_u128_to_string(part2));
+    }
+    name
+  }
+
   fn decode_u128_to_string(&self, encoded: u128) -> String {
     let mut result: String = String::new();
     for i in 0..16 {
@@ -0,0 +1,234 @@
+//! Native build of the collection's rendering pipeline, for marketplaces and indexers that
+//! render thousands of orbitals off-chain and check their output against `GetDataHash`
+//! instead of calling `GetData` for each. The engines are the contract's own sources, so
+//! a rendering here is byte for byte what the collection serves.
+//!
+//! A collection draws from the built in trait table until the owner activates an uploaded
+//! one; `GetRenderEngineInfo` names the engine and the active table version, and uploaded
+//! tables are the `compress::pack` blobs of their JSON document, as sent in the
+//! `UploadTraitTableChunk` transactions. Honorary orbitals show uploaded art that no engine
+//! reproduces, `GetDataHash` flags them.
+
+#[path = "../../collection/src/svg_generator.rs"]
+pub mod svg_generator;
+
+#[path = "../../collection/src/render_engine.rs"]
+pub mod render_engine;
+
+#[path = "../../collection/src/parametric.rs"]
+mod parametric;
+
+use anyhow::Result;
+use orbital_support::{errors::{codes::*, Revert}, compress, hex, sha256};
+
+use crate::render_engine::RenderEngine;
+use crate::svg_generator::{RenderConfig, SvgGenerator, TraitTable};
+
+/// Length of a `GetDataHash` response
+pub const DATA_HASH_LEN: usize = 66;
+
+/// What `GetDataHash` reports for an orbital
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DataHash {
+    /// sha256 of the `GetData` payload
+    pub digest: [u8; 32],
+    /// Trait entry the orbital draws, its index unless a reveal shuffled it
+    pub art: u128,
+    pub traits_version: u128,
+    pub preview: bool,
+    pub honorary: bool,
+}
+
+impl DataHash {
+    /// Decode the response data of `GetDataHash`
+    pub fn parse(data: &[u8]) -> Result<DataHash> {
+        if data.len() != DATA_HASH_LEN {
+            return Err(Revert::new(ERR_INVALID_INPUT, "Invalid data hash response")
+                .field("length").expected(DATA_HASH_LEN as u128).actual(data.len() as u128).into());
+        }
+
+        Ok(DataHash {
+            digest: data[..32].try_into().unwrap(),
+            art: u128::from_le_bytes(data[32..48].try_into().unwrap()),
+            traits_version: u128::from_le_bytes(data[48..64].try_into().unwrap()),
+            preview: data[64] == 1,
+            honorary: data[65] == 1,
+        })
+    }
+
+    /// Decode the response data of `GetDataHash` as hex, the way RPC clients return it
+    pub fn from_hex(data: &str) -> Result<DataHash> {
+        let bytes: Vec<u8> = hex::decode(data.strip_prefix("0x").unwrap_or(data))
+            .ok_or_else(|| Revert::new(ERR_INVALID_INPUT, "Data hash response is not hex"))?;
+        Self::parse(&bytes)
+    }
+
+    /// Response data of `GetDataHash` reporting this hash
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut data: Vec<u8> = Vec::with_capacity(DATA_HASH_LEN);
+        data.extend_from_slice(&self.digest);
+        data.extend_from_slice(&self.art.to_le_bytes());
+        data.extend_from_slice(&self.traits_version.to_le_bytes());
+        data.push(self.preview as u8);
+        data.push(self.honorary as u8);
+        data
+    }
+}
+
+/// Outcome of checking one orbital
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Verdict {
+    Match,
+    /// The rendering hashes to `actual` instead of the digest the collection reported
+    Mismatch { actual: [u8; 32] },
+    /// The orbital shows honorary art, there is nothing to reproduce
+    Honorary,
+}
+
+/// Engine of a collection drawing from one trait table version
+pub struct Renderer {
+    engine: Box<dyn RenderEngine>,
+    traits_version: u128,
+}
+
+impl Renderer {
+    /// Renderer drawing from the built in table, trait table version 0
+    pub fn builtin(engine: u128) -> Result<Renderer> {
+        Self::with_table(engine, 0, TraitTable::builtin())
+    }
+
+    /// Renderer drawing from an uploaded table version, given its JSON document
+    pub fn from_table(engine: u128, traits_version: u128, json: &[u8]) -> Result<Renderer> {
+        Self::with_table(engine, traits_version, TraitTable::parse(json)?)
+    }
+
+    /// Renderer drawing from an uploaded table version, given its chunks as uploaded,
+    /// concatenated in order
+    pub fn from_packed_table(engine: u128, traits_version: u128, packed: &[u8]) -> Result<Renderer> {
+        Self::from_table(engine, traits_version, &compress::unpack(packed)?)
+    }
+
+    fn with_table(engine: u128, traits_version: u128, table: TraitTable) -> Result<Renderer> {
+        table.validate()?;
+        Ok(Renderer {
+            engine: render_engine::build(engine, SvgGenerator::new(table))?,
+            traits_version,
+        })
+    }
+
+    /// `GetData` payload of an orbital drawing entry `art`
+    pub fn data(&self, art: u128, preview: bool) -> Result<String> {
+        self.engine.render(art, &RenderConfig { preview, traits_version: self.traits_version })
+    }
+
+    /// sha256 of the `GetData` payload of an orbital drawing entry `art`
+    pub fn data_hash(&self, art: u128, preview: bool) -> Result<[u8; 32]> {
+        Ok(sha256::hash(self.data(art, preview)?.as_bytes()))
+    }
+
+    /// Render what `GetDataHash` reported and compare the digests. Hashes taken under
+    /// another trait table version are refused, they need the renderer of that version.
+    pub fn verify(&self, expected: &DataHash) -> Result<Verdict> {
+        if expected.honorary {
+            return Ok(Verdict::Honorary);
+        }
+
+        if expected.traits_version != self.traits_version {
+            return Err(Revert::new(ERR_INVALID_INPUT, "Data hash was taken under another trait table")
+                .field("traits_version").expected(self.traits_version).actual(expected.traits_version).into());
+        }
+
+        let actual: [u8; 32] = self.data_hash(expected.art, expected.preview)?;
+        Ok(if actual == expected.digest { Verdict::Match } else { Verdict::Mismatch { actual } })
+    }
+
+    /// Verify a batch of `(index, hash)` pairs, returning the indices whose rendering does
+    /// not match. Honorary orbitals are skipped.
+    pub fn verify_batch(&self, hashes: &[(u128, DataHash)]) -> Result<Vec<u128>> {
+        let mut mismatches: Vec<u128> = Vec::new();
+        for (index, expected) in hashes {
+            if let Verdict::Mismatch { .. } = self.verify(expected)? {
+                mismatches.push(*index);
+            }
+        }
+
+        Ok(mismatches)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reported(digest: [u8; 32]) -> DataHash {
+        DataHash { digest, art: 7, traits_version: 0, preview: false, honorary: false }
+    }
+
+    #[test]
+    fn parse_rejects_wrong_length() {
+        assert!(DataHash::parse(&[0u8; DATA_HASH_LEN - 1]).is_err());
+        assert!(DataHash::parse(&[0u8; DATA_HASH_LEN + 1]).is_err());
+        assert!(DataHash::parse(&[]).is_err());
+    }
+
+    #[test]
+    fn from_hex_rejects_bad_hex() {
+        let valid: String = hex::encode(&reported([0xab; 32]).to_bytes());
+
+        assert!(DataHash::from_hex(&valid.replacen('0', "g", 1)).is_err());
+        assert!(DataHash::from_hex(&valid[1..]).is_err());
+        assert!(DataHash::from_hex(&format!("0x{}", valid.to_uppercase().replacen('A', "Z", 1))).is_err());
+    }
+
+    #[test]
+    fn parse_and_format_round_trip() {
+        let hash: DataHash = DataHash {
+            digest: [0xab; 32],
+            art: u128::MAX - 1,
+            traits_version: 3,
+            preview: true,
+            honorary: true,
+        };
+
+        let data: Vec<u8> = hash.to_bytes();
+        assert_eq!(data.len(), DATA_HASH_LEN);
+        assert_eq!(DataHash::parse(&data).unwrap(), hash);
+        assert_eq!(DataHash::from_hex(&hex::encode(&data)).unwrap(), hash);
+        assert_eq!(DataHash::from_hex(&format!("0x{}", hex::encode(&data))).unwrap(), hash);
+    }
+
+    #[test]
+    fn verify_matches_the_rendering() {
+        let renderer: Renderer = Renderer::builtin(render_engine::ENGINE_LAYERED).unwrap();
+        let digest: [u8; 32] = renderer.data_hash(7, false).unwrap();
+
+        assert_eq!(renderer.verify(&reported(digest)).unwrap(), Verdict::Match);
+    }
+
+    #[test]
+    fn verify_reports_a_mismatch() {
+        let renderer: Renderer = Renderer::builtin(render_engine::ENGINE_LAYERED).unwrap();
+        let actual: [u8; 32] = renderer.data_hash(7, false).unwrap();
+
+        let mut digest: [u8; 32] = actual;
+        digest[0] ^= 0xff;
+        assert_eq!(renderer.verify(&reported(digest)).unwrap(), Verdict::Mismatch { actual });
+        assert_eq!(renderer.verify_batch(&[(7, reported(digest)), (8, reported(actual))]).unwrap(), vec![7]);
+    }
+
+    #[test]
+    fn verify_refuses_other_trait_table_versions() {
+        let renderer: Renderer = Renderer::builtin(render_engine::ENGINE_LAYERED).unwrap();
+        let hash: DataHash = DataHash { traits_version: 1, ..reported([0u8; 32]) };
+
+        assert!(renderer.verify(&hash).is_err());
+    }
+
+    #[test]
+    fn verify_skips_honorary_orbitals() {
+        let renderer: Renderer = Renderer::builtin(render_engine::ENGINE_LAYERED).unwrap();
+        let hash: DataHash = DataHash { honorary: true, ..reported([0u8; 32]) };
+
+        assert_eq!(renderer.verify(&hash).unwrap(), Verdict::Honorary);
+    }
+}
@@ -0,0 +1,80 @@
+//! Golden image regression for the render engines. `render_fixtures.json` records the
+//! sha256 of what every engine draws for a fixed set of built in trait entries, in full and
+//! thumbnail size, with and without the preview watermark. A change to a generator that
+//! would alter the art of live collections fails here before it ships; fixtures are only
+//! regenerated for an intended change of the art.
+
+use orbital_render_verify::render_engine::{self, RenderEngine};
+use orbital_render_verify::svg_generator::{RenderConfig, SvgGenerator, TraitTable};
+use orbital_support::{hex, sha256};
+use serde::Deserialize;
+
+const RENDER_FIXTURES_JSON: &str = include_str!("render_fixtures.json");
+
+/// Renderings a fixture can pin
+const MODES: [&str; 4] = ["render", "preview", "thumbnail", "preview-thumbnail"];
+
+#[derive(Deserialize)]
+struct RenderFixtures {
+    fixtures: Vec<RenderFixture>,
+}
+
+/// Digest of one rendering of a built in trait entry
+#[derive(Deserialize)]
+struct RenderFixture {
+    engine: u128,
+    mode: String,
+    art: u128,
+    sha256: String,
+}
+
+fn fixtures() -> Vec<RenderFixture> {
+    serde_json::from_str::<RenderFixtures>(RENDER_FIXTURES_JSON).unwrap().fixtures
+}
+
+fn rendering(engine: &dyn RenderEngine, mode: &str, art: u128) -> String {
+    let config: RenderConfig = RenderConfig { preview: mode.starts_with("preview"), traits_version: 0 };
+    match mode {
+        "render" | "preview" => engine.render(art, &config).unwrap(),
+        "thumbnail" | "preview-thumbnail" => engine.thumbnail(art, &config).unwrap(),
+        _ => panic!("unknown render fixture mode {}", mode),
+    }
+}
+
+#[test]
+fn fixtures_cover_every_engine_and_mode() {
+    let fixtures: Vec<RenderFixture> = fixtures();
+    for engine in 0..render_engine::ENGINE_COUNT {
+        for mode in MODES {
+            assert!(
+                fixtures.iter().any(|fixture| fixture.engine == engine && fixture.mode == mode),
+                "no fixture for engine {} in mode {}", engine, mode,
+            );
+        }
+    }
+}
+
+#[test]
+fn renderings_match_fixtures() {
+    let mut drifted: Vec<String> = Vec::new();
+    for fixture in fixtures() {
+        let engine: Box<dyn RenderEngine> = render_engine::build(fixture.engine, SvgGenerator::new(TraitTable::builtin())).unwrap();
+        let actual: String = hex::encode(&sha256::hash(rendering(engine.as_ref(), &fixture.mode, fixture.art).as_bytes()));
+        if actual != fixture.sha256 {
+            drifted.push(format!("engine {} {} art {}: expected {}, got {}", fixture.engine, fixture.mode, fixture.art, fixture.sha256, actual));
+        }
+    }
+
+    assert!(drifted.is_empty(), "renderings drifted from their fixtures:\n{}", drifted.join("\n"));
+}
+
+#[test]
+fn renderings_are_deterministic() {
+    for engine in 0..render_engine::ENGINE_COUNT {
+        let first: Box<dyn RenderEngine> = render_engine::build(engine, SvgGenerator::new(TraitTable::builtin())).unwrap();
+        let second: Box<dyn RenderEngine> = render_engine::build(engine, SvgGenerator::new(TraitTable::builtin())).unwrap();
+        for mode in MODES {
+            assert_eq!(rendering(first.as_ref(), mode, 7), rendering(second.as_ref(), mode, 7));
+        }
+    }
+}
@@ -0,0 +1,270 @@
+//! Decoding of bitcoin addresses into the output script they pay to, for the
+//! standard forms: P2PKH and P2SH (base58check), segwit v0 (bech32) and v1+ (bech32m).
+
+use crate::sha256;
+
+const BECH32_CHARSET: &[u8; 32] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+const BASE58_ALPHABET: &[u8; 58] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+/// Checksum constants of bech32 (witness v0) and bech32m (witness v1+)
+const BECH32_CONST: u32 = 1;
+const BECH32M_CONST: u32 = 0x2bc830a3;
+
+/// Segwit human readable parts
+const SEGWIT_HRPS: [(&str, Network); 3] = [("bc", Network::Mainnet), ("tb", Network::Testnet), ("bcrt", Network::Regtest)];
+
+/// Base58check version bytes, mainnet and testnet (regtest shares the testnet ones)
+const P2PKH_VERSIONS: [(u8, Network); 2] = [(0x00, Network::Mainnet), (0x6f, Network::Testnet)];
+const P2SH_VERSIONS: [(u8, Network); 2] = [(0x05, Network::Mainnet), (0xc4, Network::Testnet)];
+
+/// Network an address is encoded for
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Network {
+    Mainnet,
+    Testnet,
+    Regtest,
+}
+
+fn bech32_polymod(values: &[u8]) -> u32 {
+    const GENERATOR: [u32; 5] = [0x3b6a57b2, 0x26508e6d, 0x1ea119fa, 0x3d4233dd, 0x2a1462b3];
+
+    let mut checksum: u32 = 1;
+    for value in values {
+        let top: u32 = checksum >> 25;
+        checksum = ((checksum & 0x1ffffff) << 5) ^ (*value as u32);
+        for (i, generator) in GENERATOR.iter().enumerate() {
+            if (top >> i) & 1 == 1 {
+                checksum ^= generator;
+            }
+        }
+    }
+    checksum
+}
+
+/// Regroup 5 bit words into bytes, rejecting non-zero padding
+fn from_five_bits(words: &[u8]) -> Option<Vec<u8>> {
+    let mut bytes: Vec<u8> = Vec::with_capacity(words.len() * 5 / 8);
+    let mut accumulator: u32 = 0;
+    let mut bits: u32 = 0;
+    for word in words {
+        accumulator = ((accumulator << 5) | (*word as u32)) & 0xfff;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((accumulator >> bits) as u8);
+        }
+    }
+
+    if bits >= 5 || accumulator & ((1 << bits) - 1) != 0 {
+        return None;
+    }
+    Some(bytes)
+}
+
+fn segwit_script(address: &str) -> Option<(Network, Vec<u8>)> {
+    if address.chars().any(|c| c.is_ascii_uppercase()) && address.chars().any(|c| c.is_ascii_lowercase()) {
+        return None;
+    }
+
+    let address: String = address.to_ascii_lowercase();
+    let separator: usize = address.rfind('1')?;
+    let (hrp, data) = (&address[..separator], &address[separator + 1..]);
+    let network: Network = SEGWIT_HRPS.iter().find(|(prefix, _)| *prefix == hrp)?.1;
+    if data.len() < 7 {
+        return None;
+    }
+
+    let words: Vec<u8> = data.bytes()
+        .map(|c| BECH32_CHARSET.iter().position(|d| *d == c).map(|p| p as u8))
+        .collect::<Option<Vec<u8>>>()?;
+
+    let mut checked: Vec<u8> = hrp.bytes().map(|c| c >> 5).collect();
+    checked.push(0);
+    checked.extend(hrp.bytes().map(|c| c & 0x1f));
+    checked.extend_from_slice(&words);
+
+    let version: u8 = words[0];
+    let expected: u32 = if version == 0 { BECH32_CONST } else { BECH32M_CONST };
+    if version > 16 || bech32_polymod(&checked) != expected {
+        return None;
+    }
+
+    let program: Vec<u8> = from_five_bits(&words[1..words.len() - 6])?;
+    if program.len() < 2 || program.len() > 40 || (version == 0 && program.len() != 20 && program.len() != 32) {
+        return None;
+    }
+
+    let mut script: Vec<u8> = Vec::with_capacity(program.len() + 2);
+    script.push(if version == 0 { 0x00 } else { 0x50 + version });
+    script.push(program.len() as u8);
+    script.extend_from_slice(&program);
+    Some((network, script))
+}
+
+fn base58_decode(address: &str) -> Option<Vec<u8>> {
+    let mut bytes: Vec<u8> = Vec::new();
+    for c in address.bytes() {
+        let mut carry: u32 = BASE58_ALPHABET.iter().position(|d| *d == c)? as u32;
+        for byte in bytes.iter_mut().rev() {
+            carry += (*byte as u32) * 58;
+            *byte = carry as u8;
+            carry >>= 8;
+        }
+        while carry > 0 {
+            bytes.insert(0, carry as u8);
+            carry >>= 8;
+        }
+    }
+
+    let zeros: usize = address.bytes().take_while(|c| *c == b'1').count();
+    let mut decoded: Vec<u8> = vec![0; zeros];
+    decoded.extend(bytes);
+    Some(decoded)
+}
+
+fn legacy_script(address: &str) -> Option<(Network, Vec<u8>)> {
+    let decoded: Vec<u8> = base58_decode(address)?;
+    if decoded.len() != 25 {
+        return None;
+    }
+
+    let (payload, checksum) = decoded.split_at(21);
+    if sha256::hash(&sha256::hash(payload))[..4] != *checksum {
+        return None;
+    }
+
+    let (version, hash) = (payload[0], &payload[1..]);
+    if let Some((_, network)) = P2PKH_VERSIONS.iter().find(|(v, _)| *v == version) {
+        let mut script: Vec<u8> = vec![0x76, 0xa9, 0x14];
+        script.extend_from_slice(hash);
+        script.extend_from_slice(&[0x88, 0xac]);
+        Some((*network, script))
+    } else if let Some((_, network)) = P2SH_VERSIONS.iter().find(|(v, _)| *v == version) {
+        let mut script: Vec<u8> = vec![0xa9, 0x14];
+        script.extend_from_slice(hash);
+        script.push(0x87);
+        Some((*network, script))
+    } else {
+        None
+    }
+}
+
+/// Network and output script of an address, None when it is not a valid standard address
+pub fn decode(address: &str) -> Option<(Network, Vec<u8>)> {
+    let address: &str = address.trim();
+    segwit_script(address).or_else(|| legacy_script(address))
+}
+
+/// Output script an address pays to, None when it is not a valid standard address
+pub fn script_pubkey(address: &str) -> Option<Vec<u8>> {
+    decode(address).map(|(_, script)| script)
+}
+
+/// Network an address is encoded for
+pub fn network(address: &str) -> Option<Network> {
+    decode(address).map(|(network, _)| network)
+}
+
+/// Canonical spelling of a valid address: trimmed, and lowercase for segwit ones
+pub fn normalize(address: &str) -> Option<String> {
+    let address: &str = address.trim();
+    if segwit_script(address).is_some() {
+        Some(address.to_ascii_lowercase())
+    } else {
+        legacy_script(address).map(|_| address.to_string())
+    }
+}
+
+/// Identity of an output script, the key of every allowlist and per-address record
+pub fn script_identity(script: &[u8]) -> [u8; 32] {
+    sha256::hash(script)
+}
+
+/// Identity the contracts attribute an address to: the sha256 of its output script.
+/// Spellings of the same address (case, surrounding whitespace) share one identity.
+pub fn identity(address: &str) -> Option<[u8; 32]> {
+    script_pubkey(address).map(|script| script_identity(&script))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hex;
+
+    const P2WPKH: &str = "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4";
+    const P2WPKH_SCRIPT: &str = "0014751e76e8199196d454941c45d1b3a323f1433bd6";
+    const P2TR: &str = "bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqzk5jj0";
+    const P2TR_SCRIPT: &str = "512079be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798";
+    const P2SH: &str = "3J98t1WpEZ73CNmQviecrnyiWrnqRhWNLy";
+    const P2SH_SCRIPT: &str = "a914b472a266d0bd89c13706a4132ccfb16f7c3b9fcb87";
+    const P2PKH: &str = "1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa";
+    const P2PKH_SCRIPT: &str = "76a91462e907b15cbf27d5425399ebf6f0fb50ebb88f1888ac";
+
+    fn script_hex(address: &str) -> Option<String> {
+        script_pubkey(address).map(|script| hex::encode(&script))
+    }
+
+    #[test]
+    fn decodes_standard_scripts() {
+        assert_eq!(script_hex(P2WPKH).as_deref(), Some(P2WPKH_SCRIPT));
+        assert_eq!(script_hex(P2TR).as_deref(), Some(P2TR_SCRIPT));
+        assert_eq!(script_hex(P2SH).as_deref(), Some(P2SH_SCRIPT));
+        assert_eq!(script_hex(P2PKH).as_deref(), Some(P2PKH_SCRIPT));
+    }
+
+    #[test]
+    fn accepts_uppercase_bech32_but_not_mixed_case() {
+        assert_eq!(script_hex(&P2WPKH.to_ascii_uppercase()).as_deref(), Some(P2WPKH_SCRIPT));
+        assert_eq!(script_hex(&P2TR.to_ascii_uppercase()).as_deref(), Some(P2TR_SCRIPT));
+        assert_eq!(script_pubkey("bc1qW508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4"), None);
+    }
+
+    #[test]
+    fn splits_networks_by_prefix() {
+        assert_eq!(network(P2WPKH), Some(Network::Mainnet));
+        assert_eq!(network(P2TR), Some(Network::Mainnet));
+        assert_eq!(network(P2SH), Some(Network::Mainnet));
+        assert_eq!(network("tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx"), Some(Network::Testnet));
+
+        // The testnet spelling pays to the same script as its mainnet twin
+        assert_eq!(script_hex("tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx").as_deref(), Some(P2WPKH_SCRIPT));
+    }
+
+    #[test]
+    fn rejects_bad_checksums() {
+        assert_eq!(decode("bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t5"), None);
+        assert_eq!(decode("bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqzk5jj1"), None);
+        assert_eq!(decode("3J98t1WpEZ73CNmQviecrnyiWrnqRhWNLz"), None);
+        // A v1 program under the bech32 checksum of v0 is not valid
+        assert_eq!(decode("bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqh2y7hd"), None);
+    }
+
+    #[test]
+    fn rejects_what_is_not_an_address() {
+        for address in ["", "bc1", "bc1qqqqqqq", "xx1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4", "not an address"] {
+            assert_eq!(decode(address), None, "{}", address);
+        }
+    }
+
+    #[test]
+    fn normalizes_spellings() {
+        assert_eq!(normalize(&format!("  {}\n", P2WPKH.to_ascii_uppercase())).as_deref(), Some(P2WPKH));
+        assert_eq!(normalize(P2TR).as_deref(), Some(P2TR));
+        assert_eq!(normalize(&format!(" {} ", P2SH)).as_deref(), Some(P2SH));
+        assert_eq!(normalize("bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t5"), None);
+    }
+
+    #[test]
+    fn identity_is_the_hash_of_the_script() {
+        for (address, expected) in [(P2WPKH, P2WPKH_SCRIPT), (P2TR, P2TR_SCRIPT), (P2SH, P2SH_SCRIPT)] {
+            let script: Vec<u8> = script_pubkey(address).unwrap();
+            assert_eq!(hex::encode(&script), expected);
+            assert_eq!(identity(address), Some(sha256::hash(&script)));
+        }
+
+        assert_eq!(identity(&P2WPKH.to_ascii_uppercase()), identity(P2WPKH));
+        assert_eq!(identity(" tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx "), identity(P2WPKH));
+        assert_ne!(identity(P2WPKH), identity(P2TR));
+        assert_eq!(identity("bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t5"), None);
+    }
+}
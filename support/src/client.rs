@@ -0,0 +1,74 @@
+use crate::errors::{codes::*, Revert, TLV_PREFIX, TLV_SUFFIX};
+use crate::hex;
+
+/// Selector the runtime prepends to revert data, as in Solidity's `Error(string)`
+const REVERT_SELECTOR: [u8; 4] = [0x08, 0xc3, 0x79, 0xa0];
+
+/// Prefix the runtime puts in front of the error text
+const ERROR_PREFIX: &str = "Error: ";
+
+/// Decode the revert data of a failed call into its structured payload.
+/// Returns None for reverts that do not carry a TLV trailer.
+pub fn decode_revert(data: &[u8]) -> Option<Revert> {
+    let data: &[u8] = data.strip_prefix(&REVERT_SELECTOR[..]).unwrap_or(data);
+    let text: &str = std::str::from_utf8(data).ok()?;
+    decode_revert_text(text)
+}
+
+/// Decode the error text of a failed call into its structured payload
+pub fn decode_revert_text(text: &str) -> Option<Revert> {
+    let text: &str = text.strip_prefix(ERROR_PREFIX).unwrap_or(text).trim_end();
+
+    let start: usize = text.rfind(TLV_PREFIX)?;
+    let hex: &str = text[start + TLV_PREFIX.len()..].strip_suffix(TLV_SUFFIX)?;
+
+    Revert::from_tlv(&text[..start], &hex::decode(hex)?)
+}
+
+/// Short name of an error code, for logs and UI keys
+pub fn code_name(code: u16) -> &'static str {
+    match code {
+        ERR_FAILED => "failed",
+        ERR_UNAUTHORIZED => "unauthorized",
+        ERR_NOT_FOUND => "not_found",
+        ERR_INVALID_INPUT => "invalid_input",
+        ERR_INVALID_STATE => "invalid_state",
+        ERR_LIMIT_EXCEEDED => "limit_exceeded",
+        ERR_INSUFFICIENT_PAYMENT => "insufficient_payment",
+        ERR_OVERFLOW => "overflow",
+        ERR_STORAGE => "storage",
+        ERR_EXTERNAL_CALL => "external_call",
+        ERR_ALREADY_INITIALIZED => "already_initialized",
+        _ => "unknown",
+    }
+}
+
+/// Actionable sentence for a decoded revert
+pub fn describe_revert(revert: &Revert) -> String {
+    let hint: &str = match revert.code {
+        ERR_UNAUTHORIZED => "Attach the token, receipt or credential this action requires.",
+        ERR_NOT_FOUND => "Check the id or index you are referring to.",
+        ERR_INVALID_INPUT => "Correct the highlighted input and try again.",
+        ERR_INVALID_STATE => "This action is not available right now.",
+        ERR_LIMIT_EXCEEDED => "Lower the amount or try again in a later block.",
+        ERR_INSUFFICIENT_PAYMENT => "Send the full payment with the transaction.",
+        ERR_EXTERNAL_CALL => "A contract this action depends on did not answer as expected.",
+        ERR_ALREADY_INITIALIZED => "The contract is already deployed and initialized.",
+        _ => "The transaction was rejected.",
+    };
+
+    let mut description: String = format!("{} {}", revert.message, hint);
+
+    if let Some(field) = &revert.field {
+        description.push_str(&format!(" Field: {}.", field));
+    }
+
+    match (revert.expected, revert.actual) {
+        (Some(expected), Some(actual)) => description.push_str(&format!(" Expected {}, got {}.", expected, actual)),
+        (Some(expected), None) => description.push_str(&format!(" Expected {}.", expected)),
+        (None, Some(actual)) => description.push_str(&format!(" Got {}.", actual)),
+        (None, None) => {}
+    }
+
+    description
+}
@@ -0,0 +1,173 @@
+//! Compression of cold storage blobs. Stored values are mostly little endian integers of
+//! small magnitude, so long zero runs and fields repeating across a blob dominate; a byte
+//! oriented LZ77 pass removes both. Every packed blob starts with a tag byte telling how
+//! the rest is encoded, and blobs the pass would not shrink are kept as they are.
+//!
+//! Compressed layout after the tag: the unpacked length as a LEB128 varint, then tokens.
+//! A control byte below 0x80 introduces `control + 1` literal bytes; from 0x80 up it copies
+//! `(control & 0x7f) + MIN_MATCH` bytes starting a varint distance back in the output,
+//! which may overlap the bytes being written.
+
+use std::collections::HashMap;
+
+use crate::errors::{codes::*, Revert};
+
+/// Tag of a blob stored as it is
+pub const TAG_RAW: u8 = 0;
+
+/// Tag of a blob stored LZ77 compressed
+pub const TAG_LZ: u8 = 1;
+
+/// Shortest back-reference worth a token
+const MIN_MATCH: usize = 3;
+
+/// Longest back-reference one token copies
+const MAX_MATCH: usize = 0x7f + MIN_MATCH;
+
+/// Longest literal run one token carries
+const MAX_LITERALS: usize = 0x80;
+
+/// Farthest back a reference reaches
+const WINDOW: usize = 1 << 16;
+
+/// Pack a blob for storage, compressing it when that makes it smaller
+pub fn pack(data: &[u8]) -> Vec<u8> {
+    let compressed: Vec<u8> = compress(data);
+    if compressed.len() < data.len() + 1 {
+        return compressed;
+    }
+
+    let mut packed: Vec<u8> = Vec::with_capacity(data.len() + 1);
+    packed.push(TAG_RAW);
+    packed.extend_from_slice(data);
+    packed
+}
+
+/// Blob a `pack` produced
+pub fn unpack(packed: &[u8]) -> Result<Vec<u8>, Box<Revert>> {
+    match packed.split_first() {
+        Some((&TAG_RAW, data)) => Ok(data.to_vec()),
+        Some((&TAG_LZ, tokens)) => decompress(tokens),
+        Some((&tag, _)) => Err(Box::new(Revert::new(ERR_STORAGE, "Unknown packed blob encoding")
+            .field("tag").actual(tag as u128))),
+        None => Err(Box::new(Revert::new(ERR_STORAGE, "Packed blob is empty"))),
+    }
+}
+
+fn compress(data: &[u8]) -> Vec<u8> {
+    let mut out: Vec<u8> = vec![TAG_LZ];
+    write_varint(&mut out, data.len() as u64);
+
+    // Last position each 3 byte prefix was seen at
+    let mut seen: HashMap<[u8; MIN_MATCH], usize> = HashMap::new();
+    let mut literals_start: usize = 0;
+    let mut position: usize = 0;
+
+    while position + MIN_MATCH <= data.len() {
+        let prefix: [u8; MIN_MATCH] = [data[position], data[position + 1], data[position + 2]];
+        let candidate: Option<usize> = seen.insert(prefix, position)
+            .filter(|&earlier| position - earlier <= WINDOW);
+
+        let length: usize = candidate.map_or(0, |earlier| {
+            let limit: usize = MAX_MATCH.min(data.len() - position);
+            (0..limit).take_while(|&i| data[earlier + i] == data[position + i]).count()
+        });
+
+        if length < MIN_MATCH {
+            position += 1;
+            continue;
+        }
+
+        write_literals(&mut out, &data[literals_start..position]);
+        out.push(0x80 | (length - MIN_MATCH) as u8);
+        write_varint(&mut out, (position - candidate.unwrap_or_default()) as u64);
+
+        for skipped in position + 1..(position + length).min(data.len() + 1 - MIN_MATCH) {
+            seen.insert([data[skipped], data[skipped + 1], data[skipped + 2]], skipped);
+        }
+        position += length;
+        literals_start = position;
+    }
+
+    write_literals(&mut out, &data[literals_start..]);
+    out
+}
+
+fn decompress(tokens: &[u8]) -> Result<Vec<u8>, Box<Revert>> {
+    let mut cursor: usize = 0;
+    let length: usize = read_varint(tokens, &mut cursor)? as usize;
+
+    // Every token byte yields at most MAX_MATCH bytes, which bounds an honest length
+    if length > tokens.len().saturating_mul(MAX_MATCH) {
+        return Err(corrupt("unpacked length exceeds what the tokens can produce"));
+    }
+
+    let mut out: Vec<u8> = Vec::with_capacity(length);
+    while cursor < tokens.len() {
+        let control: u8 = tokens[cursor];
+        cursor += 1;
+
+        if control < 0x80 {
+            let count: usize = control as usize + 1;
+            let literals: &[u8] = tokens.get(cursor..cursor + count)
+                .ok_or_else(|| corrupt("literal run is truncated"))?;
+            out.extend_from_slice(literals);
+            cursor += count;
+        } else {
+            let count: usize = (control & 0x7f) as usize + MIN_MATCH;
+            let distance: usize = read_varint(tokens, &mut cursor)? as usize;
+            if distance == 0 || distance > out.len() {
+                return Err(corrupt("back-reference reaches before the start"));
+            }
+
+            let start: usize = out.len() - distance;
+            for i in 0..count {
+                out.push(out[start + i]);
+            }
+        }
+
+        if out.len() > length {
+            return Err(corrupt("tokens run past the unpacked length"));
+        }
+    }
+
+    if out.len() != length {
+        return Err(Box::new(Revert::new(ERR_STORAGE, "Packed blob is corrupt: unpacked length mismatch")
+            .field("length").expected(length as u128).actual(out.len() as u128)));
+    }
+
+    Ok(out)
+}
+
+fn write_literals(out: &mut Vec<u8>, literals: &[u8]) {
+    for run in literals.chunks(MAX_LITERALS) {
+        out.push((run.len() - 1) as u8);
+        out.extend_from_slice(run);
+    }
+}
+
+fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn read_varint(bytes: &[u8], cursor: &mut usize) -> Result<u64, Box<Revert>> {
+    let mut value: u64 = 0;
+    for shift in (0..64).step_by(7) {
+        let byte: u8 = *bytes.get(*cursor).ok_or_else(|| corrupt("varint is truncated"))?;
+        *cursor += 1;
+        value |= ((byte & 0x7f) as u64) << shift;
+        if byte < 0x80 {
+            return Ok(value);
+        }
+    }
+
+    Err(corrupt("varint is too long"))
+}
+
+fn corrupt(reason: &str) -> Box<Revert> {
+    Box::new(Revert::new(ERR_STORAGE, format!("Packed blob is corrupt: {}", reason)))
+}
@@ -0,0 +1,139 @@
+use std::fmt;
+
+use crate::hex;
+
+/// Error codes shared by every contract
+pub mod codes {
+    /// Failure without a more specific code
+    pub const ERR_FAILED: u16 = 1;
+    /// Caller did not present the required token, role or receipt
+    pub const ERR_UNAUTHORIZED: u16 = 2;
+    /// Referenced stage, listing, index or record does not exist
+    pub const ERR_NOT_FOUND: u16 = 3;
+    /// An input is malformed or out of range
+    pub const ERR_INVALID_INPUT: u16 = 4;
+    /// The operation is not allowed in the current state
+    pub const ERR_INVALID_STATE: u16 = 5;
+    /// A supply, per-block or registry limit would be exceeded
+    pub const ERR_LIMIT_EXCEEDED: u16 = 6;
+    /// Not enough of the payment alkane was sent
+    pub const ERR_INSUFFICIENT_PAYMENT: u16 = 7;
+    /// An arithmetic overflow was caught
+    pub const ERR_OVERFLOW: u16 = 8;
+    /// Stored data could not be encoded or decoded
+    pub const ERR_STORAGE: u16 = 9;
+    /// A call into another alkane failed or answered unexpectedly
+    pub const ERR_EXTERNAL_CALL: u16 = 10;
+    /// The contract was already initialized
+    pub const ERR_ALREADY_INITIALIZED: u16 = 11;
+}
+
+/// TLV tags of the revert payload
+pub const TAG_CODE: u8 = 0x01;
+pub const TAG_FIELD: u8 = 0x02;
+pub const TAG_EXPECTED: u8 = 0x03;
+pub const TAG_ACTUAL: u8 = 0x04;
+
+/// Marker opening the hex encoded TLV trailer in the revert text
+pub const TLV_PREFIX: &str = " <tlv:";
+pub const TLV_SUFFIX: &str = ">";
+
+/// Structured failure. Displays as the message followed by the TLV trailer,
+/// which is what the runtime carries as revert data.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Revert {
+    pub code: u16,
+    pub message: String,
+    pub field: Option<String>,
+    pub expected: Option<u128>,
+    pub actual: Option<u128>,
+}
+
+impl Revert {
+    pub fn new(code: u16, message: impl Into<String>) -> Self {
+        Revert {
+            code,
+            message: message.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Name the offending input or setting
+    pub fn field(mut self, field: &str) -> Self {
+        self.field = Some(field.to_string());
+        self
+    }
+
+    pub fn expected(mut self, expected: u128) -> Self {
+        self.expected = Some(expected);
+        self
+    }
+
+    pub fn actual(mut self, actual: u128) -> Self {
+        self.actual = Some(actual);
+        self
+    }
+
+    /// Serialize the payload as TLV entries
+    pub fn to_tlv(&self) -> Vec<u8> {
+        let mut tlv: Vec<u8> = Vec::new();
+        push_entry(&mut tlv, TAG_CODE, &self.code.to_le_bytes());
+
+        if let Some(field) = &self.field {
+            // Lengths are a single byte, longer field names are cut
+            let bytes: &[u8] = field.as_bytes();
+            push_entry(&mut tlv, TAG_FIELD, &bytes[..bytes.len().min(u8::MAX as usize)]);
+        }
+
+        if let Some(expected) = self.expected {
+            push_entry(&mut tlv, TAG_EXPECTED, &expected.to_le_bytes());
+        }
+
+        if let Some(actual) = self.actual {
+            push_entry(&mut tlv, TAG_ACTUAL, &actual.to_le_bytes());
+        }
+
+        tlv
+    }
+
+    /// Parse TLV entries, unknown tags are skipped so the format can grow
+    pub fn from_tlv(message: &str, tlv: &[u8]) -> Option<Self> {
+        let mut revert: Revert = Revert::new(0, message);
+        let mut has_code: bool = false;
+        let mut position: usize = 0;
+
+        while position < tlv.len() {
+            let tag: u8 = *tlv.get(position)?;
+            let length: usize = *tlv.get(position + 1)? as usize;
+            let value: &[u8] = tlv.get(position + 2..position + 2 + length)?;
+            position += 2 + length;
+
+            match tag {
+                TAG_CODE => {
+                    revert.code = u16::from_le_bytes(value.try_into().ok()?);
+                    has_code = true;
+                }
+                TAG_FIELD => revert.field = Some(String::from_utf8(value.to_vec()).ok()?),
+                TAG_EXPECTED => revert.expected = Some(u128::from_le_bytes(value.try_into().ok()?)),
+                TAG_ACTUAL => revert.actual = Some(u128::from_le_bytes(value.try_into().ok()?)),
+                _ => {}
+            }
+        }
+
+        if has_code { Some(revert) } else { None }
+    }
+}
+
+impl fmt::Display for Revert {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}{}{}", self.message, TLV_PREFIX, hex::encode(&self.to_tlv()), TLV_SUFFIX)
+    }
+}
+
+impl std::error::Error for Revert {}
+
+fn push_entry(tlv: &mut Vec<u8>, tag: u8, value: &[u8]) {
+    tlv.push(tag);
+    tlv.push(value.len() as u8);
+    tlv.extend_from_slice(value);
+}
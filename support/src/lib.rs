@@ -0,0 +1,24 @@
+//! Helpers shared by the Orbital Ador contracts and off-chain clients.
+//! Kept free of runtime dependencies so it builds for wasm and native targets alike.
+
+pub mod address;
+pub mod client;
+pub mod compress;
+pub mod errors;
+pub mod hex;
+pub mod merkle;
+pub mod pagination;
+pub mod pricing;
+pub mod rng;
+pub mod sha256;
+pub mod svg;
+pub mod unlockable;
+
+/// Build an `anyhow::Error` carrying a structured revert payload:
+/// `revert!(ERR_NOT_FOUND, "Stage {} not found", stage_id)`
+#[macro_export]
+macro_rules! revert {
+    ($code:expr, $($arg:tt)+) => {
+        ::anyhow::Error::new($crate::errors::Revert::new($code, format!($($arg)+)))
+    };
+}
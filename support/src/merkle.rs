@@ -0,0 +1,210 @@
+//! Whitelist Merkle trees in the scheme the collection verifies on-chain (`orbital-merkle-v1`).
+//!
+//! A leaf is `sha256(0x00 || identity)` where the identity is the sha256 of the output
+//! script an address pays to. A parent is `sha256(0x01 || min(a, b) || max(a, b))`, so
+//! proofs are plain sibling lists without position bits. Leaves are sorted and deduplicated,
+//! a node without a sibling moves up unchanged and the root of an empty list is all zeros.
+
+use crate::{address, sha256};
+
+/// Version tag of the scheme, reported by `GetWhitelistScheme`
+pub const SCHEME: &str = "orbital-merkle-v1";
+
+/// Domain separator prepended to a leaf before hashing
+pub const LEAF_PREFIX: u8 = 0x00;
+
+/// Domain separator prepended to a pair of children before hashing
+pub const NODE_PREFIX: u8 = 0x01;
+
+/// Leaf committing to an identity
+pub fn leaf_hash(identity: &[u8; 32]) -> [u8; 32] {
+    let mut preimage: Vec<u8> = Vec::with_capacity(33);
+    preimage.push(LEAF_PREFIX);
+    preimage.extend_from_slice(identity);
+    sha256::hash(&preimage)
+}
+
+/// Parent of two nodes
+pub fn node_hash(a: &[u8; 32], b: &[u8; 32]) -> [u8; 32] {
+    let (left, right) = if a <= b { (a, b) } else { (b, a) };
+    let mut preimage: Vec<u8> = Vec::with_capacity(65);
+    preimage.push(NODE_PREFIX);
+    preimage.extend_from_slice(left);
+    preimage.extend_from_slice(right);
+    sha256::hash(&preimage)
+}
+
+/// Whether `proof` links the identity's leaf to `root`. This is the check the contract runs.
+pub fn verify(root: &[u8; 32], identity: &[u8; 32], proof: &[[u8; 32]]) -> bool {
+    let computed: [u8; 32] = proof.iter().fold(leaf_hash(identity), |node, sibling| node_hash(&node, sibling));
+    computed == *root
+}
+
+/// Identity a free mint voucher enters the tree under, committing to the allocation too so
+/// a proof only ever claims the amount it was issued for: `sha256(identity || count)` with
+/// the count as 16 little endian bytes
+pub fn voucher_identity(identity: &[u8; 32], count: u128) -> [u8; 32] {
+    let mut preimage: Vec<u8> = Vec::with_capacity(48);
+    preimage.extend_from_slice(identity);
+    preimage.extend_from_slice(&count.to_le_bytes());
+    sha256::hash(&preimage)
+}
+
+/// Every level of a whitelist tree, leaves first
+pub struct MerkleTree {
+    levels: Vec<Vec<[u8; 32]>>,
+}
+
+impl MerkleTree {
+    /// Tree over a set of identities
+    pub fn from_identities(identities: &[[u8; 32]]) -> Self {
+        let mut leaves: Vec<[u8; 32]> = identities.iter().map(leaf_hash).collect();
+        leaves.sort();
+        leaves.dedup();
+
+        let mut levels: Vec<Vec<[u8; 32]>> = vec![leaves];
+        while levels[levels.len() - 1].len() > 1 {
+            let parents: Vec<[u8; 32]> = levels[levels.len() - 1].chunks(2)
+                .map(|pair| if pair.len() == 2 { node_hash(&pair[0], &pair[1]) } else { pair[0] })
+                .collect();
+            levels.push(parents);
+        }
+
+        MerkleTree { levels }
+    }
+
+    /// Tree over a list of addresses, failing with the first one that does not decode
+    pub fn from_addresses<S: AsRef<str>>(addresses: &[S]) -> Result<Self, String> {
+        let identities: Vec<[u8; 32]> = addresses.iter()
+            .map(|a| address::identity(a.as_ref()).ok_or_else(|| format!("Invalid address {}", a.as_ref())))
+            .collect::<Result<Vec<[u8; 32]>, String>>()?;
+        Ok(Self::from_identities(&identities))
+    }
+
+    /// Root to configure on the stage, all zeros for an empty tree
+    pub fn root(&self) -> [u8; 32] {
+        match self.levels[self.levels.len() - 1].first() {
+            Some(root) if !self.levels[0].is_empty() => *root,
+            _ => [0u8; 32],
+        }
+    }
+
+    /// Sibling hashes from the identity's leaf to the root, None when it is not in the tree
+    pub fn proof(&self, identity: &[u8; 32]) -> Option<Vec<[u8; 32]>> {
+        let mut position: usize = self.levels[0].binary_search(&leaf_hash(identity)).ok()?;
+
+        let mut proof: Vec<[u8; 32]> = Vec::with_capacity(self.levels.len());
+        for level in self.levels.iter().take(self.levels.len() - 1) {
+            if let Some(sibling) = level.get(position ^ 1) {
+                proof.push(*sibling);
+            }
+            position /= 2;
+        }
+        Some(proof)
+    }
+
+    /// Proof for an address, None when it does not decode or is not in the tree
+    pub fn proof_for_address(&self, address: &str) -> Option<Vec<[u8; 32]>> {
+        self.proof(&address::identity(address)?)
+    }
+
+    /// Proof as the contract takes it: trailing mint inputs, two little endian u128
+    /// halves per sibling, low half first
+    pub fn encode_proof(proof: &[[u8; 32]]) -> Vec<u128> {
+        proof.iter()
+            .flat_map(|node| node.chunks_exact(16))
+            .map(|half| {
+                let mut word: [u8; 16] = [0; 16];
+                word.copy_from_slice(half);
+                u128::from_le_bytes(word)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn identities(count: u8) -> Vec<[u8; 32]> {
+        (0..count).map(|i| sha256::hash(&[i])).collect()
+    }
+
+    /// Decode the trailing mint inputs the way the collection's whitelist check does
+    fn decode_proof(words: &[u128]) -> Vec<[u8; 32]> {
+        words.chunks_exact(2)
+            .map(|halves| {
+                let mut node: [u8; 32] = [0; 32];
+                node[..16].copy_from_slice(&halves[0].to_le_bytes());
+                node[16..].copy_from_slice(&halves[1].to_le_bytes());
+                node
+            })
+            .collect()
+    }
+
+    #[test]
+    fn proofs_round_trip_for_odd_and_even_trees() {
+        for count in 2..=19 {
+            let members: Vec<[u8; 32]> = identities(count);
+            let tree: MerkleTree = MerkleTree::from_identities(&members);
+            for identity in members.iter() {
+                let proof: Vec<[u8; 32]> = tree.proof(identity).unwrap();
+                assert!(verify(&tree.root(), identity, &proof), "{} leaves", count);
+            }
+        }
+    }
+
+    #[test]
+    fn single_leaf_is_its_own_root() {
+        let identity: [u8; 32] = [7; 32];
+        let tree: MerkleTree = MerkleTree::from_identities(&[identity]);
+        assert_eq!(tree.root(), leaf_hash(&identity));
+        assert_eq!(tree.proof(&identity).unwrap(), Vec::<[u8; 32]>::new());
+        assert!(verify(&tree.root(), &identity, &[]));
+    }
+
+    #[test]
+    fn empty_tree_has_a_zero_root() {
+        assert_eq!(MerkleTree::from_identities(&[]).root(), [0; 32]);
+    }
+
+    #[test]
+    fn non_members_get_no_proof_and_fail_verification() {
+        let tree: MerkleTree = MerkleTree::from_identities(&identities(5));
+        let outsider: [u8; 32] = [0xaa; 32];
+        assert!(tree.proof(&outsider).is_none());
+
+        let borrowed: Vec<[u8; 32]> = tree.proof(&identities(5)[0]).unwrap();
+        assert!(!verify(&tree.root(), &outsider, &borrowed));
+    }
+
+    #[test]
+    fn duplicates_do_not_change_the_tree() {
+        let members: Vec<[u8; 32]> = identities(4);
+        let mut doubled: Vec<[u8; 32]> = members.clone();
+        doubled.extend_from_slice(&members);
+        assert_eq!(MerkleTree::from_identities(&members).root(), MerkleTree::from_identities(&doubled).root());
+    }
+
+    #[test]
+    fn encoded_proofs_decode_as_the_collection_reads_them() {
+        let members: Vec<[u8; 32]> = identities(11);
+        let tree: MerkleTree = MerkleTree::from_identities(&members);
+        for identity in members.iter() {
+            let proof: Vec<[u8; 32]> = tree.proof(identity).unwrap();
+            let words: Vec<u128> = MerkleTree::encode_proof(&proof);
+            assert_eq!(words.len(), proof.len() * 2);
+            assert_eq!(decode_proof(&words), proof);
+            assert!(verify(&tree.root(), identity, &decode_proof(&words)));
+        }
+    }
+
+    #[test]
+    fn voucher_identities_commit_to_the_count() {
+        let identity: [u8; 32] = [3; 32];
+        let tree: MerkleTree = MerkleTree::from_identities(&[voucher_identity(&identity, 2), voucher_identity(&[4; 32], 1)]);
+        let proof: Vec<[u8; 32]> = tree.proof(&voucher_identity(&identity, 2)).unwrap();
+        assert!(verify(&tree.root(), &voucher_identity(&identity, 2), &proof));
+        assert!(!verify(&tree.root(), &voucher_identity(&identity, 3), &proof));
+    }
+}
@@ -0,0 +1,68 @@
+//! Continuation cursors shared by every batch and listing opcode. A cursor is an opaque
+//! u128 a client passes back unchanged: 0 asks for the first page and each page reports
+//! the cursor of the one after it, or none once the listing is exhausted.
+
+use std::ops::Range;
+
+use crate::errors::{codes::*, Revert};
+
+/// Upper bound on items per page, for every listing
+pub const MAX_PAGE_SIZE: u128 = 50;
+
+/// Tag in the top byte of every cursor, bumped should the position encoding ever change
+const CURSOR_VERSION: u128 = 1;
+
+/// Low bits of a cursor holding the position
+const POSITION_BITS: u32 = 120;
+
+const POSITION_MASK: u128 = (1 << POSITION_BITS) - 1;
+
+/// Cursor resuming a listing at `position`
+pub fn encode_cursor(position: u128) -> u128 {
+    (CURSOR_VERSION << POSITION_BITS) | (position & POSITION_MASK)
+}
+
+/// Position a cursor resumes at, 0 for the first page
+pub fn decode_cursor(cursor: u128) -> Result<u128, Box<Revert>> {
+    if cursor == 0 {
+        return Ok(0);
+    }
+
+    let version: u128 = cursor >> POSITION_BITS;
+    if version != CURSOR_VERSION {
+        return Err(Box::new(Revert::new(ERR_INVALID_INPUT, "Unknown cursor version")
+            .field("cursor").expected(CURSOR_VERSION).actual(version)));
+    }
+
+    Ok(cursor & POSITION_MASK)
+}
+
+/// Positions one page covers out of a listing of `total`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PageBounds {
+    pub start: u128,
+    pub end: u128,
+    pub total: u128,
+    /// Cursor of the following page, None on the last one
+    pub next: Option<u128>,
+}
+
+impl PageBounds {
+    pub fn positions(&self) -> Range<u128> {
+        self.start..self.end
+    }
+}
+
+/// Work out the page `cursor` and `limit` ask for. Limits of 0 or above `MAX_PAGE_SIZE`
+/// are refused rather than clamped, so a client never mistakes a short page for the end.
+pub fn page_bounds(cursor: u128, limit: u128, total: u128) -> Result<PageBounds, Box<Revert>> {
+    if limit == 0 || limit > MAX_PAGE_SIZE {
+        return Err(Box::new(Revert::new(ERR_LIMIT_EXCEEDED, format!("Pages hold 1 to {} items", MAX_PAGE_SIZE))
+            .field("limit").expected(MAX_PAGE_SIZE).actual(limit)));
+    }
+
+    let start: u128 = decode_cursor(cursor)?.min(total);
+    let end: u128 = start.saturating_add(limit).min(total);
+    let next: Option<u128> = if end < total { Some(encode_cursor(end)) } else { None };
+    Ok(PageBounds { start, end, total, next })
+}
@@ -0,0 +1,120 @@
+//! Declining price curve of dutch sales. Dutch stages of the primary mint and dutch
+//! listings of the marketplace both price along it, so an orbital loses value on the same
+//! schedule wherever it is sold.
+
+/// Price falling from `start_price` to `end_price` in `steps` equal steps of `interval`
+/// blocks from `start_block`. Each price holds for a whole step, the end price from the
+/// last step on, and a share of the range that does not divide evenly is dropped late.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DutchCurve {
+    pub start_price: u128,
+    pub end_price: u128,
+    pub start_block: u64,
+    pub interval: u64,
+    pub steps: u64,
+}
+
+impl DutchCurve {
+    /// Curve falling by `decay_per_block` every block until it reaches `end_price`
+    pub fn per_block(start_price: u128, end_price: u128, decay_per_block: u128, start_block: u64) -> DutchCurve {
+        let range: u128 = start_price.saturating_sub(end_price);
+        let blocks: u128 = range.div_ceil(decay_per_block.max(1));
+        DutchCurve {
+            start_price,
+            end_price,
+            start_block,
+            interval: 1,
+            steps: u64::try_from(blocks).unwrap_or(u64::MAX),
+        }
+    }
+
+    /// Price at a height and the last block it holds for, `u64::MAX` once the curve ended
+    pub fn price_at(&self, height: u64) -> (u128, u64) {
+        let interval: u64 = self.interval.max(1);
+        let steps: u64 = self.steps.max(1);
+        let step: u64 = (height.saturating_sub(self.start_block) / interval).min(steps);
+
+        // range * step / steps without overflowing u128
+        let range: u128 = self.start_price.saturating_sub(self.end_price);
+        let (whole, rest): (u128, u128) = (range / steps as u128, range % steps as u128);
+        let drop: u128 = whole * step as u128 + rest * step as u128 / steps as u128;
+
+        let step_end: u64 = if step == steps {
+            u64::MAX
+        } else {
+            self.start_block.saturating_add((step + 1).saturating_mul(interval)) - 1
+        };
+
+        (self.start_price - drop, step_end)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn curve(start_price: u128, end_price: u128, interval: u64, steps: u64) -> DutchCurve {
+        DutchCurve { start_price, end_price, start_block: 100, interval, steps }
+    }
+
+    #[test]
+    fn holds_each_price_for_a_whole_step() {
+        let curve: DutchCurve = curve(1_000, 400, 10, 3);
+        assert_eq!(curve.price_at(90), (1_000, 109));
+        assert_eq!(curve.price_at(100), (1_000, 109));
+        assert_eq!(curve.price_at(109), (1_000, 109));
+        assert_eq!(curve.price_at(110), (800, 119));
+        assert_eq!(curve.price_at(125), (600, 129));
+        assert_eq!(curve.price_at(130), (400, u64::MAX));
+        assert_eq!(curve.price_at(10_000), (400, u64::MAX));
+    }
+
+    #[test]
+    fn uneven_ranges_reach_the_end_price_on_the_last_step() {
+        let curve: DutchCurve = curve(10, 0, 1, 4);
+        let prices: Vec<u128> = (100..106).map(|height| curve.price_at(height).0).collect();
+        assert_eq!(prices, vec![10, 8, 5, 3, 0, 0]);
+    }
+
+    #[test]
+    fn prices_never_rise() {
+        let curve: DutchCurve = curve(987_654_321, 123_456, 7, 13);
+        let mut last: u128 = u128::MAX;
+        for height in 0..300 {
+            let (price, step_end) = curve.price_at(height);
+            assert!(price <= last && price >= curve.end_price);
+            assert!(step_end >= height);
+            last = price;
+        }
+        assert_eq!(last, curve.end_price);
+    }
+
+    #[test]
+    fn full_width_prices_do_not_overflow() {
+        let curve: DutchCurve = curve(u128::MAX, 0, 1, 2);
+        assert_eq!(curve.price_at(101).0, u128::MAX - u128::MAX / 2);
+        assert_eq!(curve.price_at(102).0, 0);
+        assert!(DutchCurve::per_block(u128::MAX, 0, 1, 0).price_at(u64::MAX - 1).0 > 0);
+    }
+
+    #[test]
+    fn per_block_curves_fall_by_the_decay_every_block() {
+        let curve: DutchCurve = DutchCurve::per_block(1_000, 700, 100, 50);
+        let prices: Vec<u128> = (49..56).map(|height| curve.price_at(height).0).collect();
+        assert_eq!(prices, vec![1_000, 1_000, 900, 800, 700, 700, 700]);
+    }
+
+    #[test]
+    fn per_block_curves_reach_the_floor_on_the_block_the_decay_passes_it() {
+        let curve: DutchCurve = DutchCurve::per_block(1_000, 0, 300, 0);
+        assert_eq!(curve.steps, 4);
+        assert_eq!(curve.price_at(3).0, 250);
+        assert_eq!(curve.price_at(4), (0, u64::MAX));
+    }
+
+    #[test]
+    fn flat_curves_keep_their_price() {
+        assert_eq!(curve(500, 500, 10, 5).price_at(1_000), (500, u64::MAX));
+        assert_eq!(DutchCurve::per_block(500, 500, 1, 0).price_at(1_000).0, 500);
+    }
+}
@@ -0,0 +1,208 @@
+//! Deterministic randomness for trait draws, shuffles and raffles. A generator is seeded
+//! from committed entropy, such as a block header, and a salt naming what it draws for, so
+//! every consumer of the same entropy gets an independent stream and anyone holding the
+//! seed can replay a draw off-chain. The generator is xoshiro256** keyed by the SHA-256 of
+//! seed and salt.
+
+use crate::sha256;
+
+/// xoshiro256** over a committed seed
+pub struct Rng {
+    state: [u64; 4],
+}
+
+impl Rng {
+    /// Generator for `salt` over `seed`
+    pub fn new(seed: &[u8], salt: &[u8]) -> Self {
+        let mut preimage: Vec<u8> = Vec::with_capacity(seed.len() + salt.len() + 4);
+        preimage.extend_from_slice(&(seed.len() as u32).to_le_bytes());
+        preimage.extend_from_slice(seed);
+        preimage.extend_from_slice(salt);
+
+        let digest: [u8; 32] = sha256::hash(&preimage);
+        let mut state: [u64; 4] = [0; 4];
+        for (word, bytes) in state.iter_mut().zip(digest.chunks_exact(8)) {
+            *word = u64::from_le_bytes(bytes.try_into().unwrap());
+        }
+
+        // The all zero state is the one fixed point of xoshiro
+        if state == [0; 4] {
+            state[0] = 1;
+        }
+
+        Rng { state }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        let result: u64 = self.state[1].wrapping_mul(5).rotate_left(7).wrapping_mul(9);
+        let shifted: u64 = self.state[1] << 17;
+
+        self.state[2] ^= self.state[0];
+        self.state[3] ^= self.state[1];
+        self.state[1] ^= self.state[2];
+        self.state[0] ^= self.state[3];
+        self.state[2] ^= shifted;
+        self.state[3] = self.state[3].rotate_left(45);
+
+        result
+    }
+
+    pub fn next_u128(&mut self) -> u128 {
+        ((self.next_u64() as u128) << 64) | self.next_u64() as u128
+    }
+
+    /// Uniform draw from `0..bound`, 0 for an empty range. Draws landing in the short last
+    /// span of the u128 range are rejected so every value is equally likely.
+    pub fn below(&mut self, bound: u128) -> u128 {
+        if bound == 0 {
+            return 0;
+        }
+
+        let zone: u128 = u128::MAX - (u128::MAX - bound + 1) % bound;
+        loop {
+            let draw: u128 = self.next_u128();
+            if draw <= zone {
+                return draw % bound;
+            }
+        }
+    }
+
+    /// Position drawn with probability proportional to its weight, None when every
+    /// weight is zero
+    pub fn weighted(&mut self, weights: &[u128]) -> Option<usize> {
+        let total: u128 = weights.iter().fold(0u128, |sum, weight| sum.saturating_add(*weight));
+        if total == 0 {
+            return None;
+        }
+
+        let mut draw: u128 = self.below(total);
+        for (position, weight) in weights.iter().enumerate() {
+            if draw < *weight {
+                return Some(position);
+            }
+            draw -= weight;
+        }
+
+        None
+    }
+
+    /// Fisher-Yates shuffle in place
+    pub fn shuffle<T>(&mut self, items: &mut [T]) {
+        for last in (1..items.len()).rev() {
+            let other: usize = self.below(last as u128 + 1) as usize;
+            items.swap(last, other);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    /// Pearson statistic of observed bucket counts against expected ones
+    fn chi_square(observed: &[u64], expected: &[f64]) -> f64 {
+        observed.iter().zip(expected)
+            .map(|(observed, expected)| (*observed as f64 - expected).powi(2) / expected)
+            .sum()
+    }
+
+    #[test]
+    fn next_u64_matches_the_reference_vector() {
+        let mut rng: Rng = Rng::new(b"orbital", b"reference");
+        let draws: Vec<u64> = (0..4).map(|_| rng.next_u64()).collect();
+        assert_eq!(draws, [0xb2afed67cfb59a9a, 0xdbedc2a180d864d0, 0xa5ad5eec540a5635, 0xcc44fdee862d2373]);
+    }
+
+    #[test]
+    fn same_seed_and_salt_replay_the_same_stream() {
+        let mut first: Rng = Rng::new(b"block header", b"shuffle");
+        let mut second: Rng = Rng::new(b"block header", b"shuffle");
+        for _ in 0..1000 {
+            assert_eq!(first.next_u128(), second.next_u128());
+        }
+    }
+
+    #[test]
+    fn salts_and_seeds_separate_streams() {
+        let draw = |seed: &[u8], salt: &[u8]| Rng::new(seed, salt).next_u64();
+        assert_ne!(draw(b"block header", b"shuffle"), draw(b"block header", b"raffle"));
+        assert_ne!(draw(b"block header", b"shuffle"), draw(b"other header", b"shuffle"));
+        // The seed is length prefixed, moving bytes between seed and salt changes the stream
+        assert_ne!(draw(b"ab", b"c"), draw(b"a", b"bc"));
+    }
+
+    #[test]
+    fn below_is_uniform() {
+        let mut rng: Rng = Rng::new(b"uniform", b"below");
+        let mut buckets: [u64; 10] = [0; 10];
+        for _ in 0..100_000 {
+            buckets[rng.below(10) as usize] += 1;
+        }
+
+        // 9 degrees of freedom, p = 0.001
+        assert!(chi_square(&buckets, &[10_000.0; 10]) < 27.88, "{:?}", buckets);
+    }
+
+    #[test]
+    fn below_stays_in_range() {
+        let mut rng: Rng = Rng::new(b"uniform", b"range");
+        assert_eq!(rng.below(0), 0);
+        assert_eq!(rng.below(1), 0);
+        for bound in [2, 3, 7, u64::MAX as u128, u128::MAX / 2 + 1, u128::MAX] {
+            for _ in 0..100 {
+                assert!(rng.below(bound) < bound);
+            }
+        }
+    }
+
+    #[test]
+    fn weighted_follows_the_weights() {
+        let mut rng: Rng = Rng::new(b"uniform", b"weighted");
+        let weights: [u128; 5] = [1, 2, 0, 3, 4];
+        let mut buckets: [u64; 5] = [0; 5];
+        for _ in 0..100_000 {
+            buckets[rng.weighted(&weights).unwrap()] += 1;
+        }
+
+        assert_eq!(buckets[2], 0);
+        let observed: [u64; 4] = [buckets[0], buckets[1], buckets[3], buckets[4]];
+        // 3 degrees of freedom, p = 0.001
+        assert!(chi_square(&observed, &[10_000.0, 20_000.0, 30_000.0, 40_000.0]) < 16.27, "{:?}", buckets);
+    }
+
+    #[test]
+    fn weighted_without_weight_draws_nothing() {
+        let mut rng: Rng = Rng::new(b"uniform", b"weighted");
+        assert_eq!(rng.weighted(&[]), None);
+        assert_eq!(rng.weighted(&[0, 0]), None);
+    }
+
+    #[test]
+    fn shuffle_draws_every_permutation_equally() {
+        let mut rng: Rng = Rng::new(b"uniform", b"shuffle");
+        let mut buckets: BTreeMap<[u8; 4], u64> = BTreeMap::new();
+        for _ in 0..48_000 {
+            let mut items: [u8; 4] = [0, 1, 2, 3];
+            rng.shuffle(&mut items);
+            *buckets.entry(items).or_default() += 1;
+        }
+
+        // All 24 orderings, 23 degrees of freedom, p = 0.001
+        assert_eq!(buckets.len(), 24);
+        let observed: Vec<u64> = buckets.values().copied().collect();
+        assert!(chi_square(&observed, &[2_000.0; 24]) < 49.73, "{:?}", buckets);
+    }
+
+    #[test]
+    fn shuffle_keeps_every_item() {
+        let mut rng: Rng = Rng::new(b"uniform", b"shuffle");
+        let mut items: Vec<u32> = (0..100).collect();
+        rng.shuffle(&mut items);
+        items.sort();
+        assert_eq!(items, (0..100).collect::<Vec<u32>>());
+
+        let mut empty: [u32; 0] = [];
+        rng.shuffle(&mut empty);
+    }
+}
@@ -0,0 +1,207 @@
+//! Policy SVG has to meet before the collection serves or stores it. Wallets and
+//! marketplaces inline the art into their own pages, where a script, an event handler or
+//! a reference loading a remote resource would run with the page's privileges. Documents
+//! must be a single well formed `svg` element within the size cap, without scripts,
+//! foreign objects, event handler attributes, document type declarations or references to
+//! anything outside the document itself and inline `data:image/` payloads.
+//!
+//! The check is a conservative scan of the XML structure rather than a full parser: any
+//! construct it does not understand is rejected.
+
+use crate::errors::{codes::*, Revert};
+
+/// Largest document accepted, in bytes
+pub const MAX_SVG_BYTES: usize = 64 * 1024;
+
+/// Deepest element nesting accepted
+const MAX_DEPTH: usize = 64;
+
+/// Elements rejected wherever they appear, compared case insensitively
+const FORBIDDEN_ELEMENTS: [&str; 5] = ["script", "foreignobject", "iframe", "object", "embed"];
+
+/// Check a document against the policy, revealing the first violation found
+pub fn validate(svg: &str) -> Result<(), Box<Revert>> {
+    if svg.len() > MAX_SVG_BYTES {
+        return Err(violation(format!("SVG exceeds {} bytes", MAX_SVG_BYTES))
+            .field("svg_bytes").expected(MAX_SVG_BYTES as u128).actual(svg.len() as u128).into());
+    }
+
+    observe_references(svg)?;
+
+    let mut open: Vec<&str> = Vec::new();
+    let mut root_closed: bool = false;
+    let mut rest: &str = svg;
+
+    while let Some(start) = rest.find('<') {
+        if !rest[..start].trim().is_empty() && open.is_empty() {
+            return Err(violation("Text outside the svg element").into());
+        }
+
+        let markup: &str = &rest[start..];
+        if let Some(body) = markup.strip_prefix("<?") {
+            if !open.is_empty() || root_closed {
+                return Err(violation("Processing instructions only lead the document").into());
+            }
+            rest = after(body, "?>", "Unterminated processing instruction")?;
+        } else if let Some(body) = markup.strip_prefix("<!--") {
+            rest = after(body, "-->", "Unterminated comment")?;
+        } else if let Some(body) = markup.strip_prefix("<![CDATA[") {
+            if open.is_empty() {
+                return Err(violation("Character data outside the svg element").into());
+            }
+            rest = after(body, "]]>", "Unterminated character data")?;
+        } else if markup.starts_with("<!") {
+            return Err(violation("Document type declarations are not allowed").into());
+        } else if let Some(body) = markup.strip_prefix("</") {
+            let end: usize = body.find('>').ok_or_else(|| violation("Unterminated closing tag"))?;
+            let name: &str = body[..end].trim_end();
+            match open.pop() {
+                Some(expected) if expected == name => {}
+                Some(expected) => return Err(violation(format!("Closing tag {} does not match {}", name, expected)).into()),
+                None => return Err(violation(format!("Closing tag {} without an open element", name)).into()),
+            }
+            root_closed = open.is_empty();
+            rest = &body[end + 1..];
+        } else {
+            if root_closed {
+                return Err(violation("Elements after the svg element").into());
+            }
+
+            let (name, self_closing, remainder) = read_start_tag(&markup[1..])?;
+            if open.is_empty() && name != "svg" {
+                return Err(violation(format!("Document root is {}, not svg", name)).into());
+            }
+
+            if self_closing {
+                root_closed = open.is_empty();
+            } else {
+                if open.len() >= MAX_DEPTH {
+                    return Err(violation("Elements are nested too deeply")
+                        .field("depth").expected(MAX_DEPTH as u128).actual(open.len() as u128 + 1).into());
+                }
+                open.push(name);
+            }
+            rest = remainder;
+        }
+    }
+
+    if let Some(name) = open.last() {
+        return Err(violation(format!("Element {} is never closed", name)).into());
+    }
+
+    if !root_closed {
+        return Err(violation("Document holds no svg element").into());
+    }
+
+    if !rest.trim().is_empty() {
+        return Err(violation("Text after the svg element").into());
+    }
+
+    Ok(())
+}
+
+/// Read a start tag after its `<`: the element name, whether it closes itself and the
+/// document past the tag
+fn read_start_tag(tag: &str) -> Result<(&str, bool, &str), Box<Revert>> {
+    let name_end: usize = tag.find(|c: char| c.is_whitespace() || c == '/' || c == '>').unwrap_or(tag.len());
+    let name: &str = &tag[..name_end];
+    if name.is_empty() || !name.chars().all(is_name_char) {
+        return Err(violation("Malformed element name").into());
+    }
+
+    if FORBIDDEN_ELEMENTS.contains(&local_name(name).to_ascii_lowercase().as_str()) {
+        return Err(violation(format!("Element {} is not allowed", name)).into());
+    }
+
+    let mut rest: &str = &tag[name_end..];
+    loop {
+        rest = rest.trim_start();
+        if let Some(remainder) = rest.strip_prefix("/>") {
+            return Ok((name, true, remainder));
+        }
+        if let Some(remainder) = rest.strip_prefix('>') {
+            return Ok((name, false, remainder));
+        }
+
+        let attribute_end: usize = rest.find(|c: char| c.is_whitespace() || c == '=').unwrap_or(rest.len());
+        let attribute: &str = &rest[..attribute_end];
+        if attribute.is_empty() || !attribute.chars().all(is_name_char) {
+            return Err(violation(format!("Malformed attribute in {}", name)).into());
+        }
+
+        rest = rest[attribute_end..].trim_start();
+        rest = rest.strip_prefix('=')
+            .ok_or_else(|| violation(format!("Attribute {} has no value", attribute)))?
+            .trim_start();
+
+        let quote: char = rest.chars().next()
+            .filter(|quote| *quote == '"' || *quote == '\'')
+            .ok_or_else(|| violation(format!("Attribute {} is not quoted", attribute)))?;
+        let value_end: usize = rest[1..].find(quote)
+            .ok_or_else(|| violation(format!("Attribute {} is never closed", attribute)))?;
+        observe_attribute(attribute, &rest[1..1 + value_end])?;
+        rest = &rest[value_end + 2..];
+    }
+}
+
+fn observe_attribute(attribute: &str, value: &str) -> Result<(), Box<Revert>> {
+    let local: String = local_name(attribute).to_ascii_lowercase();
+    if local.starts_with("on") {
+        return Err(violation(format!("Event handler {} is not allowed", attribute)).into());
+    }
+
+    if value.contains('<') {
+        return Err(violation(format!("Attribute {} holds markup", attribute)).into());
+    }
+
+    if local == "href" || local == "src" {
+        let target: String = value.trim().to_ascii_lowercase();
+        if !target.starts_with('#') && !target.starts_with("data:image/") {
+            return Err(violation(format!("Attribute {} references an external resource", attribute)).into());
+        }
+    }
+
+    Ok(())
+}
+
+/// Style sheets are scanned as text: every `url(` must point into the document or at an
+/// inline image, and nothing may be imported
+fn observe_references(svg: &str) -> Result<(), Box<Revert>> {
+    let lowered: String = svg.to_ascii_lowercase();
+    if lowered.contains("@import") {
+        return Err(violation("Style sheet imports are not allowed").into());
+    }
+
+    if lowered.contains("javascript:") {
+        return Err(violation("Script URLs are not allowed").into());
+    }
+
+    let mut rest: &str = &lowered;
+    while let Some(start) = rest.find("url(") {
+        rest = &rest[start + 4..];
+        let target: &str = rest.trim_start().trim_start_matches(['"', '\'']);
+        if !target.starts_with('#') && !target.starts_with("data:image/") {
+            return Err(violation("Style references an external resource").into());
+        }
+    }
+
+    Ok(())
+}
+
+/// Name past its namespace prefix
+fn local_name(name: &str) -> &str {
+    name.rsplit(':').next().unwrap_or(name)
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == ':' || c == '-' || c == '_' || c == '.'
+}
+
+fn after<'a>(body: &'a str, terminator: &str, message: &str) -> Result<&'a str, Box<Revert>> {
+    let end: usize = body.find(terminator).ok_or_else(|| violation(message))?;
+    Ok(&body[end + terminator.len()..])
+}
+
+fn violation(message: impl Into<String>) -> Revert {
+    Revert::new(ERR_INVALID_INPUT, message)
+}
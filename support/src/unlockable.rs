@@ -0,0 +1,56 @@
+//! Off-chain side of content key rotation: packing keys and re-encrypted payloads into
+//! the inputs of `RotateContentKey` and `UploadUnlockableChunk`, and putting downloaded
+//! chunks back together. Encryption itself is left to the caller.
+
+/// Opcode of `RotateContentKey { key_words }`
+pub const ROTATE_CONTENT_KEY: u128 = 333;
+
+/// Opcode of `UploadUnlockableChunk { index, epoch, chunk, length }`
+pub const UPLOAD_UNLOCKABLE_CHUNK: u128 = 334;
+
+/// Largest key reference the contract accepts, in bytes
+pub const MAX_KEY_BYTES: usize = 64;
+
+/// Largest chunk the contract accepts, in bytes
+pub const MAX_CHUNK_BYTES: usize = 1024;
+
+/// Pack bytes into little endian u128 words, zero padding the last one
+pub fn pack_words(bytes: &[u8]) -> Vec<u128> {
+    bytes.chunks(16)
+        .map(|chunk| {
+            let mut word: [u8; 16] = [0; 16];
+            word[..chunk.len()].copy_from_slice(chunk);
+            u128::from_le_bytes(word)
+        })
+        .collect()
+}
+
+/// Inputs of the `RotateContentKey` call installing `key` as the next epoch
+pub fn rotate_key_inputs(key: &[u8]) -> Result<Vec<u128>, String> {
+    if key.is_empty() || key.len() > MAX_KEY_BYTES {
+        return Err(format!("key reference must hold 1 to {} bytes", MAX_KEY_BYTES));
+    }
+
+    let words: Vec<u128> = pack_words(key);
+    let mut inputs: Vec<u128> = vec![ROTATE_CONTENT_KEY, words.len() as u128];
+    inputs.extend(words);
+    Ok(inputs)
+}
+
+/// Inputs of the `UploadUnlockableChunk` calls uploading a payload re-encrypted under
+/// `epoch`, one call per chunk and in the order they have to be sent
+pub fn upload_inputs(index: u128, epoch: u128, ciphertext: &[u8]) -> Vec<Vec<u128>> {
+    ciphertext.chunks(MAX_CHUNK_BYTES)
+        .enumerate()
+        .map(|(chunk, bytes)| {
+            let mut inputs: Vec<u128> = vec![UPLOAD_UNLOCKABLE_CHUNK, index, epoch, chunk as u128, bytes.len() as u128];
+            inputs.extend(pack_words(bytes));
+            inputs
+        })
+        .collect()
+}
+
+/// Concatenate chunks fetched with `GetUnlockableChunk` back into the ciphertext
+pub fn reassemble(chunks: &[Vec<u8>]) -> Vec<u8> {
+    chunks.concat()
+}